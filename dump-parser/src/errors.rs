@@ -10,10 +10,48 @@ pub enum DumpFileError {
     DoesNotExist,
     ReadError(std::io::Error),
     MalFormatted,
+    /// `COPY ... FROM stdin` statement with a payload in binary format
+    BinaryCopyFormat(String),
+    /// statement (or `COPY` payload row) larger than the given number of bytes
+    StatementTooLarge(usize),
+    /// `COPY` payload row which is not valid UTF-8 - at the given line of the dump
+    InvalidCopyRow(usize),
+    /// statement which is not valid UTF-8 - read up to the given line of the dump
+    InvalidStatement(usize),
 }
 
 impl From<DumpFileError> for std::io::Error {
     fn from(err: DumpFileError) -> Self {
-        std::io::Error::new(ErrorKind::Other, format!("{:?}", err))
+        match err {
+            DumpFileError::BinaryCopyFormat(statement) => std::io::Error::new(
+                ErrorKind::Other,
+                format!(
+                    "COPY payloads in binary format are not supported - use the text format instead: {}",
+                    statement
+                ),
+            ),
+            DumpFileError::StatementTooLarge(max_bytes) => std::io::Error::new(
+                ErrorKind::Other,
+                format!(
+                    "the dump contains a statement larger than {} bytes - it is not read to not run out of memory",
+                    max_bytes
+                ),
+            ),
+            DumpFileError::InvalidCopyRow(line_number) => std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "the COPY payload row at line {} of the dump is not valid UTF-8",
+                    line_number
+                ),
+            ),
+            DumpFileError::InvalidStatement(line_number) => std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "the statement read up to line {} of the dump is not valid UTF-8",
+                    line_number
+                ),
+            ),
+            err => std::io::Error::new(ErrorKind::Other, format!("{:?}", err)),
+        }
     }
 }
@@ -28,8 +28,16 @@ pub struct Metadata {
     pub collection: String,
     pub metadata: String,
     pub size: i32,
+    /// `collection`, `view` or `timeseries`
     pub r#type: String,
 }
+
+impl Metadata {
+    /// a view has no data block in the archive - only its definition in the metadata
+    pub fn has_data_block(&self) -> bool {
+        self.r#type != "view"
+    }
+}
 /// Mongo archive namespace document.
 ///
 /// namespaces are found in the archive before any data blocks, and also after them,
@@ -50,6 +58,37 @@ pub struct Namespace {
 pub type Prefix = String;
 pub type Collection = Vec<Document>;
 pub type PrefixedCollections = HashMap<Prefix, Collection>;
+
+/// Read the names of the databases contained in an archive.
+///
+/// Only the magic bytes, the header and the metadata documents are read, the data blocks are left untouched.
+pub fn get_database_names_from_archive<R: Read>(
+    mut reader: BufReader<R>,
+) -> Result<Vec<String>, Error> {
+    let mut buf: [u8; 4] = [0; 4];
+    let mut database_names: Vec<String> = vec![];
+
+    reader.read_exact(&mut buf)?;
+    if buf != MAGIC_BYTES {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Stream or file does not appear to be a mongodump archive",
+        ));
+    }
+
+    let _: Header = bson::from_reader(&mut reader)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}", e)))?;
+
+    while let Ok(collection_metadata_doc) = bson::from_reader(&mut reader) {
+        let metadata_doc: Metadata = collection_metadata_doc;
+        if !database_names.contains(&metadata_doc.db) {
+            database_names.push(metadata_doc.db);
+        }
+    }
+
+    Ok(database_names)
+}
+
 /// # Archive
 /// reference: https://github.com/mongodb/mongo-tools-common/blob/v4.2/archive/archive.go
 ///
@@ -92,7 +131,8 @@ pub type PrefixedCollections = HashMap<Prefix, Collection>;
 #[derive(Debug, Clone)]
 pub struct Archive {
     header: Header,
-    metadata_docs: Vec<Metadata>,
+    // kept as read to re-emit the metadata of every collection type (views, timeseries...) untouched
+    metadata_docs: Vec<Document>,
     namespace_docs: Vec<Namespace>,
     prefixed_collections: PrefixedCollections, // prefix is <db_name>.<collection_name>
 }
@@ -119,10 +159,20 @@ impl Archive {
             .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}", e)))?;
 
         // read metadata headers and seperator (seperator is read when while let fails for the 1st time)
-        while let Ok(collection_metadata_doc) = bson::from_reader(&mut reader) {
-            let metadata_doc = Metadata::from(collection_metadata_doc);
-            metadata_docs.push(metadata_doc);
-            num_blocks += 1;
+        while let Ok(collection_metadata_doc) = Document::from_reader(&mut reader) {
+            let metadata_doc: Metadata = bson::from_document(collection_metadata_doc.clone())
+                .map_err(|err| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Error reading metadata doc: {}", err),
+                    )
+                })?;
+
+            if metadata_doc.has_data_block() {
+                num_blocks += 1;
+            }
+
+            metadata_docs.push(collection_metadata_doc);
         }
 
         if num_blocks > 0 {
@@ -184,15 +234,12 @@ impl Archive {
                 )
             })?;
         for metadata_doc in &self.metadata_docs {
-            bson::to_document(&metadata_doc)
-                .unwrap()
-                .to_writer(&mut buf)
-                .map_err(|err| {
-                    Error::new(
-                        ErrorKind::Other,
-                        format!("Error writing metadata doc: {}", err),
-                    )
-                })?;
+            metadata_doc.to_writer(&mut buf).map_err(|err| {
+                Error::new(
+                    ErrorKind::Other,
+                    format!("Error writing metadata doc: {}", err),
+                )
+            })?;
         }
         buf.extend_from_slice(&SEPERATOR_BYTES);
         for namespace_doc in &mut self.namespace_docs {
@@ -250,7 +297,11 @@ impl Archive {
 
 #[cfg(test)]
 mod tests {
-    use crate::{mongodb::Archive, utils::decode_hex};
+    use crate::{
+        mongodb::{get_database_names_from_archive, Archive, Metadata, SEPERATOR_BYTES},
+        utils::decode_hex,
+    };
+    use bson::doc;
     use std::{fmt::Write, io::BufReader};
 
     #[test]
@@ -287,4 +338,50 @@ mod tests {
         }
         assert_eq!(out.as_str(), dump_str);
     }
+
+    #[test]
+    fn mongo_archive_database_names() {
+        let dump_str = "6de299816600000010636f6e63757272656e745f636f6c6c656374696f6e7300040000000276657273696f6e0004000000302e3100027365727665725f76657273696f6e0006000000352e302e360002746f6f6c5f76657273696f6e00080000003130302e352e32000003010000026462000600000074657374320002636f6c6c656374696f6e0006000000557365727300026d6574616461746100ad0000007b22696e6465786573223a5b7b2276223a7b22246e756d626572496e74223a2232227d2c226b6579223a7b225f6964223a7b22246e756d626572496e74223a2231227d7d2c226e616d65223a225f69645f227d5d2c2275756964223a223732306531616132326231373435643739663139373530626162323933303837222c22636f6c6c656374696f6e4e616d65223a225573657273222c2274797065223a22636f6c6c656374696f6e227d001073697a6500000000000274797065000b000000636f6c6c656374696f6e0000ffffffff3c000000026462000600000074657374320002636f6c6c656374696f6e000600000055736572730008454f46000012435243000000000000000000002e000000075f696400623f23928e7f1feed4d5e3e1026e616d6500050000004a6f686e0010616765002a00000000ffffffff3c000000026462000600000074657374320002636f6c6c656374696f6e000600000055736572730008454f4600011243524300ff2a87dec3c86e6e00ffffffff";
+        let hexdump = decode_hex(dump_str).unwrap();
+        let reader = BufReader::new(hexdump.as_slice());
+        let database_names = get_database_names_from_archive(reader).unwrap();
+        assert_eq!(database_names, vec!["test2".to_string()]);
+
+        let reader = BufReader::new("not an archive".as_bytes());
+        assert!(get_database_names_from_archive(reader).is_err());
+    }
+
+    #[test]
+    fn mongo_archive_with_view_to_bytes() {
+        // same archive as above with a view "UsersView" on "Users" - a view has metadata but no data block
+        let dump_str = "6de299816600000010636f6e63757272656e745f636f6c6c656374696f6e7300040000000276657273696f6e0004000000302e3100027365727665725f76657273696f6e0006000000352e302e360002746f6f6c5f76657273696f6e00080000003130302e352e32000003010000026462000600000074657374320002636f6c6c656374696f6e0006000000557365727300026d6574616461746100ad0000007b22696e6465786573223a5b7b2276223a7b22246e756d626572496e74223a2232227d2c226b6579223a7b225f6964223a7b22246e756d626572496e74223a2231227d7d2c226e616d65223a225f69645f227d5d2c2275756964223a223732306531616132326231373435643739663139373530626162323933303837222c22636f6c6c656374696f6e4e616d65223a225573657273222c2274797065223a22636f6c6c656374696f6e227d001073697a6500000000000274797065000b000000636f6c6c656374696f6e0000ffffffff3c000000026462000600000074657374320002636f6c6c656374696f6e000600000055736572730008454f46000012435243000000000000000000002e000000075f696400623f23928e7f1feed4d5e3e1026e616d6500050000004a6f686e0010616765002a00000000ffffffff3c000000026462000600000074657374320002636f6c6c656374696f6e000600000055736572730008454f4600011243524300ff2a87dec3c86e6e00ffffffff";
+        let mut hexdump = decode_hex(dump_str).unwrap();
+
+        let view_metadata = doc! {
+            "db": "test2",
+            "collection": "UsersView",
+            "metadata": r#"{"options":{"viewOn":"Users","pipeline":[{"$project":{"name":1}}]},"indexes":[],"collectionName":"UsersView","type":"view"}"#,
+            "size": 0,
+            "type": "view",
+        };
+        let metadata: Metadata = bson::from_document(view_metadata.clone()).unwrap();
+        assert!(!metadata.has_data_block());
+
+        // insert the view metadata right before the seperator ending the metadata docs
+        let mut view_metadata_bytes = vec![];
+        view_metadata.to_writer(&mut view_metadata_bytes).unwrap();
+        let seperator_index = hexdump
+            .windows(SEPERATOR_BYTES.len())
+            .position(|bytes| bytes == SEPERATOR_BYTES)
+            .unwrap();
+        hexdump.splice(seperator_index..seperator_index, view_metadata_bytes);
+
+        let reader = BufReader::new(hexdump.as_slice());
+        let archive = Archive::from_reader(reader).unwrap();
+        assert_eq!(archive.metadata_docs.len(), 2);
+        assert!(archive.prefixed_collections.contains_key("test2.Users"));
+        assert!(!archive.prefixed_collections.contains_key("test2.UsersView"));
+
+        assert_eq!(archive.into_bytes().unwrap(), hexdump);
+    }
 }
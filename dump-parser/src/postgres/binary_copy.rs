@@ -0,0 +1,292 @@
+use std::io::{ErrorKind, Read, Write};
+
+use crate::DumpFileError;
+use crate::DumpFileError::{MalFormatted, ReadError};
+
+/// first bytes of a `COPY ... FROM stdin WITH (FORMAT binary);` payload
+pub const SIGNATURE: &[u8; 11] = b"PGCOPY\n\xff\r\n\0";
+/// field count marking the end of the payload
+const TRAILER: i16 = -1;
+/// field length of a NULL value
+const NULL_LENGTH: i32 = -1;
+
+/// Column types that can be decoded from (and encoded into) a binary COPY payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Bool,
+    Int2,
+    Int4,
+    Int8,
+    Float4,
+    Float8,
+    /// text, varchar, bpchar, name... - the value is the raw UTF-8 string
+    Text,
+}
+
+impl ColumnType {
+    /// column type from the type name of a `CREATE TABLE` statement - `None` if the type is not supported
+    pub fn from_type_name(type_name: &str) -> Option<Self> {
+        let type_name = type_name.to_lowercase();
+        // the length is not part of the binary format - e.g. `character varying(15)`
+        let type_name = match type_name.find('(') {
+            Some(idx) => type_name[..idx].trim(),
+            None => type_name.trim(),
+        };
+
+        match type_name {
+            "boolean" | "bool" => Some(ColumnType::Bool),
+            "smallint" | "int2" => Some(ColumnType::Int2),
+            "integer" | "int" | "int4" => Some(ColumnType::Int4),
+            "bigint" | "int8" => Some(ColumnType::Int8),
+            "real" | "float4" => Some(ColumnType::Float4),
+            "double precision" | "float8" => Some(ColumnType::Float8),
+            "text" | "character varying" | "varchar" | "character" | "char" | "bpchar" | "name" => {
+                Some(ColumnType::Text)
+            }
+            _ => None,
+        }
+    }
+
+    /// decode a (not NULL) field into its text representation
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, DumpFileError> {
+        let value = match self {
+            ColumnType::Bool => match bytes {
+                [0] => "f".to_string(),
+                [1] => "t".to_string(),
+                _ => return Err(MalFormatted),
+            },
+            ColumnType::Int2 => i16::from_be_bytes(to_array(bytes)?).to_string(),
+            ColumnType::Int4 => i32::from_be_bytes(to_array(bytes)?).to_string(),
+            ColumnType::Int8 => i64::from_be_bytes(to_array(bytes)?).to_string(),
+            ColumnType::Float4 => f32::from_be_bytes(to_array(bytes)?).to_string(),
+            ColumnType::Float8 => f64::from_be_bytes(to_array(bytes)?).to_string(),
+            ColumnType::Text => match std::str::from_utf8(bytes) {
+                Ok(value) => value.to_string(),
+                Err(_) => return Err(MalFormatted),
+            },
+        };
+
+        Ok(value)
+    }
+
+    /// encode the text representation of a value into a field
+    pub fn encode(&self, value: &str) -> Result<Vec<u8>, DumpFileError> {
+        let bytes = match self {
+            ColumnType::Bool => match value {
+                "t" | "true" => vec![1],
+                "f" | "false" => vec![0],
+                _ => return Err(MalFormatted),
+            },
+            ColumnType::Int2 => parse::<i16>(value)?.to_be_bytes().to_vec(),
+            ColumnType::Int4 => parse::<i32>(value)?.to_be_bytes().to_vec(),
+            ColumnType::Int8 => parse::<i64>(value)?.to_be_bytes().to_vec(),
+            ColumnType::Float4 => parse::<f32>(value)?.to_be_bytes().to_vec(),
+            ColumnType::Float8 => parse::<f64>(value)?.to_be_bytes().to_vec(),
+            ColumnType::Text => value.as_bytes().to_vec(),
+        };
+
+        Ok(bytes)
+    }
+}
+
+/// Read the rows of a binary COPY payload - a row is a list of fields, `None` is a NULL value
+pub struct BinaryCopyReader<R: Read> {
+    reader: R,
+    is_over: bool,
+}
+
+impl<R: Read> BinaryCopyReader<R> {
+    /// check the signature and skip the header of the payload
+    pub fn new(mut reader: R) -> Result<Self, DumpFileError> {
+        let mut signature = [0u8; 11];
+        read_exact(&mut reader, &mut signature)?;
+
+        if &signature != SIGNATURE {
+            return Err(MalFormatted);
+        }
+
+        // flags - bit 16 is set when the OIDs are included, which is not supported
+        let flags = i32::from_be_bytes(read_array(&mut reader)?);
+        if flags & (1 << 16) != 0 {
+            return Err(MalFormatted);
+        }
+
+        let extension_length = i32::from_be_bytes(read_array(&mut reader)?);
+        if extension_length < 0 {
+            return Err(MalFormatted);
+        }
+
+        let mut extension = vec![0u8; extension_length as usize];
+        read_exact(&mut reader, &mut extension)?;
+
+        Ok(BinaryCopyReader {
+            reader,
+            is_over: false,
+        })
+    }
+
+    /// next row of the payload - `None` once the trailer is read
+    pub fn next_row(&mut self) -> Result<Option<Vec<Option<Vec<u8>>>>, DumpFileError> {
+        if self.is_over {
+            return Ok(None);
+        }
+
+        let field_count = i16::from_be_bytes(read_array(&mut self.reader)?);
+        if field_count == TRAILER {
+            self.is_over = true;
+            return Ok(None);
+        }
+
+        if field_count < 0 {
+            return Err(MalFormatted);
+        }
+
+        let mut fields = Vec::with_capacity(field_count as usize);
+        for _ in 0..field_count {
+            let length = i32::from_be_bytes(read_array(&mut self.reader)?);
+
+            if length == NULL_LENGTH {
+                fields.push(None);
+                continue;
+            }
+
+            if length < 0 {
+                return Err(MalFormatted);
+            }
+
+            let mut field = vec![0u8; length as usize];
+            read_exact(&mut self.reader, &mut field)?;
+            fields.push(Some(field));
+        }
+
+        Ok(Some(fields))
+    }
+}
+
+/// write the header of a binary COPY payload
+pub fn write_header<W: Write>(writer: &mut W) -> Result<(), DumpFileError> {
+    write_all(writer, SIGNATURE)?;
+    // no flags and no header extension
+    write_all(writer, &0i32.to_be_bytes())?;
+    write_all(writer, &0i32.to_be_bytes())
+}
+
+/// write a row of a binary COPY payload - `None` is a NULL value
+pub fn write_row<W: Write>(
+    writer: &mut W,
+    fields: &[Option<Vec<u8>>],
+) -> Result<(), DumpFileError> {
+    write_all(writer, &(fields.len() as i16).to_be_bytes())?;
+
+    for field in fields {
+        match field {
+            Some(field) => {
+                write_all(writer, &(field.len() as i32).to_be_bytes())?;
+                write_all(writer, field)?;
+            }
+            None => write_all(writer, &NULL_LENGTH.to_be_bytes())?,
+        }
+    }
+
+    Ok(())
+}
+
+/// write the trailer of a binary COPY payload
+pub fn write_trailer<W: Write>(writer: &mut W) -> Result<(), DumpFileError> {
+    write_all(writer, &TRAILER.to_be_bytes())
+}
+
+fn to_array<const N: usize>(bytes: &[u8]) -> Result<[u8; N], DumpFileError> {
+    bytes.try_into().map_err(|_| MalFormatted)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, DumpFileError> {
+    value.parse::<T>().map_err(|_| MalFormatted)
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], DumpFileError> {
+    let mut bytes = [0u8; N];
+    read_exact(reader, &mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_exact<R: Read>(reader: &mut R, bytes: &mut [u8]) -> Result<(), DumpFileError> {
+    reader.read_exact(bytes).map_err(|err| match err.kind() {
+        // the payload is truncated
+        ErrorKind::UnexpectedEof => MalFormatted,
+        _ => ReadError(err),
+    })
+}
+
+fn write_all<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), DumpFileError> {
+    writer.write_all(bytes).map_err(ReadError)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::postgres::binary_copy::{
+        write_header, write_row, write_trailer, BinaryCopyReader, ColumnType, SIGNATURE,
+    };
+
+    #[test]
+    fn decode_and_encode_values() {
+        let values = vec![
+            (ColumnType::Bool, "t"),
+            (ColumnType::Int2, "-12"),
+            (ColumnType::Int4, "1234"),
+            (ColumnType::Int8, "-9876543210"),
+            (ColumnType::Float4, "1.5"),
+            (ColumnType::Float8, "-0.25"),
+            (ColumnType::Text, "Rue de l'Eglise"),
+        ];
+
+        for (column_type, value) in values {
+            let bytes = column_type.encode(value).unwrap();
+            assert_eq!(column_type.decode(bytes.as_slice()).unwrap(), value);
+        }
+
+        assert_eq!(ColumnType::Int4.decode(&[0, 0, 0, 42]).unwrap(), "42");
+        assert!(ColumnType::Int4.decode(&[0, 42]).is_err());
+        assert!(ColumnType::Int2.encode("not a number").is_err());
+
+        assert_eq!(
+            ColumnType::from_type_name("character varying(15)"),
+            Some(ColumnType::Text)
+        );
+        assert_eq!(
+            ColumnType::from_type_name("SMALLINT"),
+            Some(ColumnType::Int2)
+        );
+        assert_eq!(ColumnType::from_type_name("bytea"), None);
+    }
+
+    #[test]
+    fn read_and_write_payload() {
+        let rows = vec![
+            vec![Some(vec![0, 1]), Some(b"Beverages".to_vec())],
+            vec![Some(vec![0, 2]), None],
+        ];
+
+        let mut payload = vec![];
+        write_header(&mut payload).unwrap();
+        for row in &rows {
+            write_row(&mut payload, row).unwrap();
+        }
+        write_trailer(&mut payload).unwrap();
+
+        assert!(payload.starts_with(SIGNATURE));
+
+        let mut reader = BinaryCopyReader::new(payload.as_slice()).unwrap();
+        assert_eq!(reader.next_row().unwrap(), Some(rows[0].clone()));
+        assert_eq!(reader.next_row().unwrap(), Some(rows[1].clone()));
+        assert_eq!(reader.next_row().unwrap(), None);
+        assert_eq!(reader.next_row().unwrap(), None);
+
+        // truncated payload
+        let mut reader = BinaryCopyReader::new(&payload[..payload.len() - 4]).unwrap();
+        assert!(reader.next_row().is_ok());
+        assert!(reader.next_row().is_err());
+
+        assert!(BinaryCopyReader::new(&b"COPY public.categories"[..]).is_err());
+    }
+}
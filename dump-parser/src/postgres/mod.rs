@@ -3,10 +3,15 @@ use std::iter::Peekable;
 use std::str::Chars;
 
 use crate::postgres::Keyword::{
-    Add, Alter, Constraint, Copy, Create, Database, Foreign, From, Function, Insert,
-    Into as KeywordInto, Key, NoKeyword, Not, Null, Only, Primary, References, Replace, Table,
+    Add, Alter, Column, Comment, Constraint, Copy, Create, Database, Foreign, From, Function,
+    Insert, Into as KeywordInto, Is, Key, NoKeyword, Not, Null, On, Only, Primary, References,
+    Replace, Table, Values,
 };
 
+/// decoder of the `COPY ... FROM stdin WITH (FORMAT binary);` payloads
+#[cfg(feature = "binary-copy")]
+pub mod binary_copy;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Token {
     /// An end-of-file marker, not a real token
@@ -144,6 +149,11 @@ impl Token {
                     "REFERENCES" => References,
                     "KEY" => Key,
                     "FUNCTION" => Function,
+                    "COMMENT" => Comment,
+                    "ON" => On,
+                    "COLUMN" => Column,
+                    "IS" => Is,
+                    "VALUES" => Values,
                     _ => NoKeyword,
                 }
             } else {
@@ -190,6 +200,11 @@ pub enum Keyword {
     References,
     Key,
     Function,
+    Comment,
+    On,
+    Column,
+    Is,
+    Values,
     NoKeyword,
 }
 
@@ -779,6 +794,162 @@ pub fn get_column_values_str_from_insert_into_query(tokens: &Vec<Token>) -> Vec<
         .collect::<Vec<_>>()
 }
 
+pub fn get_column_names_from_copy_query(tokens: &Vec<Token>) -> Vec<String> {
+    if !match_keyword_at_position(Keyword::Copy, &tokens, 0) {
+        // it means that the query is not a COPY.. one
+        return Vec::new();
+    }
+
+    tokens
+        .iter()
+        .skip_while(|token| match **token {
+            Token::LParen => false,
+            _ => true,
+        })
+        .take_while(|token| match **token {
+            Token::RParen => false,
+            _ => true,
+        })
+        .filter_map(|token| match token {
+            Token::Word(word) => {
+                Some(format!(
+                    "{quote_style}{value}{quote_style}",
+                    value = word.value.as_str(),
+                    quote_style = match word.quote_style {
+                        Some(quote) => quote.to_string(),
+                        None => "".to_string(),
+                    }
+                )) // column name with escaping
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+}
+
+/// check if the query is a `COPY ... FROM stdin` with a payload in binary format -
+/// e.g. `COPY ... FROM stdin WITH (FORMAT binary);`, `COPY ... FROM stdin WITH BINARY;` or `COPY BINARY ... FROM stdin;`
+pub fn is_binary_copy_query(tokens: &Vec<Token>) -> bool {
+    if !match_keyword_at_position(Keyword::Copy, tokens, 0) {
+        return false;
+    }
+
+    let mut words = tokens.iter().skip(1).filter_map(|token| match token {
+        Token::Word(word) if word.quote_style.is_none() => Some(word.value.as_str()),
+        Token::Word(_) => Some(""),
+        Token::SingleQuotedString(value) => Some(value.as_str()),
+        _ => None,
+    });
+
+    if let Some(word) = words.next() {
+        if word.eq_ignore_ascii_case("binary") {
+            return true;
+        }
+    }
+
+    // only the options are after `stdin` - the table and column names can't match
+    words
+        .skip_while(|word| !word.eq_ignore_ascii_case("stdin"))
+        .any(|word| word.eq_ignore_ascii_case("binary"))
+}
+
+/// parse a row from a `COPY ... FROM stdin;` payload (text format).
+/// Return the unescaped value of each column, `None` is a NULL value (`\N`).
+pub fn get_column_values_from_copy_row(row: &str) -> Vec<Option<String>> {
+    row.split('\t')
+        .map(|value| match value {
+            "\\N" => None,
+            value => Some(unescape_copy_value(value)),
+        })
+        .collect::<Vec<_>>()
+}
+
+/// build a row for a `COPY ... FROM stdin;` payload (text format) from the column values.
+/// `None` is a NULL value (`\N`).
+pub fn to_copy_row(values: &Vec<Option<String>>) -> String {
+    values
+        .iter()
+        .map(|value| match value {
+            Some(value) => escape_copy_value(value.as_str()),
+            None => "\\N".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
+fn unescape_copy_value(value: &str) -> String {
+    let mut s = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            s.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('b') => s.push('\u{8}'),
+            Some('f') => s.push('\u{c}'),
+            Some('n') => s.push('\n'),
+            Some('r') => s.push('\r'),
+            Some('t') => s.push('\t'),
+            Some('v') => s.push('\u{b}'),
+            Some('x') => {
+                // \xhh - one or two hex digits
+                let mut hex = String::new();
+                while hex.len() < 2 {
+                    match chars.peek() {
+                        Some(digit) if digit.is_ascii_hexdigit() => hex.push(chars.next().unwrap()),
+                        _ => break,
+                    }
+                }
+
+                match u8::from_str_radix(hex.as_str(), 16) {
+                    Ok(byte) => s.push(byte as char),
+                    Err(_) => s.push('x'),
+                }
+            }
+            Some(digit) if digit.is_digit(8) => {
+                // \ooo - one to three octal digits
+                let mut octal = digit.to_string();
+                while octal.len() < 3 {
+                    match chars.peek() {
+                        Some(digit) if digit.is_digit(8) => octal.push(chars.next().unwrap()),
+                        _ => break,
+                    }
+                }
+
+                match u8::from_str_radix(octal.as_str(), 8) {
+                    Ok(byte) => s.push(byte as char),
+                    Err(_) => s.push_str(octal.as_str()),
+                }
+            }
+            Some(ch) => s.push(ch),
+            None => s.push('\\'),
+        }
+    }
+
+    s
+}
+
+fn escape_copy_value(value: &str) -> String {
+    let mut s = String::with_capacity(value.len());
+
+    for ch in value.chars() {
+        match ch {
+            '\\' => s.push_str("\\\\"),
+            '\u{8}' => s.push_str("\\b"),
+            '\u{c}' => s.push_str("\\f"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            '\u{b}' => s.push_str("\\v"),
+            ch => s.push(ch),
+        }
+    }
+
+    s
+}
+
 pub fn get_column_names_from_create_query(tokens: &Vec<Token>) -> Vec<String> {
     if !match_keyword_at_position(Create, &tokens, 0) {
         return Vec::new();
@@ -813,6 +984,113 @@ pub fn get_column_names_from_create_query(tokens: &Vec<Token>) -> Vec<String> {
         .collect::<Vec<_>>()
 }
 
+/// name and type of the columns of a `CREATE TABLE` query - the type is its first word
+/// (e.g. `character` for `character varying(40)`), followed by `[]` for an array (e.g. `integer[]`).
+/// The table constraints are listed as well.
+pub fn get_column_types_from_create_query(tokens: &Vec<Token>) -> Vec<(String, String)> {
+    if !match_keyword_at_position(Create, &tokens, 0) {
+        return Vec::new();
+    }
+
+    let mut columns = vec![];
+    let mut words: Vec<&str> = vec![];
+    let mut is_array = false;
+    let mut depth = 0;
+
+    let column_type = |column_type: &str, is_array: bool| {
+        if is_array {
+            format!("{}[]", column_type)
+        } else {
+            column_type.to_string()
+        }
+    };
+
+    for token in tokens.iter().skip_while(|token| **token != Token::LParen) {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            // the parentheses of a type (e.g. `numeric(10,2)`) contain commas too
+            Token::Comma if depth == 1 => {
+                if let [name, type_name, ..] = words.as_slice() {
+                    columns.push((name.to_string(), column_type(type_name, is_array)));
+                }
+                words.clear();
+                is_array = false;
+            }
+            // `integer[]` or `integer ARRAY` - but not in the default value (e.g. `DEFAULT '{}'::integer[]`)
+            Token::LBracket if depth == 1 && !has_default_value(&words) => is_array = true,
+            Token::Word(word) if depth == 1 => {
+                if word.value.eq_ignore_ascii_case("array") && !has_default_value(&words) {
+                    is_array = true;
+                }
+                words.push(word.value.as_str())
+            }
+            _ => {}
+        }
+    }
+
+    if let [name, type_name, ..] = words.as_slice() {
+        columns.push((name.to_string(), column_type(type_name, is_array)));
+    }
+
+    columns
+}
+
+fn has_default_value(words: &[&str]) -> bool {
+    words
+        .iter()
+        .any(|word| word.eq_ignore_ascii_case("default"))
+}
+
+/// name of the columns declared `NOT NULL` (or `PRIMARY KEY`) in a `CREATE TABLE` query
+pub fn get_not_null_column_names_from_create_query(tokens: &Vec<Token>) -> Vec<String> {
+    if !match_keyword_at_position(Create, &tokens, 0) {
+        return Vec::new();
+    }
+
+    let is_not_null = |words: &Vec<&str>| {
+        words.windows(2).any(|pair| {
+            (pair[0].eq_ignore_ascii_case("NOT") && pair[1].eq_ignore_ascii_case("NULL"))
+                || (pair[0].eq_ignore_ascii_case("PRIMARY") && pair[1].eq_ignore_ascii_case("KEY"))
+        })
+    };
+
+    let mut columns = vec![];
+    let mut words: Vec<&str> = vec![];
+    let mut depth = 0;
+
+    for token in tokens.iter().skip_while(|token| **token != Token::LParen) {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Token::Comma if depth == 1 => {
+                if words.len() > 1 && is_not_null(&words) {
+                    columns.push(words[0].to_string());
+                }
+                words.clear();
+            }
+            Token::Word(word) if depth == 1 => words.push(word.value.as_str()),
+            _ => {}
+        }
+    }
+
+    if words.len() > 1 && is_not_null(&words) {
+        columns.push(words[0].to_string());
+    }
+
+    columns
+}
+
 pub fn get_tokens_from_query_str(query: &str) -> Vec<Token> {
     // query by query
     let mut tokenizer = Tokenizer::new(query);
@@ -842,8 +1120,11 @@ pub fn trim_pre_whitespaces(tokens: Vec<Token>) -> Vec<Token> {
 #[cfg(test)]
 mod tests {
     use crate::postgres::{
-        get_column_names_from_insert_into_query, get_column_values_from_insert_into_query,
-        trim_pre_whitespaces, Token, Tokenizer, Whitespace,
+        get_column_names_from_copy_query, get_column_names_from_insert_into_query,
+        get_column_types_from_create_query, get_column_values_from_copy_row,
+        get_column_values_from_insert_into_query, get_not_null_column_names_from_create_query,
+        get_tokens_from_query_str, is_binary_copy_query, to_copy_row, trim_pre_whitespaces, Token,
+        Tokenizer, Whitespace,
     };
 
     #[test]
@@ -1062,4 +1343,121 @@ VALUES ('Romaric', true);
             ]
         );
     }
+
+    #[test]
+    fn test_get_column_names_from_copy_query() {
+        let q = r#"COPY public.categories (category_id, "categoryName", description) FROM stdin;"#;
+
+        let mut tokenizer = Tokenizer::new(q);
+        let tokens_result = tokenizer.tokenize();
+        assert_eq!(tokens_result.is_ok(), true);
+
+        let tokens = trim_pre_whitespaces(tokens_result.unwrap());
+        let column_names = get_column_names_from_copy_query(&tokens);
+
+        assert_eq!(
+            column_names,
+            vec!["category_id", "\"categoryName\"", "description"]
+        );
+    }
+
+    #[test]
+    fn test_get_column_values_from_copy_row() {
+        let row = r"1	Beverages	Soft drinks\tcoffees\nteas	\N	C:\\drinks";
+
+        let column_values = get_column_values_from_copy_row(row);
+
+        assert_eq!(
+            column_values,
+            vec![
+                Some("1".to_string()),
+                Some("Beverages".to_string()),
+                Some("Soft drinks\tcoffees\nteas".to_string()),
+                None,
+                Some(r"C:\drinks".to_string()),
+            ]
+        );
+
+        // the row is the same once serialized back
+        assert_eq!(to_copy_row(&column_values), row);
+    }
+
+    #[test]
+    fn test_is_binary_copy_query() {
+        let is_binary = |query: &str| is_binary_copy_query(&get_tokens_from_query_str(query));
+
+        assert!(is_binary(
+            "COPY public.categories (category_id, picture) FROM stdin WITH (FORMAT binary);"
+        ));
+        assert!(is_binary(
+            "COPY public.categories FROM stdin (FORMAT 'binary', FREEZE);"
+        ));
+        assert!(is_binary("COPY public.categories FROM stdin WITH BINARY;"));
+        assert!(is_binary("COPY BINARY public.categories FROM stdin;"));
+
+        assert!(!is_binary(
+            "COPY public.categories (category_id, picture) FROM stdin;"
+        ));
+        assert!(!is_binary("COPY public.binary (binary) FROM stdin;"));
+        assert!(!is_binary(r#"COPY "binary" FROM stdin;"#));
+        assert!(!is_binary(
+            "COPY public.categories FROM stdin WITH (FORMAT csv);"
+        ));
+        assert!(!is_binary("INSERT INTO public.binary (binary) VALUES (1);"));
+    }
+
+    #[test]
+    fn test_get_column_types_from_create_query() {
+        let q = r#"
+CREATE TABLE public.products (
+    product_id smallint NOT NULL,
+    product_name character varying(40) NOT NULL,
+    unit_price numeric(10,2),
+    discontinued boolean DEFAULT false,
+    "Active" bool,
+    tags character varying(20)[] DEFAULT '{}'::character varying[],
+    scores integer ARRAY,
+    flags boolean DEFAULT '{}'::boolean[]
+);"#;
+
+        let tokens = get_tokens_from_query_str(q);
+        assert_eq!(
+            get_column_types_from_create_query(&tokens),
+            vec![
+                ("product_id".to_string(), "smallint".to_string()),
+                ("product_name".to_string(), "character".to_string()),
+                ("unit_price".to_string(), "numeric".to_string()),
+                ("discontinued".to_string(), "boolean".to_string()),
+                ("\"Active\"".to_string(), "bool".to_string()),
+                ("tags".to_string(), "character[]".to_string()),
+                ("scores".to_string(), "integer[]".to_string()),
+                ("flags".to_string(), "boolean".to_string()),
+            ]
+        );
+
+        let tokens = get_tokens_from_query_str("INSERT INTO public.products (product_id) VALUES (1);");
+        assert!(get_column_types_from_create_query(&tokens).is_empty());
+    }
+
+    #[test]
+    fn test_get_not_null_column_names_from_create_query() {
+        let q = r#"
+CREATE TABLE public.orders (
+    order_id smallint PRIMARY KEY,
+    customer_id bpchar NOT NULL,
+    employee_id smallint,
+    ship_name character varying(40) DEFAULT 'n/a'::character varying NOT NULL,
+    ship_via smallint null
+);"#;
+
+        let tokens = get_tokens_from_query_str(q);
+        assert_eq!(
+            get_not_null_column_names_from_create_query(&tokens),
+            vec![
+                "order_id".to_string(),
+                "customer_id".to_string(),
+                "ship_name".to_string(),
+            ]
+        );
+    }
 }
@@ -1,10 +1,17 @@
+use crate::postgres::{is_binary_copy_query, Tokenizer};
 use crate::DumpFileError;
-use crate::DumpFileError::ReadError;
+use crate::DumpFileError::{
+    BinaryCopyFormat, InvalidCopyRow, InvalidStatement, ReadError, StatementTooLarge,
+};
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read};
 use std::str;
 
 const COMMENT_CHARS: &str = "--";
+/// line terminating the payload of a `COPY ... FROM stdin;` statement
+pub const COPY_PAYLOAD_TERMINATOR: &str = "\\.";
+/// size of the largest statement read from a dump - 1GB, the largest value PostgreSQL can store in a field
+pub const MAX_STATEMENT_BYTES: usize = 1024 * 1024 * 1024;
 
 pub enum ListQueryResult {
     Continue,
@@ -31,7 +38,25 @@ where
 
 /// read dump and callback query function with each valid query inside the dump
 pub fn list_sql_queries_from_dump_reader<R, F>(
+    dump_reader: BufReader<R>,
+    query: F,
+) -> Result<(), DumpFileError>
+where
+    R: Read,
+    F: FnMut(&str) -> ListQueryResult,
+{
+    list_sql_queries_from_dump_reader_with_max_statement_bytes(
+        dump_reader,
+        MAX_STATEMENT_BYTES,
+        query,
+    )
+}
+
+/// same as `list_sql_queries_from_dump_reader` - a statement (or a `COPY` payload row) larger than
+/// `max_statement_bytes` is not buffered, and fails with a `StatementTooLarge` error
+pub fn list_sql_queries_from_dump_reader_with_max_statement_bytes<R, F>(
     mut dump_reader: BufReader<R>,
+    max_statement_bytes: usize,
     mut query: F,
 ) -> Result<(), DumpFileError>
 where
@@ -41,14 +66,98 @@ where
     let mut count_empty_lines = 0;
     let mut buf_bytes: Vec<u8> = Vec::new();
     let mut line_buf_bytes: Vec<u8> = Vec::new();
+    let mut is_copy_payload = false;
+    // number of the lines fully read - to locate the invalid COPY payload rows
+    let mut read_lines = 0usize;
+    // number of the last line holding bytes - to locate the invalid statements
+    let mut last_line_number = 0usize;
 
     loop {
-        let bytes = dump_reader.read_until(b'\n', &mut line_buf_bytes);
+        if buf_bytes.len() > max_statement_bytes {
+            // the buffer has been flushed - what is left is a single incomplete statement
+            return Err(StatementTooLarge(max_statement_bytes));
+        }
+
+        // never read more than one byte past the limit - even from a line without end
+        let max_line_bytes = (max_statement_bytes + 1).saturating_sub(buf_bytes.len());
+        let bytes = (&mut dump_reader)
+            .take(max_line_bytes as u64)
+            .read_until(b'\n', &mut line_buf_bytes);
         let total_bytes = match bytes {
             Ok(bytes) => bytes,
             Err(err) => return Err(ReadError(err)),
         };
 
+        let line_number = read_lines + 1;
+        if total_bytes > 0 {
+            last_line_number = line_number;
+        }
+
+        if line_buf_bytes.last() == Some(&b'\n') {
+            read_lines += 1;
+        }
+
+        if is_copy_payload && line_buf_bytes.len() > max_statement_bytes {
+            return Err(StatementTooLarge(max_statement_bytes));
+        }
+
+        if is_copy_payload && total_bytes == 0 {
+            // EOF without the `\.` terminator - the dump is truncated
+            is_copy_payload = false;
+        }
+
+        if is_copy_payload {
+            // COPY payload rows are not SQL - each line is a row and is sent as it is
+            let line = trim_end_of_line(line_buf_bytes.as_slice());
+            if line == COPY_PAYLOAD_TERMINATOR.as_bytes() {
+                is_copy_payload = false;
+            }
+
+            let line = match str::from_utf8(line) {
+                Ok(line) => line,
+                // the row can't be sent as it is - and must not be lost
+                Err(_) => return Err(InvalidCopyRow(line_number)),
+            };
+
+            let query_res = query(line);
+
+            line_buf_bytes.clear();
+            count_empty_lines = 0;
+
+            match query_res {
+                ListQueryResult::Continue => continue,
+                ListQueryResult::Break => break,
+            }
+        }
+
+        if is_binary_copy_from_stdin_line(line_buf_bytes.as_slice())
+            && buf_bytes.iter().all(|byte| byte.is_ascii_whitespace())
+        {
+            // the payload is not made of lines - it can't be split (and transformed) as a text payload
+            let line = String::from_utf8_lossy(trim_end_of_line(line_buf_bytes.as_slice()));
+            return Err(BinaryCopyFormat(line.trim().to_string()));
+        }
+
+        if is_copy_from_stdin_line(line_buf_bytes.as_slice())
+            && buf_bytes.iter().all(|byte| byte.is_ascii_whitespace())
+        {
+            // the next lines are the COPY payload rows
+            let query_res = match str::from_utf8(trim_end_of_line(line_buf_bytes.as_slice())) {
+                Ok(line) => query(line),
+                Err(_) => ListQueryResult::Continue,
+            };
+
+            buf_bytes.clear();
+            line_buf_bytes.clear();
+            count_empty_lines = 0;
+            is_copy_payload = true;
+
+            match query_res {
+                ListQueryResult::Continue => continue,
+                ListQueryResult::Break => break,
+            }
+        }
+
         let last_real_char_idx = if buf_bytes.len() > 1 {
             buf_bytes.len() - 2
         } else if buf_bytes.len() == 1 {
@@ -67,26 +176,41 @@ where
 
         buf_bytes.append(&mut line_buf_bytes);
 
-        if total_bytes <= 1 || is_last_line_buf_bytes_by_end_of_query {
+        // the buffer is also flushed once it reaches the size limit - to only keep the last statement,
+        // which is not complete yet
+        let is_buf_bytes_full = buf_bytes.len() > max_statement_bytes;
+
+        if total_bytes <= 1 || is_last_line_buf_bytes_by_end_of_query || is_buf_bytes_full {
             let mut buf_bytes_to_keep: Vec<u8> = Vec::new();
 
             if buf_bytes.len() > 1 {
-                let query_str = match str::from_utf8(buf_bytes.as_slice()) {
-                    Ok(t) => t,
-                    Err(e) => continue
+                let valid_up_to = match str::from_utf8(buf_bytes.as_slice()) {
+                    Ok(_) => buf_bytes.len(),
+                    // a read stopped at the size limit can end in the middle of a character
+                    Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                    // the statement can't be sent as it is - and must not be lost
+                    Err(_) => return Err(InvalidStatement(last_line_number)),
                 };
 
+                let query_str = str::from_utf8(&buf_bytes[..valid_up_to]).unwrap();
+
                 for statement in list_statements(query_str) {
+                    if let ListQueryResult::Break = query_res {
+                        break;
+                    }
+
                     match statement {
                         Statement::NewLine => {
-                            query("\n");
+                            query_res = query("\n");
                         }
                         Statement::CommentLine(comment_statement) => {
-                            query(comment_statement.statement);
+                            query_res = query(comment_statement.statement);
                         }
                         Statement::Query(sql_statement) => {
-                            if sql_statement.valid {
-                                query(sql_statement.statement);
+                            if sql_statement.valid
+                                && (!is_buf_bytes_full || sql_statement.statement.ends_with(';'))
+                            {
+                                query_res = query(sql_statement.statement);
                             } else {
                                 // the query is not complete, so keep it for the next iteration
                                 buf_bytes_to_keep
@@ -95,6 +219,8 @@ where
                         }
                     }
                 }
+
+                buf_bytes_to_keep.extend_from_slice(&buf_bytes[valid_up_to..]);
             }
 
             let _ = buf_bytes.clear();
@@ -120,6 +246,51 @@ where
     Ok(())
 }
 
+/// check if the line is a `COPY <table> (<columns>) FROM stdin ...;` statement with a payload in binary format
+fn is_binary_copy_from_stdin_line(line: &[u8]) -> bool {
+    let line = match str::from_utf8(trim_end_of_line(line)) {
+        Ok(line) => line.trim(),
+        Err(_) => return false,
+    };
+
+    let is_copy_from_stdin = match line.get(..5) {
+        Some(prefix) => prefix.eq_ignore_ascii_case("COPY ") && line.ends_with(';'),
+        None => false,
+    };
+
+    if !is_copy_from_stdin {
+        return false;
+    }
+
+    match Tokenizer::new(line).tokenize() {
+        Ok(tokens) => is_binary_copy_query(&tokens),
+        Err(_) => false,
+    }
+}
+
+/// check if the line is a `COPY <table> (<columns>) FROM stdin;` statement
+fn is_copy_from_stdin_line(line: &[u8]) -> bool {
+    let line = match str::from_utf8(trim_end_of_line(line)) {
+        Ok(line) => line.trim(),
+        Err(_) => return false,
+    };
+
+    match line.get(..5) {
+        Some(prefix) => {
+            prefix.eq_ignore_ascii_case("COPY ") && line.to_uppercase().ends_with("FROM STDIN;")
+        }
+        None => false,
+    }
+}
+
+/// remove the `\n` at the end of the line
+fn trim_end_of_line(line: &[u8]) -> &[u8] {
+    match line {
+        [rest @ .., b'\n'] => rest,
+        line => line,
+    }
+}
+
 /// Decodes a hex string to a byte `Vec`.
 /// #### example:
 ///
@@ -304,9 +475,12 @@ fn list_statements(query: &str) -> Vec<Statement> {
 #[cfg(test)]
 mod tests {
     use crate::utils::{
-        list_sql_queries_from_dump_reader, list_statements, ListQueryResult, Statement,
+        list_sql_queries_from_dump_reader,
+        list_sql_queries_from_dump_reader_with_max_statement_bytes, list_statements,
+        ListQueryResult, Statement,
     };
-    use std::io::BufReader;
+    use crate::DumpFileError;
+    use std::io::{BufReader, Read};
 
     #[test]
     fn check_list_sql_queries_from_dump_reader() {
@@ -320,14 +494,245 @@ Etiam augue augue, bibendum et molestie non, finibus non nulla. Etiam quis rhonc
 
         let mut queries = vec![];
 
-        list_sql_queries_from_dump_reader(reader, |query| {
+        let result = list_sql_queries_from_dump_reader(reader, |query| {
             queries.push(query.to_string());
             ListQueryResult::Continue
         });
 
+        assert!(result.is_ok());
         assert!(queries.len() > 0);
     }
 
+    #[test]
+    fn check_list_sql_queries_from_dump_reader_with_copy_payload() {
+        let r = r#"CREATE TABLE public.categories (
+    category_id smallint NOT NULL,
+    category_name character varying(15) NOT NULL
+);
+
+COPY public.categories (category_id, category_name) FROM stdin;
+1	Beverages; coffees
+2	Grains (and 'cereals'
+\.
+
+ALTER TABLE ONLY public.categories ADD CONSTRAINT pk_categories PRIMARY KEY (category_id);
+"#
+        .as_bytes();
+        let reader = BufReader::new(r);
+
+        let mut queries = vec![];
+
+        let result = list_sql_queries_from_dump_reader(reader, |query| {
+            queries.push(query.to_string());
+            ListQueryResult::Continue
+        });
+
+        assert!(result.is_ok());
+
+        // each row of the COPY payload is a query, sent as it is
+        let copy_idx = queries
+            .iter()
+            .position(|query| query.starts_with("COPY public.categories"))
+            .unwrap();
+
+        assert_eq!(queries[copy_idx + 1], "1\tBeverages; coffees");
+        assert_eq!(queries[copy_idx + 2], "2\tGrains (and 'cereals'");
+        assert_eq!(queries[copy_idx + 3], "\\.");
+        assert!(queries
+            .iter()
+            .any(|query| query.starts_with("ALTER TABLE ONLY public.categories")));
+    }
+
+    #[test]
+    fn check_list_sql_queries_from_dump_reader_with_invalid_copy_row() {
+        let r = b"COPY public.categories (category_id, category_name) FROM stdin;
+1\tBeverages
+2\tGrains \xe9
+\\.
+"
+        .to_vec();
+
+        let mut queries = vec![];
+
+        let result = list_sql_queries_from_dump_reader(BufReader::new(r.as_slice()), |query| {
+            queries.push(query.to_string());
+            ListQueryResult::Continue
+        });
+
+        // the row is not dropped silently
+        assert!(matches!(result, Err(DumpFileError::InvalidCopyRow(3))));
+        assert_eq!(queries.last().unwrap(), "1\tBeverages");
+
+        let err = std::io::Error::from(DumpFileError::InvalidCopyRow(3));
+        assert!(err.to_string().contains("line 3"));
+    }
+
+    #[test]
+    fn check_list_sql_queries_from_dump_reader_with_invalid_statement_at_the_end() {
+        let r = b"INSERT INTO public.categories (category_id, category_name) VALUES (1, 'Beverages');
+INSERT INTO public.categories (category_id, category_name) VALUES (2, 'Grains \xe9');
+"
+        .to_vec();
+
+        let mut queries = vec![];
+
+        let result = list_sql_queries_from_dump_reader(BufReader::new(r.as_slice()), |query| {
+            queries.push(query.to_string());
+            ListQueryResult::Continue
+        });
+
+        // the statement is not dropped silently - and the end of the dump is reached
+        assert!(matches!(result, Err(DumpFileError::InvalidStatement(2))));
+        assert!(queries.iter().all(|query| !query.contains("Grains")));
+
+        let err = std::io::Error::from(DumpFileError::InvalidStatement(2));
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn check_list_sql_queries_from_dump_reader_with_break() {
+        let r = r#"COPY public.categories (category_id, category_name) FROM stdin;
+1	Beverages
+2	Grains
+\.
+
+INSERT INTO public.categories (category_id, category_name) VALUES (3, 'Dairy');
+INSERT INTO public.categories (category_id, category_name) VALUES (4, 'Meat');
+"#;
+
+        // a break within the COPY payload
+        let mut queries = vec![];
+        let result = list_sql_queries_from_dump_reader(BufReader::new(r.as_bytes()), |query| {
+            queries.push(query.to_string());
+            match query.starts_with("1\t") {
+                true => ListQueryResult::Break,
+                false => ListQueryResult::Continue,
+            }
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(queries.last().unwrap(), "1\tBeverages");
+
+        // and after a statement
+        let mut queries = vec![];
+        let result = list_sql_queries_from_dump_reader(BufReader::new(r.as_bytes()), |query| {
+            queries.push(query.to_string());
+            match query.contains("'Dairy'") {
+                true => ListQueryResult::Break,
+                false => ListQueryResult::Continue,
+            }
+        });
+
+        assert!(result.is_ok());
+        assert!(queries.last().unwrap().contains("'Dairy'"));
+        assert!(!queries.iter().any(|query| query.contains("'Meat'")));
+    }
+
+    #[test]
+    fn check_list_sql_queries_from_dump_reader_with_max_statement_bytes() {
+        let insert = "INSERT INTO public.users (id, name) VALUES (1, 'a'), (2, 'b'), (3, 'c');\n";
+        let r = format!("{}{}", insert, insert.repeat(10).replace(";\n", ",\n"));
+
+        // the statements under the limit are listed
+        let mut queries = vec![];
+        let result = list_sql_queries_from_dump_reader_with_max_statement_bytes(
+            BufReader::new(r.as_bytes()),
+            insert.len(),
+            |query| {
+                if !query.trim().is_empty() {
+                    queries.push(query.to_string());
+                }
+                ListQueryResult::Continue
+            },
+        );
+
+        assert!(matches!(result, Err(DumpFileError::StatementTooLarge(_))));
+        assert_eq!(queries, vec![insert.trim_end().to_string()]);
+
+        // the buffer holding several statements is flushed when full - they are all listed
+        let r = insert.repeat(10);
+        let mut queries = vec![];
+        let result = list_sql_queries_from_dump_reader_with_max_statement_bytes(
+            BufReader::new(r.as_bytes()),
+            insert.len() * 2,
+            |query| {
+                if !query.trim().is_empty() {
+                    queries.push(query.trim().to_string());
+                }
+                ListQueryResult::Continue
+            },
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(queries, vec![insert.trim_end().to_string(); 10]);
+
+        // a line without end is not buffered beyond the limit
+        let endless_line = "INSERT INTO public.users (id) VALUES (1)"
+            .as_bytes()
+            .chain(std::io::repeat(b' '));
+        let result = list_sql_queries_from_dump_reader_with_max_statement_bytes(
+            BufReader::new(endless_line),
+            1024,
+            |_| ListQueryResult::Continue,
+        );
+
+        match result {
+            Err(DumpFileError::StatementTooLarge(max_bytes)) => assert_eq!(max_bytes, 1024),
+            _ => panic!("the statement must be too large"),
+        }
+
+        // and a COPY payload row neither
+        let endless_row = "COPY public.users (id) FROM stdin;\n1\t"
+            .as_bytes()
+            .chain(std::io::repeat(b'a'));
+        let result = list_sql_queries_from_dump_reader_with_max_statement_bytes(
+            BufReader::new(endless_row),
+            1024,
+            |_| ListQueryResult::Continue,
+        );
+
+        assert!(matches!(result, Err(DumpFileError::StatementTooLarge(_))));
+    }
+
+    #[test]
+    fn check_list_sql_queries_from_dump_reader_with_binary_copy_payload() {
+        let mut r = b"CREATE TABLE public.categories (
+    category_id smallint NOT NULL
+);
+
+COPY public.categories (category_id) FROM stdin WITH (FORMAT binary);
+"
+        .to_vec();
+        r.extend_from_slice(b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0\0\x01\0\0\0\x02\0\x01\xff\xff");
+        let reader = BufReader::new(r.as_slice());
+
+        let mut queries = vec![];
+
+        let result = list_sql_queries_from_dump_reader(reader, |query| {
+            queries.push(query.to_string());
+            ListQueryResult::Continue
+        });
+
+        // the binary payload must not be read as SQL queries
+        match result {
+            Err(DumpFileError::BinaryCopyFormat(statement)) => assert_eq!(
+                statement,
+                "COPY public.categories (category_id) FROM stdin WITH (FORMAT binary);"
+            ),
+            _ => panic!("a binary COPY payload must be reported"),
+        }
+
+        let err = std::io::Error::from(DumpFileError::BinaryCopyFormat(
+            "COPY public.categories FROM stdin WITH BINARY;".to_string(),
+        ));
+        assert!(err.to_string().contains("binary format are not supported"));
+
+        assert!(queries
+            .iter()
+            .any(|query| query.contains("CREATE TABLE public.categories")));
+        assert!(!queries.iter().any(|query| query.contains("PGCOPY")));
+    }
+
     #[test]
     fn check_list_sql_statements_with_multiple_lines() {
         let s = list_statements(
@@ -2,12 +2,14 @@ use std::path::PathBuf;
 
 use clap::{Args, Parser, Subcommand};
 
+use crate::logger::LogFormat;
+
 /// Replibyte is a tool to seed your databases with your production data while keeping sensitive data safe, just pass `-h`
 #[derive(Parser, Debug)]
 #[clap(version, about, long_about = None)]
 #[clap(propagate_version = true)]
 pub struct CLI {
-    /// Replibyte configuration file
+    /// Replibyte configuration file (a file path, an http(s) URL or `-` for stdin)
     #[clap(short, long, parse(from_os_str), value_name = "configuration file")]
     pub config: PathBuf,
     #[clap(subcommand)]
@@ -15,6 +17,12 @@ pub struct CLI {
     /// disable telemetry
     #[clap(short, long)]
     pub no_telemetry: bool,
+    /// disable the progress bar
+    #[clap(long)]
+    pub no_progress: bool,
+    /// log lines format
+    #[clap(long, arg_enum, default_value = "text", value_name = "text | json")]
+    pub log_format: LogFormat,
 }
 
 /// sub commands
@@ -29,13 +37,16 @@ pub enum SubCommand {
     /// all transformer commands
     #[clap(subcommand)]
     Transformer(TransformerCommand),
+    /// all datastore commands
+    #[clap(subcommand)]
+    Datastore(DatastoreCommand),
 }
 
 /// all dump commands
 #[derive(Subcommand, Debug)]
 pub enum DumpCommand {
     /// list available dumps
-    List,
+    List(DumpListArgs),
     /// launch dump -- use `-h` to show all the options
     Create(DumpCreateArgs),
     /// all restore commands
@@ -43,6 +54,14 @@ pub enum DumpCommand {
     Restore(RestoreCommand),
     /// delete a dump from the defined datastore
     Delete(DumpDeleteArgs),
+    /// stream the source into the configured destination without creating a dump
+    Pipe,
+    /// show the number of rows and bytes of every table of a dump
+    Stats(DumpStatsArgs),
+    /// encrypt again a dump created with the legacy encryption (fixed nonce)
+    Reencrypt(DumpReencryptArgs),
+    /// copy a dump into the datastore of another configuration file - e.g. from a staging bucket to a production one
+    Copy(DumpCopyArgs),
 }
 
 /// all transformer commands
@@ -50,6 +69,15 @@ pub enum DumpCommand {
 pub enum TransformerCommand {
     /// list available transformers
     List,
+    /// apply a transformer on each line of a file (or stdin) and print the results - no database is needed
+    Test(TransformerTestArgs),
+}
+
+/// all datastore commands
+#[derive(Subcommand, Debug)]
+pub enum DatastoreCommand {
+    /// check the index file against the stored objects - report the missing and orphaned objects without changing anything
+    Fsck,
 }
 
 /// all restore commands
@@ -70,6 +98,35 @@ pub struct RestoreArgs {
     /// stream output on stdout
     #[clap(short, long)]
     pub output: bool,
+    /// write the SQL to restore into a file instead of the destination - column mappings are applied
+    #[clap(
+        long,
+        parse(from_os_str),
+        value_name = "file",
+        conflicts_with = "output"
+    )]
+    pub output_file: Option<PathBuf>,
+    /// truncate the tables of the dump in the destination before restoring it
+    #[clap(long, conflicts_with_all = &["output", "output-file"])]
+    pub truncate_target: bool,
+    /// rename a column of the dump before restoring it (PostgreSQL only) - can be repeated. Example: `--map-column public.users.name:full_name`
+    #[clap(long = "map-column", value_name = "[database.]table.column:new_column", parse(try_from_str = parse_map_column))]
+    pub map_columns: Vec<(String, String)>,
+    /// drop a column of the dump before restoring it (PostgreSQL only) - can be repeated. Example: `--drop-column public.users.legacy_id`
+    #[clap(long = "drop-column", value_name = "[database.]table.column")]
+    pub drop_columns: Vec<String>,
+    /// check that the dump can be restored (PostgreSQL only) - every statement is parsed and must fill an existing table, nothing is written
+    #[clap(long, conflicts_with_all = &["output", "output-file", "truncate-target"])]
+    pub dry_run: bool,
+    /// restore the `INSERT INTO` rows as `COPY ... FROM stdin;` payloads (PostgreSQL only) - the consecutive rows of a table are loaded by a single statement
+    #[clap(long, conflicts_with_all = &["output", "output-file", "dry-run", "max-errors"])]
+    pub use_copy: bool,
+    /// number of connections restoring the dump (PostgreSQL only) - the rows of independent tables are loaded in parallel
+    #[clap(long, value_name = "connections", default_value = "1", conflicts_with_all = &["output", "output-file", "dry-run"])]
+    pub concurrency: usize,
+    /// abort the restore at the Nth failed statement (PostgreSQL only) - the failed statements are skipped until then and reported at the end
+    #[clap(long, value_name = "N", parse(try_from_str = parse_max_errors), conflicts_with_all = &["output", "output-file", "dry-run"])]
+    pub max_errors: Option<usize>,
 }
 
 /// restore dump in a local Docker container
@@ -90,7 +147,7 @@ pub struct RestoreLocalArgs {
     /// Remove the Docker container on Ctrl-c
     #[clap(short, long)]
     pub remove: bool,
-    /// Docker image type
+    /// Docker image type - the type of the dump by default
     #[clap(short, long, value_name = "[postgresql | mysql | mongodb]")]
     pub image: Option<String>,
 }
@@ -110,6 +167,125 @@ pub struct DumpCreateArgs {
     /// dump name
     #[clap(short, long)]
     pub name: Option<String>,
+    /// append to an existing dump instead of creating a new one - use `dump list` command to list all dumps available
+    #[clap(long, value_name = "dump name", conflicts_with = "name")]
+    pub append_to: Option<String>,
+    /// replace the dump with the same name if it already exists
+    #[clap(long, conflicts_with = "append-to")]
+    pub overwrite: bool,
+    /// label to attach to the dump - can be repeated. Example: `--label env=staging`
+    #[clap(long = "label", value_name = "key=value", parse(try_from_str = parse_label))]
+    pub labels: Vec<(String, String)>,
+    /// format of the dump (MongoDB only) - `extended-json` writes one document by line as MongoDB Extended JSON
+    #[clap(long, value_name = "[archive | extended-json]", possible_values = &["archive", "extended-json"])]
+    pub format: Option<String>,
+    /// only keep the schema and data statements - the comments and the session settings not needed to restore the dump are dropped (PostgreSQL only)
+    #[clap(long)]
+    pub minimal: bool,
+    /// number of threads visiting the tables of the database subset - the rows referencing different parent rows are visited in parallel
+    #[clap(long, value_name = "threads")]
+    pub parallel_tables: Option<usize>,
+    /// minimum size of a dump part in MB - the smaller parts are merged, except the last one
+    #[clap(long, value_name = "MB")]
+    pub min_part_size: Option<usize>,
+    /// maximum number of parts of the dump - the last part holds the rest of the dump and is kept in memory until the end
+    #[clap(long, value_name = "parts")]
+    pub max_parts: Option<u16>,
+    /// only dump the schema - the tables, indexes, sequences... without any row (PostgreSQL and MySQL only)
+    #[clap(long, conflicts_with = "data-only")]
+    pub schema_only: bool,
+    /// only dump the rows - the dump is restored into an existing schema (PostgreSQL and MySQL only)
+    #[clap(long)]
+    pub data_only: bool,
+    /// abort the dump on the first transformation error (PostgreSQL only)
+    #[clap(long, conflicts_with = "collect-errors")]
+    pub fail_fast: bool,
+    /// keep the values the transformations fail on and report the errors at the end of the dump - by default
+    #[clap(long)]
+    pub collect_errors: bool,
+    /// drop the large objects (`lo_*`) of the database - their definitions and their data (PostgreSQL only)
+    #[clap(long)]
+    pub skip_large_objects: bool,
+    /// skip the rows of the tables unchanged since this dump - the dump only holds the changed tables (PostgreSQL only)
+    #[clap(long, value_name = "dump name", conflicts_with_all = &["append-to", "schema-only"])]
+    pub since_dump: Option<String>,
+}
+
+/// list dumps
+#[derive(Args, Debug)]
+pub struct DumpListArgs {
+    /// only list the dumps with this label - can be repeated. Example: `--label env=staging`
+    #[clap(long = "label", value_name = "key=value", parse(try_from_str = parse_label))]
+    pub labels: Vec<(String, String)>,
+}
+
+/// show the statistics of a dump
+#[derive(Args, Debug)]
+pub struct DumpStatsArgs {
+    /// dump to read -- set `latest` or `<dump name>` - use `dump list` command to list all dumps available
+    #[clap(short, long, value_name = "[latest | dump name]")]
+    pub name: String,
+}
+
+/// test a transformer
+#[derive(Args, Debug)]
+pub struct TransformerTestArgs {
+    /// transformer to apply with its default options - use `transformer list` command to list all transformers available
+    #[clap(long, value_name = "transformer name")]
+    pub id: String,
+    /// file with a value per line - stdin if it is not set or `-`
+    #[clap(short, long, parse(from_os_str), value_name = "input file")]
+    pub input: Option<PathBuf>,
+}
+
+/// re-encrypt a legacy dump
+#[derive(Args, Debug)]
+pub struct DumpReencryptArgs {
+    /// dump to re-encrypt - use `dump list` command to list all dumps available
+    #[clap(short, long, value_name = "dump name")]
+    pub name: String,
+}
+
+/// copy a dump into another datastore
+#[derive(Args, Debug)]
+pub struct DumpCopyArgs {
+    /// dump to copy - use `dump list` command to list all dumps available
+    #[clap(short, long, value_name = "dump name")]
+    pub name: String,
+    /// configuration file of the target datastore - only its `datastore` section is used
+    #[clap(long, parse(from_os_str), value_name = "configuration file")]
+    pub to_datastore: PathBuf,
+}
+
+/// parse a `key=value` label
+fn parse_label(label: &str) -> Result<(String, String), String> {
+    match label.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("invalid label '{}' - use `key=value`", label)),
+    }
+}
+
+/// parse a `table.column:new_column` column mapping
+fn parse_map_column(map_column: &str) -> Result<(String, String), String> {
+    match map_column.split_once(':') {
+        Some((column, new_column)) if !column.is_empty() && !new_column.is_empty() => {
+            Ok((column.to_string(), new_column.to_string()))
+        }
+        _ => Err(format!(
+            "invalid column mapping '{}' - use `table.column:new_column`",
+            map_column
+        )),
+    }
+}
+
+fn parse_max_errors(max_errors: &str) -> Result<usize, String> {
+    match max_errors.parse::<usize>() {
+        Ok(max_errors) if max_errors > 0 => Ok(max_errors),
+        _ => Err(format!(
+            "invalid maximum number of errors '{}' - use a number greater than 0",
+            max_errors
+        )),
+    }
 }
 
 #[derive(Args, Debug)]
@@ -131,4 +307,8 @@ pub struct DumpDeleteArgs {
 pub enum SourceCommand {
     /// Show the database schema. When used with MongoDB, the schema will be probabilistic and returned as a JSON document
     Schema,
+    /// Show the number of rows by table the configured database subset would produce, without creating a dump (PostgreSQL only)
+    SubsetEstimate,
+    /// Sample the values of the columns and print the transformers of the ones looking like personal data (emails, phone numbers, credit cards) (PostgreSQL only)
+    DetectPii,
 }
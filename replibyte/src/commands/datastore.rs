@@ -0,0 +1,196 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Error;
+
+use crate::datastore::local_disk::FORMAT_VERSION_FILE_NAME;
+use crate::datastore::{part_number, Datastore, INDEX_FILE_NAME, MANIFEST_FILE_NAME};
+use crate::utils::table;
+
+/// objects of the datastore which do not match its index file
+#[derive(Debug, Default, PartialEq)]
+pub struct Inconsistencies {
+    // parts of the dumps of the index file which are not stored
+    pub missing: Vec<String>,
+    // stored objects which do not belong to any dump of the index file
+    pub orphaned: Vec<String>,
+}
+
+impl Inconsistencies {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.orphaned.is_empty()
+    }
+}
+
+/// cross-reference the dumps of the index file with the stored objects - nothing is modified.
+/// The parts of a dump are numbered from 1 without gap.
+pub fn find_inconsistencies(datastore: &dyn Datastore) -> Result<Inconsistencies, Error> {
+    let index_file = datastore.index_file()?;
+    let objects = datastore.list_all_objects()?;
+
+    // directories with their own index file belong to another namespace sharing the datastore
+    let namespaces = objects
+        .iter()
+        .filter_map(|key| key.strip_suffix(format!("/{}", INDEX_FILE_NAME).as_str()))
+        .map(|namespace| format!("{}/", namespace))
+        .collect::<Vec<_>>();
+
+    let mut parts_by_dump = index_file
+        .dumps
+        .iter()
+        .map(|dump| (dump.directory_name.as_str(), BTreeSet::new()))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut inconsistencies = Inconsistencies::default();
+
+    for key in &objects {
+        if key == INDEX_FILE_NAME
+            || key == FORMAT_VERSION_FILE_NAME
+            || namespaces
+                .iter()
+                .any(|namespace| key.starts_with(namespace.as_str()))
+        {
+            continue;
+        }
+
+        // the manifest of a dump is stored next to its parts
+        if let Some((dump_name, MANIFEST_FILE_NAME)) = key.split_once('/') {
+            if parts_by_dump.contains_key(dump_name) {
+                continue;
+            }
+        }
+
+        let part = key
+            .split_once('/')
+            .and_then(|(dump_name, file_name)| Some((dump_name, part_number(file_name)?)));
+
+        match part.and_then(|(dump_name, part)| Some((parts_by_dump.get_mut(dump_name)?, part))) {
+            Some((parts, part)) => {
+                let _ = parts.insert(part);
+            }
+            None => inconsistencies.orphaned.push(key.clone()),
+        }
+    }
+
+    for dump in &index_file.dumps {
+        let parts = &parts_by_dump[dump.directory_name.as_str()];
+
+        // a dump with some data has one part at least
+        let last_part = match parts.iter().last() {
+            Some(last_part) => *last_part,
+            None if dump.size > 0 => 1,
+            None => 0,
+        };
+
+        for part in 1..=last_part {
+            if !parts.contains(&part) {
+                inconsistencies
+                    .missing
+                    .push(format!("{}/{}.dump", dump.directory_name, part));
+            }
+        }
+    }
+
+    Ok(inconsistencies)
+}
+
+/// report the missing and orphaned objects of the datastore - an error is returned if there are some
+pub fn fsck(datastore: Box<dyn Datastore>) -> anyhow::Result<()> {
+    let inconsistencies = find_inconsistencies(datastore.as_ref())?;
+
+    if inconsistencies.is_empty() {
+        println!("The index file matches the stored objects");
+        return Ok(());
+    }
+
+    let mut table = table();
+    table.set_titles(row!["object", "issue"]);
+
+    for key in &inconsistencies.missing {
+        table.add_row(row![key, "missing - the dump is in the index file"]);
+    }
+
+    for key in &inconsistencies.orphaned {
+        table.add_row(row![key, "orphaned - not in the index file"]);
+    }
+
+    let _ = table.printstd();
+
+    Err(anyhow::anyhow!(
+        "the datastore has {} missing and {} orphaned objects",
+        inconsistencies.missing.len(),
+        inconsistencies.orphaned.len()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, remove_file, write};
+
+    use tempfile::tempdir;
+
+    use crate::commands::datastore::find_inconsistencies;
+    use crate::connector::Connector;
+    use crate::datastore::local_disk::LocalDisk;
+    use crate::datastore::Datastore;
+
+    #[test]
+    fn find_missing_and_orphaned_objects() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let path = dir.path();
+
+        let mut local_disk = LocalDisk::new(path.to_str().unwrap());
+        local_disk.init().expect("local_disk init failed");
+
+        for name in ["dump-1", "dump-2"] {
+            local_disk.set_dump_name(name.to_string());
+            for part in 1..=3 {
+                local_disk
+                    .write(part, b"INSERT INTO t VALUES (1);".to_vec())
+                    .unwrap();
+            }
+        }
+
+        // the manifest of a dump is not orphaned
+        write(path.join("dump-1").join("manifest.json"), b"{}").unwrap();
+
+        let inconsistencies = find_inconsistencies(&local_disk).unwrap();
+        assert!(inconsistencies.is_empty());
+
+        // a part deleted by hand, a dump written without index entry and a leftover file
+        remove_file(path.join("dump-1").join("2.dump")).unwrap();
+        create_dir_all(path.join("dump-3")).unwrap();
+        write(path.join("dump-3").join("1.dump"), b"data").unwrap();
+        write(path.join("dump-3").join("manifest.json"), b"{}").unwrap();
+        write(path.join("dump-2").join("notes.txt"), b"notes").unwrap();
+
+        // another namespace sharing the same directory is ignored
+        create_dir_all(path.join("staging").join("dump-9")).unwrap();
+        write(path.join("staging").join("metadata.json"), b"{}").unwrap();
+        write(path.join("staging").join("dump-9").join("1.dump"), b"data").unwrap();
+
+        let inconsistencies = find_inconsistencies(&local_disk).unwrap();
+        assert_eq!(inconsistencies.missing, vec!["dump-1/2.dump".to_string()]);
+        assert_eq!(
+            inconsistencies.orphaned,
+            vec![
+                "dump-2/notes.txt".to_string(),
+                "dump-3/1.dump".to_string(),
+                "dump-3/manifest.json".to_string()
+            ]
+        );
+
+        // every part of a dump is missing
+        std::fs::remove_dir_all(path.join("dump-2")).unwrap();
+        let inconsistencies = find_inconsistencies(&local_disk).unwrap();
+        assert_eq!(
+            inconsistencies.missing,
+            vec!["dump-1/2.dump".to_string(), "dump-2/1.dump".to_string()]
+        );
+        assert_eq!(
+            inconsistencies.orphaned,
+            vec![
+                "dump-3/1.dump".to_string(),
+                "dump-3/manifest.json".to_string()
+            ]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+pub mod datastore;
 pub mod dump;
 pub mod source;
 pub mod transformer;
@@ -1,10 +1,18 @@
 use std::io::{Error, ErrorKind};
 
 use crate::config::{Config, ConnectionUri};
+use crate::connector::Connector;
 use crate::source::Explain;
 use crate::source::mongodb::MongoDB;
 use crate::source::mysql::Mysql;
-use crate::source::postgres::Postgres;
+use crate::source::pii_detector::{to_transformers_config, PiiDetector};
+use crate::source::postgres::{insert_into_columns, Postgres};
+use crate::source::{Source, SourceOptions};
+use crate::transformer::TransformerMetrics;
+use crate::utils::table;
+
+/// number of values sampled by column to detect personal data
+const PII_DETECTION_SAMPLES: usize = 1000;
 
 /// show the database schema
 pub fn schema(config: Config) -> anyhow::Result<()> {
@@ -54,3 +62,200 @@ pub fn schema(config: Config) -> anyhow::Result<()> {
         }
     }
 }
+
+/// show the number of rows by table the database subset would produce
+pub fn subset_estimate(config: Config) -> anyhow::Result<()> {
+    let source = match config.source {
+        Some(source) => source,
+        None => {
+            return Err(anyhow::Error::from(Error::new(
+                ErrorKind::Other,
+                "missing <source> object in the configuration file",
+            )));
+        }
+    };
+
+    let subset_config = match &source.database_subset {
+        Some(subset_config) => subset_config,
+        None => {
+            return Err(anyhow::Error::from(Error::new(
+                ErrorKind::Other,
+                "missing <source.database_subset> object in the configuration file",
+            )));
+        }
+    };
+
+    let estimate = match source.connection_uri()? {
+        ConnectionUri::Postgres(host, port, username, password, database) => {
+            let mut postgres = Postgres::new(
+                host.as_str(),
+                port,
+                database.as_str(),
+                username.as_str(),
+                password.as_str(),
+            );
+
+            postgres.set_privileges(source.privileges.unwrap_or(true));
+            postgres.set_owner(source.owner.unwrap_or(false));
+            postgres.set_use_copy(source.use_copy.unwrap_or(false));
+            postgres.set_extra_args(source.extra_args.clone().unwrap_or_default());
+
+            // the rows are read as they are in the source
+            let transformers = vec![];
+            let empty_skip_config = vec![];
+            let empty_only_tables_config = vec![];
+            let empty_exclude_schemas_config = vec![];
+            let transformer_metrics = TransformerMetrics::default();
+
+            let options = SourceOptions {
+                transformers: &transformers,
+                skip_config: source.skip.as_ref().unwrap_or(&empty_skip_config),
+                database_subset: &source.database_subset,
+                only_tables: source
+                    .only_tables
+                    .as_ref()
+                    .unwrap_or(&empty_only_tables_config),
+                exclude_schemas: source
+                    .exclude_schemas
+                    .as_ref()
+                    .unwrap_or(&empty_exclude_schemas_config),
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &transformer_metrics,
+                sort_rows: &vec![],
+                max_cpu_percent: source.max_cpu_percent()?,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            postgres.subset_estimate(&options)?
+        }
+        ConnectionUri::MongoDB(uri, database) => {
+            let mongodb = MongoDB::new(uri.as_str(), database.as_str());
+
+            mongodb.subset_estimate(subset_config)?
+        }
+        ConnectionUri::Mysql(host, port, username, password, database) => {
+            let mysql = Mysql::new(
+                host.as_str(),
+                port,
+                database.as_str(),
+                username.as_str(),
+                password.as_str(),
+            );
+
+            mysql.subset_estimate(subset_config)?
+        }
+    };
+
+    let mut estimate = estimate.into_iter().collect::<Vec<_>>();
+    estimate.sort();
+
+    let mut table = table();
+    table.set_titles(row!["table", "rows"]);
+
+    for (table_name, rows) in estimate {
+        table.add_row(row![table_name, rows]);
+    }
+
+    let _ = table.printstd();
+
+    Ok(())
+}
+
+/// sample the values of the columns and print the transformers of the ones looking like personal data
+pub fn detect_pii(config: Config) -> anyhow::Result<()> {
+    let source = match config.source {
+        Some(source) => source,
+        None => {
+            return Err(anyhow::Error::from(Error::new(
+                ErrorKind::Other,
+                "missing <source> object in the configuration file",
+            )));
+        }
+    };
+
+    // the values are sampled as they are in the source
+    let transformers = vec![];
+    let empty_skip_config = vec![];
+    let empty_only_tables_config = vec![];
+    let empty_exclude_schemas_config = vec![];
+    let transformer_metrics = TransformerMetrics::default();
+
+    let options = SourceOptions {
+        transformers: &transformers,
+        skip_config: source.skip.as_ref().unwrap_or(&empty_skip_config),
+        database_subset: &source.database_subset,
+        only_tables: source
+            .only_tables
+            .as_ref()
+            .unwrap_or(&empty_only_tables_config),
+        exclude_schemas: source
+            .exclude_schemas
+            .as_ref()
+            .unwrap_or(&empty_exclude_schemas_config),
+        column_tags: &vec![],
+        transform_default_values: false,
+        transformer_metrics: &transformer_metrics,
+        sort_rows: &vec![],
+        max_cpu_percent: source.max_cpu_percent()?,
+        minimal: false,
+        ddl_rewrites: &vec![],
+        sequences: None,
+        content: None,
+        string_escaping: None,
+        fail_fast: false,
+        skip_large_objects: false,
+    };
+
+    let mut detector = PiiDetector::new(PII_DETECTION_SAMPLES);
+
+    match source.connection_uri()? {
+        ConnectionUri::Postgres(host, port, username, password, database) => {
+            let mut postgres = Postgres::new(
+                host.as_str(),
+                port,
+                database.as_str(),
+                username.as_str(),
+                password.as_str(),
+            );
+
+            postgres.set_extra_args(source.extra_args.clone().unwrap_or_default());
+
+            let _ = postgres.init()?;
+            let _ = postgres.read(options, |original_query, _| {
+                let query = String::from_utf8_lossy(original_query.data());
+
+                if let Some((database, table, columns)) = insert_into_columns(query.as_ref()) {
+                    for column in columns {
+                        if let Some(value) = column.string_value() {
+                            detector.add_value(&database, &table, column.name(), value);
+                        }
+                    }
+                }
+            })?;
+        }
+        _ => {
+            return Err(anyhow::Error::from(Error::new(
+                ErrorKind::Other,
+                "personal data detection is only supported with PostgreSQL",
+            )));
+        }
+    }
+
+    let columns = detector.detected_columns();
+    if columns.is_empty() {
+        println!("no column looking like personal data found");
+        return Ok(());
+    }
+
+    println!("# suggested transformers - review them before adding them to the <source> object of the configuration file");
+    print!("{}", to_transformers_config(&columns));
+
+    Ok(())
+}
@@ -1,4 +1,9 @@
-use crate::transformer::transformers;
+use std::fs::File;
+use std::io::{stdin, stdout, BufRead, BufReader, Read, Write};
+
+use crate::cli::TransformerTestArgs;
+use crate::transformer::{transformer_by_id, transformers, Transformer};
+use crate::types::Column;
 use crate::utils::table;
 
 /// display all transformers available
@@ -12,3 +17,102 @@ pub fn list() {
 
     let _ = table.printstd();
 }
+
+/// apply a transformer (with its default options) on each line of a file or stdin and print the results
+pub fn test(args: &TransformerTestArgs) -> anyhow::Result<()> {
+    let transformer = transformer_by_id(args.id.as_str()).ok_or_else(|| {
+        anyhow::anyhow!(
+            "unknown transformer '{}' - use `transformer list` to list them",
+            args.id
+        )
+    })?;
+
+    let input: Box<dyn Read> = match &args.input {
+        Some(path) if path.to_string_lossy() != "-" => {
+            Box::new(File::open(path).map_err(|err| {
+                anyhow::anyhow!("missing input file '{}': {}", path.display(), err)
+            })?)
+        }
+        _ => Box::new(stdin()),
+    };
+
+    transform_lines(transformer.as_ref(), BufReader::new(input), stdout().lock())
+}
+
+/// write the transformed value of each line - in the same order
+fn transform_lines<R: BufRead, W: Write>(
+    transformer: &dyn Transformer,
+    input: R,
+    mut output: W,
+) -> anyhow::Result<()> {
+    let column_name = transformer.column_name().to_string();
+
+    if transformer.transforms_all_values() {
+        let columns = input
+            .lines()
+            .map(|line| line.map(|line| to_column(column_name.as_str(), line)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for column in transformer.transform_all(columns) {
+            writeln!(output, "{}", to_value(column))?;
+        }
+    } else {
+        for line in input.lines() {
+            let column = transformer.transform(to_column(column_name.as_str(), line?));
+            writeln!(output, "{}", to_value(column))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// the lines are not typed - numbers are guessed from the value itself, as in a COPY payload
+fn to_column(column_name: &str, value: String) -> Column {
+    match value.parse::<i128>() {
+        Ok(number) => Column::NumberValue(column_name.to_string(), number),
+        Err(_) => match value.parse::<f64>() {
+            Ok(number) if value.contains('.') => {
+                Column::FloatNumberValue(column_name.to_string(), number)
+            }
+            _ => Column::StringValue(column_name.to_string(), value),
+        },
+    }
+}
+
+fn to_value(column: Column) -> String {
+    match column {
+        Column::NumberValue(_, value) => value.to_string(),
+        Column::FloatNumberValue(_, value) => value.to_string(),
+        Column::StringValue(_, value) => value,
+        Column::CharValue(_, value) => value.to_string(),
+        Column::BooleanValue(_, value) => value.to_string(),
+        Column::None(_) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::transformer::transform_lines;
+    use crate::transformer::transformer_by_id;
+
+    #[test]
+    fn transform_lines_of_input() {
+        let input = "Doe\nFuller\n1234567\nanne@example.org\n";
+
+        let transformer = transformer_by_id("redacted").unwrap();
+        let mut output = vec![];
+        transform_lines(transformer.as_ref(), input.as_bytes(), &mut output).unwrap();
+
+        // the short values and the numbers are kept
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Doe\nFul**********\n1234567\nann**********\n"
+        );
+
+        let transformer = transformer_by_id("keep-first-char").unwrap();
+        let mut output = vec![];
+        transform_lines(transformer.as_ref(), input.as_bytes(), &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "D\nF\n1\na\n");
+    }
+}
@@ -1,5 +1,6 @@
-use std::fs::{read, read_dir, remove_dir_all, write, DirBuilder, OpenOptions};
-use std::io::{BufReader, Error, Read, Write};
+use std::collections::BTreeMap;
+use std::fs::{metadata, read, read_dir, remove_dir_all, write, DirBuilder, OpenOptions};
+use std::io::{BufReader, Error, ErrorKind, Read, Write};
 use std::path::Path;
 
 use log::{debug, error, info};
@@ -9,13 +10,32 @@ use crate::connector::Connector;
 use crate::types;
 use crate::utils::epoch_millis;
 
-use super::{compress, decompress, decrypt, encrypt, Datastore, Dump, IndexFile, INDEX_FILE_NAME};
+use super::{
+    compress, decompress_part, decrypt, encrypt, part_number, remove_dump_entry, Datastore, Dump,
+    DumpContent, DumpManifest, EncryptionAlgorithm, IndexFile, ENCRYPTION_VERSION, INDEX_FILE_NAME,
+    MANIFEST_FILE_NAME,
+};
+
+/// file of the datastore root (or namespace) holding the version of its layout - `<dump>/<part>.dump` parts and
+/// a `metadata.json` index file. A datastore without this file has been created before it and uses the version 1
+pub const FORMAT_VERSION_FILE_NAME: &str = "format-version";
+/// version of the layout written by this release - bump it when the layout changes in a way older releases can't read.
+/// 2: a `<dump>/manifest.json` file is stored next to the parts - the version 1 reads it as a part
+const FORMAT_VERSION: u32 = 2;
 
 pub struct LocalDisk {
     dir: String,
     dump_name: String,
     enable_compression: bool,
     encryption_key: Option<String>,
+    encryption_algorithm: EncryptionAlgorithm,
+    parts_dir: Option<String>,
+    namespace: Option<String>,
+    source_type: Option<String>,
+    labels: BTreeMap<String, String>,
+    content: Option<DumpContent>,
+    table_hashes: BTreeMap<String, String>,
+    part_offset: u16,
 }
 
 impl LocalDisk {
@@ -24,10 +44,119 @@ impl LocalDisk {
             dir: dir.into(),
             enable_compression: true,
             encryption_key: None,
+            encryption_algorithm: EncryptionAlgorithm::default(),
             dump_name: format!("dump-{}", epoch_millis()),
+            parts_dir: None,
+            namespace: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            table_hashes: BTreeMap::new(),
+            part_offset: 0,
         }
     }
 
+    /// directory containing the index file and the dumps
+    fn root_dir(&self) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}/{}", self.dir, namespace),
+            None => self.dir.clone(),
+        }
+    }
+
+    /// debug mode - keep a plain SQL copy (not compressed and not encrypted) of each dump part
+    /// inside `<parts_dir>/<dump_name>/<part>.sql`
+    pub fn set_parts_dir<S: Into<String>>(&mut self, parts_dir: S) {
+        info!("keep plain dump parts");
+        self.parts_dir = Some(parts_dir.into());
+    }
+
+    fn write_plain_part(&self, file_part: u16, data: &types::Bytes) -> Result<(), Error> {
+        let parts_dir = match &self.parts_dir {
+            Some(parts_dir) => parts_dir,
+            None => return Ok(()),
+        };
+
+        let part_dir_path = format!("{}/{}", parts_dir, self.dump_name);
+        let part_file_path = format!("{}/{}.sql", part_dir_path, file_part);
+
+        DirBuilder::new()
+            .recursive(true)
+            .create(&part_dir_path)
+            .map_err(|err| {
+                error!("error while creating the parts directory: {}", part_dir_path);
+                err
+            })?;
+
+        debug!("writing plain dump part at: {}", part_file_path);
+        write(&part_file_path, data).map_err(|err| {
+            error!("error while writing plain dump part at: {}", part_file_path);
+            err
+        })
+    }
+
+    /// version of the layout of the datastore - `None` if the datastore has no format version file
+    fn format_version(&self) -> Result<Option<u32>, Error> {
+        let format_version_path = format!("{}/{}", self.root_dir(), FORMAT_VERSION_FILE_NAME);
+
+        let content = match read(&format_version_path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        match String::from_utf8_lossy(&content).trim().parse::<u32>() {
+            Ok(format_version) => Ok(Some(format_version)),
+            Err(_) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "the format version file '{}' is invalid - it must hold a number",
+                    format_version_path
+                ),
+            )),
+        }
+    }
+
+    /// return an error if the datastore has been written by a newer release with a layout this one can't read -
+    /// to not corrupt it
+    fn check_format_version(&self) -> Result<(), Error> {
+        match self.format_version()? {
+            Some(format_version) if format_version > FORMAT_VERSION => Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "the local disk datastore '{}' uses the format version {} but this release of Replibyte only \
+                    supports the format version {} or lower - upgrade Replibyte to use this datastore",
+                    self.root_dir(),
+                    format_version,
+                    FORMAT_VERSION
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// write the format version file - the datastores using an older version are upgraded, the dumps they hold
+    /// are read the same way
+    fn create_format_version_file(&self) -> Result<(), Error> {
+        match self.format_version()? {
+            Some(format_version) if format_version >= FORMAT_VERSION => return Ok(()),
+            _ => {}
+        }
+
+        let format_version_path = format!("{}/{}", self.root_dir(), FORMAT_VERSION_FILE_NAME);
+        info!(
+            "writing the format version file at: {}",
+            format_version_path
+        );
+        write(&format_version_path, format!("{}\n", FORMAT_VERSION)).map_err(|err| {
+            error!(
+                "error while writing the format version file at: {}",
+                format_version_path
+            );
+            err
+        })
+    }
+
     fn create_index_file(&self) -> Result<IndexFile, Error> {
         match self.index_file() {
             Ok(index_file) => Ok(index_file),
@@ -44,17 +173,31 @@ impl LocalDisk {
 impl Connector for LocalDisk {
     fn init(&mut self) -> Result<(), Error> {
         debug!("initializing datastore");
-        self.create_index_file().map(|_| ())
+
+        // create the namespace directory if needed
+        let root_dir = self.root_dir();
+        DirBuilder::new()
+            .recursive(true)
+            .create(&root_dir)
+            .map_err(|err| {
+                error!("error while creating the datastore directory: {}", root_dir);
+                err
+            })?;
+
+        let _ = self.check_format_version()?;
+        let _ = self.create_index_file()?;
+        self.create_format_version_file()
     }
 }
 
 impl Datastore for LocalDisk {
     fn index_file(&self) -> Result<IndexFile, Error> {
-        debug!("reading index_file at: {}", &self.dir);
+        debug!("reading index_file at: {}", self.root_dir());
+        let _ = self.check_format_version()?;
 
         let file = OpenOptions::new()
             .read(true)
-            .open(format!("{}/{}", self.dir, INDEX_FILE_NAME))?;
+            .open(format!("{}/{}", self.root_dir(), INDEX_FILE_NAME))?;
 
         let reader = BufReader::new(file);
 
@@ -65,11 +208,12 @@ impl Datastore for LocalDisk {
     }
 
     fn raw_index_file(&self) -> Result<Value, Error> {
-        info!("reading raw index_file at: {}", &self.dir);
+        info!("reading raw index_file at: {}", self.root_dir());
+        let _ = self.check_format_version()?;
 
         let file = OpenOptions::new()
             .read(true)
-            .open(format!("{}/{}", self.dir, INDEX_FILE_NAME))?;
+            .open(format!("{}/{}", self.root_dir(), INDEX_FILE_NAME))?;
 
         let reader = BufReader::new(file);
 
@@ -80,7 +224,7 @@ impl Datastore for LocalDisk {
 
     fn write_index_file(&self, index_file: &IndexFile) -> Result<(), Error> {
         info!("writing index_file");
-        let index_file_path = format!("{}/{}", self.dir, INDEX_FILE_NAME);
+        let index_file_path = format!("{}/{}", self.root_dir(), INDEX_FILE_NAME);
 
         debug!("opening index_file at {}", index_file_path);
         let file = OpenOptions::new()
@@ -95,7 +239,7 @@ impl Datastore for LocalDisk {
 
     fn write_raw_index_file(&self, raw_index_file: &Value) -> Result<(), Error> {
         info!("writing raw index_file");
-        let index_file_path = format!("{}/{}", self.dir, INDEX_FILE_NAME);
+        let index_file_path = format!("{}/{}", self.root_dir(), INDEX_FILE_NAME);
 
         debug!("opening index_file at {}", index_file_path);
         let file = OpenOptions::new()
@@ -108,7 +252,13 @@ impl Datastore for LocalDisk {
         serde_json::to_writer(file, raw_index_file).map_err(|err| Error::from(err))
     }
 
-    fn write(&self, file_part: u16, data: types::Bytes) -> Result<(), Error> {
+    fn write_part(&self, file_part: u16, data: types::Bytes) -> Result<usize, Error> {
+        let file_part = file_part + self.part_offset;
+        let _ = self.check_format_version()?;
+
+        // keep a plain copy of the part?
+        let _ = self.write_plain_part(file_part, &data)?;
+
         // compress data?
         let data = if self.compression_enabled() {
             compress(data)?
@@ -118,12 +268,12 @@ impl Datastore for LocalDisk {
 
         // encrypt data?
         let data = match self.encryption_key() {
-            Some(key) => encrypt(data, key.as_str())?,
+            Some(key) => encrypt(data, key.as_str(), self.encryption_algorithm)?,
             None => data,
         };
 
         let data_size = data.len();
-        let dump_dir_path = format!("{}/{}", self.dir, self.dump_name);
+        let dump_dir_path = format!("{}/{}", self.root_dir(), self.dump_name);
         let dump_file_path = format!("{}/{}.dump", dump_dir_path, file_part);
 
         // create the dump directory if needed
@@ -141,38 +291,75 @@ impl Datastore for LocalDisk {
             err
         })?;
 
-        // update index file
+        // the part must be fully written before the dump is recorded in the index file
+        let stored_size = metadata(&dump_file_path)?.len() as usize;
+        if stored_size != data_size {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "the dump part '{}' is incomplete: {} bytes written instead of {}",
+                    dump_file_path, stored_size, data_size
+                ),
+            ));
+        }
+
+        Ok(data_size)
+    }
+
+    fn write_dump_entry(&self, size: usize, raw_size: usize) -> Result<(), Error> {
         let mut index_file = self.index_file()?;
 
-        let mut new_dump = Dump {
+        index_file.add_dump_size(Dump {
             directory_name: self.dump_name.to_string(),
-            size: 0,
+            size,
             created_at: epoch_millis(),
             compressed: self.compression_enabled(),
             encrypted: self.encryption_key().is_some(),
-        };
-
-        // find or create Dump
-        let mut dump = index_file
-            .dumps
-            .iter_mut()
-            .find(|b| b.directory_name.as_str() == self.dump_name)
-            .unwrap_or(&mut new_dump);
-
-        if dump.size == 0 {
-            // it means it's a new dump.
-            // We need to add it into the index_file.dumps
-            new_dump.size = data_size;
-            index_file.dumps.push(new_dump);
-        } else {
-            // update total dump size
-            dump.size = dump.size + data_size;
-        }
+            encryption_algorithm: self
+                .encryption_key()
+                .as_ref()
+                .map(|_| self.encryption_algorithm),
+            encryption_version: self.encryption_key().as_ref().map(|_| ENCRYPTION_VERSION),
+            source_type: self.source_type.clone(),
+            labels: self.labels.clone(),
+            content: self.content,
+            raw_size: Some(raw_size),
+            table_hashes: self.table_hashes.clone(),
+        });
 
-        // save index file
         self.write_index_file(&index_file)
     }
 
+    fn write_dump_manifest(&self, manifest: &DumpManifest) -> Result<(), Error> {
+        let dump_dir_path = format!("{}/{}", self.root_dir(), self.dump_name);
+        let manifest_path = format!("{}/{}", dump_dir_path, MANIFEST_FILE_NAME);
+
+        DirBuilder::new()
+            .recursive(true)
+            .create(&dump_dir_path)
+            .map_err(|err| {
+                error!("error while creating the dump directory: {}", dump_dir_path);
+                err
+            })?;
+
+        debug!("writing dump manifest at: {}", manifest_path);
+        let manifest_json = serde_json::to_vec_pretty(manifest)?;
+        write(&manifest_path, manifest_json).map_err(|err| {
+            error!("error while writing dump manifest at: {}", manifest_path);
+            err
+        })
+    }
+
+    fn read_dump_manifest(&self, name: &str) -> Result<DumpManifest, Error> {
+        let manifest_path = format!("{}/{}/{}", self.root_dir(), name, MANIFEST_FILE_NAME);
+
+        debug!("reading dump manifest at: {}", manifest_path);
+        let manifest = read(&manifest_path)?;
+        let manifest = serde_json::from_slice(manifest.as_slice())?;
+
+        Ok(manifest)
+    }
+
     fn read(
         &self,
         options: &super::ReadOptions,
@@ -180,10 +367,17 @@ impl Datastore for LocalDisk {
     ) -> Result<(), Error> {
         let mut index_file = self.index_file()?;
         let dump = index_file.find_dump(options)?;
-        let entries = read_dir(format!("{}/{}", self.dir, dump.directory_name))?;
+        let mut entries = read_dir(format!("{}/{}", self.root_dir(), dump.directory_name))?
+            .collect::<Result<Vec<_>, Error>>()?
+            .into_iter()
+            // the manifest is stored next to the parts
+            .filter(|entry| part_number(entry.file_name().to_str().unwrap_or_default()).is_some())
+            .collect::<Vec<_>>();
+
+        // read the parts in the order they have been written
+        entries.sort_by_key(|entry| part_number(entry.file_name().to_str().unwrap_or_default()));
 
         for entry in entries {
-            let entry = entry?;
             let data = read(entry.path())?;
 
             // decrypt data?
@@ -191,17 +385,18 @@ impl Datastore for LocalDisk {
                 // It should be safe to unwrap here because the dump is marked as encrypted in the dump manifest
                 // so if there is no encryption key set at the datastore level we want to panic.
                 let encryption_key = self.encryption_key.as_ref().unwrap();
-                decrypt(data, encryption_key.as_str())?
+                decrypt(
+                    data,
+                    encryption_key.as_str(),
+                    dump.cipher(),
+                    dump.encryption_version(),
+                )?
             } else {
                 data
             };
 
             // decompress data?
-            let data = if dump.compressed {
-                decompress(data)?
-            } else {
-                data
-            };
+            let data = decompress_part(data, dump.compressed)?;
 
             data_callback(data);
         }
@@ -230,39 +425,174 @@ impl Datastore for LocalDisk {
         self.encryption_key = Some(key)
     }
 
+    fn encryption_algorithm(&self) -> EncryptionAlgorithm {
+        self.encryption_algorithm
+    }
+
+    fn set_encryption_algorithm(&mut self, algorithm: EncryptionAlgorithm) {
+        info!("set datastore encryption_algorithm");
+        self.encryption_algorithm = algorithm
+    }
+
+    fn dump_name(&self) -> &str {
+        self.dump_name.as_str()
+    }
+
     fn set_dump_name(&mut self, name: String) {
         self.dump_name = name
     }
 
+    fn set_namespace(&mut self, namespace: String) {
+        info!("set datastore namespace");
+        self.namespace = Some(namespace.trim_matches('/').to_string())
+    }
+
+    fn set_source_type(&mut self, source_type: String) {
+        self.source_type = Some(source_type)
+    }
+
+    fn set_labels(&mut self, labels: BTreeMap<String, String>) {
+        self.labels = labels
+    }
+
+    fn set_content(&mut self, content: Option<DumpContent>) {
+        self.content = content;
+    }
+
+    fn set_table_hashes(&mut self, table_hashes: BTreeMap<String, String>) {
+        self.table_hashes = table_hashes;
+    }
+
+    fn last_part(&self, name: &str) -> Result<u16, Error> {
+        let entries = match read_dir(format!("{}/{}", self.root_dir(), name)) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err),
+        };
+
+        let mut last_part = 0;
+        for entry in entries {
+            if let Some(part) = part_number(entry?.file_name().to_str().unwrap_or_default()) {
+                last_part = last_part.max(part);
+            }
+        }
+
+        Ok(last_part)
+    }
+
+    fn part_numbers(&self, name: &str) -> Result<Vec<u16>, Error> {
+        let entries = match read_dir(format!("{}/{}", self.root_dir(), name)) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+
+        let mut part_numbers = vec![];
+        for entry in entries {
+            if let Some(part) = part_number(entry?.file_name().to_str().unwrap_or_default()) {
+                part_numbers.push(part);
+            }
+        }
+
+        part_numbers.sort_unstable();
+        Ok(part_numbers)
+    }
+
+    fn set_part_offset(&mut self, offset: u16) {
+        info!("set datastore part offset to {}", offset);
+        self.part_offset = offset
+    }
+
+    fn parts_size(&self, name: &str) -> Result<usize, Error> {
+        let entries = match read_dir(format!("{}/{}", self.root_dir(), name)) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err),
+        };
+
+        let mut parts_size = 0;
+        for entry in entries {
+            let entry = entry?;
+            if part_number(entry.file_name().to_str().unwrap_or_default()).is_some() {
+                parts_size += entry.metadata()?.len() as usize;
+            }
+        }
+
+        Ok(parts_size)
+    }
+
     fn delete_by_name(&self, name: String) -> Result<(), Error> {
-        let mut index_file = self.index_file()?;
+        let dump_dir_path = format!("{}/{}", self.root_dir(), name);
+        match remove_dir_all(&dump_dir_path) {
+            Ok(_) => {}
+            // nothing has been written yet for this dump
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                error!("error while removing the dump directory: {}", dump_dir_path);
+                return Err(err);
+            }
+        }
 
-        let dump_dir_path = format!("{}/{}", self.dir, name);
-        remove_dir_all(&dump_dir_path).map_err(|err| {
-            error!("error while removing the dump directory: {}", dump_dir_path);
-            err
-        })?;
+        remove_dump_entry(self, name.as_str())
+    }
 
-        // update the index_file.
-        index_file.dumps.retain(|b| b.directory_name != name);
+    fn list_all_objects(&self) -> Result<Vec<String>, Error> {
+        let root_dir = self.root_dir();
+        let mut objects = vec![];
+
+        // directories to list, relative to the root directory
+        let mut dirs = vec![String::new()];
+        while let Some(dir) = dirs.pop() {
+            let entries = match read_dir(Path::new(root_dir.as_str()).join(dir.as_str())) {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
 
-        self.write_index_file(&index_file)
+            for entry in entries {
+                let entry = entry?;
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let key = if dir.is_empty() {
+                    file_name
+                } else {
+                    format!("{}/{}", dir, file_name)
+                };
+
+                if entry.file_type()?.is_dir() {
+                    dirs.push(key);
+                } else {
+                    objects.push(key);
+                }
+            }
+        }
+
+        objects.sort();
+        Ok(objects)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
     use std::{fs::OpenOptions};
+    use std::fs::{create_dir, read, write};
+    use std::io::{BufReader, Write};
     use std::path::Path;
 
     use chrono::{Duration, Utc};
+    use dump_parser::utils::{list_sql_queries_from_dump_reader, ListQueryResult};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
     use serde_json::json;
     use tempfile::tempdir;
 
     use crate::{
         cli::DumpDeleteArgs,
         connector::Connector,
-        datastore::{Datastore, Dump, ReadOptions, INDEX_FILE_NAME},
+        datastore::{
+            compress, encrypt_with_nonce, Datastore, Dump, EncryptionAlgorithm, ReadOptions,
+            ENCRYPTION_VERSION, INDEX_FILE_NAME, LEGACY_NONCE,
+        },
         migration::{
             rename_backups_to_dumps::RenameBackupsToDump,
             update_version_number::UpdateVersionNumber, Migrator,
@@ -270,7 +600,7 @@ mod tests {
         utils::epoch_millis,
     };
 
-    use super::LocalDisk;
+    use super::{LocalDisk, FORMAT_VERSION, FORMAT_VERSION_FILE_NAME};
 
     // update_dump_date is a helper function that updates the date of a dump inside the index file.
     fn update_dump_date(local_disk: &LocalDisk, dump_name: String, days_before_now: i64) {
@@ -296,6 +626,50 @@ mod tests {
         assert!(local_disk.init().is_ok());
     }
 
+    #[test]
+    fn test_format_version() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        let format_version_path = dir.path().join(FORMAT_VERSION_FILE_NAME);
+        assert_eq!(read(&format_version_path).unwrap(), b"2\n".to_vec());
+        assert_eq!(local_disk.format_version().unwrap(), Some(FORMAT_VERSION));
+
+        // a datastore created before the format version file is read as the version 1
+        std::fs::remove_file(&format_version_path).unwrap();
+        assert!(local_disk.index_file().is_ok());
+        assert!(local_disk.init().is_ok());
+        assert!(format_version_path.exists());
+
+        // and upgraded - like the datastores of an older version
+        write(&format_version_path, "1\n").unwrap();
+        assert!(local_disk.index_file().is_ok());
+        assert!(local_disk.init().is_ok());
+        assert_eq!(local_disk.format_version().unwrap(), Some(FORMAT_VERSION));
+
+        // a newer format is rejected
+        write(&format_version_path, format!("{}\n", FORMAT_VERSION + 1)).unwrap();
+        let err = local_disk.index_file().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(format!("uses the format version {}", FORMAT_VERSION + 1).as_str()));
+        assert!(err.to_string().contains("upgrade Replibyte"));
+        assert!(local_disk.raw_index_file().is_err());
+        assert!(local_disk.init().is_err());
+
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        assert!(local_disk.init().is_err());
+        assert!(local_disk.write(1, b"hello".to_vec()).is_err());
+        assert_eq!(
+            read(&format_version_path).unwrap(),
+            format!("{}\n", FORMAT_VERSION + 1).into_bytes()
+        );
+
+        write(&format_version_path, "not a version").unwrap();
+        assert!(local_disk.index_file().is_err());
+    }
+
     #[test]
     fn test_write_and_read() {
         let dir = tempdir().expect("cannot create tempdir");
@@ -330,6 +704,322 @@ mod tests {
         assert_eq!(dump_content, b"hello world".to_vec())
     }
 
+    #[test]
+    fn test_write_and_read_with_encryption_algorithms() {
+        for algorithm in [
+            EncryptionAlgorithm::Aes256Gcm,
+            EncryptionAlgorithm::ChaCha20Poly1305,
+        ] {
+            let dir = tempdir().expect("cannot create tempdir");
+            let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+            local_disk.set_encryption_key("this is my secret".to_string());
+            local_disk.set_encryption_algorithm(algorithm);
+            let _ = local_disk.init().expect("local_disk init failed");
+
+            assert!(local_disk.write(1, b"hello world".to_vec()).is_ok());
+
+            // the cipher is recorded in the dump manifest
+            let mut index_file = local_disk.index_file().unwrap();
+            let dump = index_file.find_dump(&ReadOptions::Latest).unwrap();
+            assert!(dump.encrypted);
+            assert_eq!(dump.encryption_algorithm, Some(algorithm));
+
+            // a datastore configured with another cipher reads the dump with the recorded one
+            let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+            local_disk.set_encryption_key("this is my secret".to_string());
+
+            let mut dump_content: Vec<u8> = vec![];
+            assert!(local_disk
+                .read(&ReadOptions::Latest, &mut |bytes| {
+                    let mut b = bytes;
+                    dump_content.append(&mut b);
+                })
+                .is_ok());
+            assert_eq!(dump_content, b"hello world".to_vec());
+        }
+    }
+
+    #[test]
+    fn test_read_and_reencrypt_legacy_dump() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        local_disk.set_encryption_key("this is my secret".to_string());
+        local_disk.set_encryption_algorithm(EncryptionAlgorithm::ChaCha20Poly1305);
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        // a dump encrypted with the fixed nonce - its version is not recorded
+        let dump_dir = dir.path().join("dump-legacy");
+        create_dir(&dump_dir).unwrap();
+        let mut size = 0;
+        for (part, data) in [(1, "hello "), (2, "world")] {
+            let data = encrypt_with_nonce(
+                compress(data.as_bytes().to_vec()).unwrap(),
+                "this is my secret",
+                EncryptionAlgorithm::ChaCha20Poly1305,
+                LEGACY_NONCE,
+            )
+            .unwrap();
+            size += data.len();
+            write(dump_dir.join(format!("{}.dump", part)), data).unwrap();
+        }
+
+        let mut index_file = local_disk.index_file().unwrap();
+        index_file.dumps.push(Dump {
+            directory_name: "dump-legacy".to_string(),
+            size,
+            created_at: epoch_millis(),
+            compressed: true,
+            encrypted: true,
+            encryption_algorithm: Some(EncryptionAlgorithm::ChaCha20Poly1305),
+            encryption_version: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            raw_size: None,
+            table_hashes: BTreeMap::new(),
+        });
+        let _ = local_disk.write_index_file(&index_file).unwrap();
+
+        let options = ReadOptions::Dump {
+            name: "dump-legacy".to_string(),
+        };
+        let read_dump = |local_disk: &LocalDisk| {
+            let mut dump_content: Vec<u8> = vec![];
+            assert!(local_disk
+                .read(&options, &mut |bytes| {
+                    let mut b = bytes;
+                    dump_content.append(&mut b);
+                })
+                .is_ok());
+            dump_content
+        };
+
+        assert_eq!(read_dump(&local_disk), b"hello world".to_vec());
+
+        // new parts can't be appended before the dump is re-encrypted
+        assert!(local_disk
+            .append_to("dump-legacy".to_string(), "postgresql")
+            .is_err());
+
+        assert!(local_disk.reencrypt("dump-legacy").unwrap());
+
+        let mut index_file = local_disk.index_file().unwrap();
+        let dump = index_file.find_dump(&options).unwrap();
+        assert_eq!(dump.encryption_version, Some(ENCRYPTION_VERSION));
+        assert_eq!(
+            dump.encryption_algorithm,
+            Some(EncryptionAlgorithm::ChaCha20Poly1305)
+        );
+        assert_eq!(local_disk.size_mismatch("dump-legacy").unwrap(), None);
+        assert_ne!(
+            read(dump_dir.join("1.dump")).unwrap()[..12],
+            LEGACY_NONCE[..]
+        );
+        assert_eq!(read_dump(&local_disk), b"hello world".to_vec());
+
+        // the dump is only re-encrypted once
+        let reencrypted_part = read(dump_dir.join("1.dump")).unwrap();
+        assert!(!local_disk.reencrypt("dump-legacy").unwrap());
+        assert_eq!(read(dump_dir.join("1.dump")).unwrap(), reencrypted_part);
+        assert!(local_disk
+            .append_to("dump-legacy".to_string(), "postgresql")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_copy_dump() {
+        let source_dir = tempdir().expect("cannot create tempdir");
+        let mut source = LocalDisk::new(source_dir.path().to_str().unwrap().to_string());
+        source.set_encryption_key("this is my secret".to_string());
+        source.set_encryption_algorithm(EncryptionAlgorithm::ChaCha20Poly1305);
+        source.set_dump_name("dump-staging".to_string());
+        source.set_labels(BTreeMap::from([("env".to_string(), "staging".to_string())]));
+        let _ = source.init().expect("local_disk init failed");
+
+        assert!(source.write(1, b"hello ".to_vec()).is_ok());
+        assert!(source.write(2, b"world".to_vec()).is_ok());
+
+        let target_dir = tempdir().expect("cannot create tempdir");
+        let mut target = LocalDisk::new(target_dir.path().to_str().unwrap().to_string());
+        let _ = target.init().expect("local_disk init failed");
+
+        assert!(source.copy_dump("dump-staging", &mut target).is_ok());
+
+        let options = ReadOptions::Dump {
+            name: "dump-staging".to_string(),
+        };
+        let mut source_index_file = source.index_file().unwrap();
+        let mut target_index_file = target.index_file().unwrap();
+        assert_eq!(
+            target_index_file.find_dump(&options).unwrap(),
+            source_index_file.find_dump(&options).unwrap()
+        );
+        assert_eq!(target.part_numbers("dump-staging").unwrap(), vec![1, 2]);
+        assert_eq!(target.size_mismatch("dump-staging").unwrap(), None);
+
+        let mut dump_content: Vec<u8> = vec![];
+        assert!(target
+            .read(&options, &mut |bytes| {
+                let mut b = bytes;
+                dump_content.append(&mut b);
+            })
+            .is_ok());
+        assert_eq!(dump_content, b"hello world".to_vec());
+
+        // the dump is not overwritten
+        assert!(source.copy_dump("dump-staging", &mut target).is_err());
+        assert!(source.copy_dump("dump-unknown", &mut target).is_err());
+
+        // the encryption key is required to copy an encrypted dump
+        let mut source = LocalDisk::new(source_dir.path().to_str().unwrap().to_string());
+        let _ = source.init().expect("local_disk init failed");
+        let target_dir = tempdir().expect("cannot create tempdir");
+        let mut target = LocalDisk::new(target_dir.path().to_str().unwrap().to_string());
+        let _ = target.init().expect("local_disk init failed");
+        assert!(source.copy_dump("dump-staging", &mut target).is_err());
+    }
+
+    #[test]
+    fn test_write_plain_parts() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let parts_dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        local_disk.set_parts_dir(parts_dir.path().to_str().unwrap());
+        local_disk.set_encryption_key("this is my secret".to_string());
+        local_disk.set_dump_name("dump-1".to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        let part_1 = b"CREATE TABLE public.users (id integer NOT NULL);\n".to_vec();
+        let part_2 = b"INSERT INTO public.users (id) VALUES (1);\n".to_vec();
+
+        assert!(local_disk.write(1, part_1.clone()).is_ok());
+        assert!(local_disk.write(2, part_2.clone()).is_ok());
+
+        for (file_part, expected_data) in [(1, part_1), (2, part_2)] {
+            let part_path = format!(
+                "{}/dump-1/{}.sql",
+                parts_dir.path().to_str().unwrap(),
+                file_part
+            );
+
+            // parts are kept as plain SQL
+            let data = read(&part_path).unwrap();
+            assert_eq!(data, expected_data);
+
+            // and each part can be parsed on its own
+            let mut queries = vec![];
+            assert!(list_sql_queries_from_dump_reader(BufReader::new(data.as_slice()), |query| {
+                if !query.trim().is_empty() {
+                    queries.push(query.to_string());
+                }
+                ListQueryResult::Continue
+            })
+            .is_ok());
+            assert_eq!(queries.len(), 1);
+        }
+
+        // the dump itself is still compressed and encrypted
+        assert!(!Path::new(&format!("{}/dump-1/1.sql", dir.path().to_str().unwrap())).exists());
+        let dump_content = read(format!("{}/dump-1/1.dump", dir.path().to_str().unwrap())).unwrap();
+        assert_ne!(
+            dump_content,
+            b"CREATE TABLE public.users (id integer NOT NULL);\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_read_parts_compressed_with_another_format() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        let parts = [
+            b"CREATE TABLE public.users (id integer NOT NULL);\n".to_vec(),
+            b"INSERT INTO public.users (id) VALUES (1);\n".to_vec(),
+            b"INSERT INTO public.users (id) VALUES (2);\n".to_vec(),
+        ];
+
+        let mut gzip_encoder = GzEncoder::new(vec![], Compression::default());
+        gzip_encoder.write_all(parts[1].as_slice()).unwrap();
+        let gzip_part = gzip_encoder.finish().unwrap();
+        let zstd_part = zstd::encode_all(parts[2].as_slice(), 0).unwrap();
+
+        // the index file is right for the first part only - the other parts are replaced by hand
+        for (dump_name, compressed) in [("dump-compressed", true), ("dump-plain", false)] {
+            local_disk.set_compression(compressed);
+            local_disk.set_dump_name(dump_name.to_string());
+            for (file_part, data) in parts.iter().enumerate() {
+                assert!(local_disk.write(file_part as u16 + 1, data.clone()).is_ok());
+            }
+
+            let dump_dir = dir.path().join(dump_name);
+            write(dump_dir.join("2.dump"), gzip_part.as_slice()).unwrap();
+            write(dump_dir.join("3.dump"), zstd_part.as_slice()).unwrap();
+        }
+
+        // a zlib part of a dump flagged as not compressed
+        write(
+            dir.path().join("dump-plain").join("1.dump"),
+            compress(parts[0].clone()).unwrap(),
+        )
+        .unwrap();
+
+        for dump_name in ["dump-compressed", "dump-plain"] {
+            let mut dump_content: Vec<Vec<u8>> = vec![];
+            assert!(local_disk
+                .read(
+                    &ReadOptions::Dump {
+                        name: dump_name.to_string(),
+                    },
+                    &mut |bytes| dump_content.push(bytes),
+                )
+                .is_ok());
+            assert_eq!(dump_content, parts.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let dir = tempdir().expect("cannot create tempdir");
+
+        let mut team_a = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        team_a.set_namespace("team-a".to_string());
+        team_a.set_dump_name("dump-1".to_string());
+        let _ = team_a.init().expect("local_disk init failed");
+
+        let mut team_b = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        team_b.set_namespace("team-b/".to_string());
+        team_b.set_dump_name("dump-1".to_string());
+        let _ = team_b.init().expect("local_disk init failed");
+
+        assert!(team_a.write(1, b"team a".to_vec()).is_ok());
+        assert!(team_b.write(1, b"team b".to_vec()).is_ok());
+        assert!(team_b.write(2, b"team b again".to_vec()).is_ok());
+
+        // each namespace has its own index file and dumps
+        let root = dir.path().to_str().unwrap();
+        assert!(Path::new(&format!("{}/team-a/{}", root, INDEX_FILE_NAME)).exists());
+        assert!(Path::new(&format!("{}/team-b/{}", root, INDEX_FILE_NAME)).exists());
+        assert!(!Path::new(&format!("{}/{}", root, INDEX_FILE_NAME)).exists());
+        assert!(Path::new(&format!("{}/team-a/dump-1/1.dump", root)).exists());
+        assert!(!Path::new(&format!("{}/team-a/dump-1/2.dump", root)).exists());
+
+        assert_eq!(team_a.index_file().unwrap().dumps.len(), 1);
+        assert_eq!(team_b.index_file().unwrap().dumps.len(), 1);
+
+        let mut data = vec![];
+        let _ = team_a
+            .read(&ReadOptions::Latest, &mut |bytes| data.extend(bytes))
+            .unwrap();
+        assert_eq!(data, b"team a".to_vec());
+
+        // deleting a dump from a namespace does not touch the other one
+        assert!(team_a.delete_by_name("dump-1".to_string()).is_ok());
+        assert!(team_a.index_file().unwrap().dumps.is_empty());
+        assert_eq!(team_b.index_file().unwrap().dumps.len(), 1);
+        assert!(Path::new(&format!("{}/team-b/dump-1/1.dump", root)).exists());
+    }
+
     #[test]
     fn test_index_file() {
         let dir = tempdir().expect("cannot create tempdir");
@@ -348,6 +1038,13 @@ mod tests {
             created_at: epoch_millis(),
             compressed: true,
             encrypted: false,
+            encryption_algorithm: None,
+            encryption_version: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            raw_size: None,
+            table_hashes: BTreeMap::new(),
         });
 
         assert!(local_disk.write_index_file(&index_file).is_ok());
@@ -355,6 +1052,35 @@ mod tests {
         assert_eq!(local_disk.index_file().unwrap().dumps.len(), 1);
     }
 
+    #[test]
+    fn test_size_mismatch() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+        local_disk.set_compression(true);
+        local_disk.set_dump_name("dump-1".to_string());
+
+        assert!(local_disk.write(1, b"hello world".to_vec()).is_ok());
+        assert!(local_disk.write(2, b"hello world again".to_vec()).is_ok());
+
+        // the recorded size is the size of the compressed parts
+        let parts_size = local_disk.parts_size("dump-1").unwrap();
+        assert!(parts_size > 0);
+        assert_eq!(local_disk.size_mismatch("dump-1").unwrap(), None);
+        assert_eq!(local_disk.parts_size("dump-2").unwrap(), 0);
+
+        // the size recorded in the index file is wrong
+        let mut index_file = local_disk.index_file().unwrap();
+        index_file.dumps[0].size += 42;
+        let _ = local_disk.write_index_file(&index_file).unwrap();
+
+        assert_eq!(
+            local_disk.size_mismatch("dump-1").unwrap(),
+            Some((parts_size + 42, parts_size))
+        );
+        assert!(local_disk.size_mismatch("dump-2").is_err());
+    }
+
     #[test]
     fn test_dump_name() {
         let dir = tempdir().expect("cannot create tempdir");
@@ -475,6 +1201,65 @@ mod tests {
         assert!(Path::new(&format!("{}/dump-3", dir.path().to_str().unwrap())).exists());
     }
 
+    #[test]
+    fn test_labels() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        local_disk.init().expect("local_disk init failed");
+
+        // create a dump without labels
+        local_disk.set_dump_name("dump-1".to_string());
+        assert!(local_disk
+            .write(1, b"hello world from dump-1".to_vec())
+            .is_ok());
+
+        // create labeled dumps
+        let mut labels = BTreeMap::new();
+        let _ = labels.insert("env".to_string(), "staging".to_string());
+        local_disk.set_labels(labels.clone());
+        local_disk.set_dump_name("dump-2".to_string());
+        assert!(local_disk
+            .write(1, b"hello world from dump-2".to_vec())
+            .is_ok());
+
+        let _ = labels.insert("source".to_string(), "primary".to_string());
+        local_disk.set_labels(labels);
+        local_disk.set_dump_name("dump-3".to_string());
+        assert!(local_disk
+            .write(1, b"hello world from dump-3".to_vec())
+            .is_ok());
+
+        let index_file = local_disk.index_file().unwrap();
+        assert!(index_file.dumps[0].labels.is_empty());
+        assert_eq!(
+            index_file.dumps[2].labels.get("source"),
+            Some(&"primary".to_string())
+        );
+
+        let dump_names = |labels: &[(String, String)]| {
+            index_file
+                .dumps
+                .iter()
+                .filter(|dump| dump.has_labels(labels))
+                .map(|dump| dump.directory_name.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(dump_names(&[]), vec!["dump-1", "dump-2", "dump-3"]);
+        assert_eq!(
+            dump_names(&[("env".to_string(), "staging".to_string())]),
+            vec!["dump-2", "dump-3"]
+        );
+        assert_eq!(
+            dump_names(&[
+                ("env".to_string(), "staging".to_string()),
+                ("source".to_string(), "primary".to_string())
+            ]),
+            vec!["dump-3"]
+        );
+        assert!(dump_names(&[("env".to_string(), "production".to_string())]).is_empty());
+    }
+
     #[test]
     fn test_delete_older_than() {
         let dir = tempdir().expect("cannot create tempdir");
@@ -626,7 +1411,14 @@ mod tests {
                 size: 62279,
                 created_at: 1234,
                 compressed: true,
-                encrypted: false
+                encrypted: false,
+                encryption_algorithm: None,
+                encryption_version: None,
+                source_type: None,
+                labels: BTreeMap::new(),
+                content: None,
+                raw_size: None,
+                table_hashes: BTreeMap::new()
             })
         );
         assert_eq!(
@@ -636,7 +1428,14 @@ mod tests {
                 size: 62283,
                 created_at: 5678,
                 compressed: true,
-                encrypted: false
+                encrypted: false,
+                encryption_algorithm: None,
+                encryption_version: None,
+                source_type: None,
+                labels: BTreeMap::new(),
+                content: None,
+                raw_size: None,
+                table_hashes: BTreeMap::new()
             })
         );
     }
@@ -1,10 +1,12 @@
 use aes_gcm::aead::{Aead, NewAead};
 use aes_gcm::{Aes256Gcm, Key, Nonce};
+use chacha20poly1305::ChaCha20Poly1305;
 use chrono::{Duration, Utc};
 use serde_json::Value;
+use std::collections::BTreeMap;
 use std::io::{Error, ErrorKind, Read, Write};
 
-use flate2::read::ZlibDecoder;
+use flate2::read::{GzDecoder, ZlibDecoder};
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
 use serde::{Deserialize, Serialize};
@@ -15,9 +17,20 @@ use crate::types::Bytes;
 use crate::utils::get_replibyte_version;
 
 pub mod local_disk;
+pub mod multi;
+pub mod rate_limiter;
 pub mod s3;
-
-const INDEX_FILE_NAME: &str = "metadata.json";
+pub mod sftp;
+
+pub const INDEX_FILE_NAME: &str = "metadata.json";
+/// file of a dump stored next to its parts - e.g. `dump-1/manifest.json`
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+/// dumps encrypted with the same fixed nonce for every part
+const LEGACY_ENCRYPTION_VERSION: u8 = 1;
+/// encryption of the new dumps - every part is encrypted with a random nonce stored before the encrypted data
+const ENCRYPTION_VERSION: u8 = 2;
+const LEGACY_NONCE: &[u8; NONCE_LENGTH] = b"unique nonce";
+const NONCE_LENGTH: usize = 12;
 
 pub trait Datastore: Connector + Send + Sync {
     /// Getting Index file with all the dumps information
@@ -25,7 +38,16 @@ pub trait Datastore: Connector + Send + Sync {
     fn raw_index_file(&self) -> Result<Value, Error>;
     fn write_index_file(&self, index_file: &IndexFile) -> Result<(), Error>;
     fn write_raw_index_file(&self, raw_index_file: &Value) -> Result<(), Error>;
-    fn write(&self, file_part: u16, data: Bytes) -> Result<(), Error>;
+    /// Store a part of the dump and check it has been stored - the index file is not updated.
+    /// Return the stored size of the part
+    fn write_part(&self, file_part: u16, data: Bytes) -> Result<usize, Error>;
+    /// Record the dump in the index file with `size` bytes of new parts (`raw_size` bytes before their compression
+    /// and encryption) - the sizes are added to the dump if it is already recorded (e.g. parts appended to it)
+    fn write_dump_entry(&self, size: usize, raw_size: usize) -> Result<(), Error>;
+    /// Store the manifest of the dump next to its parts - it is neither compressed nor encrypted
+    fn write_dump_manifest(&self, manifest: &DumpManifest) -> Result<(), Error>;
+    /// Manifest stored next to the parts of a dump - the dumps created before the manifests have none
+    fn read_dump_manifest(&self, name: &str) -> Result<DumpManifest, Error>;
     fn read(
         &self,
         options: &ReadOptions,
@@ -35,8 +57,267 @@ pub trait Datastore: Connector + Send + Sync {
     fn set_compression(&mut self, enable: bool);
     fn encryption_key(&self) -> &Option<String>;
     fn set_encryption_key(&mut self, key: String);
+    fn encryption_algorithm(&self) -> EncryptionAlgorithm;
+    fn set_encryption_algorithm(&mut self, algorithm: EncryptionAlgorithm);
+    fn dump_name(&self) -> &str;
     fn set_dump_name(&mut self, name: String);
+    /// Store the index file and the dumps under a prefix, to share the same datastore between several dump sets
+    fn set_namespace(&mut self, namespace: String);
+    /// Source type (postgresql, mysql or mongodb) recorded in the new dumps
+    fn set_source_type(&mut self, source_type: String);
+    /// Key/value labels recorded in the new dumps
+    fn set_labels(&mut self, labels: BTreeMap<String, String>);
+    /// What the new dumps hold - the schema and the rows if `None`
+    fn set_content(&mut self, content: Option<DumpContent>);
+    /// Hashes of the original rows of each table recorded in the new dumps
+    fn set_table_hashes(&mut self, table_hashes: BTreeMap<String, String>);
+    /// Highest part number of a dump - 0 if the dump has no part
+    fn last_part(&self, name: &str) -> Result<u16, Error>;
+    /// Numbers of the stored parts of a dump in the order they have been written
+    fn part_numbers(&self, name: &str) -> Result<Vec<u16>, Error>;
+    /// Shift the number of the written parts - to not overwrite the parts of an existing dump
+    fn set_part_offset(&mut self, offset: u16);
+    /// Total size of the stored parts of a dump - 0 if the dump has no part
+    fn parts_size(&self, name: &str) -> Result<usize, Error>;
     fn delete_by_name(&self, name: String) -> Result<(), Error>;
+    /// Keys of all the objects stored under the datastore root (or namespace) - e.g. `metadata.json`, `dump-1/1.dump`
+    fn list_all_objects(&self) -> Result<Vec<String>, Error>;
+
+    /// Store a part of the dump and record it in the index file right away
+    fn write(&self, file_part: u16, data: Bytes) -> Result<(), Error> {
+        let raw_size = data.len();
+        let size = self.write_part(file_part, data)?;
+        self.write_dump_entry(size, raw_size)
+    }
+
+    /// true if a dump with this name is in the index file
+    fn exists(&self, name: &str) -> bool {
+        match self.index_file() {
+            Ok(index_file) => index_file
+                .dumps
+                .iter()
+                .any(|dump| dump.directory_name.as_str() == name),
+            Err(_) => false,
+        }
+    }
+
+    /// Compare the size of a dump recorded in the index file with the total size of its stored parts.
+    /// Return `Some((recorded size, parts size))` if they do not match
+    fn size_mismatch(&self, name: &str) -> Result<Option<(usize, usize)>, Error> {
+        let mut index_file = self.index_file()?;
+        let recorded_size = index_file
+            .find_dump(&ReadOptions::Dump {
+                name: name.to_string(),
+            })?
+            .size;
+
+        let parts_size = self.parts_size(name)?;
+        if recorded_size == parts_size {
+            return Ok(None);
+        }
+
+        Ok(Some((recorded_size, parts_size)))
+    }
+
+    /// Write the next parts into an existing dump instead of creating a new one
+    fn append_to(&mut self, name: String, source_type: &str) -> Result<(), Error> {
+        let mut index_file = self.index_file()?;
+        let dump = index_file.find_dump(&ReadOptions::Dump { name: name.clone() })?;
+
+        if let Some(dump_source_type) = &dump.source_type {
+            if dump_source_type.as_str() != source_type {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    format!(
+                        "can't append a {} dump to the {} dump '{}'",
+                        source_type, dump_source_type, name
+                    ),
+                ));
+            }
+        }
+
+        // every part of a dump is read with the same options
+        if dump.compressed != self.compression_enabled() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "can't append to the dump '{}': the compression option does not match",
+                    name
+                ),
+            ));
+        }
+
+        if dump.encrypted != self.encryption_key().is_some()
+            || (dump.encrypted && dump.cipher() != self.encryption_algorithm())
+        {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "can't append to the dump '{}': the encryption options do not match",
+                    name
+                ),
+            ));
+        }
+
+        if dump.encrypted && dump.encryption_version() != ENCRYPTION_VERSION {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "can't append to the dump '{}': it uses a legacy encryption - run `dump reencrypt --name {}` first",
+                    name, name
+                ),
+            ));
+        }
+
+        let last_part = self.last_part(name.as_str())?;
+        self.set_dump_name(name);
+        self.set_part_offset(last_part);
+
+        Ok(())
+    }
+
+    /// Encrypt again the parts of a dump encrypted with a legacy version - every part is overwritten once read.
+    /// Return false if the dump already uses the last encryption version
+    fn reencrypt(&mut self, name: &str) -> Result<bool, Error> {
+        let options = ReadOptions::Dump {
+            name: name.to_string(),
+        };
+
+        let mut index_file = self.index_file()?;
+        let dump = index_file.find_dump(&options)?;
+
+        if !dump.encrypted {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!("the dump '{}' is not encrypted", name),
+            ));
+        }
+
+        if self.encryption_key().is_none() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "the encryption key is required to re-encrypt the dump '{}'",
+                    name
+                ),
+            ));
+        }
+
+        if dump.encryption_version() == ENCRYPTION_VERSION {
+            return Ok(false);
+        }
+
+        self.set_dump_name(name.to_string());
+        self.set_compression(dump.compressed);
+        self.set_encryption_algorithm(dump.cipher());
+        self.set_part_offset(0);
+
+        let mut part_numbers = self.part_numbers(name)?.into_iter();
+        let mut result = Ok(());
+        let _ = self.read(&options, &mut |data| {
+            if result.is_err() {
+                return;
+            }
+
+            result = match part_numbers.next() {
+                Some(part) => self.write_part(part, data).map(|_| ()),
+                None => Err(Error::new(
+                    ErrorKind::Other,
+                    format!("the dump '{}' has more parts than expected", name),
+                )),
+            };
+        })?;
+        let _ = result?;
+
+        // the parts are bigger with their nonce
+        let parts_size = self.parts_size(name)?;
+        let mut index_file = self.index_file()?;
+        if let Some(dump) = index_file
+            .dumps
+            .iter_mut()
+            .find(|dump| dump.directory_name.as_str() == name)
+        {
+            dump.size = parts_size;
+            dump.encryption_version = Some(ENCRYPTION_VERSION);
+        }
+
+        let _ = self.write_index_file(&index_file)?;
+
+        Ok(true)
+    }
+
+    /// Copy a dump into another datastore - the parts are written with the compression and encryption options of the dump,
+    /// and its entry of the index file (creation date, labels...) is kept
+    fn copy_dump(&self, name: &str, target: &mut dyn Datastore) -> Result<(), Error> {
+        let options = ReadOptions::Dump {
+            name: name.to_string(),
+        };
+
+        let mut index_file = self.index_file()?;
+        let dump = index_file.find_dump(&options)?.clone();
+
+        if target.exists(name) {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!("the dump '{}' already exists in the target datastore", name),
+            ));
+        }
+
+        target.set_dump_name(name.to_string());
+        target.set_compression(dump.compressed);
+        target.set_part_offset(0);
+
+        if dump.encrypted {
+            // the copy is encrypted again with the same key
+            let encryption_key = self.encryption_key().clone().ok_or_else(|| {
+                Error::new(
+                    ErrorKind::Other,
+                    format!("the encryption key is required to copy the dump '{}'", name),
+                )
+            })?;
+
+            target.set_encryption_key(encryption_key);
+            target.set_encryption_algorithm(dump.cipher());
+        }
+
+        let mut part_numbers = self.part_numbers(name)?.into_iter();
+        let mut size = 0;
+        let mut result = Ok(());
+        let _ = self.read(&options, &mut |data| {
+            if result.is_err() {
+                return;
+            }
+
+            result = match part_numbers.next() {
+                Some(part) => target.write_part(part, data).map(|part_size| size += part_size),
+                None => Err(Error::new(
+                    ErrorKind::Other,
+                    format!("the dump '{}' has more parts than expected", name),
+                )),
+            };
+        })?;
+        let _ = result?;
+
+        let mut target_index_file = target.index_file()?;
+        target_index_file.dumps.push(Dump {
+            size,
+            // a legacy dump is copied with the last encryption version
+            encryption_version: if dump.encrypted {
+                Some(ENCRYPTION_VERSION)
+            } else {
+                None
+            },
+            ..dump
+        });
+
+        let _ = target.write_index_file(&target_index_file)?;
+
+        // the dumps created before the manifests have none
+        match self.read_dump_manifest(name) {
+            Ok(manifest) => target.write_dump_manifest(&manifest),
+            Err(_) => Ok(()),
+        }
+    }
 
     fn delete(&self, args: &DumpDeleteArgs) -> Result<(), Error> {
         if let Some(dump_name) = &args.dump {
@@ -44,27 +325,15 @@ pub trait Datastore: Connector + Send + Sync {
         }
 
         if let Some(older_than) = &args.older_than {
-            let days = match older_than.chars().nth_back(0) {
-                Some('d') => {
-                    // remove the last character which corresponds to the unit
-                    let mut older_than = older_than.to_string();
-                    older_than.pop();
-
-                    match older_than.parse::<i64>() {
-                        Ok(days) => days,
-                        Err(err) => return Err(Error::new(
-                            ErrorKind::Other,
-                            format!("command error: {} - invalid `--older-than` format. Use `--older-than=14d`", err),
-                        )),
-                    }
-                }
-                _ => {
-                    return Err(Error::new(
-                        ErrorKind::Other,
-                        "command error: invalid `--older-than` format. Use `--older-than=14d`",
-                    ));
-                }
-            };
+            let days = parse_days(older_than).map_err(|err| {
+                Error::new(
+                    ErrorKind::Other,
+                    format!(
+                        "command error: {} - invalid `--older-than` format. Use `--older-than=14d`",
+                        err
+                    ),
+                )
+            })?;
 
             return self.delete_older_than(days);
         }
@@ -88,7 +357,10 @@ pub trait Datastore: Connector + Send + Sync {
         let dumps_to_delete: Vec<Dump> = index_file
             .dumps
             .into_iter()
-            .filter(|b| b.created_at.lt(&threshold_date))
+            // never delete the dump being written - it can be an older dump we append to
+            .filter(|b| {
+                b.created_at.lt(&threshold_date) && b.directory_name.as_str() != self.dump_name()
+            })
             .collect();
 
         for dump in dumps_to_delete {
@@ -131,6 +403,27 @@ impl IndexFile {
         }
     }
 
+    /// add the size of `new_dump` to the dump with the same name - `new_dump` is added if there is no such dump
+    pub fn add_dump_size(&mut self, new_dump: Dump) {
+        match self
+            .dumps
+            .iter_mut()
+            .find(|dump| dump.directory_name == new_dump.directory_name)
+        {
+            Some(dump) => {
+                dump.size += new_dump.size;
+                // unknown if the dump has been created before the raw size is recorded
+                dump.raw_size = match (dump.raw_size, new_dump.raw_size) {
+                    (Some(raw_size), Some(new_raw_size)) => Some(raw_size + new_raw_size),
+                    _ => None,
+                };
+                // the hashes of the tables do not cover the appended rows
+                dump.table_hashes.clear();
+            }
+            None => self.dumps.push(new_dump),
+        }
+    }
+
     pub fn find_dump(&mut self, options: &ReadOptions) -> Result<&Dump, Error> {
         match options {
             ReadOptions::Latest => {
@@ -160,13 +453,114 @@ impl IndexFile {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Clone)]
 pub struct Dump {
     pub directory_name: String,
     pub size: usize,
     pub created_at: u128,
     pub compressed: bool,
     pub encrypted: bool,
+    /// cipher used to encrypt the dump - dumps created before this option are using AES-256-GCM
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption_algorithm: Option<EncryptionAlgorithm>,
+    /// how the dump is encrypted - dumps created before this option are using the legacy fixed nonce
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption_version: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_type: Option<String>,
+    /// labels to organize the dumps - e.g. `env=staging`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// the schema or the rows only - dumps holding both don't record it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<DumpContent>,
+    /// size of the parts before their compression and encryption - not recorded by the dumps created before
+    /// this option
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_size: Option<usize>,
+    /// hash of the original rows of each table, by `<database>.<table>` - to find the tables changed since this dump
+    /// (PostgreSQL only)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub table_hashes: BTreeMap<String, String>,
+}
+
+impl Dump {
+    /// number of bytes read to restore the dump - `None` if unknown
+    pub fn restored_size(&self) -> Option<usize> {
+        match self.raw_size {
+            Some(raw_size) => Some(raw_size),
+            None if !self.compressed && !self.encrypted => Some(self.size),
+            None => None,
+        }
+    }
+
+    /// cipher to use to decrypt the dump
+    pub fn cipher(&self) -> EncryptionAlgorithm {
+        self.encryption_algorithm.unwrap_or_default()
+    }
+
+    /// encryption version to use to decrypt the dump
+    pub fn encryption_version(&self) -> u8 {
+        self.encryption_version.unwrap_or(LEGACY_ENCRYPTION_VERSION)
+    }
+
+    /// true if the dump has all the given labels
+    pub fn has_labels(&self, labels: &[(String, String)]) -> bool {
+        labels
+            .iter()
+            .all(|(key, value)| self.labels.get(key) == Some(value))
+    }
+}
+
+/// description of a dump stored next to its parts - to audit a dump without the index file of the datastore
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct DumpManifest {
+    /// version of Replibyte which has created the dump
+    pub replibyte_version: String,
+    /// entry of the dump in the index file - its engine, sizes, labels...
+    pub dump: Dump,
+    /// id of the transformer applied to each column, by `<database>.<table>.<column>`
+    #[serde(default)]
+    pub transformers: BTreeMap<String, String>,
+    /// number of rows of each table, by `<database>.<table>` - not recorded for the MySQL and MongoDB dumps, and the
+    /// dumps with appended parts
+    #[serde(default)]
+    pub rows: BTreeMap<String, u64>,
+    /// parts of the dump in the order they have been written
+    pub parts: Vec<DumpManifestPart>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct DumpManifestPart {
+    pub part: u16,
+    /// size of the part before its compression and encryption
+    pub raw_size: usize,
+    /// FNV-1a hash of the part before its compression and encryption, in hexadecimal
+    pub checksum: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
+pub enum DumpContent {
+    /// the statements creating the tables, indexes, sequences... without any row
+    #[serde(rename = "schema-only")]
+    SchemaOnly,
+    /// the rows only - restored into an existing schema
+    #[serde(rename = "data-only")]
+    DataOnly,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
+pub enum EncryptionAlgorithm {
+    #[serde(rename = "aes-256-gcm")]
+    Aes256Gcm,
+    #[serde(rename = "chacha20-poly1305")]
+    ChaCha20Poly1305,
+}
+
+impl Default for EncryptionAlgorithm {
+    fn default() -> Self {
+        EncryptionAlgorithm::Aes256Gcm
+    }
 }
 
 #[derive(Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Clone)]
@@ -175,6 +569,40 @@ pub enum ReadOptions {
     Dump { name: String },
 }
 
+/// parse a number of days - e.g. `14d`
+pub fn parse_days(value: &str) -> Result<i64, Error> {
+    match value.strip_suffix('d') {
+        Some(days) => days
+            .parse::<i64>()
+            .map_err(|err| Error::new(ErrorKind::Other, err.to_string())),
+        None => Err(Error::new(
+            ErrorKind::Other,
+            format!("'{}' is not a number of days", value),
+        )),
+    }
+}
+
+/// return the part number from a dump part file name - e.g. `12.dump` -> 12
+pub fn part_number(file_name: &str) -> Option<u16> {
+    file_name
+        .strip_suffix(".dump")
+        .and_then(|part| part.parse::<u16>().ok())
+}
+
+/// remove a dump from the index file once its parts are deleted - the index file is not rewritten if the dump is not
+/// recorded in it (e.g. a dump which failed before its entry was written)
+fn remove_dump_entry(datastore: &dyn Datastore, name: &str) -> Result<(), Error> {
+    let mut index_file = datastore.index_file()?;
+    let dumps = index_file.dumps.len();
+
+    index_file.dumps.retain(|dump| dump.directory_name != name);
+    if index_file.dumps.len() == dumps {
+        return Ok(());
+    }
+
+    datastore.write_index_file(&index_file)
+}
+
 fn compress(data: Bytes) -> Result<Bytes, Error> {
     let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
     let _ = enc.write_all(data.as_slice());
@@ -188,6 +616,50 @@ fn decompress(data: Bytes) -> Result<Bytes, Error> {
     Ok(decoded_data)
 }
 
+/// compression of a dump part - detected from its leading magic bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompressionFormat {
+    Zlib,
+    Gzip,
+    Zstd,
+}
+
+impl CompressionFormat {
+    fn detect(data: &[u8]) -> Option<Self> {
+        match data {
+            [0x1f, 0x8b, ..] => Some(CompressionFormat::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(CompressionFormat::Zstd),
+            // zlib has no magic bytes - only the headers of the fast, default and best levels are detected.
+            // `x^` (levels 2 to 5) is left out, it is too likely at the start of a plain text
+            [0x78, 0x01 | 0x9c | 0xda, ..] => Some(CompressionFormat::Zlib),
+            _ => None,
+        }
+    }
+}
+
+/// decompress a part read from the datastore - gzip and zstd parts (e.g. uploaded by hand) are detected whatever the
+/// `compressed` flag of the dump. Otherwise the flag is used - a part of a dump not flagged as compressed is
+/// decompressed only if it starts with a zlib header
+fn decompress_part(data: Bytes, compressed: bool) -> Result<Bytes, Error> {
+    match (CompressionFormat::detect(data.as_slice()), compressed) {
+        (Some(CompressionFormat::Gzip), _) => {
+            let mut decoded_data = Vec::new();
+            let _ = GzDecoder::new(data.as_slice()).read_to_end(&mut decoded_data)?;
+            Ok(decoded_data)
+        }
+        (Some(CompressionFormat::Zstd), _) => zstd::decode_all(data.as_slice()),
+        (_, true) => decompress(data),
+        (Some(CompressionFormat::Zlib), false) => {
+            let decoded_data = decompress(data.clone())?;
+            match decoded_data.is_empty() {
+                true => Ok(data),
+                false => Ok(decoded_data),
+            }
+        }
+        (None, false) => Ok(data),
+    }
+}
+
 fn get_encryption_key_with_correct_length(key: &str) -> String {
     if key.len() >= 32 {
         return key[0..32].to_string();
@@ -201,13 +673,38 @@ fn get_encryption_key_with_correct_length(key: &str) -> String {
     key_string
 }
 
-fn encrypt(data: Bytes, encryption_key: &str) -> Result<Bytes, Error> {
+/// encrypt the data with a random nonce - the nonce is stored before the encrypted data
+fn encrypt(
+    data: Bytes,
+    encryption_key: &str,
+    algorithm: EncryptionAlgorithm,
+) -> Result<Bytes, Error> {
+    let nonce = rand::random::<[u8; NONCE_LENGTH]>();
+
+    let mut encrypted_data = nonce.to_vec();
+    encrypted_data.extend(encrypt_with_nonce(data, encryption_key, algorithm, &nonce)?);
+
+    Ok(encrypted_data)
+}
+
+fn encrypt_with_nonce(
+    data: Bytes,
+    encryption_key: &str,
+    algorithm: EncryptionAlgorithm,
+    nonce: &[u8; NONCE_LENGTH],
+) -> Result<Bytes, Error> {
     let key = get_encryption_key_with_correct_length(encryption_key);
     let key = Key::from_slice(key.as_bytes());
-    let cipher = Aes256Gcm::new(key);
-    let nonce = Nonce::from_slice(b"unique nonce");
+    let nonce = Nonce::from_slice(nonce);
+
+    let encrypted_data = match algorithm {
+        EncryptionAlgorithm::Aes256Gcm => Aes256Gcm::new(key).encrypt(nonce, data.as_slice()),
+        EncryptionAlgorithm::ChaCha20Poly1305 => {
+            ChaCha20Poly1305::new(key).encrypt(nonce, data.as_slice())
+        }
+    };
 
-    let encrypted_data = match cipher.encrypt(nonce, data.as_slice()) {
+    let encrypted_data = match encrypted_data {
         Ok(data) => data,
         Err(err) => return Err(Error::new(ErrorKind::Other, format!("{:?}", err))),
     };
@@ -215,13 +712,67 @@ fn encrypt(data: Bytes, encryption_key: &str) -> Result<Bytes, Error> {
     Ok(encrypted_data)
 }
 
-fn decrypt(encrypted_data: Bytes, encryption_key: &str) -> Result<Bytes, Error> {
+/// decrypt the data with the nonce of the encryption version of the dump
+fn decrypt(
+    encrypted_data: Bytes,
+    encryption_key: &str,
+    algorithm: EncryptionAlgorithm,
+    version: u8,
+) -> Result<Bytes, Error> {
+    match version {
+        LEGACY_ENCRYPTION_VERSION => {
+            decrypt_with_nonce(&encrypted_data, encryption_key, algorithm, LEGACY_NONCE).or_else(
+                |err| {
+                    // the part may already be re-encrypted by an interrupted `dump reencrypt`
+                    decrypt(
+                        encrypted_data,
+                        encryption_key,
+                        algorithm,
+                        ENCRYPTION_VERSION,
+                    )
+                    .map_err(|_| err)
+                },
+            )
+        }
+        ENCRYPTION_VERSION => {
+            if encrypted_data.len() < NONCE_LENGTH {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    "the encrypted data is too short to contain its nonce",
+                ));
+            }
+
+            let (nonce, encrypted_data) = encrypted_data.split_at(NONCE_LENGTH);
+            decrypt_with_nonce(encrypted_data, encryption_key, algorithm, nonce)
+        }
+        version => Err(Error::new(
+            ErrorKind::Other,
+            format!(
+                "unknown encryption version {} - upgrade replibyte to read this dump",
+                version
+            ),
+        )),
+    }
+}
+
+fn decrypt_with_nonce(
+    encrypted_data: &[u8],
+    encryption_key: &str,
+    algorithm: EncryptionAlgorithm,
+    nonce: &[u8],
+) -> Result<Bytes, Error> {
     let key = get_encryption_key_with_correct_length(encryption_key);
     let key = Key::from_slice(key.as_bytes());
-    let cipher = Aes256Gcm::new(key);
-    let nonce = Nonce::from_slice(b"unique nonce");
+    let nonce = Nonce::from_slice(nonce);
+
+    let data = match algorithm {
+        EncryptionAlgorithm::Aes256Gcm => Aes256Gcm::new(key).decrypt(nonce, encrypted_data),
+        EncryptionAlgorithm::ChaCha20Poly1305 => {
+            ChaCha20Poly1305::new(key).decrypt(nonce, encrypted_data)
+        }
+    };
 
-    let data = match cipher.decrypt(nonce, encrypted_data.as_slice()) {
+    let data = match data {
         Ok(data) => data,
         Err(err) => return Err(Error::new(ErrorKind::Other, format!("{:?}", err))),
     };
@@ -231,7 +782,16 @@ fn decrypt(encrypted_data: Bytes, encryption_key: &str) -> Result<Bytes, Error>
 
 #[cfg(test)]
 mod tests {
-    use crate::datastore::{compress, decompress, decrypt, encrypt};
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use crate::datastore::{
+        compress, decompress, decompress_part, decrypt, encrypt, encrypt_with_nonce,
+        CompressionFormat, Dump, EncryptionAlgorithm, ENCRYPTION_VERSION,
+        LEGACY_ENCRYPTION_VERSION, LEGACY_NONCE,
+    };
 
     #[test]
     fn test_compression() {
@@ -241,21 +801,208 @@ mod tests {
         assert_eq!(decompress(compressed_data).unwrap(), data);
     }
 
+    #[test]
+    fn test_detect_compression_format() {
+        let data = b"INSERT INTO public.users (id, name) VALUES (1, 'xavier');".to_vec();
+
+        let zlib_data = compress(data.clone()).unwrap();
+        let mut gzip_encoder = GzEncoder::new(vec![], Compression::default());
+        gzip_encoder.write_all(data.as_slice()).unwrap();
+        let gzip_data = gzip_encoder.finish().unwrap();
+        let zstd_data = zstd::encode_all(data.as_slice(), 0).unwrap();
+
+        assert_eq!(
+            CompressionFormat::detect(zlib_data.as_slice()),
+            Some(CompressionFormat::Zlib)
+        );
+        assert_eq!(
+            CompressionFormat::detect(gzip_data.as_slice()),
+            Some(CompressionFormat::Gzip)
+        );
+        assert_eq!(
+            CompressionFormat::detect(zstd_data.as_slice()),
+            Some(CompressionFormat::Zstd)
+        );
+        assert_eq!(CompressionFormat::detect(data.as_slice()), None);
+        assert_eq!(CompressionFormat::detect(b"x^2 + y^2"), None);
+        assert_eq!(CompressionFormat::detect(b"x"), None);
+        assert_eq!(CompressionFormat::detect(b""), None);
+
+        // whatever the compression flag of the dump
+        for compressed in [true, false] {
+            for compressed_data in [&zlib_data, &gzip_data, &zstd_data] {
+                assert_eq!(
+                    decompress_part(compressed_data.clone(), compressed).unwrap(),
+                    data
+                );
+            }
+        }
+
+        assert_eq!(decompress_part(data.clone(), false).unwrap(), data);
+        // a zlib header followed by something else is kept as it is
+        assert_eq!(
+            decompress_part(vec![0x78, 0x9c, 0xff, 0xff], false).unwrap(),
+            vec![0x78, 0x9c, 0xff, 0xff]
+        );
+    }
+
     #[test]
     fn test_encryption_1() {
         let key = "this is my secret";
         let data = b"hello w0rld hello w0rld hello w0rld hello w0rld hello w0rld".to_vec();
-        let encrypted_data = encrypt(data.clone(), key).unwrap();
+        let encrypted_data = encrypt(data.clone(), key, EncryptionAlgorithm::Aes256Gcm).unwrap();
         assert_ne!(encrypted_data, data);
-        assert_eq!(decrypt(encrypted_data, key).unwrap(), data);
+        assert_eq!(
+            decrypt(
+                encrypted_data,
+                key,
+                EncryptionAlgorithm::Aes256Gcm,
+                ENCRYPTION_VERSION
+            )
+            .unwrap(),
+            data
+        );
     }
 
     #[test]
     fn test_encryption_2() {
         let key = "this is my secret very very very long and greater than 32 chars";
         let data = b"hello w0rld hello w0rld hello w0rld hello w0rld hello w0rld".to_vec();
-        let encrypted_data = encrypt(data.clone(), key).unwrap();
+        let encrypted_data = encrypt(data.clone(), key, EncryptionAlgorithm::Aes256Gcm).unwrap();
         assert_ne!(encrypted_data, data);
-        assert_eq!(decrypt(encrypted_data, key).unwrap(), data);
+        assert_eq!(
+            decrypt(
+                encrypted_data,
+                key,
+                EncryptionAlgorithm::Aes256Gcm,
+                ENCRYPTION_VERSION
+            )
+            .unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn test_encryption_chacha20_poly1305() {
+        let key = "this is my secret";
+        let data = b"hello w0rld hello w0rld hello w0rld hello w0rld hello w0rld".to_vec();
+        let encrypted_data =
+            encrypt(data.clone(), key, EncryptionAlgorithm::ChaCha20Poly1305).unwrap();
+        assert_ne!(encrypted_data, data);
+        assert_ne!(
+            encrypted_data,
+            encrypt(data.clone(), key, EncryptionAlgorithm::Aes256Gcm).unwrap()
+        );
+        // the data can't be decrypted with another cipher
+        assert!(decrypt(
+            encrypted_data.clone(),
+            key,
+            EncryptionAlgorithm::Aes256Gcm,
+            ENCRYPTION_VERSION
+        )
+        .is_err());
+        assert_eq!(
+            decrypt(
+                encrypted_data,
+                key,
+                EncryptionAlgorithm::ChaCha20Poly1305,
+                ENCRYPTION_VERSION
+            )
+            .unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn test_encryption_random_nonce() {
+        let key = "this is my secret";
+        let data = b"hello w0rld hello w0rld hello w0rld hello w0rld hello w0rld".to_vec();
+        let encrypted_data = encrypt(data.clone(), key, EncryptionAlgorithm::Aes256Gcm).unwrap();
+        // the same data is never encrypted twice the same way
+        assert_ne!(
+            encrypted_data,
+            encrypt(data.clone(), key, EncryptionAlgorithm::Aes256Gcm).unwrap()
+        );
+        assert!(decrypt(
+            encrypted_data[1..].to_vec(),
+            key,
+            EncryptionAlgorithm::Aes256Gcm,
+            ENCRYPTION_VERSION
+        )
+        .is_err());
+        assert!(decrypt(
+            vec![1, 2],
+            key,
+            EncryptionAlgorithm::Aes256Gcm,
+            ENCRYPTION_VERSION
+        )
+        .is_err());
+        assert!(decrypt(encrypted_data, key, EncryptionAlgorithm::Aes256Gcm, 3).is_err());
+    }
+
+    #[test]
+    fn test_legacy_encryption() {
+        let key = "this is my secret";
+        let data = b"hello w0rld hello w0rld hello w0rld hello w0rld hello w0rld".to_vec();
+        let legacy_encrypted_data = encrypt_with_nonce(
+            data.clone(),
+            key,
+            EncryptionAlgorithm::ChaCha20Poly1305,
+            LEGACY_NONCE,
+        )
+        .unwrap();
+        assert_eq!(
+            decrypt(
+                legacy_encrypted_data.clone(),
+                key,
+                EncryptionAlgorithm::ChaCha20Poly1305,
+                LEGACY_ENCRYPTION_VERSION
+            )
+            .unwrap(),
+            data
+        );
+        assert!(decrypt(
+            legacy_encrypted_data,
+            key,
+            EncryptionAlgorithm::ChaCha20Poly1305,
+            ENCRYPTION_VERSION
+        )
+        .is_err());
+
+        // the parts of a legacy dump can already be re-encrypted
+        let encrypted_data =
+            encrypt(data.clone(), key, EncryptionAlgorithm::ChaCha20Poly1305).unwrap();
+        assert_eq!(
+            decrypt(
+                encrypted_data,
+                key,
+                EncryptionAlgorithm::ChaCha20Poly1305,
+                LEGACY_ENCRYPTION_VERSION
+            )
+            .unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn test_dump_encryption_algorithm() {
+        // dumps created before the encryption algorithm option are using AES-256-GCM
+        let dump: Dump = serde_json::from_str(
+            r#"{"directory_name":"dump-1","size":10,"created_at":1,"compressed":true,"encrypted":true}"#,
+        )
+        .unwrap();
+        assert_eq!(dump.encryption_algorithm, None);
+        assert_eq!(dump.cipher(), EncryptionAlgorithm::Aes256Gcm);
+        assert_eq!(dump.encryption_version(), LEGACY_ENCRYPTION_VERSION);
+
+        let dump: Dump = serde_json::from_str(
+            r#"{"directory_name":"dump-1","size":10,"created_at":1,"compressed":true,"encrypted":true,"encryption_algorithm":"chacha20-poly1305"}"#,
+        )
+        .unwrap();
+        assert_eq!(dump.cipher(), EncryptionAlgorithm::ChaCha20Poly1305);
+        assert_eq!(dump.encryption_version(), LEGACY_ENCRYPTION_VERSION);
+        assert!(serde_json::to_string(&dump)
+            .unwrap()
+            .contains(r#""encryption_algorithm":"chacha20-poly1305""#));
     }
 }
@@ -0,0 +1,396 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::warn;
+use serde_json::Value;
+
+use crate::connector::Connector;
+use crate::datastore::{
+    Datastore, DumpContent, DumpManifest, EncryptionAlgorithm, IndexFile, ReadOptions,
+};
+use crate::types::Bytes;
+
+/// Write the dumps to several datastores (e.g. a primary and a replica bucket) - they are read from the
+/// first healthy one. A datastore failing a write is reported and left out of the next writes,
+/// to not record a dump with missing parts in its index file.
+pub struct MultiDatastore {
+    datastores: Vec<Box<dyn Datastore>>,
+    healthy: Vec<AtomicBool>,
+}
+
+impl MultiDatastore {
+    pub fn new(datastores: Vec<Box<dyn Datastore>>) -> Self {
+        let healthy = datastores.iter().map(|_| AtomicBool::new(true)).collect();
+
+        MultiDatastore {
+            datastores,
+            healthy,
+        }
+    }
+
+    fn healthy_datastores(&self) -> impl Iterator<Item = (usize, &Box<dyn Datastore>)> {
+        self.datastores
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| self.healthy[*i].load(Ordering::SeqCst))
+    }
+
+    fn primary(&self) -> &dyn Datastore {
+        match self.healthy_datastores().next() {
+            Some((_, datastore)) => datastore.as_ref(),
+            None => self.datastores[0].as_ref(),
+        }
+    }
+
+    fn set_unhealthy(&self, i: usize, action: &str, err: &Error) {
+        warn!(
+            "datastore #{} failed to {} - it is not written anymore: {}",
+            i + 1,
+            action,
+            err
+        );
+
+        self.healthy[i].store(false, Ordering::SeqCst);
+    }
+
+    /// run `f` on every healthy datastore - it fails only if it fails on all of them
+    fn write_all<F: Fn(&dyn Datastore) -> Result<(), Error>>(
+        &self,
+        action: &str,
+        f: F,
+    ) -> Result<(), Error> {
+        let mut errors = vec![];
+
+        for (i, datastore) in self.healthy_datastores() {
+            if let Err(err) = f(datastore.as_ref()) {
+                self.set_unhealthy(i, action, &err);
+                errors.push(format!("datastore #{}: {}", i + 1, err));
+            }
+        }
+
+        if self.healthy_datastores().next().is_none() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "failed to {} on every datastore ({})",
+                    action,
+                    errors.join(", ")
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// result of `f` on the first healthy datastore it succeeds on
+    fn read_first<'a, T, F: Fn(&'a dyn Datastore) -> Result<T, Error>>(
+        &'a self,
+        action: &str,
+        f: F,
+    ) -> Result<T, Error> {
+        let mut last_error = Error::new(ErrorKind::Other, "no healthy datastore");
+
+        for (i, datastore) in self.healthy_datastores() {
+            match f(datastore.as_ref()) {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    warn!(
+                        "datastore #{} failed to {} - trying the next one: {}",
+                        i + 1,
+                        action,
+                        err
+                    );
+
+                    last_error = err;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+impl Connector for MultiDatastore {
+    fn init(&mut self) -> Result<(), Error> {
+        let mut errors = vec![];
+
+        for (i, datastore) in self.datastores.iter_mut().enumerate() {
+            if let Err(err) = datastore.init() {
+                warn!("datastore #{} failed to initialize: {}", i + 1, err);
+                errors.push(format!("datastore #{}: {}", i + 1, err));
+                self.healthy[i].store(false, Ordering::SeqCst);
+            }
+        }
+
+        if errors.len() == self.datastores.len() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "failed to initialize every datastore ({})",
+                    errors.join(", ")
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Datastore for MultiDatastore {
+    fn index_file(&self) -> Result<IndexFile, Error> {
+        self.read_first("read the index file", |datastore| datastore.index_file())
+    }
+
+    fn raw_index_file(&self) -> Result<Value, Error> {
+        self.read_first("read the index file", |datastore| {
+            datastore.raw_index_file()
+        })
+    }
+
+    fn write_index_file(&self, index_file: &IndexFile) -> Result<(), Error> {
+        self.write_all("write the index file", |datastore| {
+            datastore.write_index_file(index_file)
+        })
+    }
+
+    fn write_raw_index_file(&self, raw_index_file: &Value) -> Result<(), Error> {
+        self.write_all("write the index file", |datastore| {
+            datastore.write_raw_index_file(raw_index_file)
+        })
+    }
+
+    fn write_part(&self, file_part: u16, data: Bytes) -> Result<usize, Error> {
+        // the parts are written with the same compression and encryption settings - so have the same size
+        let size = Cell::new(0);
+
+        let _ = self.write_all(
+            format!("write the part {}", file_part).as_str(),
+            |datastore| {
+                size.set(datastore.write_part(file_part, data.clone())?);
+                Ok(())
+            },
+        )?;
+
+        Ok(size.get())
+    }
+
+    fn write_dump_entry(&self, size: usize, raw_size: usize) -> Result<(), Error> {
+        self.write_all("write the index file", |datastore| {
+            datastore.write_dump_entry(size, raw_size)
+        })
+    }
+
+    fn write_dump_manifest(&self, manifest: &DumpManifest) -> Result<(), Error> {
+        self.write_all("write the dump manifest", |datastore| {
+            datastore.write_dump_manifest(manifest)
+        })
+    }
+
+    fn read_dump_manifest(&self, name: &str) -> Result<DumpManifest, Error> {
+        self.read_first("read the dump manifest", |datastore| {
+            datastore.read_dump_manifest(name)
+        })
+    }
+
+    fn read(
+        &self,
+        options: &ReadOptions,
+        data_callback: &mut dyn FnMut(Bytes),
+    ) -> Result<(), Error> {
+        // the dump is read from a single datastore - a failure halfway can't be resumed from another one
+        let datastore = self.read_first("find the dump", |datastore| {
+            let _ = datastore.index_file()?.find_dump(options)?;
+            Ok(datastore)
+        })?;
+
+        datastore.read(options, data_callback)
+    }
+
+    fn compression_enabled(&self) -> bool {
+        self.primary().compression_enabled()
+    }
+
+    fn set_compression(&mut self, enable: bool) {
+        for datastore in self.datastores.iter_mut() {
+            datastore.set_compression(enable);
+        }
+    }
+
+    fn encryption_key(&self) -> &Option<String> {
+        self.primary().encryption_key()
+    }
+
+    fn set_encryption_key(&mut self, key: String) {
+        for datastore in self.datastores.iter_mut() {
+            datastore.set_encryption_key(key.clone());
+        }
+    }
+
+    fn encryption_algorithm(&self) -> EncryptionAlgorithm {
+        self.primary().encryption_algorithm()
+    }
+
+    fn set_encryption_algorithm(&mut self, algorithm: EncryptionAlgorithm) {
+        for datastore in self.datastores.iter_mut() {
+            datastore.set_encryption_algorithm(algorithm);
+        }
+    }
+
+    fn dump_name(&self) -> &str {
+        self.primary().dump_name()
+    }
+
+    fn set_dump_name(&mut self, name: String) {
+        for datastore in self.datastores.iter_mut() {
+            datastore.set_dump_name(name.clone());
+        }
+    }
+
+    fn set_namespace(&mut self, namespace: String) {
+        for datastore in self.datastores.iter_mut() {
+            datastore.set_namespace(namespace.clone());
+        }
+    }
+
+    fn set_source_type(&mut self, source_type: String) {
+        for datastore in self.datastores.iter_mut() {
+            datastore.set_source_type(source_type.clone());
+        }
+    }
+
+    fn set_labels(&mut self, labels: BTreeMap<String, String>) {
+        for datastore in self.datastores.iter_mut() {
+            datastore.set_labels(labels.clone());
+        }
+    }
+
+    fn set_content(&mut self, content: Option<DumpContent>) {
+        for datastore in self.datastores.iter_mut() {
+            datastore.set_content(content);
+        }
+    }
+
+    fn set_table_hashes(&mut self, table_hashes: BTreeMap<String, String>) {
+        for datastore in self.datastores.iter_mut() {
+            datastore.set_table_hashes(table_hashes.clone());
+        }
+    }
+
+    fn last_part(&self, name: &str) -> Result<u16, Error> {
+        self.read_first("read the dump parts", |datastore| datastore.last_part(name))
+    }
+
+    fn part_numbers(&self, name: &str) -> Result<Vec<u16>, Error> {
+        self.read_first("read the dump parts", |datastore| {
+            datastore.part_numbers(name)
+        })
+    }
+
+    fn set_part_offset(&mut self, offset: u16) {
+        for datastore in self.datastores.iter_mut() {
+            datastore.set_part_offset(offset);
+        }
+    }
+
+    fn parts_size(&self, name: &str) -> Result<usize, Error> {
+        self.read_first("read the dump parts", |datastore| {
+            datastore.parts_size(name)
+        })
+    }
+
+    fn list_all_objects(&self) -> Result<Vec<String>, Error> {
+        self.read_first("list the objects", |datastore| datastore.list_all_objects())
+    }
+
+    fn delete_by_name(&self, name: String) -> Result<(), Error> {
+        self.write_all(
+            format!("delete the dump '{}'", name).as_str(),
+            |datastore| datastore.delete_by_name(name.clone()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::connector::Connector;
+    use crate::datastore::local_disk::LocalDisk;
+    use crate::datastore::multi::MultiDatastore;
+    use crate::datastore::{Datastore, ReadOptions};
+
+    fn local_disk(dir: &str) -> Box<dyn Datastore> {
+        Box::new(LocalDisk::new(dir.to_string()))
+    }
+
+    fn read(datastore: &dyn Datastore) -> Vec<u8> {
+        let mut data = vec![];
+        let _ = datastore
+            .read(&ReadOptions::Latest, &mut |bytes| data.extend(bytes))
+            .unwrap();
+
+        data
+    }
+
+    #[test]
+    fn write_to_every_datastore() {
+        let primary_dir = tempdir().expect("cannot create tempdir");
+        let replica_dir = tempdir().expect("cannot create tempdir");
+        let primary_dir = primary_dir.path().to_str().unwrap();
+        let replica_dir = replica_dir.path().to_str().unwrap();
+
+        let mut datastore =
+            MultiDatastore::new(vec![local_disk(primary_dir), local_disk(replica_dir)]);
+        let _ = datastore.init().expect("multi datastore init failed");
+        datastore.set_dump_name("dump-1".to_string());
+
+        let _ = datastore.write(1, b"hello".to_vec()).unwrap();
+        let _ = datastore.write(2, b" world".to_vec()).unwrap();
+
+        // the dump lands in both datastores
+        for dir in [primary_dir, replica_dir] {
+            let mut local_disk = local_disk(dir);
+            let _ = local_disk.init().unwrap();
+
+            let mut index_file = local_disk.index_file().unwrap();
+            assert_eq!(index_file.dumps.len(), 1);
+            assert_eq!(
+                index_file
+                    .find_dump(&ReadOptions::Latest)
+                    .unwrap()
+                    .directory_name,
+                "dump-1"
+            );
+
+            assert_eq!(read(local_disk.as_ref()), b"hello world".to_vec());
+        }
+
+        assert_eq!(read(&datastore), b"hello world".to_vec());
+    }
+
+    #[test]
+    fn report_partial_failures() {
+        let primary_dir = tempdir().expect("cannot create tempdir");
+        let replica_dir = tempdir().expect("cannot create tempdir");
+        let primary_dir = primary_dir.path().to_str().unwrap();
+        let replica_dir = replica_dir.path().to_str().unwrap();
+
+        let mut datastore =
+            MultiDatastore::new(vec![local_disk(primary_dir), local_disk(replica_dir)]);
+        let _ = datastore.init().expect("multi datastore init failed");
+        datastore.set_dump_name("dump-1".to_string());
+
+        // the primary can't be written anymore - the replica still gets the dump
+        fs::remove_dir_all(primary_dir).unwrap();
+        assert!(datastore.write(1, b"hello".to_vec()).is_ok());
+        assert_eq!(read(&datastore), b"hello".to_vec());
+
+        // nothing can be written anymore
+        fs::remove_dir_all(replica_dir).unwrap();
+        assert!(datastore.write(2, b" world".to_vec()).is_err());
+    }
+}
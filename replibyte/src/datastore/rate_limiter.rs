@@ -0,0 +1,79 @@
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// an idle datastore does not earn more than this credit to go over the limit
+const MAX_BURST: Duration = Duration::from_secs(1);
+
+/// Limit the bandwidth used by the transfers - a transfer is put to sleep until the average rate is under the limit
+pub struct RateLimiter {
+    bytes_per_second: f64,
+    // start of the measure and bytes transferred since
+    state: Mutex<(Instant, u64)>,
+}
+
+impl RateLimiter {
+    pub fn new(megabytes_per_second: f64) -> Self {
+        RateLimiter {
+            bytes_per_second: megabytes_per_second * 1024.0 * 1024.0,
+            state: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /// account for `bytes` transferred and wait as long as required to stay under the limit
+    pub fn throttle(&self, bytes: usize) {
+        let mut state = self.state.lock().unwrap();
+        let (start, transferred_bytes) = &mut *state;
+
+        if start.elapsed() > self.expected_duration(*transferred_bytes) + MAX_BURST {
+            *start = Instant::now();
+            *transferred_bytes = 0;
+        }
+
+        *transferred_bytes += bytes as u64;
+
+        let expected_duration = self.expected_duration(*transferred_bytes);
+        let elapsed = start.elapsed();
+
+        if expected_duration > elapsed {
+            sleep(expected_duration - elapsed);
+        }
+    }
+
+    fn expected_duration(&self, bytes: u64) -> Duration {
+        Duration::from_secs_f64(bytes as f64 / self.bytes_per_second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::datastore::rate_limiter::RateLimiter;
+
+    #[test]
+    fn throttle_transfers() {
+        // 1MB/s
+        let rate_limiter = RateLimiter::new(1.0);
+        let payload = vec![0u8; 512 * 1024];
+
+        let start = Instant::now();
+        for chunk in payload.chunks(64 * 1024) {
+            rate_limiter.throttle(chunk.len());
+        }
+
+        // 512KB at 1MB/s
+        assert!(start.elapsed() >= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn do_not_throttle_under_the_limit() {
+        // 100MB/s
+        let rate_limiter = RateLimiter::new(100.0);
+
+        let start = Instant::now();
+        rate_limiter.throttle(1024);
+
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+}
@@ -1,6 +1,10 @@
 use std::borrow::Cow;
-use std::io::{Error, ErrorKind};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::SystemTime;
 
 use aws_config::profile::retry_config::ProfileFileRetryConfigProvider;
 use aws_config::profile::{ProfileFileCredentialsProvider, ProfileFileRegionProvider};
@@ -8,17 +12,23 @@ use aws_sdk_s3::model::{
     BucketLocationConstraint, CreateBucketConfiguration, Delete, Object, ObjectIdentifier,
 };
 use aws_sdk_s3::types::ByteStream;
-use aws_sdk_s3::{Client, Endpoint as SdkEndpoint};
+use aws_sdk_s3::{Client, Config as SdkConfig, Endpoint as SdkEndpoint};
+use aws_smithy_client::hyper_ext::Adapter;
 use aws_types::region::Region;
 use aws_types::Credentials;
-use log::{error, info};
+use log::{error, info, warn};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, RootCertStore, ServerName};
 use serde_json::Value;
 
-use crate::config::{AwsCredentials, Endpoint};
+use crate::config::{AwsCredentials, Endpoint, TlsOptions};
 use crate::connector::Connector;
+use crate::datastore::rate_limiter::RateLimiter;
 use crate::datastore::s3::S3Error::FailedObjectUpload;
 use crate::datastore::{
-    compress, decompress, decrypt, encrypt, Datastore, Dump, IndexFile, ReadOptions,
+    compress, decompress_part, decrypt, encrypt, remove_dump_entry, Datastore, Dump, DumpContent,
+    DumpManifest, EncryptionAlgorithm, IndexFile, ReadOptions, ENCRYPTION_VERSION,
+    MANIFEST_FILE_NAME,
 };
 use crate::runtime::block_on;
 use crate::types::Bytes;
@@ -27,6 +37,12 @@ use crate::utils::epoch_millis;
 use super::INDEX_FILE_NAME;
 
 const GOOGLE_CLOUD_STORAGE_URL: &str = "https://storage.googleapis.com";
+// tokens of the key layout of the dump parts
+const DUMP_NAME_TOKEN: &str = "{dump_name}";
+const PART_TOKEN: &str = "{part}";
+const EXTENSION_TOKEN: &str = "{extension}";
+const DEFAULT_KEY_LAYOUT: &str = "{dump_name}/{part}.{extension}";
+const PART_EXTENSION: &str = "dump";
 
 pub struct S3 {
     bucket: String,
@@ -36,6 +52,16 @@ pub struct S3 {
     client: Client,
     enable_compression: bool,
     encryption_key: Option<String>,
+    encryption_algorithm: EncryptionAlgorithm,
+    namespace: Option<String>,
+    source_type: Option<String>,
+    labels: BTreeMap<String, String>,
+    content: Option<DumpContent>,
+    table_hashes: BTreeMap<String, String>,
+    part_offset: u16,
+    rate_limiter: Option<RateLimiter>,
+    create_bucket_if_missing: bool,
+    key_layout: String,
 }
 
 impl S3 {
@@ -45,6 +71,7 @@ impl S3 {
         profile: Option<S>,
         credentials: Option<AwsCredentials>,
         endpoint: Endpoint,
+        tls: TlsOptions,
     ) -> anyhow::Result<Self>
     where
         S: 'static + AsRef<str> + Into<Cow<'static, str>> + Clone,
@@ -109,9 +136,19 @@ impl S3 {
             root_key: format!("dump-{}", epoch_millis()),
             region: region.map(|region| region.as_ref().into()),
             endpoint,
-            client: Client::from_conf(s3_config),
+            client: client(s3_config, &tls)?,
             enable_compression: true,
             encryption_key: None,
+            encryption_algorithm: EncryptionAlgorithm::default(),
+            namespace: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            table_hashes: BTreeMap::new(),
+            part_offset: 0,
+            rate_limiter: None,
+            create_bucket_if_missing: true,
+            key_layout: DEFAULT_KEY_LAYOUT.to_string(),
         })
     }
 
@@ -121,6 +158,7 @@ impl S3 {
         access_key: S,
         secret: S,
         endpoint: Endpoint,
+        tls: TlsOptions,
     ) -> anyhow::Result<Self>
     where
         S: 'static + AsRef<str> + Into<Cow<'static, str>> + Clone,
@@ -142,9 +180,81 @@ impl S3 {
                 session_token: None,
             }),
             endpoint,
+            tls,
         )
     }
 
+    /// Limit the upload and download rate (in MB/s)
+    pub fn set_bandwidth_limit(&mut self, megabytes_per_second: f64) {
+        self.rate_limiter = Some(RateLimiter::new(megabytes_per_second));
+    }
+
+    /// Do not try to create the bucket on init - it must exist already (e.g. the credentials can't create buckets)
+    pub fn set_create_bucket_if_missing(&mut self, create_bucket_if_missing: bool) {
+        self.create_bucket_if_missing = create_bucket_if_missing;
+    }
+
+    /// Store the parts of the dumps under keys following this template - e.g. `backups/{dump_name}/{part}.{extension}`.
+    /// The `{part}` token must appear once.
+    pub fn set_key_layout(&mut self, key_layout: String) {
+        self.key_layout = key_layout;
+    }
+
+    /// wait as long as required to not transfer `bytes` above the bandwidth limit
+    fn throttle(&self, bytes: usize) {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.throttle(bytes);
+        }
+    }
+
+    /// return the object key prefixed with the namespace, if any
+    fn key(&self, key: &str) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}/{}", namespace, key),
+            None => key.to_string(),
+        }
+    }
+
+    /// return the object key of a part of a dump, from the key layout
+    fn part_key(&self, name: &str, part: u16) -> String {
+        self.key(
+            render_key_layout(self.key_layout.as_str(), name, part.to_string().as_str()).as_str(),
+        )
+    }
+
+    /// return the object key of the manifest of a dump - next to the parts of the default key layout
+    fn manifest_key(&self, name: &str) -> String {
+        self.key(format!("{}/{}", name, MANIFEST_FILE_NAME).as_str())
+    }
+
+    /// return the stored parts of a dump sorted by part number - the object keys are matched against the key layout
+    fn parts(&self, name: &str) -> Result<Vec<(u16, Object)>, Error> {
+        let pattern =
+            self.key(render_key_layout(self.key_layout.as_str(), name, PART_TOKEN).as_str());
+        let (prefix, suffix) = pattern.split_once(PART_TOKEN).ok_or_else(|| {
+            Error::new(
+                ErrorKind::Other,
+                format!(
+                    "the key layout '{}' must contain the {} token",
+                    self.key_layout, PART_TOKEN
+                ),
+            )
+        })?;
+
+        let mut parts = list_objects(&self.client, self.bucket.as_str(), Some(prefix))?
+            .into_iter()
+            .filter_map(|object| {
+                let part = object
+                    .key()
+                    .and_then(|key| key_part_number(key, prefix, suffix))?;
+                Some((part, object))
+            })
+            .collect::<Vec<_>>();
+
+        parts.sort_by_key(|(part, _)| *part);
+        Ok(parts)
+    }
+
     fn create_index_file(&self) -> Result<IndexFile, Error> {
         match self.index_file() {
             Ok(index_file) => Ok(index_file),
@@ -163,6 +273,12 @@ impl Connector for S3 {
             Endpoint::Custom(url) if url.as_str() == GOOGLE_CLOUD_STORAGE_URL => {
                 // Do not try to create bucket - the current S3 client does not supports well GCP Cloud Storage
             }
+            _ if !self.create_bucket_if_missing => {
+                info!(
+                    "bucket creation is disabled - the bucket {} must exist",
+                    self.bucket
+                );
+            }
             _ => {
                 let _ = create_bucket(&self.client, self.bucket.as_str(), self.region.as_ref())?;
             }
@@ -174,13 +290,17 @@ impl Connector for S3 {
 
 impl Datastore for S3 {
     fn index_file(&self) -> Result<IndexFile, Error> {
-        let object = get_object(&self.client, self.bucket.as_str(), INDEX_FILE_NAME)?;
+        let index_file_key = self.key(INDEX_FILE_NAME);
+        let object = get_object(&self.client, self.bucket.as_str(), index_file_key.as_str())?;
+        self.throttle(object.len());
         let index_file: IndexFile = serde_json::from_slice(object.as_slice())?;
         Ok(index_file)
     }
 
     fn raw_index_file(&self) -> Result<Value, Error> {
-        let object = get_object(&self.client, self.bucket.as_str(), INDEX_FILE_NAME)?;
+        let index_file_key = self.key(INDEX_FILE_NAME);
+        let object = get_object(&self.client, self.bucket.as_str(), index_file_key.as_str())?;
+        self.throttle(object.len());
         let index_file = serde_json::from_slice(object.as_slice())?;
 
         Ok(index_file)
@@ -188,11 +308,12 @@ impl Datastore for S3 {
 
     fn write_index_file(&self, index_file: &IndexFile) -> Result<(), Error> {
         let index_file_json = serde_json::to_vec(index_file)?;
+        self.throttle(index_file_json.len());
 
         create_object(
             &self.client,
             self.bucket.as_str(),
-            INDEX_FILE_NAME,
+            self.key(INDEX_FILE_NAME).as_str(),
             index_file_json,
         )
         .map_err(|err| Error::from(err))
@@ -200,25 +321,75 @@ impl Datastore for S3 {
 
     fn write_raw_index_file(&self, raw_index_file: &Value) -> Result<(), Error> {
         let index_file_json = serde_json::to_vec(raw_index_file)?;
+        self.throttle(index_file_json.len());
 
         create_object(
             &self.client,
             self.bucket.as_str(),
-            INDEX_FILE_NAME,
+            self.key(INDEX_FILE_NAME).as_str(),
             index_file_json,
         )
         .map_err(|err| Error::from(err))
     }
 
-    fn write(&self, file_part: u16, data: Bytes) -> Result<(), Error> {
-        write_objects(
+    fn write_part(&self, file_part: u16, data: Bytes) -> Result<usize, Error> {
+        let file_part = file_part + self.part_offset;
+
+        write_object(
             self,
             file_part,
             data,
             self.bucket.as_str(),
-            self.root_key.as_str(),
+            self.part_key(self.root_key.as_str(), file_part).as_str(),
+            &self.client,
+            self.rate_limiter.as_ref(),
+        )
+    }
+
+    fn write_dump_entry(&self, size: usize, raw_size: usize) -> Result<(), Error> {
+        let mut index_file = self.index_file()?;
+
+        index_file.add_dump_size(Dump {
+            directory_name: self.root_key.to_string(),
+            size,
+            created_at: epoch_millis(),
+            compressed: self.compression_enabled(),
+            encrypted: self.encryption_key().is_some(),
+            encryption_algorithm: self
+                .encryption_key()
+                .as_ref()
+                .map(|_| self.encryption_algorithm()),
+            encryption_version: self.encryption_key().as_ref().map(|_| ENCRYPTION_VERSION),
+            source_type: self.source_type.clone(),
+            labels: self.labels.clone(),
+            content: self.content,
+            raw_size: Some(raw_size),
+            table_hashes: self.table_hashes.clone(),
+        });
+
+        self.write_index_file(&index_file)
+    }
+
+    fn write_dump_manifest(&self, manifest: &DumpManifest) -> Result<(), Error> {
+        let manifest_json = serde_json::to_vec_pretty(manifest)?;
+        self.throttle(manifest_json.len());
+
+        create_object(
             &self.client,
+            self.bucket.as_str(),
+            self.manifest_key(self.root_key.as_str()).as_str(),
+            manifest_json,
         )
+        .map_err(|err| Error::from(err))
+    }
+
+    fn read_dump_manifest(&self, name: &str) -> Result<DumpManifest, Error> {
+        let manifest_key = self.manifest_key(name);
+        let object = get_object(&self.client, self.bucket.as_str(), manifest_key.as_str())?;
+        self.throttle(object.len());
+        let manifest = serde_json::from_slice(object.as_slice())?;
+
+        Ok(manifest)
     }
 
     fn read(
@@ -229,29 +400,28 @@ impl Datastore for S3 {
         let mut index_file = self.index_file()?;
         let dump = index_file.find_dump(options)?;
 
-        for object in list_objects(
-            &self.client,
-            self.bucket.as_str(),
-            Some(dump.directory_name.as_str()),
-        )? {
+        // read the parts in the order they have been written
+        for (_, object) in self.parts(dump.directory_name.as_str())? {
             let data = get_object(&self.client, self.bucket.as_str(), object.key().unwrap())?;
+            self.throttle(data.len());
 
             // decrypt data?
             let data = if dump.encrypted {
                 // It should be safe to unwrap here because the dump is marked as encrypted in the dump manifest
                 // so if there is no encryption key set at the datastore level we want to panic.
                 let encryption_key = self.encryption_key.as_ref().unwrap();
-                decrypt(data, encryption_key.as_str())?
+                decrypt(
+                    data,
+                    encryption_key.as_str(),
+                    dump.cipher(),
+                    dump.encryption_version(),
+                )?
             } else {
                 data
             };
 
             // decompress data?
-            let data = if dump.compressed {
-                decompress(data)?
-            } else {
-                data
-            };
+            let data = decompress_part(data, dump.compressed)?;
 
             data_callback(data);
         }
@@ -263,14 +433,77 @@ impl Datastore for S3 {
         self.encryption_key = Some(key);
     }
 
+    fn set_encryption_algorithm(&mut self, algorithm: EncryptionAlgorithm) {
+        self.encryption_algorithm = algorithm;
+    }
+
     fn set_compression(&mut self, enable: bool) {
         self.enable_compression = enable;
     }
 
+    fn dump_name(&self) -> &str {
+        self.root_key.as_str()
+    }
+
     fn set_dump_name(&mut self, name: String) {
         self.root_key = name;
     }
 
+    fn set_namespace(&mut self, namespace: String) {
+        self.namespace = Some(namespace.trim_matches('/').to_string());
+    }
+
+    fn set_source_type(&mut self, source_type: String) {
+        self.source_type = Some(source_type);
+    }
+
+    fn set_labels(&mut self, labels: BTreeMap<String, String>) {
+        self.labels = labels;
+    }
+
+    fn set_content(&mut self, content: Option<DumpContent>) {
+        self.content = content;
+    }
+
+    fn set_table_hashes(&mut self, table_hashes: BTreeMap<String, String>) {
+        self.table_hashes = table_hashes;
+    }
+
+    fn last_part(&self, name: &str) -> Result<u16, Error> {
+        let last_part = self
+            .parts(name)?
+            .iter()
+            .map(|(part, _)| *part)
+            .max()
+            .unwrap_or(0);
+
+        Ok(last_part)
+    }
+
+    fn part_numbers(&self, name: &str) -> Result<Vec<u16>, Error> {
+        let part_numbers = self
+            .parts(name)?
+            .iter()
+            .map(|(part, _)| *part)
+            .collect::<Vec<_>>();
+
+        Ok(part_numbers)
+    }
+
+    fn set_part_offset(&mut self, offset: u16) {
+        self.part_offset = offset;
+    }
+
+    fn parts_size(&self, name: &str) -> Result<usize, Error> {
+        let parts_size = self
+            .parts(name)?
+            .iter()
+            .map(|(_, object)| object.size() as usize)
+            .sum();
+
+        Ok(parts_size)
+    }
+
     fn compression_enabled(&self) -> bool {
         self.enable_compression
     }
@@ -279,27 +512,92 @@ impl Datastore for S3 {
         &self.encryption_key
     }
 
-    fn delete_by_name(&self, name: String) -> Result<(), Error> {
-        let mut index_file = self.index_file()?;
+    fn encryption_algorithm(&self) -> EncryptionAlgorithm {
+        self.encryption_algorithm
+    }
 
+    fn delete_by_name(&self, name: String) -> Result<(), Error> {
         let bucket = &self.bucket;
+        let mut keys = self
+            .parts(name.as_str())?
+            .iter()
+            .filter_map(|(_, object)| object.key().map(str::to_string))
+            .collect::<Vec<_>>();
 
-        let _ = delete_directory(&self.client, bucket, &name).map_err(|err| Error::from(err))?;
+        // deleting a missing object is not an error - the dumps created before the manifests have none
+        keys.push(self.manifest_key(name.as_str()));
 
-        index_file.dumps.retain(|b| b.directory_name != name);
+        let _ = delete_objects(&self.client, bucket, keys).map_err(|err| Error::from(err))?;
 
-        self.write_index_file(&index_file)
+        remove_dump_entry(self, name.as_str())
+    }
+
+    fn list_all_objects(&self) -> Result<Vec<String>, Error> {
+        let prefix = self
+            .namespace
+            .as_ref()
+            .map(|namespace| format!("{}/", namespace));
+
+        let objects = list_objects(&self.client, self.bucket.as_str(), prefix.as_deref())?
+            .iter()
+            .filter_map(|object| object.key())
+            .map(|key| match &prefix {
+                Some(prefix) => key.strip_prefix(prefix.as_str()).unwrap_or(key).to_string(),
+                None => key.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        if self.key_layout == DEFAULT_KEY_LAYOUT {
+            return Ok(objects);
+        }
+
+        // the parts of the dumps are listed with the default key layout - e.g. `dump-1/1.dump`
+        let patterns = self
+            .index_file()?
+            .dumps
+            .into_iter()
+            .map(|dump| {
+                let pattern = render_key_layout(
+                    self.key_layout.as_str(),
+                    dump.directory_name.as_str(),
+                    PART_TOKEN,
+                );
+                (dump.directory_name, pattern)
+            })
+            .collect::<Vec<_>>();
+
+        let objects = objects
+            .into_iter()
+            .map(|key| {
+                patterns
+                    .iter()
+                    .find_map(|(name, pattern)| {
+                        let (prefix, suffix) = pattern.split_once(PART_TOKEN)?;
+                        let part = key_part_number(key.as_str(), prefix, suffix)?;
+                        Some(render_key_layout(
+                            DEFAULT_KEY_LAYOUT,
+                            name,
+                            part.to_string().as_str(),
+                        ))
+                    })
+                    .unwrap_or(key)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(objects)
     }
 }
 
-fn write_objects<B: Datastore>(
+/// upload a part of a dump and check it has been stored - return its stored size
+fn write_object<B: Datastore>(
     datastore: &B,
     file_part: u16,
     data: Bytes,
     bucket: &str,
-    root_key: &str,
+    key: &str,
     client: &Client,
-) -> Result<(), Error> {
+    rate_limiter: Option<&RateLimiter>,
+) -> Result<usize, Error> {
     // compress data?
     let data = if datastore.compression_enabled() {
         compress(data)?
@@ -309,47 +607,49 @@ fn write_objects<B: Datastore>(
 
     // encrypt data?
     let data = match datastore.encryption_key() {
-        Some(key) => encrypt(data, key.as_str())?,
+        Some(key) => encrypt(data, key.as_str(), datastore.encryption_algorithm())?,
         None => data,
     };
 
     let data_size = data.len();
-    let key = format!("{}/{}.dump", root_key, file_part);
 
-    info!("upload object '{}' part {} on", key.as_str(), file_part);
+    info!("upload object '{}' part {} on", key, file_part);
 
-    let _ = create_object(client, bucket, key.as_str(), data)?;
-
-    // update index file
-    let mut index_file = datastore.index_file()?;
+    if let Some(rate_limiter) = rate_limiter {
+        rate_limiter.throttle(data_size);
+    }
 
-    let mut new_dump = Dump {
-        directory_name: root_key.to_string(),
-        size: 0,
-        created_at: epoch_millis(),
-        compressed: datastore.compression_enabled(),
-        encrypted: datastore.encryption_key().is_some(),
-    };
+    let _ = create_object(client, bucket, key, data)?;
 
-    // find or create dump
-    let mut dump = index_file
-        .dumps
-        .iter_mut()
-        .find(|b| b.directory_name.as_str() == root_key)
-        .unwrap_or(&mut new_dump);
-
-    if dump.size == 0 {
-        // it means it's a new dump.
-        // We need to add it into the index_file.dumps
-        new_dump.size = data_size;
-        index_file.dumps.push(new_dump);
-    } else {
-        // update total dump size
-        dump.size = dump.size + data_size;
+    // the part must be fully uploaded before the dump is recorded in the index file
+    let stored_size = head_object(client, bucket, key)?;
+    if stored_size != data_size {
+        return Err(Error::new(
+            ErrorKind::Other,
+            format!(
+                "the object '{}/{}' is incomplete: {} bytes stored instead of {}",
+                bucket, key, stored_size, data_size
+            ),
+        ));
     }
 
-    // save index file
-    datastore.write_index_file(&index_file)
+    Ok(data_size)
+}
+
+/// return the key layout with its tokens replaced by the dump name, the part and the extension
+fn render_key_layout(key_layout: &str, dump_name: &str, part: &str) -> String {
+    key_layout
+        .replace(DUMP_NAME_TOKEN, dump_name)
+        .replace(EXTENSION_TOKEN, PART_EXTENSION)
+        .replace(PART_TOKEN, part)
+}
+
+/// return the part number of an object key - `prefix` and `suffix` surround the part in the key layout
+fn key_part_number(key: &str, prefix: &str, suffix: &str) -> Option<u16> {
+    key.strip_prefix(prefix)?
+        .strip_suffix(suffix)?
+        .parse::<u16>()
+        .ok()
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -361,7 +661,7 @@ enum S3Error<'a> {
     FailedObjectDownload { bucket: &'a str, key: &'a str },
     FailedObjectUpload { bucket: &'a str, key: &'a str },
     FailedToDeleteObject { bucket: &'a str, key: &'a str },
-    FailedToDeleteDirectory { bucket: &'a str, directory: &'a str },
+    FailedToDeleteObjects { bucket: &'a str },
 }
 
 impl<'a> From<S3Error<'a>> for Error {
@@ -407,14 +707,95 @@ impl<'a> From<S3Error<'a>> for Error {
                 ErrorKind::Other,
                 format!("failed to delete object '{}/{}'", bucket, object),
             ),
-            S3Error::FailedToDeleteDirectory { bucket, directory } => Error::new(
+            S3Error::FailedToDeleteObjects { bucket } => Error::new(
                 ErrorKind::Other,
-                format!("failed to delete directory '{}/{}'", bucket, directory),
+                format!("failed to delete objects from bucket '{}'", bucket),
             ),
         }
     }
 }
 
+/// Build the S3 client - the default HTTPS connector of the SDK is replaced by one trusting the
+/// CA bundle (or skipping the certificate verification) when set
+fn client(s3_config: SdkConfig, tls: &TlsOptions) -> Result<Client, Error> {
+    if tls.ca_bundle_path.is_none() && !tls.insecure_skip_tls_verify {
+        return Ok(Client::from_conf(s3_config));
+    }
+
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_tls_config(tls_client_config(tls)?)
+        .https_or_http()
+        .enable_http1()
+        .build();
+
+    Ok(Client::from_conf_conn(
+        s3_config,
+        Adapter::builder().build(connector),
+    ))
+}
+
+fn tls_client_config(tls: &TlsOptions) -> Result<ClientConfig, Error> {
+    let root_store = match &tls.ca_bundle_path {
+        Some(ca_bundle_path) => ca_bundle_root_store(ca_bundle_path.as_str())?,
+        None => RootCertStore::empty(),
+    };
+
+    let mut config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(root_store)
+        .with_no_client_auth();
+
+    if tls.insecure_skip_tls_verify {
+        warn!("TLS certificate verification is disabled - do not use it in production");
+        config
+            .dangerous()
+            .set_certificate_verifier(Arc::new(NoCertificateVerification));
+    }
+
+    Ok(config)
+}
+
+/// trust store made of the certificates of a PEM CA bundle
+fn ca_bundle_root_store(ca_bundle_path: &str) -> Result<RootCertStore, Error> {
+    let file = File::open(ca_bundle_path).map_err(|err| {
+        Error::new(
+            ErrorKind::Other,
+            format!("failed to open CA bundle '{}': {}", ca_bundle_path, err),
+        )
+    })?;
+
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))?;
+
+    let mut root_store = RootCertStore::empty();
+    let (added, _) = root_store.add_parsable_certificates(certs.as_slice());
+
+    if added == 0 {
+        return Err(Error::new(
+            ErrorKind::Other,
+            format!("no valid certificate found in CA bundle '{}'", ca_bundle_path),
+        ));
+    }
+
+    Ok(root_store)
+}
+
+/// accept any server certificate - only used with `insecure_skip_tls_verify`
+struct NoCertificateVerification;
+
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
 fn create_bucket<'a, S: AsRef<str>>(
     client: &Client,
     bucket: &'a str,
@@ -509,6 +890,16 @@ fn create_object<'a>(
     Ok(())
 }
 
+/// size of a stored object
+fn head_object<'a>(client: &Client, bucket: &'a str, key: &'a str) -> Result<usize, S3Error<'a>> {
+    let result = block_on(client.head_object().bucket(bucket).key(key).send());
+
+    match result {
+        Ok(output) => Ok(output.content_length() as usize),
+        Err(_) => Err(S3Error::ObjectDoesNotExist { bucket, key }),
+    }
+}
+
 fn get_object<'a>(client: &Client, bucket: &'a str, key: &'a str) -> Result<Vec<u8>, S3Error<'a>> {
     let result = block_on(client.get_object().bucket(bucket).key(key).send());
 
@@ -568,57 +959,49 @@ fn delete_object<'a>(client: &Client, bucket: &'a str, key: &'a str) -> Result<(
     }
 }
 
-fn delete_directory<'a>(
+fn delete_objects<'a>(
     client: &Client,
     bucket: &'a str,
-    directory: &'a str,
+    keys: Vec<String>,
 ) -> Result<(), S3Error<'a>> {
-    if let Ok(objects) = block_on(
-        client
-            .list_objects_v2()
-            .bucket(bucket)
-            .prefix(directory)
-            .send(),
-    ) {
-        let mut delete_objects: Vec<ObjectIdentifier> = vec![];
-        for obj in objects.contents().unwrap_or_default() {
-            let obj_id = ObjectIdentifier::builder()
-                .set_key(Some(obj.key().unwrap().to_string()))
-                .build();
-            delete_objects.push(obj_id);
-        }
-
-        match block_on(
+    // a delete request takes 1000 keys at most - and S3 rejects empty delete requests
+    for keys in keys.chunks(1000) {
+        let objects = keys
+            .iter()
+            .map(|key| ObjectIdentifier::builder().key(key).build())
+            .collect::<Vec<_>>();
+
+        if let Err(err) = block_on(
             client
                 .delete_objects()
                 .bucket(bucket)
-                .delete(Delete::builder().set_objects(Some(delete_objects)).build())
+                .delete(Delete::builder().set_objects(Some(objects)).build())
                 .send(),
         ) {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                error!("{}", err.to_string());
-                Err(S3Error::FailedToDeleteDirectory { bucket, directory })
-            }
+            error!("{}", err.to_string());
+            return Err(S3Error::FailedToDeleteObjects { bucket });
         }
-    } else {
-        Err(S3Error::FailedToListObjects { bucket })
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use chrono::{Duration, Utc};
     use fake::{Fake, Faker};
     use serde_json::json;
 
     use crate::cli::DumpDeleteArgs;
-    use crate::config::{AwsCredentials, Endpoint};
+    use crate::config::{AwsCredentials, Endpoint, TlsOptions};
     use crate::connector::Connector;
     use crate::datastore::s3::{
-        create_bucket, create_object, delete_bucket, delete_object, get_object, S3Error,
+        ca_bundle_root_store, create_bucket, create_object, delete_bucket, delete_object,
+        get_object, tls_client_config, S3Error,
     };
-    use crate::datastore::{Datastore, Dump, INDEX_FILE_NAME};
+    use crate::datastore::{Datastore, Dump, ReadOptions, INDEX_FILE_NAME};
     use crate::migration::rename_backups_to_dumps::RenameBackupsToDump;
     use crate::migration::update_version_number::UpdateVersionNumber;
     use crate::migration::Migrator;
@@ -628,6 +1011,18 @@ mod tests {
     const REGION: &str = "us-east-2";
     const MINIO_ENDPOINT: &str = "http://localhost:9000";
     const MINIO_CREDENTIALS: &str = "minioadmin";
+    // self-signed CA - only used to check the CA bundle loading
+    const CA_BUNDLE: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBjzCCATWgAwIBAgIUZXyvhdZWagCWXUBzmxlPqMJfT3swCgYIKoZIzj0EAwIw\n\
+HDEaMBgGA1UEAwwRcmVwbGlieXRlLXRlc3QtY2EwIBcNMjYxMDE2MTQwMzA4WhgP\n\
+MjEyNjA5MjIxNDAzMDhaMBwxGjAYBgNVBAMMEXJlcGxpYnl0ZS10ZXN0LWNhMFkw\n\
+EwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE9UX+BFQ0R8OphmGA5nok0Txm8l2lDeuZ\n\
+g1ItwSlPxGe/VE8g5uKPnvW5CTjexqt57yp/AJyjGrHsEzgG7mx4naNTMFEwHQYD\n\
+VR0OBBYEFK6knj8a0WVCTVDhRkWMrFhYSqbuMB8GA1UdIwQYMBaAFK6knj8a0WVC\n\
+TVDhRkWMrFhYSqbuMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIh\n\
+AJ/Ibsaj6q/Bnvc3myLvUE+zwaO35gMlVf3pu1LKEAz1AiApSjw8hn6HMDKQZScd\n\
++IiNKsVcdEq77bSRwmZTaHeUiQ==\n\
+-----END CERTIFICATE-----";
 
     fn aws_bucket() -> String {
         format!("replibyte-test-{}", Faker.fake::<String>().to_lowercase())
@@ -657,6 +1052,7 @@ mod tests {
                 session_token: None,
             }),
             Endpoint::Custom(MINIO_ENDPOINT.to_string()),
+            TlsOptions::default(),
         )
         .unwrap()
     }
@@ -684,6 +1080,7 @@ mod tests {
             access_key,
             secret,
             endpoint,
+            TlsOptions::default(),
         )
         .unwrap()
     }
@@ -699,6 +1096,24 @@ mod tests {
         assert!(delete_bucket(&s3.client, bucket.as_str(), true).is_ok());
     }
 
+    #[test]
+    fn init_s3_without_bucket_creation() {
+        let bucket = aws_bucket();
+        let mut s3 = aws_s3(bucket.as_str());
+        s3.set_create_bucket_if_missing(false);
+
+        // the bucket is not created - the index file can't be written
+        assert!(s3.init().is_err());
+        assert!(get_object(&s3.client, bucket.as_str(), INDEX_FILE_NAME).is_err());
+
+        // a pre-provisioned bucket
+        assert!(create_bucket(&s3.client, bucket.as_str(), Some(REGION.to_string())).is_ok());
+        assert!(s3.init().is_ok());
+        assert!(get_object(&s3.client, bucket.as_str(), INDEX_FILE_NAME).is_ok());
+
+        assert!(delete_bucket(&s3.client, bucket.as_str(), true).is_ok());
+    }
+
     #[test]
     fn create_and_get_and_delete_object_for_aws_s3() {
         let bucket = aws_bucket();
@@ -857,6 +1272,13 @@ mod tests {
             created_at: epoch_millis(),
             compressed: true,
             encrypted: false,
+            encryption_algorithm: None,
+            encryption_version: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            raw_size: None,
+            table_hashes: BTreeMap::new(),
         });
 
         assert!(s3.write_index_file(&index_file).is_ok());
@@ -866,6 +1288,87 @@ mod tests {
         assert!(delete_bucket(&s3.client, bucket.as_str(), true).is_ok());
     }
 
+    #[test]
+    fn test_s3_namespaces() {
+        let bucket = aws_bucket();
+
+        let mut team_a = aws_s3(bucket.as_str());
+        team_a.set_namespace("team-a".to_string());
+        team_a.set_dump_name("dump-1".to_string());
+        let _ = team_a.init().expect("s3 init failed");
+
+        let mut team_b = aws_s3(bucket.as_str());
+        team_b.set_namespace("team-b".to_string());
+        team_b.set_dump_name("dump-1".to_string());
+        let _ = team_b.init().expect("s3 init failed");
+
+        assert!(team_a.write(1, b"team a".to_vec()).is_ok());
+        assert!(team_b.write(1, b"team b".to_vec()).is_ok());
+
+        // each namespace has its own index file and dumps
+        assert!(get_object(&team_a.client, bucket.as_str(), "team-a/metadata.json").is_ok());
+        assert!(get_object(&team_a.client, bucket.as_str(), "team-b/metadata.json").is_ok());
+        assert!(get_object(&team_a.client, bucket.as_str(), INDEX_FILE_NAME).is_err());
+        assert!(get_object(&team_a.client, bucket.as_str(), "team-a/dump-1/1.dump").is_ok());
+
+        let mut data = vec![];
+        assert!(team_a
+            .read(&ReadOptions::Latest, &mut |bytes| data.extend(bytes))
+            .is_ok());
+        assert_eq!(data, b"team a".to_vec());
+
+        // deleting a dump from a namespace does not touch the other one
+        assert!(team_a.delete_by_name("dump-1".to_string()).is_ok());
+        assert!(team_a.index_file().unwrap().dumps.is_empty());
+        assert_eq!(team_b.index_file().unwrap().dumps.len(), 1);
+        assert!(get_object(&team_b.client, bucket.as_str(), "team-b/dump-1/1.dump").is_ok());
+
+        assert!(delete_bucket(&team_a.client, bucket.as_str(), true).is_ok());
+    }
+
+    #[test]
+    fn test_s3_custom_key_layout() {
+        let bucket = aws_bucket();
+        let mut s3 = aws_s3(bucket.as_str());
+        s3.set_key_layout("backups/{dump_name}/part-{part}.sql.{extension}".to_string());
+        s3.set_dump_name("2022-05-01".to_string());
+        let _ = s3.init().expect("s3 init failed");
+
+        assert!(s3.write(2, b"hello ".to_vec()).is_ok());
+        assert!(s3.write(10, b"w0rld".to_vec()).is_ok());
+
+        assert!(get_object(
+            &s3.client,
+            bucket.as_str(),
+            "backups/2022-05-01/part-2.sql.dump"
+        )
+        .is_ok());
+        assert!(get_object(&s3.client, bucket.as_str(), "2022-05-01/2.dump").is_err());
+
+        // the keys are read from the same layout
+        assert_eq!(s3.part_numbers("2022-05-01").unwrap(), vec![2, 10]);
+        assert_eq!(s3.last_part("2022-05-01").unwrap(), 10);
+
+        let mut data = vec![];
+        assert!(s3
+            .read(&ReadOptions::Latest, &mut |bytes| data.extend(bytes))
+            .is_ok());
+        assert_eq!(data, b"hello w0rld".to_vec());
+
+        // the parts are listed with the default layout
+        let mut objects = s3.list_all_objects().unwrap();
+        objects.sort();
+        assert_eq!(
+            objects,
+            vec!["2022-05-01/10.dump", "2022-05-01/2.dump", INDEX_FILE_NAME]
+        );
+
+        assert!(s3.delete_by_name("2022-05-01".to_string()).is_ok());
+        assert!(s3.part_numbers("2022-05-01").unwrap().is_empty());
+
+        assert!(delete_bucket(&s3.client, bucket.as_str(), true).is_ok());
+    }
+
     #[test]
     fn test_dump_name() {
         let bucket = aws_bucket();
@@ -896,6 +1399,13 @@ mod tests {
             created_at: epoch_millis(),
             compressed: true,
             encrypted: false,
+            encryption_algorithm: None,
+            encryption_version: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            raw_size: None,
+            table_hashes: BTreeMap::new(),
         });
 
         index_file.dumps.push(Dump {
@@ -904,6 +1414,13 @@ mod tests {
             created_at: epoch_millis(),
             compressed: true,
             encrypted: false,
+            encryption_algorithm: None,
+            encryption_version: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            raw_size: None,
+            table_hashes: BTreeMap::new(),
         });
 
         assert!(s3.write_index_file(&index_file).is_ok());
@@ -968,6 +1485,13 @@ mod tests {
             created_at: (Utc::now() - Duration::days(5)).timestamp_millis() as u128,
             compressed: true,
             encrypted: false,
+            encryption_algorithm: None,
+            encryption_version: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            raw_size: None,
+            table_hashes: BTreeMap::new(),
         });
 
         // Add a dump from now
@@ -977,6 +1501,13 @@ mod tests {
             created_at: epoch_millis(),
             compressed: true,
             encrypted: false,
+            encryption_algorithm: None,
+            encryption_version: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            raw_size: None,
+            table_hashes: BTreeMap::new(),
         });
 
         assert!(s3.write_index_file(&index_file).is_ok());
@@ -1042,6 +1573,13 @@ mod tests {
             created_at: (Utc::now() - Duration::days(3)).timestamp_millis() as u128,
             compressed: true,
             encrypted: false,
+            encryption_algorithm: None,
+            encryption_version: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            raw_size: None,
+            table_hashes: BTreeMap::new(),
         });
 
         index_file.dumps.push(Dump {
@@ -1050,6 +1588,13 @@ mod tests {
             created_at: (Utc::now() - Duration::days(5)).timestamp_millis() as u128,
             compressed: true,
             encrypted: false,
+            encryption_algorithm: None,
+            encryption_version: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            raw_size: None,
+            table_hashes: BTreeMap::new(),
         });
 
         index_file.dumps.push(Dump {
@@ -1058,6 +1603,13 @@ mod tests {
             created_at: epoch_millis(),
             compressed: true,
             encrypted: false,
+            encryption_algorithm: None,
+            encryption_version: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            raw_size: None,
+            table_hashes: BTreeMap::new(),
         });
 
         assert!(s3.write_index_file(&index_file).is_ok());
@@ -1175,7 +1727,14 @@ mod tests {
                 size: 62279,
                 created_at: 1234,
                 compressed: true,
-                encrypted: false
+                encrypted: false,
+                encryption_algorithm: None,
+                encryption_version: None,
+                source_type: None,
+                labels: BTreeMap::new(),
+                content: None,
+                raw_size: None,
+                table_hashes: BTreeMap::new()
             })
         );
         assert_eq!(
@@ -1185,8 +1744,44 @@ mod tests {
                 size: 62283,
                 created_at: 5678,
                 compressed: true,
-                encrypted: false
+                encrypted: false,
+                encryption_algorithm: None,
+                encryption_version: None,
+                source_type: None,
+                labels: BTreeMap::new(),
+                content: None,
+                raw_size: None,
+                table_hashes: BTreeMap::new()
             })
         );
     }
+
+    #[test]
+    fn tls_client_config_with_ca_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let ca_bundle_path = dir.path().join("ca.pem");
+        let ca_bundle_path = ca_bundle_path.to_str().unwrap();
+
+        // missing file
+        assert!(ca_bundle_root_store(ca_bundle_path).is_err());
+
+        std::fs::write(ca_bundle_path, "not a certificate").unwrap();
+        assert!(ca_bundle_root_store(ca_bundle_path).is_err());
+
+        std::fs::write(ca_bundle_path, CA_BUNDLE).unwrap();
+        let root_store = ca_bundle_root_store(ca_bundle_path).unwrap();
+        assert_eq!(root_store.len(), 1);
+
+        let tls = TlsOptions {
+            ca_bundle_path: Some(ca_bundle_path.to_string()),
+            insecure_skip_tls_verify: false,
+        };
+        assert!(tls_client_config(&tls).is_ok());
+
+        let tls = TlsOptions {
+            ca_bundle_path: None,
+            insecure_skip_tls_verify: true,
+        };
+        assert!(tls_client_config(&tls).is_ok());
+    }
 }
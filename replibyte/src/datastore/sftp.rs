@@ -0,0 +1,838 @@
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+
+use log::{debug, error, info};
+use serde_json::Value;
+use ssh2::{CheckResult, HashType, KnownHostFileKind, Session};
+
+use crate::connector::Connector;
+use crate::types;
+use crate::utils::epoch_millis;
+
+use super::{
+    compress, decompress_part, decrypt, encrypt, part_number, remove_dump_entry, Datastore, Dump,
+    DumpContent, DumpManifest, EncryptionAlgorithm, IndexFile, ENCRYPTION_VERSION, INDEX_FILE_NAME,
+    MANIFEST_FILE_NAME,
+};
+
+const DEFAULT_PORT: u16 = 22;
+const DIR_MODE: i32 = 0o755;
+
+/// datastore on a remote directory reached over SFTP - the layout is the same as the local disk one
+pub struct Sftp {
+    host: String,
+    port: u16,
+    user: String,
+    private_key_path: Option<String>,
+    passphrase: Option<String>,
+    password: Option<String>,
+    known_hosts_path: Option<String>,
+    host_key_fingerprint: Option<String>,
+    dir: String,
+    client: Option<ssh2::Sftp>,
+    dump_name: String,
+    enable_compression: bool,
+    encryption_key: Option<String>,
+    encryption_algorithm: EncryptionAlgorithm,
+    namespace: Option<String>,
+    source_type: Option<String>,
+    labels: BTreeMap<String, String>,
+    content: Option<DumpContent>,
+    table_hashes: BTreeMap<String, String>,
+    part_offset: u16,
+}
+
+impl Sftp {
+    pub fn new<S: Into<String>>(host: S, port: Option<u16>, user: S, dir: S) -> Self {
+        Self {
+            host: host.into(),
+            port: port.unwrap_or(DEFAULT_PORT),
+            user: user.into(),
+            private_key_path: None,
+            passphrase: None,
+            password: None,
+            known_hosts_path: None,
+            host_key_fingerprint: None,
+            dir: dir.into(),
+            client: None,
+            enable_compression: true,
+            encryption_key: None,
+            encryption_algorithm: EncryptionAlgorithm::default(),
+            dump_name: format!("dump-{}", epoch_millis()),
+            namespace: None,
+            source_type: None,
+            labels: BTreeMap::new(),
+            content: None,
+            table_hashes: BTreeMap::new(),
+            part_offset: 0,
+        }
+    }
+
+    /// authenticate with a private key (and its passphrase) instead of the ssh agent
+    pub fn set_private_key(&mut self, private_key_path: String, passphrase: Option<String>) {
+        info!("set sftp private key");
+        self.private_key_path = Some(private_key_path);
+        self.passphrase = passphrase;
+    }
+
+    /// authenticate with a password instead of the ssh agent
+    pub fn set_password(&mut self, password: String) {
+        info!("set sftp password");
+        self.password = Some(password);
+    }
+
+    /// known hosts file the host key is checked against - `~/.ssh/known_hosts` by default
+    pub fn set_known_hosts(&mut self, known_hosts_path: String) {
+        self.known_hosts_path = Some(known_hosts_path);
+    }
+
+    /// SHA256 fingerprint of the host key (as printed by `ssh-keygen -l`) - checked instead of the known hosts file
+    pub fn set_host_key_fingerprint(&mut self, host_key_fingerprint: String) {
+        self.host_key_fingerprint = Some(host_key_fingerprint);
+    }
+
+    /// directory containing the index file and the dumps
+    fn root_dir(&self) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}/{}", self.dir.trim_end_matches('/'), namespace),
+            None => self.dir.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn connect(&self) -> Result<ssh2::Sftp, Error> {
+        debug!(
+            "connecting to sftp://{}@{}:{}",
+            self.user, self.host, self.port
+        );
+        let tcp = TcpStream::connect((self.host.as_str(), self.port)).map_err(|err| {
+            error!("error while connecting to {}:{}", self.host, self.port);
+            err
+        })?;
+
+        let mut session = Session::new()?;
+        session.set_tcp_stream(tcp);
+        session.handshake()?;
+
+        // no credential is sent to an unknown host
+        self.check_host_key(&session)?;
+
+        match (&self.private_key_path, &self.password) {
+            (Some(private_key_path), _) => session.userauth_pubkey_file(
+                self.user.as_str(),
+                None,
+                Path::new(private_key_path.as_str()),
+                self.passphrase.as_deref(),
+            )?,
+            (None, Some(password)) => {
+                session.userauth_password(self.user.as_str(), password.as_str())?
+            }
+            (None, None) => session.userauth_agent(self.user.as_str())?,
+        }
+
+        if !session.authenticated() {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("sftp authentication failed for the user '{}'", self.user),
+            ));
+        }
+
+        Ok(session.sftp()?)
+    }
+
+    /// refuse a host whose key is unknown or does not match the configured one
+    fn check_host_key(&self, session: &Session) -> Result<(), Error> {
+        if let Some(expected_fingerprint) = &self.host_key_fingerprint {
+            let fingerprint = host_key_fingerprint(session).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::Other,
+                    "the sftp server did not send its host key",
+                )
+            })?;
+
+            let expected_fingerprint = expected_fingerprint.trim();
+            if expected_fingerprint.trim_start_matches("SHA256:")
+                != fingerprint.trim_start_matches("SHA256:")
+            {
+                return Err(Error::new(
+                    ErrorKind::PermissionDenied,
+                    format!(
+                        "the host key fingerprint of {}:{} is {} - it does not match the configured one {}",
+                        self.host, self.port, fingerprint, expected_fingerprint
+                    ),
+                ));
+            }
+
+            return Ok(());
+        }
+
+        let (host_key, _) = session.host_key().ok_or_else(|| {
+            Error::new(
+                ErrorKind::Other,
+                "the sftp server did not send its host key",
+            )
+        })?;
+
+        let known_hosts_path = match &self.known_hosts_path {
+            Some(known_hosts_path) => PathBuf::from(known_hosts_path),
+            None => default_known_hosts_path()?,
+        };
+
+        let mut known_hosts = session.known_hosts()?;
+        let _ = known_hosts
+            .read_file(known_hosts_path.as_path(), KnownHostFileKind::OpenSSH)
+            .map_err(|err| {
+                error!(
+                    "error while reading the known hosts file: {}",
+                    known_hosts_path.display()
+                );
+                Error::from(err)
+            })?;
+
+        match known_hosts.check_port(self.host.as_str(), self.port, host_key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::Mismatch => Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "the host key of {}:{} does not match the one of {}",
+                    self.host,
+                    self.port,
+                    known_hosts_path.display()
+                ),
+            )),
+            CheckResult::NotFound => Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "unknown host {}:{} - add its key to {} (e.g. with `ssh-keyscan`) or set `host_key_fingerprint`",
+                    self.host,
+                    self.port,
+                    known_hosts_path.display()
+                ),
+            )),
+            CheckResult::Failure => Err(Error::new(
+                ErrorKind::Other,
+                format!("error while checking the host key of {}:{}", self.host, self.port),
+            )),
+        }
+    }
+
+    fn client(&self) -> Result<&ssh2::Sftp, Error> {
+        self.client.as_ref().ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotConnected,
+                "the sftp datastore is not initialized",
+            )
+        })
+    }
+
+    /// create the directory and its missing parents
+    fn create_dir_all(&self, dir: &str) -> Result<(), Error> {
+        let client = self.client()?;
+
+        let mut path = if dir.starts_with('/') {
+            String::from("/")
+        } else {
+            String::new()
+        };
+
+        for component in dir.split('/').filter(|component| !component.is_empty()) {
+            path.push_str(component);
+
+            if client.stat(Path::new(path.as_str())).is_err() {
+                client
+                    .mkdir(Path::new(path.as_str()), DIR_MODE)
+                    .map_err(|err| {
+                        error!("error while creating the directory: {}", path);
+                        Error::from(err)
+                    })?;
+            }
+
+            path.push('/');
+        }
+
+        Ok(())
+    }
+
+    /// delete the directory with all its content
+    fn remove_dir_all(&self, dir: &str) -> Result<(), Error> {
+        let client = self.client()?;
+
+        for (path, stat) in client.readdir(Path::new(dir)).map_err(Error::from)? {
+            if stat.is_dir() {
+                self.remove_dir_all(path.to_string_lossy().as_ref())?;
+            } else {
+                client.unlink(path.as_path())?;
+            }
+        }
+
+        Ok(client.rmdir(Path::new(dir))?)
+    }
+
+    /// dump parts of the dump directory - an empty list if nothing has been written yet
+    fn parts(&self, name: &str) -> Result<Vec<(u16, u64)>, Error> {
+        let dump_dir_path = format!("{}/{}", self.root_dir(), name);
+        let entries = match self.client()?.readdir(Path::new(dump_dir_path.as_str())) {
+            Ok(entries) => entries,
+            Err(err) => {
+                let err = Error::from(err);
+                if err.kind() == ErrorKind::NotFound {
+                    return Ok(vec![]);
+                }
+
+                return Err(err);
+            }
+        };
+
+        let mut parts = vec![];
+        for (path, stat) in entries {
+            let file_name = path
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            if let Some(part) = part_number(file_name.as_str()) {
+                parts.push((part, stat.size.unwrap_or(0)));
+            }
+        }
+
+        // in the order they have been written
+        parts.sort_unstable();
+        Ok(parts)
+    }
+
+    fn create_index_file(&self) -> Result<IndexFile, Error> {
+        match self.index_file() {
+            Ok(index_file) => Ok(index_file),
+            Err(_) => {
+                info!("creating a new index_file");
+                let index_file = IndexFile::new();
+                let _ = self.write_index_file(&index_file)?;
+                Ok(index_file)
+            }
+        }
+    }
+}
+
+impl Connector for Sftp {
+    fn init(&mut self) -> Result<(), Error> {
+        debug!("initializing datastore");
+
+        if self.client.is_none() {
+            self.client = Some(self.connect()?);
+        }
+
+        // create the namespace directory if needed
+        self.create_dir_all(self.root_dir().as_str())?;
+
+        self.create_index_file().map(|_| ())
+    }
+}
+
+impl Datastore for Sftp {
+    fn index_file(&self) -> Result<IndexFile, Error> {
+        debug!("reading index_file at: {}", self.root_dir());
+
+        let index_file_path = format!("{}/{}", self.root_dir(), INDEX_FILE_NAME);
+        let file = self.client()?.open(Path::new(index_file_path.as_str()))?;
+
+        let index_file: IndexFile =
+            serde_json::from_reader(file).map_err(|err| Error::from(err))?;
+
+        Ok(index_file)
+    }
+
+    fn raw_index_file(&self) -> Result<Value, Error> {
+        info!("reading raw index_file at: {}", self.root_dir());
+
+        let index_file_path = format!("{}/{}", self.root_dir(), INDEX_FILE_NAME);
+        let file = self.client()?.open(Path::new(index_file_path.as_str()))?;
+
+        let raw_index_file = serde_json::from_reader(file).map_err(|err| Error::from(err))?;
+
+        Ok(raw_index_file)
+    }
+
+    fn write_index_file(&self, index_file: &IndexFile) -> Result<(), Error> {
+        info!("writing index_file");
+        let index_file_path = format!("{}/{}", self.root_dir(), INDEX_FILE_NAME);
+
+        debug!("writing index_file at {}", index_file_path);
+        let file = self.client()?.create(Path::new(index_file_path.as_str()))?;
+
+        serde_json::to_writer(file, index_file).map_err(|err| Error::from(err))
+    }
+
+    fn write_raw_index_file(&self, raw_index_file: &Value) -> Result<(), Error> {
+        info!("writing raw index_file");
+        let index_file_path = format!("{}/{}", self.root_dir(), INDEX_FILE_NAME);
+
+        debug!("writing raw index_file at {}", index_file_path);
+        let file = self.client()?.create(Path::new(index_file_path.as_str()))?;
+
+        serde_json::to_writer(file, raw_index_file).map_err(|err| Error::from(err))
+    }
+
+    fn write_part(&self, file_part: u16, data: types::Bytes) -> Result<usize, Error> {
+        let file_part = file_part + self.part_offset;
+
+        // compress data?
+        let data = if self.compression_enabled() {
+            compress(data)?
+        } else {
+            data
+        };
+
+        // encrypt data?
+        let data = match self.encryption_key() {
+            Some(key) => encrypt(data, key.as_str(), self.encryption_algorithm)?,
+            None => data,
+        };
+
+        let data_size = data.len();
+        let dump_dir_path = format!("{}/{}", self.root_dir(), self.dump_name);
+        let dump_file_path = format!("{}/{}.dump", dump_dir_path, file_part);
+
+        // create the dump directory if needed
+        self.create_dir_all(dump_dir_path.as_str())?;
+
+        debug!("writing dump at: {}", dump_file_path);
+        let client = self.client()?;
+        let mut file = client.create(Path::new(dump_file_path.as_str()))?;
+        let _ = file.write_all(&data).map_err(|err| {
+            error!("error while writing dump at: {}", dump_file_path);
+            err
+        })?;
+        drop(file);
+
+        // the part must be fully written before the dump is recorded in the index file
+        let stored_size = client
+            .stat(Path::new(dump_file_path.as_str()))?
+            .size
+            .unwrap_or(0) as usize;
+
+        if stored_size != data_size {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "the dump part '{}' is incomplete: {} bytes written instead of {}",
+                    dump_file_path, stored_size, data_size
+                ),
+            ));
+        }
+
+        Ok(data_size)
+    }
+
+    fn write_dump_entry(&self, size: usize, raw_size: usize) -> Result<(), Error> {
+        let mut index_file = self.index_file()?;
+
+        index_file.add_dump_size(Dump {
+            directory_name: self.dump_name.to_string(),
+            size,
+            created_at: epoch_millis(),
+            compressed: self.compression_enabled(),
+            encrypted: self.encryption_key().is_some(),
+            encryption_algorithm: self
+                .encryption_key()
+                .as_ref()
+                .map(|_| self.encryption_algorithm),
+            encryption_version: self.encryption_key().as_ref().map(|_| ENCRYPTION_VERSION),
+            source_type: self.source_type.clone(),
+            labels: self.labels.clone(),
+            content: self.content,
+            raw_size: Some(raw_size),
+            table_hashes: self.table_hashes.clone(),
+        });
+
+        self.write_index_file(&index_file)
+    }
+
+    fn write_dump_manifest(&self, manifest: &DumpManifest) -> Result<(), Error> {
+        let dump_dir_path = format!("{}/{}", self.root_dir(), self.dump_name);
+        let manifest_path = format!("{}/{}", dump_dir_path, MANIFEST_FILE_NAME);
+
+        self.create_dir_all(dump_dir_path.as_str())?;
+
+        debug!("writing dump manifest at: {}", manifest_path);
+        let file = self.client()?.create(Path::new(manifest_path.as_str()))?;
+
+        serde_json::to_writer_pretty(file, manifest).map_err(|err| Error::from(err))
+    }
+
+    fn read_dump_manifest(&self, name: &str) -> Result<DumpManifest, Error> {
+        let manifest_path = format!("{}/{}/{}", self.root_dir(), name, MANIFEST_FILE_NAME);
+
+        debug!("reading dump manifest at: {}", manifest_path);
+        let file = self.client()?.open(Path::new(manifest_path.as_str()))?;
+
+        serde_json::from_reader(file).map_err(|err| Error::from(err))
+    }
+
+    fn read(
+        &self,
+        options: &super::ReadOptions,
+        data_callback: &mut dyn FnMut(types::Bytes),
+    ) -> Result<(), Error> {
+        let mut index_file = self.index_file()?;
+        let dump = index_file.find_dump(options)?;
+        let client = self.client()?;
+
+        for (part, _) in self.parts(dump.directory_name.as_str())? {
+            let dump_file_path =
+                format!("{}/{}/{}.dump", self.root_dir(), dump.directory_name, part);
+
+            let mut data = vec![];
+            let _ = client
+                .open(Path::new(dump_file_path.as_str()))?
+                .read_to_end(&mut data)?;
+
+            // decrypt data?
+            let data = if dump.encrypted {
+                // It should be safe to unwrap here because the dump is marked as encrypted in the dump manifest
+                // so if there is no encryption key set at the datastore level we want to panic.
+                let encryption_key = self.encryption_key.as_ref().unwrap();
+                decrypt(
+                    data,
+                    encryption_key.as_str(),
+                    dump.cipher(),
+                    dump.encryption_version(),
+                )?
+            } else {
+                data
+            };
+
+            // decompress data?
+            let data = decompress_part(data, dump.compressed)?;
+
+            data_callback(data);
+        }
+
+        Ok(())
+    }
+
+    fn compression_enabled(&self) -> bool {
+        self.enable_compression
+    }
+
+    fn set_compression(&mut self, enable: bool) {
+        if !enable {
+            info!("disable datastore compression");
+        }
+
+        self.enable_compression = enable;
+    }
+
+    fn encryption_key(&self) -> &Option<String> {
+        &self.encryption_key
+    }
+
+    fn set_encryption_key(&mut self, key: String) {
+        info!("set datastore encryption_key");
+        self.encryption_key = Some(key)
+    }
+
+    fn encryption_algorithm(&self) -> EncryptionAlgorithm {
+        self.encryption_algorithm
+    }
+
+    fn set_encryption_algorithm(&mut self, algorithm: EncryptionAlgorithm) {
+        info!("set datastore encryption_algorithm");
+        self.encryption_algorithm = algorithm
+    }
+
+    fn dump_name(&self) -> &str {
+        self.dump_name.as_str()
+    }
+
+    fn set_dump_name(&mut self, name: String) {
+        self.dump_name = name
+    }
+
+    fn set_namespace(&mut self, namespace: String) {
+        info!("set datastore namespace");
+        self.namespace = Some(namespace.trim_matches('/').to_string())
+    }
+
+    fn set_source_type(&mut self, source_type: String) {
+        self.source_type = Some(source_type)
+    }
+
+    fn set_labels(&mut self, labels: BTreeMap<String, String>) {
+        self.labels = labels
+    }
+
+    fn set_content(&mut self, content: Option<DumpContent>) {
+        self.content = content;
+    }
+
+    fn set_table_hashes(&mut self, table_hashes: BTreeMap<String, String>) {
+        self.table_hashes = table_hashes;
+    }
+
+    fn last_part(&self, name: &str) -> Result<u16, Error> {
+        Ok(self.parts(name)?.last().map(|(part, _)| *part).unwrap_or(0))
+    }
+
+    fn part_numbers(&self, name: &str) -> Result<Vec<u16>, Error> {
+        Ok(self
+            .parts(name)?
+            .into_iter()
+            .map(|(part, _)| part)
+            .collect())
+    }
+
+    fn set_part_offset(&mut self, offset: u16) {
+        info!("set datastore part offset to {}", offset);
+        self.part_offset = offset
+    }
+
+    fn parts_size(&self, name: &str) -> Result<usize, Error> {
+        Ok(self
+            .parts(name)?
+            .into_iter()
+            .map(|(_, size)| size as usize)
+            .sum())
+    }
+
+    fn delete_by_name(&self, name: String) -> Result<(), Error> {
+        let dump_dir_path = format!("{}/{}", self.root_dir(), name);
+        match self.remove_dir_all(dump_dir_path.as_str()) {
+            Ok(_) => {}
+            // nothing has been written yet for this dump
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                error!("error while removing the dump directory: {}", dump_dir_path);
+                return Err(err);
+            }
+        }
+
+        remove_dump_entry(self, name.as_str())
+    }
+
+    fn list_all_objects(&self) -> Result<Vec<String>, Error> {
+        let root_dir = self.root_dir();
+        let client = self.client()?;
+        let mut objects = vec![];
+
+        // directories to list, relative to the root directory
+        let mut dirs = vec![String::new()];
+        while let Some(dir) = dirs.pop() {
+            let dir_path = if dir.is_empty() {
+                root_dir.clone()
+            } else {
+                format!("{}/{}", root_dir, dir)
+            };
+
+            let entries = match client.readdir(Path::new(dir_path.as_str())) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    let err = Error::from(err);
+                    if err.kind() == ErrorKind::NotFound {
+                        continue;
+                    }
+
+                    return Err(err);
+                }
+            };
+
+            for (path, stat) in entries {
+                let file_name = path
+                    .file_name()
+                    .map(|file_name| file_name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                let key = if dir.is_empty() {
+                    file_name
+                } else {
+                    format!("{}/{}", dir, file_name)
+                };
+
+                if stat.is_dir() {
+                    dirs.push(key);
+                } else {
+                    objects.push(key);
+                }
+            }
+        }
+
+        objects.sort();
+        Ok(objects)
+    }
+}
+
+/// `SHA256:<base64>` fingerprint of the host key - the format of `ssh-keygen -l`
+fn host_key_fingerprint(session: &Session) -> Option<String> {
+    session.host_key_hash(HashType::Sha256).map(|hash| {
+        format!(
+            "SHA256:{}",
+            base64::encode_config(hash, base64::STANDARD_NO_PAD)
+        )
+    })
+}
+
+fn default_known_hosts_path() -> Result<PathBuf, Error> {
+    match std::env::var_os("HOME") {
+        Some(home) => Ok(PathBuf::from(home).join(".ssh").join("known_hosts")),
+        None => Err(Error::new(
+            ErrorKind::NotFound,
+            "no home directory to find the known hosts file - set `known_hosts` or `host_key_fingerprint`",
+        )),
+    }
+}
+
+/// tests against the SFTP server of docker-compose-dev.yml - run with `--features sftp-tests`
+#[cfg(all(test, feature = "sftp-tests"))]
+mod tests {
+    use std::net::TcpStream;
+
+    use rand::Rng;
+    use ssh2::{KnownHostFileKind, Session};
+
+    use crate::cli::DumpDeleteArgs;
+    use crate::connector::Connector;
+    use crate::datastore::{Datastore, ReadOptions, INDEX_FILE_NAME};
+
+    use super::{host_key_fingerprint, Sftp};
+
+    const HOST: &str = "localhost";
+    const PORT: u16 = 2222;
+    const USER: &str = "replibyte";
+    const PASSWORD: &str = "password";
+
+    fn session() -> Session {
+        let mut session = Session::new().unwrap();
+        session.set_tcp_stream(TcpStream::connect((HOST, PORT)).unwrap());
+        session.handshake().unwrap();
+        session
+    }
+
+    fn untrusted_sftp() -> Sftp {
+        // a new directory for each test
+        let dir = format!("/upload/test-{}", rand::thread_rng().gen::<u32>());
+
+        let mut sftp = Sftp::new(HOST.to_string(), Some(PORT), USER.to_string(), dir);
+        sftp.set_password(PASSWORD.to_string());
+        sftp
+    }
+
+    fn sftp() -> Sftp {
+        let mut sftp = untrusted_sftp();
+        sftp.set_host_key_fingerprint(host_key_fingerprint(&session()).unwrap());
+        sftp
+    }
+
+    #[test]
+    fn init_sftp() {
+        let mut sftp = sftp();
+
+        // executed twice to check that there is no error at the second call
+        assert!(sftp.init().is_ok());
+        assert!(sftp.init().is_ok());
+        assert!(sftp.index_file().unwrap().dumps.is_empty());
+    }
+
+    #[test]
+    fn check_host_key() {
+        let session = session();
+        let (host_key, host_key_type) = session.host_key().unwrap();
+
+        // unknown host
+        let known_hosts_file = tempfile::NamedTempFile::new().unwrap();
+        let mut sftp = untrusted_sftp();
+        sftp.set_known_hosts(known_hosts_file.path().to_string_lossy().to_string());
+        assert!(sftp.init().is_err());
+
+        // known host
+        let mut known_hosts = session.known_hosts().unwrap();
+        known_hosts
+            .add(
+                format!("[{}]:{}", HOST, PORT).as_str(),
+                host_key,
+                "",
+                host_key_type.into(),
+            )
+            .unwrap();
+        known_hosts
+            .write_file(known_hosts_file.path(), KnownHostFileKind::OpenSSH)
+            .unwrap();
+        assert!(sftp.init().is_ok());
+
+        // mismatched fingerprint
+        let mut sftp = untrusted_sftp();
+        sftp.set_host_key_fingerprint(
+            "SHA256:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string(),
+        );
+        assert!(sftp.init().is_err());
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let mut sftp = sftp();
+        sftp.set_encryption_key("test_encryption_key".to_string());
+        let _ = sftp.init().expect("sftp init failed");
+
+        let bytes_1: Vec<u8> =
+            b"INSERT INTO public.users (first_name) VALUES ('Lucas');\n".to_vec();
+        let bytes_2: Vec<u8> =
+            b"INSERT INTO public.users (first_name) VALUES ('Romaric');\n".to_vec();
+
+        assert!(sftp.write(1, bytes_1.clone()).is_ok());
+        assert!(sftp.write(2, bytes_2.clone()).is_ok());
+
+        let dump_name = sftp.dump_name().to_string();
+        assert_eq!(sftp.part_numbers(dump_name.as_str()).unwrap(), vec![1, 2]);
+        assert_eq!(sftp.last_part(dump_name.as_str()).unwrap(), 2);
+        assert_eq!(
+            sftp.parts_size(dump_name.as_str()).unwrap(),
+            sftp.index_file().unwrap().dumps[0].size
+        );
+
+        let mut data = vec![];
+        sftp.read(
+            &ReadOptions::Dump {
+                name: dump_name.clone(),
+            },
+            &mut |part| {
+                data.extend(part);
+            },
+        )
+        .unwrap();
+
+        assert_eq!(data, [bytes_1, bytes_2].concat());
+
+        assert_eq!(
+            sftp.list_all_objects().unwrap(),
+            vec![
+                format!("{}/1.dump", dump_name),
+                format!("{}/2.dump", dump_name),
+                INDEX_FILE_NAME.to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delete_dump_by_name() {
+        let mut sftp = sftp();
+        let _ = sftp.init().expect("sftp init failed");
+
+        let dump_name = sftp.dump_name().to_string();
+        assert!(sftp.write(1, b"hello w0rld".to_vec()).is_ok());
+        assert!(sftp.exists(dump_name.as_str()));
+
+        assert!(sftp
+            .delete(&DumpDeleteArgs {
+                dump: Some(dump_name.clone()),
+                older_than: None,
+                keep_last: None,
+            })
+            .is_ok());
+
+        assert!(!sftp.exists(dump_name.as_str()));
+        assert!(sftp.part_numbers(dump_name.as_str()).unwrap().is_empty());
+        assert_eq!(
+            sftp.list_all_objects().unwrap(),
+            vec![INDEX_FILE_NAME.to_string()]
+        );
+    }
+}
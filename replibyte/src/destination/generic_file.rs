@@ -0,0 +1,51 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Write};
+use std::path::PathBuf;
+
+use crate::connector::Connector;
+use crate::destination::Destination;
+use crate::types::Bytes;
+
+/// Write dump output into a file - the file is replaced if it already exists
+pub struct GenericFile {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl GenericFile {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        GenericFile {
+            path: path.into(),
+            file: None,
+        }
+    }
+}
+
+impl Connector for GenericFile {
+    fn init(&mut self) -> Result<(), Error> {
+        let file = File::create(self.path.as_path()).map_err(|err| {
+            Error::new(
+                ErrorKind::Other,
+                format!("unable to create '{}': {}", self.path.display(), err),
+            )
+        })?;
+
+        self.file = Some(file);
+        Ok(())
+    }
+}
+
+impl Destination for GenericFile {
+    fn write(&self, data: Bytes) -> Result<(), Error> {
+        match &self.file {
+            Some(file) => {
+                let mut file = file;
+                file.write_all(data.as_slice())
+            }
+            None => Err(Error::new(
+                ErrorKind::Other,
+                format!("the file '{}' is not created", self.path.display()),
+            )),
+        }
+    }
+}
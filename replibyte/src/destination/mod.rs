@@ -1,9 +1,10 @@
-use std::io::Error;
+use std::io::{Error, ErrorKind, Read};
 
 use crate::connector::Connector;
 use crate::types::Bytes;
 
 mod docker;
+pub mod generic_file;
 pub mod generic_stdout;
 pub mod mongodb;
 pub mod mongodb_docker;
@@ -14,4 +15,156 @@ pub mod postgres_docker;
 
 pub trait Destination: Connector {
     fn write(&self, data: Bytes) -> Result<(), Error>;
+
+    /// list the tables of a dump - used to truncate them before the restore
+    fn dump_tables(&self, _dump: &mut dyn Read) -> Result<Vec<String>, Error> {
+        Err(truncate_not_supported())
+    }
+
+    /// empty the tables listed by `dump_tables` before the restore
+    fn truncate_tables(&self, _tables: &Vec<String>) -> Result<(), Error> {
+        Err(truncate_not_supported())
+    }
+
+    /// check that the dump can be restored without writing anything in the destination -
+    /// the first problem met is returned. Return the number of checked statements
+    fn dry_run(&self, _dump: &mut dyn Read) -> Result<usize, Error> {
+        Err(Error::new(
+            ErrorKind::Other,
+            "the dry run is not supported by this destination",
+        ))
+    }
+
+    /// restore the dump with `concurrency` connections - the rows of independent tables are loaded in parallel
+    fn write_concurrently(&self, _dump: &mut dyn Read, _concurrency: usize) -> Result<(), Error> {
+        Err(Error::new(
+            ErrorKind::Other,
+            "the concurrent restore is not supported by this destination",
+        ))
+    }
+
+    /// restore the dump and go on after the failed statements - the restore is aborted at the `max_errors`th
+    /// failed statement. The restored statements and the errors are counted in `report`
+    fn write_with_max_errors(
+        &self,
+        _dump: &mut dyn Read,
+        _max_errors: usize,
+        _report: &mut RestoreReport,
+    ) -> Result<(), Error> {
+        Err(Error::new(
+            ErrorKind::Other,
+            "restoring with a maximum number of errors is not supported by this destination",
+        ))
+    }
+}
+
+/// Statements restored by `Destination::write_with_max_errors`
+#[derive(Debug, Default, PartialEq)]
+pub struct RestoreReport {
+    pub restored_statements: usize,
+    /// error of each failed statement, in the dump order
+    pub errors: Vec<String>,
+}
+
+/// Type of database a dump can be restored into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DestinationType {
+    Postgres,
+    Mysql,
+    MongoDB,
+}
+
+impl DestinationType {
+    /// from a source type recorded in the index file or a Docker image type - e.g. `postgresql`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "postgres" | "postgresql" => Some(DestinationType::Postgres),
+            "mysql" => Some(DestinationType::Mysql),
+            "mongodb" => Some(DestinationType::MongoDB),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            DestinationType::Postgres => "postgresql",
+            DestinationType::Mysql => "mysql",
+            DestinationType::MongoDB => "mongodb",
+        }
+    }
+}
+
+/// pick the destination type matching the dump - the requested destination type is checked against the dump
+/// type, and is used as it is for the dumps created before the source type was recorded
+pub fn destination_type(
+    dump_source_type: Option<&str>,
+    requested: Option<DestinationType>,
+) -> Result<Option<DestinationType>, Error> {
+    let dump_type = match dump_source_type {
+        Some(source_type) => match DestinationType::from_name(source_type) {
+            Some(dump_type) => dump_type,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    format!("unknown dump source type '{}'", source_type),
+                ))
+            }
+        },
+        None => return Ok(requested),
+    };
+
+    match requested {
+        Some(requested) if requested != dump_type => Err(Error::new(
+            ErrorKind::Other,
+            format!(
+                "the dump is a {} dump - it can't be restored into a {} destination",
+                dump_type.name(),
+                requested.name()
+            ),
+        )),
+        _ => Ok(Some(dump_type)),
+    }
+}
+
+fn truncate_not_supported() -> Error {
+    Error::new(
+        ErrorKind::Other,
+        "truncating the target tables is not supported by this destination",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::destination::{destination_type, DestinationType};
+
+    #[test]
+    fn destination_type_from_the_dump() {
+        // no destination type requested - the one of the dump is used
+        assert_eq!(
+            destination_type(Some("mongodb"), None).unwrap(),
+            Some(DestinationType::MongoDB)
+        );
+        assert_eq!(
+            destination_type(Some("postgresql"), None).unwrap(),
+            Some(DestinationType::Postgres)
+        );
+        assert_eq!(
+            destination_type(Some("mysql"), Some(DestinationType::Mysql)).unwrap(),
+            Some(DestinationType::Mysql)
+        );
+
+        // dumps created before the source type was recorded
+        assert_eq!(
+            destination_type(None, Some(DestinationType::Postgres)).unwrap(),
+            Some(DestinationType::Postgres)
+        );
+        assert_eq!(destination_type(None, None).unwrap(), None);
+
+        let err = destination_type(Some("mongodb"), Some(DestinationType::Postgres)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the dump is a mongodb dump - it can't be restored into a postgresql destination"
+        );
+        assert!(destination_type(Some("oracle"), None).is_err());
+    }
 }
@@ -1,6 +1,12 @@
-use std::io::{Error, Write};
+use std::io::{BufReader, Error, ErrorKind, Read, Write};
 use std::process::{Command, Stdio};
 
+use dump_parser::mysql::{
+    get_single_quoted_string_value_at_position, get_tokens_from_query_str,
+    match_keyword_at_position, Keyword,
+};
+use dump_parser::utils::{list_sql_queries_from_dump_reader, ListQueryResult};
+
 use crate::connector::Connector;
 use crate::destination::Destination;
 use crate::types::Bytes;
@@ -77,12 +83,65 @@ impl<'a> Destination for Mysql<'a> {
 
         wait_for_command(&mut process)
     }
+
+    fn dump_tables(&self, dump: &mut dyn Read) -> Result<Vec<String>, Error> {
+        dump_tables(dump)
+    }
+
+    fn truncate_tables(&self, tables: &Vec<String>) -> Result<(), Error> {
+        if tables.is_empty() {
+            return Ok(());
+        }
+
+        self.write(truncate_tables_query(tables).into_bytes())
+    }
+}
+
+/// list the tables created or filled by the dump, in order of appearance
+fn dump_tables<R: Read>(dump: R) -> Result<Vec<String>, Error> {
+    let mut tables: Vec<String> = vec![];
+
+    match list_sql_queries_from_dump_reader(BufReader::new(dump), |query| {
+        let tokens = get_tokens_from_query_str(query);
+
+        let is_insert_into_or_create_table =
+            (match_keyword_at_position(Keyword::Insert, &tokens, 0)
+                && match_keyword_at_position(Keyword::Into, &tokens, 2))
+                || (match_keyword_at_position(Keyword::Create, &tokens, 0)
+                    && match_keyword_at_position(Keyword::Table, &tokens, 2));
+
+        if is_insert_into_or_create_table {
+            if let Some(table_name) = get_single_quoted_string_value_at_position(&tokens, 4) {
+                let table = format!("`{}`", table_name);
+                if !tables.contains(&table) {
+                    tables.push(table);
+                }
+            }
+        }
+
+        ListQueryResult::Continue
+    }) {
+        Ok(_) => Ok(tables),
+        Err(err) => Err(Error::new(ErrorKind::Other, format!("{:?}", err))),
+    }
+}
+
+/// foreign key checks are disabled - MySQL does not support `TRUNCATE ... CASCADE`
+fn truncate_tables_query(tables: &Vec<String>) -> String {
+    let mut query = String::from("SET FOREIGN_KEY_CHECKS = 0;\n");
+
+    for table in tables {
+        query.push_str(format!("TRUNCATE TABLE {};\n", table).as_str());
+    }
+
+    query.push_str("SET FOREIGN_KEY_CHECKS = 1;\n");
+    query
 }
 
 #[cfg(test)]
 mod tests {
     use crate::connector::Connector;
-    use crate::destination::mysql::Mysql;
+    use crate::destination::mysql::{dump_tables, truncate_tables_query, Mysql};
     use crate::destination::Destination;
 
     fn get_mysql() -> Mysql<'static> {
@@ -106,4 +165,30 @@ mod tests {
 
     #[test]
     fn test_inserts() {}
+
+    #[test]
+    fn list_dump_tables_and_truncate() {
+        let dump = r#"
+DROP TABLE IF EXISTS `customers`;
+CREATE TABLE `customers` (
+  `id` int NOT NULL
+) ENGINE=InnoDB;
+INSERT INTO `customers` VALUES (1),(2);
+INSERT INTO `orders` VALUES (1);
+"#;
+
+        let tables = dump_tables(dump.as_bytes()).unwrap();
+        assert_eq!(
+            tables,
+            vec!["`customers`".to_string(), "`orders`".to_string()]
+        );
+
+        assert_eq!(
+            truncate_tables_query(&tables),
+            "SET FOREIGN_KEY_CHECKS = 0;\n\
+             TRUNCATE TABLE `customers`;\n\
+             TRUNCATE TABLE `orders`;\n\
+             SET FOREIGN_KEY_CHECKS = 1;\n"
+        );
+    }
 }
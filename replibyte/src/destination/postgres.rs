@@ -1,9 +1,27 @@
-use std::io::{Error, ErrorKind, Write};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufReader, Error, ErrorKind, Read, Write};
 use std::process::{Command, Stdio};
+use std::str;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::Mutex;
+use std::thread;
 
+use dump_parser::postgres::{
+    get_column_names_from_copy_query, get_column_names_from_insert_into_query,
+    get_column_values_from_insert_into_query, get_tokens_from_query_str,
+    get_word_value_at_position, match_keyword_at_position, to_copy_row, trim_pre_whitespaces,
+    Keyword, Token, Tokenizer,
+};
+use dump_parser::utils::{
+    list_sql_queries_from_dump_reader, ListQueryResult, COPY_PAYLOAD_TERMINATOR,
+};
+use log::warn;
+
+use crate::config::StringEscapingConfig;
 use crate::connector::Connector;
-use crate::destination::Destination;
-use crate::types::Bytes;
+use crate::destination::{Destination, RestoreReport};
+use crate::source::postgres::{has_literal_values, to_column, to_query};
+use crate::types::{Bytes, InsertIntoQuery, Query};
 use crate::utils::{binary_exists, wait_for_command};
 
 pub struct Postgres<'a> {
@@ -33,6 +51,106 @@ impl<'a> Postgres<'a> {
             wipe_database,
         }
     }
+
+    /// tables of the destination (`schema.table`) still there once the database is wiped
+    fn existing_tables(&self) -> Result<HashSet<String>, Error> {
+        let _ = binary_exists("psql")?;
+
+        let s_port = self.port.to_string();
+        let output = Command::new("psql")
+            .env("PGPASSWORD", self.password)
+            .args([
+                "-h",
+                self.host,
+                "-p",
+                s_port.as_str(),
+                "-d",
+                self.database,
+                "-U",
+                self.username,
+                "-A",
+                "-t",
+                "-c",
+                "SELECT table_schema, table_name FROM information_schema.tables;",
+            ])
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "command error: {}",
+                    String::from_utf8_lossy(output.stderr.as_slice())
+                ),
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(output.stdout.as_slice())
+            .lines()
+            .filter_map(|line| line.split_once('|'))
+            // the public schema is dropped by the wipe
+            .filter(|(schema, _)| !(self.wipe_database && *schema == "public"))
+            .map(|(schema, table)| format!("{}.{}", schema, table))
+            .collect())
+    }
+
+    /// restore the statements on a new connection until one of them fails - return the index of the failed
+    /// statement with its error. The statements before it are restored
+    fn write_until_error(&self, statements: &[String]) -> Result<Option<(usize, String)>, Error> {
+        let s_port = self.port.to_string();
+
+        let mut process = Command::new("psql")
+            .env("PGPASSWORD", self.password)
+            .args([
+                "-h",
+                self.host,
+                "-p",
+                s_port.as_str(),
+                "-d",
+                self.database,
+                "-U",
+                self.username,
+                "-v",
+                "ON_ERROR_STOP=1",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let mut stdin = process.stdin.take().unwrap();
+
+        // psql stops reading at the failed statement - its stderr is read while the statements are written
+        let output = thread::scope(|scope| {
+            let _ = scope.spawn(move || {
+                for statement in statements {
+                    if stdin.write_all(statement.as_bytes()).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            process.wait_with_output()
+        })?;
+
+        if output.status.success() {
+            return Ok(None);
+        }
+
+        let stderr = String::from_utf8_lossy(output.stderr.as_slice());
+
+        // psql exits with 3 when a statement fails with ON_ERROR_STOP
+        if output.status.code() == Some(3) {
+            if let Some((line, error)) = psql_error(&stderr) {
+                return Ok(Some((statement_at_line(statements, line), error)));
+            }
+        }
+
+        Err(Error::new(
+            ErrorKind::Other,
+            format!("command error: {}", stderr),
+        ))
+    }
 }
 
 impl<'a> Connector for Postgres<'a> {
@@ -97,6 +215,1240 @@ impl<'a> Destination for Postgres<'a> {
 
         wait_for_command(&mut process)
     }
+
+    fn dump_tables(&self, dump: &mut dyn Read) -> Result<Vec<String>, Error> {
+        dump_tables(dump)
+    }
+
+    fn dry_run(&self, dump: &mut dyn Read) -> Result<usize, Error> {
+        let existing_tables = self.existing_tables()?;
+        dry_run(dump, existing_tables)
+    }
+
+    fn write_concurrently(&self, dump: &mut dyn Read, concurrency: usize) -> Result<(), Error> {
+        write_concurrently(dump, concurrency, |data| self.write(data))
+    }
+
+    fn write_with_max_errors(
+        &self,
+        dump: &mut dyn Read,
+        max_errors: usize,
+        report: &mut RestoreReport,
+    ) -> Result<(), Error> {
+        write_with_max_errors(dump, max_errors, report, |statements| {
+            self.write_until_error(statements)
+        })
+    }
+
+    fn truncate_tables(&self, tables: &Vec<String>) -> Result<(), Error> {
+        if self.wipe_database || tables.is_empty() {
+            // the public schema is recreated on init - there is nothing to truncate
+            return Ok(());
+        }
+
+        self.write(truncate_tables_query(tables).into_bytes())
+    }
+}
+
+/// list the tables created or filled by the dump, in order of appearance
+fn dump_tables<R: Read>(dump: R) -> Result<Vec<String>, Error> {
+    let mut tables: Vec<String> = vec![];
+    let mut is_copy_payload = false;
+
+    match list_sql_queries_from_dump_reader(BufReader::new(dump), |query| {
+        if is_copy_payload {
+            is_copy_payload = query != COPY_PAYLOAD_TERMINATOR;
+            return ListQueryResult::Continue;
+        }
+
+        let tokens = get_tokens_from_query_str(query);
+
+        let (database_name_pos, table_name_pos) =
+            if match_keyword_at_position(Keyword::Copy, &tokens, 0) {
+                is_copy_payload = true;
+                (2, 4)
+            } else if (match_keyword_at_position(Keyword::Insert, &tokens, 0)
+                && match_keyword_at_position(Keyword::Into, &tokens, 2))
+                || (match_keyword_at_position(Keyword::Create, &tokens, 0)
+                    && match_keyword_at_position(Keyword::Table, &tokens, 2))
+            {
+                (4, 6)
+            } else {
+                return ListQueryResult::Continue;
+            };
+
+        if let (Some(database_name), Some(table_name)) = (
+            identifier_at_position(&tokens, database_name_pos),
+            identifier_at_position(&tokens, table_name_pos),
+        ) {
+            let table = format!("{}.{}", database_name, table_name);
+            if !tables.contains(&table) {
+                tables.push(table);
+            }
+        }
+
+        ListQueryResult::Continue
+    }) {
+        Ok(_) => Ok(tables),
+        Err(err) => Err(Error::new(ErrorKind::Other, format!("{:?}", err))),
+    }
+}
+
+/// check that every statement of the dump can be tokenized and fills a table created by
+/// the dump or existing in the destination. Return the number of statements
+pub(crate) fn dry_run<R: Read>(dump: R, existing_tables: HashSet<String>) -> Result<usize, Error> {
+    let mut tables = existing_tables;
+    let mut statements = 0usize;
+    let mut copy_table: Option<String> = None;
+    let mut problem: Option<String> = None;
+
+    let result = list_sql_queries_from_dump_reader(BufReader::new(dump), |query| {
+        if problem.is_some() {
+            // only the first problem is reported
+            return ListQueryResult::Break;
+        }
+
+        if copy_table.is_some() {
+            if query == COPY_PAYLOAD_TERMINATOR {
+                copy_table = None;
+            }
+
+            return ListQueryResult::Continue;
+        }
+
+        if query.trim().is_empty() || query.trim_start().starts_with("--") {
+            return ListQueryResult::Continue;
+        }
+
+        statements += 1;
+
+        let tokens = match Tokenizer::new(query).tokenize() {
+            Ok(tokens) => trim_pre_whitespaces(tokens),
+            Err(err) => {
+                problem = Some(format!("statement {} can't be parsed: {}", statements, err));
+                return ListQueryResult::Break;
+            }
+        };
+
+        let (database_name_pos, table_name_pos, is_created) =
+            if match_keyword_at_position(Keyword::Copy, &tokens, 0) {
+                (2, 4, false)
+            } else if match_keyword_at_position(Keyword::Insert, &tokens, 0)
+                && match_keyword_at_position(Keyword::Into, &tokens, 2)
+            {
+                (4, 6, false)
+            } else if match_keyword_at_position(Keyword::Create, &tokens, 0)
+                && match_keyword_at_position(Keyword::Table, &tokens, 2)
+            {
+                (4, 6, true)
+            } else {
+                return ListQueryResult::Continue;
+            };
+
+        let table = match (
+            identifier_at_position(&tokens, database_name_pos),
+            identifier_at_position(&tokens, table_name_pos),
+        ) {
+            (Some(database_name), Some(table_name)) => format!(
+                "{}.{}",
+                folded_identifier(database_name.as_str()),
+                folded_identifier(table_name.as_str())
+            ),
+            _ => return ListQueryResult::Continue,
+        };
+
+        if is_created {
+            let _ = tables.insert(table);
+        } else if !tables.contains(table.as_str()) {
+            problem = Some(format!(
+                "statement {}: the table '{}' does not exist in the destination",
+                statements, table
+            ));
+            return ListQueryResult::Break;
+        } else if match_keyword_at_position(Keyword::Copy, &tokens, 0) {
+            copy_table = Some(table);
+        }
+
+        ListQueryResult::Continue
+    });
+
+    if let Err(err) = result {
+        return Err(Error::new(ErrorKind::Other, format!("{:?}", err)));
+    }
+
+    if let Some(table) = copy_table {
+        problem = Some(format!(
+            "the COPY payload of the table '{}' is not terminated by '{}'",
+            table, COPY_PAYLOAD_TERMINATOR
+        ));
+    }
+
+    match problem {
+        Some(problem) => Err(Error::new(ErrorKind::Other, problem)),
+        None => Ok(statements),
+    }
+}
+
+/// size of the batches of statements sent to a connection by the concurrent restore
+const CONCURRENT_BATCH_BYTES: usize = 4 * 1024 * 1024;
+
+enum WorkerMessage {
+    Statements(Bytes),
+    /// acknowledged once the statements sent before are restored
+    Wait(SyncSender<()>),
+}
+
+/// restore the dump with `concurrency` connections - `write` runs a batch of statements on a new connection.
+/// The rows of the tables are loaded in parallel until a statement changing the schema is met, and the tables
+/// linked by a foreign key existing while they are filled are loaded by the same connection, in the dump order
+fn write_concurrently<R, W>(dump: R, concurrency: usize, write: W) -> Result<(), Error>
+where
+    R: Read,
+    W: Fn(Bytes) -> Result<(), Error> + Sync,
+{
+    let failure: Mutex<Option<Error>> = Mutex::new(None);
+
+    thread::scope(|scope| {
+        let workers = (0..concurrency.max(1))
+            .map(|_| {
+                let (tx, rx) = mpsc::sync_channel::<WorkerMessage>(1);
+                let write = &write;
+                let failure = &failure;
+
+                let _ = scope.spawn(move || {
+                    for message in rx {
+                        match message {
+                            WorkerMessage::Statements(data) => {
+                                // nothing else is restored once a connection failed
+                                if failure.lock().unwrap().is_some() {
+                                    continue;
+                                }
+
+                                if let Err(err) = write(data) {
+                                    let _ = failure.lock().unwrap().get_or_insert(err);
+                                }
+                            }
+                            WorkerMessage::Wait(done) => {
+                                let _ = done.send(());
+                            }
+                        }
+                    }
+                });
+
+                tx
+            })
+            .collect::<Vec<_>>();
+
+        let mut restore = ConcurrentRestore::new(workers, &write, &failure);
+        let mut result = Ok(());
+
+        let list_result = list_sql_queries_from_dump_reader(BufReader::new(dump), |query| {
+            result = restore.push(query);
+            match result {
+                Ok(_) => ListQueryResult::Continue,
+                Err(_) => ListQueryResult::Break,
+            }
+        });
+
+        if let Err(err) = list_result {
+            return Err(Error::new(ErrorKind::Other, format!("{:?}", err)));
+        }
+
+        let _ = result?;
+        restore.finish()
+        // the workers stop once their channel is dropped with `restore`
+    })
+}
+
+/// dispatch the statements of a dump to the connections of the concurrent restore
+struct ConcurrentRestore<'a, W> {
+    workers: Vec<SyncSender<WorkerMessage>>,
+    write: &'a W,
+    failure: &'a Mutex<Option<Error>>,
+    /// `SET` statements sent before every batch - each batch is restored in a new session
+    session_settings: String,
+    /// statements restored alone, once the previous rows are loaded
+    serial_statements: String,
+    /// statements waiting to be sent to each connection
+    batches: Vec<String>,
+    /// tables linked by a foreign key - a table is linked to the table it is mapped to
+    linked_tables: HashMap<String, String>,
+    /// connection loading the rows of each group of linked tables since the last schema change
+    table_workers: HashMap<String, usize>,
+    /// connection and statement of the COPY payload being read
+    copy: Option<(usize, String)>,
+}
+
+impl<'a, W> ConcurrentRestore<'a, W>
+where
+    W: Fn(Bytes) -> Result<(), Error>,
+{
+    fn new(
+        workers: Vec<SyncSender<WorkerMessage>>,
+        write: &'a W,
+        failure: &'a Mutex<Option<Error>>,
+    ) -> Self {
+        let batches = vec![String::new(); workers.len()];
+
+        ConcurrentRestore {
+            workers,
+            write,
+            failure,
+            session_settings: String::new(),
+            serial_statements: String::new(),
+            batches,
+            linked_tables: HashMap::new(),
+            table_workers: HashMap::new(),
+            copy: None,
+        }
+    }
+
+    fn push(&mut self, query: &str) -> Result<(), Error> {
+        if self.copy.is_some() {
+            if !query.contains('\n') {
+                return self.push_copy_row(query);
+            }
+
+            // the payload can also come as a block of lines, followed by the next statements
+            let mut lines = query.split('\n');
+            while self.copy.is_some() {
+                match lines.next() {
+                    Some("") => {}
+                    Some(row) => self.push_copy_row(row)?,
+                    None => return Ok(()),
+                }
+            }
+
+            return self.push(lines.collect::<Vec<_>>().join("\n").as_str());
+        }
+
+        let statement = query.trim_start_matches('\n');
+        if statement.trim().is_empty() || statement.trim_start().starts_with("--") {
+            return Ok(());
+        }
+
+        let statement = format!("{}\n", statement);
+        let lowercase_statement = statement.to_lowercase();
+
+        // the psql meta-commands (e.g. `\restrict`) apply to the session as well
+        if starts_with_keyword(statement.as_str(), "SET")
+            || lowercase_statement.contains("pg_catalog.set_config(")
+            || statement.starts_with('\\')
+        {
+            self.session_settings.push_str(statement.as_str());
+            return Ok(());
+        }
+
+        let tokens = match Tokenizer::new(statement.as_str()).tokenize() {
+            Ok(tokens) => trim_pre_whitespaces(tokens),
+            // restored as it is - the error is reported by the destination
+            Err(_) => vec![],
+        };
+
+        let is_copy = match_keyword_at_position(Keyword::Copy, &tokens, 0);
+        let filled_table = if is_copy {
+            table_at_position(&tokens, 2)
+        } else if match_keyword_at_position(Keyword::Insert, &tokens, 0)
+            && match_keyword_at_position(Keyword::Into, &tokens, 2)
+        {
+            table_at_position(&tokens, 4)
+        } else {
+            None
+        };
+
+        if let Some(table) = filled_table {
+            // the schema changes are restored before the rows
+            let _ = self.run_serial_statements()?;
+
+            let group = self.linked_table(table.as_str());
+            let next_worker = self.table_workers.len() % self.workers.len();
+            let worker = *self.table_workers.entry(group).or_insert(next_worker);
+
+            self.batches[worker].push_str(statement.as_str());
+            if is_copy {
+                self.copy = Some((worker, statement));
+            } else if self.batches[worker].len() > CONCURRENT_BATCH_BYTES {
+                let _ = self.send_batch(worker)?;
+            }
+
+            return Ok(());
+        }
+
+        // the sequences values do not depend on the rows being loaded
+        if !lowercase_statement.contains("pg_catalog.setval(") {
+            let _ = self.wait_workers()?;
+            self.link_tables(&tokens);
+        }
+
+        self.serial_statements.push_str(statement.as_str());
+        if self.serial_statements.len() > CONCURRENT_BATCH_BYTES && self.table_workers.is_empty() {
+            let _ = self.run_serial_statements()?;
+        }
+
+        Ok(())
+    }
+
+    fn push_copy_row(&mut self, row: &str) -> Result<(), Error> {
+        let worker = match &self.copy {
+            Some((worker, _)) => *worker,
+            None => return Ok(()),
+        };
+
+        self.batches[worker].push_str(row);
+        self.batches[worker].push('\n');
+
+        if row == COPY_PAYLOAD_TERMINATOR {
+            self.copy = None;
+        } else if self.batches[worker].len() > CONCURRENT_BATCH_BYTES {
+            // a big payload is restored by several COPY statements
+            self.batches[worker].push_str(COPY_PAYLOAD_TERMINATOR);
+            self.batches[worker].push('\n');
+            let _ = self.send_batch(worker)?;
+
+            if let Some((_, copy_statement)) = &self.copy {
+                self.batches[worker] = copy_statement.clone();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), Error> {
+        let _ = self.wait_workers()?;
+        self.run_serial_statements()
+    }
+
+    fn send_batch(&mut self, worker: usize) -> Result<(), Error> {
+        if self.batches[worker].is_empty() {
+            return Ok(());
+        }
+
+        let mut data = self.session_settings.clone().into_bytes();
+        data.append(&mut std::mem::take(&mut self.batches[worker]).into_bytes());
+
+        if self.workers[worker]
+            .send(WorkerMessage::Statements(data))
+            .is_err()
+        {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "a restore connection stopped unexpectedly",
+            ));
+        }
+
+        self.check_failure()
+    }
+
+    /// send the pending batches and wait for every connection to restore them
+    fn wait_workers(&mut self) -> Result<(), Error> {
+        if self.table_workers.is_empty() {
+            return Ok(());
+        }
+
+        for worker in 0..self.workers.len() {
+            let _ = self.send_batch(worker)?;
+        }
+
+        let (done_tx, done_rx) = mpsc::sync_channel::<()>(self.workers.len());
+        for worker in &self.workers {
+            let _ = worker.send(WorkerMessage::Wait(done_tx.clone()));
+        }
+
+        drop(done_tx);
+        for _ in 0..self.workers.len() {
+            if done_rx.recv().is_err() {
+                break;
+            }
+        }
+
+        self.table_workers.clear();
+        self.check_failure()
+    }
+
+    fn run_serial_statements(&mut self) -> Result<(), Error> {
+        if self.serial_statements.is_empty() {
+            return Ok(());
+        }
+
+        let mut data = self.session_settings.clone().into_bytes();
+        data.append(&mut std::mem::take(&mut self.serial_statements).into_bytes());
+
+        (self.write)(data)
+    }
+
+    fn check_failure(&self) -> Result<(), Error> {
+        match self.failure.lock().unwrap().as_ref() {
+            Some(err) => Err(Error::new(err.kind(), err.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// first table of the group of tables linked to `table`
+    fn linked_table(&self, table: &str) -> String {
+        let mut table = table;
+        while let Some(linked_table) = self.linked_tables.get(table) {
+            table = linked_table.as_str();
+        }
+
+        table.to_string()
+    }
+
+    /// link the table created or altered by the statement to the tables it references
+    fn link_tables(&mut self, tokens: &Vec<Token>) {
+        let table = if match_keyword_at_position(Keyword::Create, tokens, 0)
+            && match_keyword_at_position(Keyword::Table, tokens, 2)
+        {
+            table_at_position(tokens, 4)
+        } else if match_keyword_at_position(Keyword::Alter, tokens, 0)
+            && match_keyword_at_position(Keyword::Table, tokens, 2)
+        {
+            if match_keyword_at_position(Keyword::Only, tokens, 4) {
+                table_at_position(tokens, 6)
+            } else {
+                table_at_position(tokens, 4)
+            }
+        } else {
+            None
+        };
+
+        let table = match table {
+            Some(table) => table,
+            None => return,
+        };
+
+        for pos in 0..tokens.len() {
+            if !match_keyword_at_position(Keyword::References, tokens, pos) {
+                continue;
+            }
+
+            if let Some(referenced_table) = table_at_position(tokens, pos + 2) {
+                let group = self.linked_table(table.as_str());
+                let referenced_group = self.linked_table(referenced_table.as_str());
+                if group != referenced_group {
+                    let _ = self.linked_tables.insert(referenced_group, group);
+                }
+            }
+        }
+    }
+}
+
+/// size of the batches of statements restored on a connection by the restore with a maximum number of errors
+const MAX_ERRORS_BATCH_BYTES: usize = 4 * 1024 * 1024;
+
+/// restore the dump by batches of statements and go on after the failed statements - `write` restores a batch
+/// until a statement fails, and the batch is restored again from the next statement. The restore is aborted at
+/// the `max_errors`th failed statement
+fn write_with_max_errors<R, W>(
+    dump: R,
+    max_errors: usize,
+    report: &mut RestoreReport,
+    write: W,
+) -> Result<(), Error>
+where
+    R: Read,
+    W: FnMut(&[String]) -> Result<Option<(usize, String)>, Error>,
+{
+    let mut restore = RestoreWithMaxErrors {
+        write,
+        max_errors,
+        report,
+        session_settings: vec![],
+        batch: vec![],
+        batch_bytes: 0,
+        statements: 0,
+        copy: false,
+    };
+
+    let mut result = Ok(());
+
+    let list_result = list_sql_queries_from_dump_reader(BufReader::new(dump), |query| {
+        result = restore.push(query);
+        match result {
+            Ok(_) => ListQueryResult::Continue,
+            Err(_) => ListQueryResult::Break,
+        }
+    });
+
+    if let Err(err) = list_result {
+        return Err(Error::new(ErrorKind::Other, format!("{:?}", err)));
+    }
+
+    let _ = result?;
+    restore.run_batch()
+}
+
+/// batch the statements of a dump for the restore with a maximum number of errors
+struct RestoreWithMaxErrors<'a, W> {
+    write: W,
+    max_errors: usize,
+    report: &'a mut RestoreReport,
+    /// `SET` statements restored before every batch - each batch is restored in a new session
+    session_settings: Vec<String>,
+    /// statements waiting to be restored - a COPY statement comes with its payload
+    batch: Vec<String>,
+    batch_bytes: usize,
+    /// number of statements met so far
+    statements: usize,
+    /// true while the payload of the last COPY statement of the batch is read
+    copy: bool,
+}
+
+impl<'a, W> RestoreWithMaxErrors<'a, W>
+where
+    W: FnMut(&[String]) -> Result<Option<(usize, String)>, Error>,
+{
+    fn push(&mut self, query: &str) -> Result<(), Error> {
+        if self.copy {
+            if !query.contains('\n') {
+                return self.push_copy_row(query);
+            }
+
+            // the payload can also come as a block of lines, followed by the next statements
+            let mut lines = query.split('\n');
+            while self.copy {
+                match lines.next() {
+                    Some("") => {}
+                    Some(row) => self.push_copy_row(row)?,
+                    None => return Ok(()),
+                }
+            }
+
+            return self.push(lines.collect::<Vec<_>>().join("\n").as_str());
+        }
+
+        let statement = query.trim_start_matches('\n');
+        if statement.trim().is_empty() || statement.trim_start().starts_with("--") {
+            return Ok(());
+        }
+
+        let statement = format!("{}\n", statement);
+
+        // the psql meta-commands (e.g. `\restrict`) apply to the session as well
+        if starts_with_keyword(statement.as_str(), "SET")
+            || statement.to_lowercase().contains("pg_catalog.set_config(")
+            || statement.starts_with('\\')
+        {
+            self.session_settings.push(statement);
+            return Ok(());
+        }
+
+        self.statements += 1;
+        self.copy = starts_with_keyword(statement.as_str(), "COPY");
+        self.batch_bytes += statement.len();
+        self.batch.push(statement);
+
+        if !self.copy && self.batch_bytes > MAX_ERRORS_BATCH_BYTES {
+            return self.run_batch();
+        }
+
+        Ok(())
+    }
+
+    fn push_copy_row(&mut self, row: &str) -> Result<(), Error> {
+        if let Some(copy_statement) = self.batch.last_mut() {
+            copy_statement.push_str(row);
+            copy_statement.push('\n');
+            self.batch_bytes += row.len() + 1;
+        }
+
+        if row == COPY_PAYLOAD_TERMINATOR {
+            self.copy = false;
+
+            if self.batch_bytes > MAX_ERRORS_BATCH_BYTES {
+                return self.run_batch();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_batch(&mut self) -> Result<(), Error> {
+        let batch = std::mem::take(&mut self.batch);
+        self.batch_bytes = 0;
+
+        // number of the statements met before the batch
+        let first_statement = self.statements - batch.len();
+        let mut from = 0;
+
+        while from < batch.len() {
+            let statements = self
+                .session_settings
+                .iter()
+                .chain(&batch[from..])
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let (failed, error) = match (self.write)(&statements)? {
+                None => {
+                    self.report.restored_statements += batch.len() - from;
+                    return Ok(());
+                }
+                Some((idx, error)) if idx < self.session_settings.len() => {
+                    return Err(Error::new(
+                        ErrorKind::Other,
+                        format!("a session setting of the dump failed: {}", error),
+                    ));
+                }
+                Some((idx, error)) => (from + idx - self.session_settings.len(), error),
+            };
+
+            self.report.restored_statements += failed - from;
+            self.report.errors.push(format!(
+                "statement {}: {}",
+                first_statement + failed + 1,
+                error
+            ));
+
+            if self.report.errors.len() >= self.max_errors {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    format!(
+                        "restore aborted at the failed statement {} - {} statements restored, {} failed: {}",
+                        first_statement + failed + 1,
+                        self.report.restored_statements,
+                        self.report.errors.len(),
+                        self.report.errors.join(", ")
+                    ),
+                ));
+            }
+
+            from = failed + 1;
+        }
+
+        Ok(())
+    }
+}
+
+/// line and message of the error reported by psql - e.g. `psql:<stdin>:3: ERROR:  relation "public.a" does not exist`
+fn psql_error(stderr: &str) -> Option<(usize, String)> {
+    stderr.lines().find_map(|line| {
+        let (line_number, message) = line.strip_prefix("psql:<stdin>:")?.split_once(": ")?;
+        if !message.starts_with("ERROR:") {
+            return None;
+        }
+
+        Some((line_number.parse::<usize>().ok()?, message.to_string()))
+    })
+}
+
+/// index of the statement holding the line (from 1) of the statements written one after the other
+fn statement_at_line(statements: &[String], line: usize) -> usize {
+    let mut first_line = 1;
+
+    for (idx, statement) in statements.iter().enumerate() {
+        let lines = statement.matches('\n').count().max(1);
+        if line < first_line + lines {
+            return idx;
+        }
+
+        first_line += lines;
+    }
+
+    statements.len().saturating_sub(1)
+}
+
+/// `schema.table` name of the table at the given position - the tables without schema are in `public`
+fn table_at_position(tokens: &Vec<Token>, pos: usize) -> Option<String> {
+    let name = identifier_at_position(tokens, pos)?;
+
+    match (tokens.get(pos + 1), identifier_at_position(tokens, pos + 2)) {
+        (Some(Token::Period), Some(table_name)) => Some(format!(
+            "{}.{}",
+            folded_identifier(name.as_str()),
+            folded_identifier(table_name.as_str())
+        )),
+        _ => Some(format!("public.{}", folded_identifier(name.as_str()))),
+    }
+}
+
+/// name of an identifier as stored by PostgreSQL - the unquoted identifiers are folded to lower case
+fn folded_identifier(name: &str) -> String {
+    if name.starts_with('"') {
+        unquote(name).to_string()
+    } else {
+        name.to_lowercase()
+    }
+}
+
+/// identifier at the given position, with its quotes if any
+fn identifier_at_position(tokens: &Vec<Token>, pos: usize) -> Option<String> {
+    match tokens.get(pos) {
+        Some(Token::Word(word)) => Some(match word.quote_style {
+            Some(quote) => format!("{}{}{}", quote, word.value, quote),
+            None => word.value.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// column of the dump to rename or to drop before the restore
+#[derive(Debug, Clone, PartialEq)]
+struct MappedColumn {
+    /// `None` matches any database
+    database: Option<String>,
+    table: String,
+    column: String,
+    /// `None` drops the column
+    new_column: Option<String>,
+}
+
+impl MappedColumn {
+    fn matches_table(&self, database_name: &str, table_name: &str) -> bool {
+        self.database
+            .as_deref()
+            .map_or(true, |database| unquote(database) == unquote(database_name))
+            && unquote(self.table.as_str()) == unquote(table_name)
+    }
+
+    fn matches(&self, database_name: &str, table_name: &str, column_name: &str) -> bool {
+        self.matches_table(database_name, table_name)
+            && unquote(self.column.as_str()) == unquote(column_name)
+    }
+}
+
+/// Rewrite the `INSERT INTO ...` and `COPY ... FROM stdin;` queries of a dump to match the columns of the
+/// target - e.g. a column renamed or dropped in the target since the dump was created.
+#[derive(Debug, Default)]
+pub struct ColumnMapping {
+    columns: Vec<MappedColumn>,
+    /// a COPY payload may be split across several dump parts
+    is_copy_payload: bool,
+    /// indexes of the columns kept in the rows of the current COPY payload - `None` to keep the rows as they are
+    copy_payload_columns: Option<Vec<usize>>,
+}
+
+impl ColumnMapping {
+    /// `map_columns` are `([database.]table.column, new_column)` pairs and `drop_columns` are `[database.]table.column`
+    pub fn new(map_columns: &[(String, String)], drop_columns: &[String]) -> Result<Self, Error> {
+        let mut columns = vec![];
+
+        for (column, new_column) in map_columns {
+            columns.push(mapped_column(
+                column.as_str(),
+                Some(new_column.to_string()),
+            )?);
+        }
+
+        for column in drop_columns {
+            columns.push(mapped_column(column.as_str(), None)?);
+        }
+
+        Ok(ColumnMapping {
+            columns,
+            ..Default::default()
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// rewrite a part of a dump - the parts must be given in order.
+    /// The queries without mapped columns are kept byte-for-byte
+    pub fn map_columns(&mut self, data: Bytes) -> Bytes {
+        if self.is_empty() {
+            return data;
+        }
+
+        let mut mapped_data = Vec::with_capacity(data.len());
+        let mut statement = String::new();
+
+        for line in data.split_inclusive(|byte| *byte == b'\n') {
+            let line = match str::from_utf8(line) {
+                Ok(line) if statement.is_empty() && self.is_copy_payload => {
+                    // COPY payload rows are not SQL - a payload may be split across several dump parts
+                    let row = line.trim_end_matches('\n');
+                    mapped_data.extend_from_slice(self.map_copy_row(row).as_bytes());
+                    mapped_data.extend_from_slice(&line.as_bytes()[row.len()..]);
+                    continue;
+                }
+                Ok(line) => line,
+                Err(_) => {
+                    mapped_data.extend_from_slice(statement.as_bytes());
+                    mapped_data.extend_from_slice(line);
+                    statement.clear();
+                    continue;
+                }
+            };
+
+            statement.push_str(line);
+            if !is_complete_statement(statement.as_str()) {
+                continue;
+            }
+
+            match self.map_statement(statement.trim_end_matches('\n')) {
+                Some(query) => {
+                    mapped_data.extend_from_slice(query.data());
+                    mapped_data.push(b'\n');
+                }
+                None => mapped_data.extend_from_slice(statement.as_bytes()),
+            }
+
+            statement.clear();
+        }
+
+        mapped_data.extend_from_slice(statement.as_bytes());
+        mapped_data
+    }
+
+    /// the statement with the mapped columns - `None` if the statement is kept as it is
+    fn map_statement(&mut self, statement: &str) -> Option<Query> {
+        // only the statements to rewrite are tokenized
+        if starts_with_keyword(statement, "COPY") {
+            // any other line is a payload row until the `\.` terminator
+            self.is_copy_payload = statement.to_uppercase().ends_with("FROM STDIN;");
+            self.copy_payload_columns = None;
+            return self.map_copy_query(&get_tokens_from_query_str(statement));
+        }
+
+        if starts_with_keyword(statement, "INSERT") {
+            return self.map_insert_into_query(&get_tokens_from_query_str(statement));
+        }
+
+        None
+    }
+
+    fn map_insert_into_query(&self, tokens: &Vec<Token>) -> Option<Query> {
+        if !match_keyword_at_position(Keyword::Insert, tokens, 0)
+            || !match_keyword_at_position(Keyword::Into, tokens, 2)
+        {
+            return None;
+        }
+
+        let (database_name, table_name) = match (
+            get_word_value_at_position(tokens, 4),
+            get_word_value_at_position(tokens, 6),
+        ) {
+            (Some(database_name), Some(table_name))
+                if self.has_table(database_name, table_name) =>
+            {
+                (database_name, table_name)
+            }
+            _ => return None,
+        };
+
+        if !has_literal_values(tokens) {
+            warn!(
+                "the columns of an INSERT INTO {}.{} query can't be mapped - the query is kept as it is",
+                database_name, table_name
+            );
+            return None;
+        }
+
+        let column_names = get_column_names_from_insert_into_query(tokens);
+        let column_values = get_column_values_from_insert_into_query(tokens);
+
+        let columns = column_names
+            .iter()
+            .zip(column_values)
+            .filter_map(|(column_name, value_token)| {
+                self.column_name(database_name, table_name, column_name.as_str())
+                    .map(|column_name| to_column(column_name.as_str(), value_token))
+            })
+            .collect::<Vec<_>>();
+
+        Some(to_query(
+            Some(database_name),
+            InsertIntoQuery {
+                table_name: table_name.to_string(),
+                columns,
+            },
+            StringEscapingConfig::Ansi,
+        ))
+    }
+
+    fn map_copy_query(&mut self, tokens: &Vec<Token>) -> Option<Query> {
+        if !self.is_copy_payload {
+            return None;
+        }
+
+        let (database_name, table_name) = match (
+            get_word_value_at_position(tokens, 2),
+            get_word_value_at_position(tokens, 4),
+        ) {
+            (Some(database_name), Some(table_name))
+                if self.has_table(database_name, table_name) =>
+            {
+                (database_name, table_name)
+            }
+            _ => return None,
+        };
+
+        let mut copy_payload_columns = vec![];
+        let mut column_names = vec![];
+        for (i, column_name) in get_column_names_from_copy_query(tokens).iter().enumerate() {
+            if let Some(column_name) = self.column_name(database_name, table_name, column_name) {
+                copy_payload_columns.push(i);
+                column_names.push(column_name);
+            }
+        }
+
+        self.copy_payload_columns = Some(copy_payload_columns);
+
+        Some(Query(
+            format!(
+                "COPY {}.{} ({}) FROM stdin;",
+                database_name,
+                table_name,
+                column_names.join(", ")
+            )
+            .into_bytes(),
+        ))
+    }
+
+    fn map_copy_row(&mut self, row: &str) -> String {
+        if row == COPY_PAYLOAD_TERMINATOR {
+            self.is_copy_payload = false;
+            self.copy_payload_columns = None;
+        }
+
+        match &self.copy_payload_columns {
+            Some(copy_payload_columns) => {
+                let values = row.split('\t').collect::<Vec<_>>();
+                copy_payload_columns
+                    .iter()
+                    .filter_map(|i| values.get(*i).copied())
+                    .collect::<Vec<_>>()
+                    .join("\t")
+            }
+            None => row.to_string(),
+        }
+    }
+
+    fn has_table(&self, database_name: &str, table_name: &str) -> bool {
+        self.columns
+            .iter()
+            .any(|column| column.matches_table(database_name, table_name))
+    }
+
+    /// name of the column in the target - `None` if the column is dropped
+    fn column_name(
+        &self,
+        database_name: &str,
+        table_name: &str,
+        column_name: &str,
+    ) -> Option<String> {
+        match self
+            .columns
+            .iter()
+            .find(|column| column.matches(database_name, table_name, column_name))
+        {
+            Some(mapped_column) => mapped_column.new_column.clone(),
+            None => Some(column_name.to_string()),
+        }
+    }
+}
+
+/// parse a `[database.]table.column` column name
+fn mapped_column(name: &str, new_column: Option<String>) -> Result<MappedColumn, Error> {
+    let (database, table, column) = match name.split('.').collect::<Vec<_>>().as_slice() {
+        [table, column] => (None, table.to_string(), column.to_string()),
+        [database, table, column] => (
+            Some(database.to_string()),
+            table.to_string(),
+            column.to_string(),
+        ),
+        _ => (None, String::new(), String::new()),
+    };
+
+    if table.is_empty() || column.is_empty() || new_column.as_deref() == Some("") {
+        return Err(Error::new(
+            ErrorKind::Other,
+            format!(
+                "invalid column '{}' - use `table.column` or `database.table.column`",
+                name
+            ),
+        ));
+    }
+
+    Ok(MappedColumn {
+        database,
+        table,
+        column,
+        new_column,
+    })
+}
+
+/// Rewrite the consecutive `INSERT INTO ...` rows of a table into a single `COPY ... FROM stdin;` payload -
+/// PostgreSQL loads a COPY payload much faster than one statement per row.
+#[derive(Debug, Default)]
+pub struct CopyBatching {
+    /// a COPY payload of the dump may be split across several dump parts
+    is_copy_payload: bool,
+}
+
+impl CopyBatching {
+    /// rewrite a part of a dump - the parts must be given in order. Each part is restored on its own connection,
+    /// so a payload never goes past the end of its part. The rows with other values than literals (e.g. a function
+    /// call) are kept as `INSERT INTO` queries
+    pub fn to_copy(&mut self, data: Bytes) -> Bytes {
+        let mut copy_data = Vec::with_capacity(data.len());
+        let mut statement = String::new();
+        // `COPY ... FROM stdin;` statement of the payload being written
+        let mut copy_statement: Option<String> = None;
+
+        for line in data.split_inclusive(|byte| *byte == b'\n') {
+            let line = match str::from_utf8(line) {
+                Ok(line) if statement.is_empty() && self.is_copy_payload => {
+                    // the COPY payloads of the dump are kept as they are
+                    if line.trim_end_matches('\n') == COPY_PAYLOAD_TERMINATOR {
+                        self.is_copy_payload = false;
+                    }
+
+                    copy_data.extend_from_slice(line.as_bytes());
+                    continue;
+                }
+                Ok(line) => line,
+                Err(_) => {
+                    end_copy_payload(&mut copy_data, &mut copy_statement);
+                    copy_data.extend_from_slice(statement.as_bytes());
+                    copy_data.extend_from_slice(line);
+                    statement.clear();
+                    continue;
+                }
+            };
+
+            statement.push_str(line);
+            if !is_complete_statement(statement.as_str()) {
+                continue;
+            }
+
+            if statement.trim().is_empty() && copy_statement.is_some() {
+                // the blank lines between the rows do not end the payload
+                statement.clear();
+                continue;
+            }
+
+            match to_copy_statement_and_row(statement.trim_end_matches('\n')) {
+                Some((statement, row)) => {
+                    if copy_statement.as_ref() != Some(&statement) {
+                        end_copy_payload(&mut copy_data, &mut copy_statement);
+                        copy_data.extend_from_slice(statement.as_bytes());
+                        copy_data.push(b'\n');
+                        copy_statement = Some(statement);
+                    }
+
+                    copy_data.extend_from_slice(row.as_bytes());
+                    copy_data.push(b'\n');
+                }
+                None => {
+                    if starts_with_keyword(statement.as_str(), "COPY") {
+                        // any other line is a payload row until the `\.` terminator
+                        self.is_copy_payload =
+                            statement.trim().to_uppercase().ends_with("FROM STDIN;");
+                    }
+
+                    end_copy_payload(&mut copy_data, &mut copy_statement);
+                    copy_data.extend_from_slice(statement.as_bytes());
+                }
+            }
+
+            statement.clear();
+        }
+
+        end_copy_payload(&mut copy_data, &mut copy_statement);
+        copy_data.extend_from_slice(statement.as_bytes());
+        copy_data
+    }
+}
+
+fn end_copy_payload(copy_data: &mut Bytes, copy_statement: &mut Option<String>) {
+    if copy_statement.take().is_some() {
+        copy_data.extend_from_slice(COPY_PAYLOAD_TERMINATOR.as_bytes());
+        copy_data.push(b'\n');
+    }
+}
+
+/// `COPY ... FROM stdin;` statement and payload row of an `INSERT INTO ...` query - `None` if the query is not an
+/// `INSERT INTO` of a single row of literal values
+fn to_copy_statement_and_row(statement: &str) -> Option<(String, String)> {
+    // only the statements to rewrite are tokenized
+    if !starts_with_keyword(statement, "INSERT") {
+        return None;
+    }
+
+    let tokens = match Tokenizer::new(statement).tokenize() {
+        Ok(tokens) => trim_pre_whitespaces(tokens),
+        Err(_) => return None,
+    };
+
+    if !match_keyword_at_position(Keyword::Insert, &tokens, 0)
+        || !match_keyword_at_position(Keyword::Into, &tokens, 2)
+        || !has_literal_values(&tokens)
+    {
+        return None;
+    }
+
+    let table = match (
+        identifier_at_position(&tokens, 4),
+        tokens.get(5),
+        identifier_at_position(&tokens, 6),
+    ) {
+        (Some(database_name), Some(Token::Period), Some(table_name)) => {
+            format!("{}.{}", database_name, table_name)
+        }
+        (Some(table_name), _, _) => table_name,
+        _ => return None,
+    };
+
+    let values = get_column_values_from_insert_into_query(&tokens)
+        .into_iter()
+        .map(to_copy_value)
+        .collect::<Option<Vec<_>>>()?;
+
+    Some((
+        format!(
+            "COPY {} ({}) FROM stdin;",
+            table,
+            get_column_names_from_insert_into_query(&tokens).join(", ")
+        ),
+        to_copy_row(&values),
+    ))
+}
+
+/// value of a COPY payload row from a literal value - `Some(None)` is a NULL value, and `None` a value that has no
+/// COPY equivalent (e.g. a bit string)
+fn to_copy_value(token: &Token) -> Option<Option<String>> {
+    match token {
+        Token::Number(value, _) => Some(Some(value.clone())),
+        // the tokenizer keeps the escaped quotes
+        Token::SingleQuotedString(value) | Token::NationalStringLiteral(value) => {
+            Some(Some(value.replace("''", "'")))
+        }
+        Token::Word(word) if word.keyword == Keyword::Null => Some(None),
+        Token::Word(word) if word.quote_style == None => Some(Some(word.value.clone())),
+        _ => None,
+    }
+}
+
+/// a statement ends with a `;` out of a string literal - the blank lines and the comments are complete statements
+fn is_complete_statement(statement: &str) -> bool {
+    let statement = statement.trim();
+
+    statement.is_empty()
+        || statement.starts_with("--")
+        || (statement.ends_with(';') && statement.matches('\'').count() % 2 == 0)
+}
+
+fn starts_with_keyword(statement: &str, keyword: &str) -> bool {
+    let statement = statement.trim_start();
+
+    statement
+        .get(..keyword.len())
+        .map_or(false, |prefix| prefix.eq_ignore_ascii_case(keyword))
+        && statement[keyword.len()..].starts_with(char::is_whitespace)
+}
+
+/// the tokenizer keeps the double quotes of the identifiers
+fn unquote(name: &str) -> &str {
+    name.trim_matches('"')
+}
+
+fn truncate_tables_query(tables: &Vec<String>) -> String {
+    tables
+        .iter()
+        .map(|table| format!("TRUNCATE TABLE {} CASCADE;\n", table))
+        .collect()
 }
 
 fn wipe_database_query(username: &str) -> String {
@@ -114,8 +1466,19 @@ fn wipe_database_query(username: &str) -> String {
 #[cfg(test)]
 mod tests {
     use crate::connector::Connector;
-    use crate::destination::postgres::Postgres;
-    use crate::destination::Destination;
+    use std::collections::HashSet;
+    use std::process::Command;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Condvar, Mutex};
+    use std::time::Duration;
+
+    use std::io::{Error, ErrorKind};
+
+    use crate::destination::postgres::{
+        dry_run, dump_tables, psql_error, statement_at_line, truncate_tables_query,
+        write_concurrently, write_with_max_errors, ColumnMapping, CopyBatching, Postgres,
+    };
+    use crate::destination::{Destination, RestoreReport};
 
     fn get_postgres() -> Postgres<'static> {
         Postgres::new("localhost", 5453, "root", "root", "password", true)
@@ -138,4 +1501,516 @@ mod tests {
 
     #[test]
     fn test_inserts() {}
+
+    #[test]
+    fn list_dump_tables_and_truncate() {
+        let dump = r#"
+CREATE TABLE public."Users" (
+    id integer NOT NULL
+);
+
+COPY public.orders (id) FROM stdin;
+1
+INSERT INTO public.fake VALUES (1);
+\.
+
+INSERT INTO public.categories (id) VALUES (1);
+INSERT INTO public.categories (id) VALUES (2);
+COPY public."Users" (id) FROM stdin;
+1
+\.
+"#;
+
+        let tables = dump_tables(dump.as_bytes()).unwrap();
+        assert_eq!(
+            tables,
+            vec![
+                "public.\"Users\"".to_string(),
+                "public.orders".to_string(),
+                "public.categories".to_string(),
+            ]
+        );
+
+        assert_eq!(
+            truncate_tables_query(&tables),
+            "TRUNCATE TABLE public.\"Users\" CASCADE;\n\
+             TRUNCATE TABLE public.orders CASCADE;\n\
+             TRUNCATE TABLE public.categories CASCADE;\n"
+        );
+    }
+
+    #[test]
+    fn dry_run_reports_missing_tables() {
+        let dump = r#"
+CREATE TABLE public."Users" (
+    id integer NOT NULL
+);
+
+COPY public."Users" (id) FROM stdin;
+1
+\.
+
+INSERT INTO public.categories (id) VALUES (1);
+INSERT INTO public.orders (id) VALUES (1);
+"#;
+
+        let existing_tables =
+            HashSet::from(["public.categories".to_string(), "public.orders".to_string()]);
+        assert_eq!(dry_run(dump.as_bytes(), existing_tables).unwrap(), 4);
+
+        // the orders table is neither created by the dump nor in the destination
+        let existing_tables = HashSet::from(["public.categories".to_string()]);
+        let err = dry_run(dump.as_bytes(), existing_tables.clone()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "statement 4: the table 'public.orders' does not exist in the destination"
+        );
+
+        // the payload of the COPY statement is cut
+        let dump = "COPY public.categories (id) FROM stdin;\n1\n2\n";
+        let err = dry_run(dump.as_bytes(), existing_tables).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the COPY payload of the table 'public.categories' is not terminated by '\\.'"
+        );
+    }
+
+    fn count_rows(table: &str) -> usize {
+        let output = Command::new("psql")
+            .env("PGPASSWORD", "password")
+            .args([
+                "-h",
+                "localhost",
+                "-p",
+                "5453",
+                "-d",
+                "root",
+                "-U",
+                "root",
+                "-A",
+                "-t",
+                "-c",
+                format!("SELECT count(*) FROM {};", table).as_str(),
+            ])
+            .output()
+            .unwrap();
+
+        String::from_utf8_lossy(output.stdout.as_slice())
+            .trim()
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn concurrent_restore() {
+        // every table takes one second to fill
+        let dump = "\
+CREATE TABLE public.slow_a (id integer);
+CREATE TABLE public.slow_b (id integer);
+CREATE TABLE public.slow_c (id integer);
+
+INSERT INTO public.slow_a (id) SELECT 1 FROM pg_sleep(1);
+INSERT INTO public.slow_b (id) SELECT 1 FROM pg_sleep(1);
+INSERT INTO public.slow_c (id) SELECT 1 FROM pg_sleep(1);
+COPY public.slow_a (id) FROM stdin;
+2
+3
+\\.
+";
+
+        let mut p = get_postgres();
+        let _ = p.init().expect("can't init postgres");
+        assert!(p.write(dump.as_bytes().to_vec()).is_ok());
+        assert_eq!(count_rows("public.slow_a"), 3);
+
+        let _ = p.init().expect("can't init postgres");
+        // number of the batches being loaded - the slow tables are loaded at the same time
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        assert!(write_concurrently(dump.as_bytes(), 3, |data| {
+            let loading = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(loading, Ordering::SeqCst);
+            let result = p.write(data);
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            result
+        })
+        .is_ok());
+        assert!(max_in_flight.load(Ordering::SeqCst) >= 2);
+        assert_eq!(count_rows("public.slow_a"), 3);
+        assert_eq!(count_rows("public.slow_b"), 1);
+        assert_eq!(count_rows("public.slow_c"), 1);
+    }
+
+    #[test]
+    fn restore_with_max_errors() {
+        let dump = "\
+SET client_encoding = 'UTF8';
+CREATE TABLE public.users (id integer);
+INSERT INTO public.users (id) VALUES (1);
+INSERT INTO public.missing (id) VALUES (1);
+INSERT INTO public.users (id) VALUES (2);
+INSERT INTO public.missing (id) VALUES (2);
+COPY public.users (id) FROM stdin;
+3
+4
+\\.
+INSERT INTO public.missing (id) VALUES (3);
+INSERT INTO public.users (id) VALUES (5);
+";
+
+        let restore = |max_errors: usize| {
+            let mut restored = vec![];
+            let mut report = RestoreReport::default();
+
+            let result =
+                write_with_max_errors(dump.as_bytes(), max_errors, &mut report, |statements| {
+                    // the session settings are restored before every batch
+                    assert_eq!(statements[0], "SET client_encoding = 'UTF8';\n");
+
+                    for (idx, statement) in statements.iter().enumerate().skip(1) {
+                        if statement.contains("public.missing") {
+                            return Ok(Some((
+                                idx,
+                                "ERROR:  relation \"public.missing\" does not exist".to_string(),
+                            )));
+                        }
+
+                        restored.push(statement.clone());
+                    }
+
+                    Ok(None)
+                });
+
+            (result, restored, report)
+        };
+
+        // aborted at the second failed statement - the next statements are not restored
+        let (result, restored, report) = restore(2);
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains(
+            "restore aborted at the failed statement 5 - 3 statements restored, 2 failed"
+        ));
+        assert_eq!(
+            restored,
+            vec![
+                "CREATE TABLE public.users (id integer);\n",
+                "INSERT INTO public.users (id) VALUES (1);\n",
+                "INSERT INTO public.users (id) VALUES (2);\n",
+            ]
+        );
+        assert_eq!(report.restored_statements, 3);
+        assert_eq!(
+            report.errors,
+            vec![
+                "statement 3: ERROR:  relation \"public.missing\" does not exist",
+                "statement 5: ERROR:  relation \"public.missing\" does not exist",
+            ]
+        );
+
+        // every statement but the failed ones is restored
+        let (result, restored, report) = restore(4);
+        assert!(result.is_ok());
+        assert_eq!(
+            restored,
+            vec![
+                "CREATE TABLE public.users (id integer);\n",
+                "INSERT INTO public.users (id) VALUES (1);\n",
+                "INSERT INTO public.users (id) VALUES (2);\n",
+                "COPY public.users (id) FROM stdin;\n3\n4\n\\.\n",
+                "INSERT INTO public.users (id) VALUES (5);\n",
+            ]
+        );
+        assert_eq!(report.restored_statements, 5);
+        assert_eq!(report.errors.len(), 3);
+        assert!(report.errors[2].starts_with("statement 7: "));
+
+        // a connection failure aborts the restore
+        let mut report = RestoreReport::default();
+        assert!(write_with_max_errors(dump.as_bytes(), 4, &mut report, |_| {
+            Err(Error::new(ErrorKind::Other, "connection refused"))
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn find_the_statement_failed_in_psql() {
+        let statements = vec![
+            "SET client_encoding = 'UTF8';\n".to_string(),
+            "CREATE TABLE public.users (\n    id integer\n);\n".to_string(),
+            "COPY public.users (id) FROM stdin;\n1\n\\.\n".to_string(),
+            "INSERT INTO public.missing (id) VALUES (1);\n".to_string(),
+        ];
+
+        let stderr = "\
+psql:<stdin>:8: ERROR:  relation \"public.missing\" does not exist
+LINE 1: INSERT INTO public.missing (id) VALUES (1);
+                    ^
+";
+
+        let (line, error) = psql_error(stderr).unwrap();
+        assert_eq!(line, 8);
+        assert_eq!(error, "ERROR:  relation \"public.missing\" does not exist");
+        assert_eq!(statement_at_line(&statements, line), 3);
+        assert_eq!(statement_at_line(&statements, 3), 1);
+        assert_eq!(statement_at_line(&statements, 6), 2);
+
+        assert!(psql_error("psql:<stdin>:1: NOTICE:  table \"users\" does not exist").is_none());
+    }
+
+    #[test]
+    fn concurrent_restore_batches() {
+        let dump = "\
+SET client_encoding = 'UTF8';
+CREATE TABLE public.users (id integer);
+CREATE TABLE public.orders (
+    id integer,
+    user_id integer REFERENCES public.users (id)
+);
+CREATE TABLE public.products (id integer);
+
+COPY public.users (id) FROM stdin;
+1
+\\.
+INSERT INTO public.products (id) VALUES (1);
+INSERT INTO public.orders (id, user_id) VALUES (1, 1);
+SELECT pg_catalog.setval('public.users_id_seq', 1, true);
+ALTER TABLE ONLY public.products ADD CONSTRAINT products_pkey PRIMARY KEY (id);
+INSERT INTO public.products (id) VALUES (2);
+";
+
+        let writes = Mutex::new(vec![]);
+        // the rows of independent tables are loaded at the same time - each batch waits for the other one
+        let loading = (Mutex::new(0), Condvar::new());
+        assert!(write_concurrently(dump.as_bytes(), 2, |data| {
+            let data = String::from_utf8(data).unwrap();
+            if data.contains("COPY public.users")
+                || data.contains("public.products (id) VALUES (1)")
+            {
+                let (batches, started) = &loading;
+                let mut batches = batches.lock().unwrap();
+                *batches += 1;
+                started.notify_all();
+
+                let (batches, _) = started
+                    .wait_timeout_while(batches, Duration::from_secs(10), |batches| *batches < 2)
+                    .unwrap();
+                if *batches < 2 {
+                    return Err(Error::new(
+                        ErrorKind::Other,
+                        "the batches are not loaded concurrently",
+                    ));
+                }
+            }
+
+            writes.lock().unwrap().push(data);
+            Ok(())
+        })
+        .is_ok());
+
+        let mut writes = writes.into_inner().unwrap();
+        // the end of the loading order of the tables is not known
+        writes[1..3].sort();
+
+        assert_eq!(
+            writes,
+            vec![
+                "\
+SET client_encoding = 'UTF8';
+CREATE TABLE public.users (id integer);
+CREATE TABLE public.orders (
+    id integer,
+    user_id integer REFERENCES public.users (id)
+);
+CREATE TABLE public.products (id integer);
+"
+                .to_string(),
+                // the orders reference the users - they are loaded by the same connection
+                "\
+SET client_encoding = 'UTF8';
+COPY public.users (id) FROM stdin;
+1
+\\.
+INSERT INTO public.orders (id, user_id) VALUES (1, 1);
+"
+                .to_string(),
+                "\
+SET client_encoding = 'UTF8';
+INSERT INTO public.products (id) VALUES (1);
+"
+                .to_string(),
+                // the schema changes wait for the rows
+                "\
+SET client_encoding = 'UTF8';
+SELECT pg_catalog.setval('public.users_id_seq', 1, true);
+ALTER TABLE ONLY public.products ADD CONSTRAINT products_pkey PRIMARY KEY (id);
+"
+                .to_string(),
+                "\
+SET client_encoding = 'UTF8';
+INSERT INTO public.products (id) VALUES (2);
+"
+                .to_string(),
+            ]
+        );
+
+        // the COPY payload can follow a statement of the same table
+        let dump = "\
+INSERT INTO public.users (id) VALUES (1);
+COPY public.users (id) FROM stdin;
+2
+\\.
+";
+        let writes = Mutex::new(vec![]);
+        assert!(write_concurrently(dump.as_bytes(), 2, |data| {
+            writes
+                .lock()
+                .unwrap()
+                .push(String::from_utf8(data).unwrap());
+            Ok(())
+        })
+        .is_ok());
+        assert_eq!(writes.into_inner().unwrap(), vec![dump.to_string()]);
+    }
+
+    fn column_mapping() -> ColumnMapping {
+        ColumnMapping::new(
+            &[("users.name".to_string(), "full_name".to_string())],
+            &["public.users.legacy_id".to_string()],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn map_and_drop_columns() {
+        let dump = "\
+INSERT INTO public.users (id, name, legacy_id) VALUES (1, 'Lucas', 42);
+INSERT INTO public.orders (id, name, legacy_id) VALUES (1, 'order', 42);
+COPY public.users (id, name, legacy_id) FROM stdin;
+2\tRomaric\t43
+3\t\\N\t44
+\\.
+COPY public.orders (id, name) FROM stdin;
+2\torder
+\\.
+";
+
+        let mut column_mapping = column_mapping();
+        let data = column_mapping.map_columns(dump.as_bytes().to_vec());
+
+        assert_eq!(
+            String::from_utf8(data).unwrap(),
+            "\
+INSERT INTO public.users (id, full_name) VALUES (1, 'Lucas');
+INSERT INTO public.orders (id, name, legacy_id) VALUES (1, 'order', 42);
+COPY public.users (id, full_name) FROM stdin;
+2\tRomaric
+3\t\\N
+\\.
+COPY public.orders (id, name) FROM stdin;
+2\torder
+\\.
+"
+        );
+
+        // nothing to map
+        let mut column_mapping = ColumnMapping::new(&[], &[]).unwrap();
+        assert!(column_mapping.is_empty());
+        let data = column_mapping.map_columns(dump.as_bytes().to_vec());
+        assert_eq!(String::from_utf8(data).unwrap(), dump);
+    }
+
+    #[test]
+    fn map_columns_of_a_copy_payload_in_several_parts() {
+        let parts = [
+            "COPY public.\"users\" (id, \"name\", legacy_id) FROM stdin;\n1\tLucas\t42\n",
+            "2\tRomaric\t43\n\\.\nINSERT INTO public.users (id, legacy_id) VALUES (3, 44);\n",
+        ];
+
+        let mut column_mapping = column_mapping();
+        let data = parts
+            .iter()
+            .map(|part| {
+                String::from_utf8(column_mapping.map_columns(part.as_bytes().to_vec())).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            data,
+            vec![
+                "COPY public.\"users\" (id, full_name) FROM stdin;\n1\tLucas\n".to_string(),
+                "2\tRomaric\n\\.\nINSERT INTO public.users (id) VALUES (3);\n".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn insert_into_rows_to_copy_payloads() {
+        let dump = "\
+CREATE TABLE public.users (id integer, name text, active boolean);
+INSERT INTO public.users (id, name, active) VALUES (1, 'it''s\ta\\test', true);
+INSERT INTO public.users (id, name, active) VALUES (-2, NULL, false);
+
+INSERT INTO public.users (id, name, active) VALUES (3, now(), true);
+INSERT INTO \"Orders\" (id) VALUES (1);
+INSERT INTO \"Orders\" (id) VALUES (2);
+COPY public.items (id) FROM stdin;
+1
+\\.
+";
+
+        let data = CopyBatching::default().to_copy(dump.as_bytes().to_vec());
+
+        assert_eq!(
+            String::from_utf8(data).unwrap(),
+            "\
+CREATE TABLE public.users (id integer, name text, active boolean);
+COPY public.users (id, name, active) FROM stdin;
+1\tit's\\ta\\\\test\ttrue
+-2\t\\N\tfalse
+\\.
+INSERT INTO public.users (id, name, active) VALUES (3, now(), true);
+COPY \"Orders\" (id) FROM stdin;
+1
+2
+\\.
+COPY public.items (id) FROM stdin;
+1
+\\.
+"
+        );
+    }
+
+    #[test]
+    fn insert_into_rows_to_copy_payloads_in_several_parts() {
+        let parts = [
+            "COPY public.users (id, name) FROM stdin;\n1\tLucas\n",
+            "INSERT INTO x\n\\.\nINSERT INTO public.users (id, name) VALUES (2, 'Romaric');\n",
+            "INSERT INTO public.users (id, name) VALUES (3, 'Pierre');\n",
+        ];
+
+        let mut copy_batching = CopyBatching::default();
+        let data = parts
+            .iter()
+            .map(|part| String::from_utf8(copy_batching.to_copy(part.as_bytes().to_vec())).unwrap())
+            .collect::<Vec<_>>();
+
+        // a payload row of the dump is not rewritten - and a payload ends with its part
+        assert_eq!(
+            data,
+            vec![
+                "COPY public.users (id, name) FROM stdin;\n1\tLucas\n".to_string(),
+                "INSERT INTO x\n\\.\nCOPY public.users (id, name) FROM stdin;\n2\tRomaric\n\\.\n"
+                    .to_string(),
+                "COPY public.users (id, name) FROM stdin;\n3\tPierre\n\\.\n".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_mapped_columns() {
+        assert!(ColumnMapping::new(&[], &["legacy_id".to_string()]).is_err());
+        assert!(ColumnMapping::new(&[], &["a.b.c.d".to_string()]).is_err());
+        assert!(ColumnMapping::new(&[("users.".to_string(), "name".to_string())], &[]).is_err());
+        assert!(ColumnMapping::new(&[("users.name".to_string(), "".to_string())], &[]).is_err());
+    }
 }
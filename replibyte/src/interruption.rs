@@ -0,0 +1,46 @@
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+lazy_static! {
+    static ref INTERRUPTED: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+}
+
+// number of running tasks able to stop cleanly on Ctrl-C
+static LISTENERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Install the Ctrl-C handler.
+/// The process exits right away, unless an `InterruptionListener` is alive - in that case the
+/// interruption flag is raised to let the task stop cleanly, and a second Ctrl-C forces the exit.
+pub fn set_handler() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        let already_interrupted = INTERRUPTED.swap(true, Ordering::SeqCst);
+
+        if already_interrupted || LISTENERS.load(Ordering::SeqCst) == 0 {
+            std::process::exit(130);
+        }
+
+        eprintln!("interrupted - stopping... (press Ctrl-C again to force)");
+    })
+}
+
+/// Flag raised on Ctrl-C
+pub fn interrupted() -> Arc<AtomicBool> {
+    INTERRUPTED.clone()
+}
+
+/// While alive, Ctrl-C raises the interruption flag instead of exiting the process
+pub struct InterruptionListener;
+
+impl InterruptionListener {
+    pub fn new() -> Self {
+        LISTENERS.fetch_add(1, Ordering::SeqCst);
+        InterruptionListener
+    }
+}
+
+impl Drop for InterruptionListener {
+    fn drop(&mut self) {
+        LISTENERS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,65 @@
+use std::io::Write;
+
+use clap::ArgEnum;
+use log::Record;
+use serde_json::json;
+
+/// Format of the log lines written on stderr
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    /// human readable log lines (env_logger default format)
+    Text,
+    /// one JSON object per line
+    Json,
+}
+
+/// Initialize the logger - the level is still driven by the `RUST_LOG` env variable
+pub fn init(format: LogFormat) {
+    let mut builder = env_logger::Builder::from_default_env();
+
+    if format == LogFormat::Json {
+        builder.format(|buf, record| writeln!(buf, "{}", json_line(record)));
+    }
+
+    builder.init();
+}
+
+/// serialize a log record into a single line JSON object
+fn json_line(record: &Record) -> String {
+    json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "level": record.level().to_string(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{Level, Record};
+    use serde_json::Value;
+
+    use crate::logger::json_line;
+
+    #[test]
+    fn json_line_is_valid_json() {
+        let message = "dump \"1\" created\nin 2s";
+
+        let line = json_line(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("replibyte::tasks")
+                .args(format_args!("{}", message))
+                .build(),
+        );
+
+        assert!(!line.contains('\n'));
+
+        let value: Value = serde_json::from_str(line.as_str()).unwrap();
+        assert_eq!(value["level"], "WARN");
+        assert_eq!(value["target"], "replibyte::tasks");
+        assert_eq!(value["message"], message);
+        assert!(value["timestamp"].is_string());
+    }
+}
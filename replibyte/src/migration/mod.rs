@@ -113,6 +113,7 @@ fn parse_str_to_u8(s: &str) -> Result<u8, Error> {
 #[cfg(test)]
 mod tests {
     use std::{
+        collections::BTreeMap,
         io::{Error, ErrorKind},
         str::FromStr,
     };
@@ -120,7 +121,9 @@ mod tests {
     use serde_json::json;
 
     use crate::connector::Connector;
-    use crate::datastore::{Datastore, IndexFile, ReadOptions};
+    use crate::datastore::{
+        Datastore, DumpContent, DumpManifest, EncryptionAlgorithm, IndexFile, ReadOptions,
+    };
 
     use super::{Migration, Migrator, Version};
 
@@ -167,7 +170,19 @@ mod tests {
             unimplemented!()
         }
 
-        fn write(&self, _file_part: u16, _data: crate::types::Bytes) -> Result<(), Error> {
+        fn write_part(&self, _file_part: u16, _data: crate::types::Bytes) -> Result<usize, Error> {
+            unimplemented!()
+        }
+
+        fn write_dump_entry(&self, _size: usize, _raw_size: usize) -> Result<(), Error> {
+            unimplemented!()
+        }
+
+        fn write_dump_manifest(&self, _manifest: &DumpManifest) -> Result<(), Error> {
+            unimplemented!()
+        }
+
+        fn read_dump_manifest(&self, _name: &str) -> Result<DumpManifest, Error> {
             unimplemented!()
         }
 
@@ -195,10 +210,62 @@ mod tests {
             unimplemented!()
         }
 
+        fn encryption_algorithm(&self) -> EncryptionAlgorithm {
+            unimplemented!()
+        }
+
+        fn set_encryption_algorithm(&mut self, _algorithm: EncryptionAlgorithm) {
+            unimplemented!()
+        }
+
+        fn dump_name(&self) -> &str {
+            unimplemented!()
+        }
+
         fn set_dump_name(&mut self, _name: String) {
             unimplemented!()
         }
 
+        fn set_namespace(&mut self, _namespace: String) {
+            unimplemented!()
+        }
+
+        fn set_source_type(&mut self, _source_type: String) {
+            unimplemented!()
+        }
+
+        fn set_labels(&mut self, _labels: BTreeMap<String, String>) {
+            unimplemented!()
+        }
+
+        fn set_content(&mut self, _content: Option<DumpContent>) {
+            unimplemented!()
+        }
+
+        fn set_table_hashes(&mut self, _table_hashes: BTreeMap<String, String>) {
+            unimplemented!()
+        }
+
+        fn last_part(&self, _name: &str) -> Result<u16, Error> {
+            unimplemented!()
+        }
+
+        fn part_numbers(&self, _name: &str) -> Result<Vec<u16>, Error> {
+            unimplemented!()
+        }
+
+        fn list_all_objects(&self) -> Result<Vec<String>, Error> {
+            unimplemented!()
+        }
+
+        fn set_part_offset(&mut self, _offset: u16) {
+            unimplemented!()
+        }
+
+        fn parts_size(&self, _name: &str) -> Result<usize, Error> {
+            unimplemented!()
+        }
+
         fn delete_by_name(&self, _name: String) -> Result<(), Error> {
             unimplemented!()
         }
@@ -0,0 +1,76 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// pausing more often than this costs more than it saves
+const MIN_BUSY_DURATION: Duration = Duration::from_millis(10);
+
+/// Limit the CPU used by a loop - the loop is put to sleep in proportion to the time it has been running.
+/// The running time is measured with the wall clock: the time spent waiting for input counts as busy.
+pub struct CpuLimiter {
+    max_cpu_percent: u8,
+    busy_since: Instant,
+}
+
+impl CpuLimiter {
+    pub fn new(max_cpu_percent: u8) -> Self {
+        CpuLimiter {
+            max_cpu_percent,
+            busy_since: Instant::now(),
+        }
+    }
+
+    /// wait as long as required for the time spent running since the last pause to stay under the limit
+    pub fn throttle(&mut self) {
+        if self.max_cpu_percent == 0 || self.max_cpu_percent >= 100 {
+            return;
+        }
+
+        let busy_duration = self.busy_since.elapsed();
+        if busy_duration < MIN_BUSY_DURATION {
+            return;
+        }
+
+        let max_cpu_percent = self.max_cpu_percent as u32;
+        sleep(busy_duration * (100 - max_cpu_percent) / max_cpu_percent);
+
+        self.busy_since = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::source::cpu_limiter::CpuLimiter;
+
+    /// 10 iterations of 10ms of work
+    fn busy_loop(cpu_limiter: &mut CpuLimiter) {
+        for _ in 0..10 {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(10) {}
+
+            cpu_limiter.throttle();
+        }
+    }
+
+    #[test]
+    fn throttle_busy_loop() {
+        // 100ms of work at 25% takes 400ms
+        let mut cpu_limiter = CpuLimiter::new(25);
+
+        let start = Instant::now();
+        busy_loop(&mut cpu_limiter);
+
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[test]
+    fn do_not_throttle_without_limit() {
+        let mut cpu_limiter = CpuLimiter::new(100);
+
+        let start = Instant::now();
+        busy_loop(&mut cpu_limiter);
+
+        assert!(start.elapsed() < Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,172 @@
+use regex::{Captures, Regex};
+
+use crate::config::DdlRewriteConfig;
+
+/// quoted literals and hash partition bounds are matched first to be kept as they are
+const KEPT: &str = r"'(?:[^']|'')*'|\bVALUES\s+WITH\s*\([^()]*\)";
+const IDENTIFIER: &str = r#"(?:"(?:[^"]|"")*"|\w+)"#;
+
+/// Rewrite the `CREATE TABLE` and `ALTER TABLE` statements of a dump - e.g. to restore it into a PostgreSQL
+/// without the same tablespaces or types.
+pub struct DdlRewriter {
+    // the `rule` group of each regex is replaced by its `prefix` group followed by the replacement
+    rules: Vec<(Regex, String)>,
+}
+
+impl DdlRewriter {
+    pub fn new(rules: &Vec<DdlRewriteConfig>) -> Self {
+        let rules = rules
+            .iter()
+            .map(|rule| match rule {
+                DdlRewriteConfig::StripTablespace => (
+                    format!(
+                        r"(?i){}|(?P<rule>\s+(?:USING\s+INDEX\s+)?TABLESPACE\s+{})",
+                        KEPT, IDENTIFIER
+                    ),
+                    String::new(),
+                ),
+                DdlRewriteConfig::StripStorageParameters => (
+                    format!(
+                        r"(?i){}|(?P<rule>\s+WITH\s*\((?:[^()']|'(?:[^']|'')*')*\))",
+                        KEPT
+                    ),
+                    String::new(),
+                ),
+                DdlRewriteConfig::ReplaceType(config) => {
+                    // the type of a column definition (one per line) or of an `ALTER COLUMN ... TYPE` -
+                    // the schema of the type is replaced as well
+                    let end = match config.from.chars().last() {
+                        Some(c) if c.is_alphanumeric() || c == '_' => r"\b",
+                        _ => "",
+                    };
+
+                    (
+                        format!(
+                            r"(?im){}|(?P<rule>(?P<prefix>^[ \t]+{}[ \t]+|\bTYPE\s+)(?:{}\.)?{}{})",
+                            KEPT,
+                            IDENTIFIER,
+                            IDENTIFIER,
+                            regex::escape(config.from.as_str()),
+                            end
+                        ),
+                        config.to.clone(),
+                    )
+                }
+            })
+            .map(|(regex, replacement)| (Regex::new(regex.as_str()).unwrap(), replacement))
+            .collect();
+
+        DdlRewriter { rules }
+    }
+
+    /// the rewritten statement - `None` if no rule applies
+    pub fn rewrite(&self, query: &str) -> Option<String> {
+        let mut rewritten_query = query.to_string();
+
+        for (regex, replacement) in &self.rules {
+            rewritten_query = regex
+                .replace_all(
+                    rewritten_query.as_str(),
+                    |captures: &Captures| match captures.name("rule") {
+                        Some(_) => format!(
+                            "{}{}",
+                            captures.name("prefix").map_or("", |m| m.as_str()),
+                            replacement
+                        ),
+                        None => captures[0].to_string(),
+                    },
+                )
+                .into_owned();
+        }
+
+        if rewritten_query == query {
+            None
+        } else {
+            Some(rewritten_query)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{DdlRewriteConfig, DdlRewriteReplaceTypeConfig};
+
+    use super::DdlRewriter;
+
+    fn replace_type(from: &str, to: &str) -> DdlRewriteConfig {
+        DdlRewriteConfig::ReplaceType(DdlRewriteReplaceTypeConfig {
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+    }
+
+    #[test]
+    fn strip_tablespace() {
+        let rewriter = DdlRewriter::new(&vec![DdlRewriteConfig::StripTablespace]);
+
+        assert_eq!(
+            rewriter.rewrite("CREATE TABLE public.users (\n    id integer\n) TABLESPACE fast_ssd;"),
+            Some("CREATE TABLE public.users (\n    id integer\n);".to_string())
+        );
+        assert_eq!(
+            rewriter.rewrite(
+                "ALTER TABLE ONLY public.users\n    ADD CONSTRAINT users_pkey PRIMARY KEY (id) USING INDEX TABLESPACE \"Fast\";"
+            ),
+            Some(
+                "ALTER TABLE ONLY public.users\n    ADD CONSTRAINT users_pkey PRIMARY KEY (id);"
+                    .to_string()
+            )
+        );
+        // not within a literal
+        assert_eq!(
+            rewriter
+                .rewrite("CREATE TABLE public.users (\n    note text DEFAULT ' tablespace x'\n);"),
+            None
+        );
+    }
+
+    #[test]
+    fn strip_storage_parameters() {
+        let rewriter = DdlRewriter::new(&vec![DdlRewriteConfig::StripStorageParameters]);
+
+        assert_eq!(
+            rewriter.rewrite(
+                "CREATE TABLE public.users (\n    id integer\n)\nWITH (fillfactor='70', autovacuum_enabled='false');"
+            ),
+            Some("CREATE TABLE public.users (\n    id integer\n);".to_string())
+        );
+        // the bound of a hash partition is not a storage parameter
+        assert_eq!(
+            rewriter.rewrite(
+                "CREATE TABLE public.users_0 PARTITION OF public.users FOR VALUES WITH (modulus 2, remainder 0);"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn replace_types() {
+        let rewriter = DdlRewriter::new(&vec![
+            replace_type("citext", "text"),
+            replace_type("character varying(255)", "text"),
+        ]);
+
+        assert_eq!(
+            rewriter.rewrite(
+                "CREATE TABLE public.users (\n    citext_id integer,\n    email public.citext NOT NULL,\n    name citext[],\n    city character varying(255)\n);"
+            ),
+            Some(
+                "CREATE TABLE public.users (\n    citext_id integer,\n    email text NOT NULL,\n    name text[],\n    city text\n);"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            rewriter.rewrite("ALTER TABLE public.users ALTER COLUMN name TYPE citext;"),
+            Some("ALTER TABLE public.users ALTER COLUMN name TYPE text;".to_string())
+        );
+        assert_eq!(
+            rewriter.rewrite("CREATE TABLE public.users (\n    id citextual\n);"),
+            None
+        );
+    }
+}
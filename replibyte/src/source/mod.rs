@@ -1,16 +1,25 @@
-use std::io::Error;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
 
-use crate::config::{DatabaseSubsetConfig, OnlyTablesConfig, SkipConfig};
+use crate::config::{
+    ColumnTagConfig, DatabaseSubsetConfig, DdlRewriteConfig, OnlyTablesConfig, SequencesConfig,
+    SkipConfig, SortRowsConfig, StringEscapingConfig,
+};
 use crate::connector::Connector;
-use crate::transformer::Transformer;
+use crate::datastore::DumpContent;
+use crate::transformer::{Transformer, TransformerMetrics};
 use crate::types::{OriginalQuery, Query};
 
+pub mod cpu_limiter;
+pub mod ddl_rewriter;
 pub mod mongodb;
 pub mod mongodb_stdin;
 pub mod mysql;
 pub mod mysql_stdin;
+pub mod pii_detector;
 pub mod postgres;
 pub mod postgres_stdin;
+pub mod sequences;
 
 pub trait Explain: Connector {
     fn schema(&self) -> Result<(), Error>;
@@ -29,4 +38,97 @@ pub struct SourceOptions<'a> {
     pub skip_config: &'a Vec<SkipConfig>,
     pub database_subset: &'a Option<DatabaseSubsetConfig>,
     pub only_tables: &'a Vec<OnlyTablesConfig>,
+    pub exclude_schemas: &'a Vec<String>,
+    pub column_tags: &'a Vec<ColumnTagConfig>,
+    pub transform_default_values: bool,
+    pub transformer_metrics: &'a TransformerMetrics,
+    pub sort_rows: &'a Vec<SortRowsConfig>,
+    /// share of a CPU core the transformation of the rows may use - from 1 to 100
+    pub max_cpu_percent: Option<u8>,
+    /// drop the comments and the session settings not needed to restore the dump (PostgreSQL only)
+    pub minimal: bool,
+    /// rules rewriting the `CREATE TABLE` and `ALTER TABLE` statements (PostgreSQL only)
+    pub ddl_rewrites: &'a Vec<DdlRewriteConfig>,
+    /// how the sequences are set - the values of the source database are kept by default (PostgreSQL only)
+    pub sequences: Option<SequencesConfig>,
+    /// keep the schema or the rows only - both by default
+    pub content: Option<DumpContent>,
+    /// how the quotes of the transformed strings are escaped - ANSI for PostgreSQL and backslash for MySQL by default
+    pub string_escaping: Option<StringEscapingConfig>,
+    /// abort on the first transformation error instead of reporting the errors at the end (PostgreSQL only)
+    pub fail_fast: bool,
+    /// drop the large objects (`pg_largeobject`) of the dump (PostgreSQL only)
+    pub skip_large_objects: bool,
+}
+
+/// number of rows and bytes of a table in a dump
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableStats {
+    pub table: String,
+    pub rows: usize,
+    pub bytes: usize,
+}
+
+/// rows and bytes by table, tallied while reading a dump
+#[derive(Default)]
+pub struct TablesStats {
+    tables: HashMap<String, TableStats>,
+}
+
+impl TablesStats {
+    pub fn add_row(&mut self, table: &str, bytes: usize) {
+        let stats = self
+            .tables
+            .entry(table.to_string())
+            .or_insert_with(|| TableStats {
+                table: table.to_string(),
+                rows: 0,
+                bytes: 0,
+            });
+
+        stats.rows += 1;
+        stats.bytes += bytes;
+    }
+
+    /// the statistics by table - the biggest tables first
+    pub fn into_sorted_vec(self) -> Vec<TableStats> {
+        let mut tables = self.tables.into_values().collect::<Vec<_>>();
+        tables.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.table.cmp(&b.table)));
+        tables
+    }
+}
+
+/// size of a row in a dump - with the line break ending it
+pub fn row_size(query: &str) -> usize {
+    query.trim().len() + 1
+}
+
+/// check that the extra arguments of a dump command do not conflict with the ones set by Replibyte
+pub fn check_extra_args(
+    command: &str,
+    extra_args: &[String],
+    managed_args: &[&str],
+) -> Result<(), Error> {
+    for extra_arg in extra_args {
+        let conflict = managed_args.iter().find(|managed_arg| {
+            if managed_arg.starts_with("--") {
+                *extra_arg == **managed_arg || extra_arg.starts_with(&format!("{}=", managed_arg))
+            } else {
+                // a short option may be followed by its value - e.g. `-p5432`
+                extra_arg.starts_with(*managed_arg)
+            }
+        });
+
+        if let Some(managed_arg) = conflict {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "the {} argument '{}' can't be set in <source.extra_args> - '{}' is managed by Replibyte",
+                    command, extra_arg, managed_arg
+                ),
+            ));
+        }
+    }
+
+    Ok(())
 }
@@ -2,25 +2,108 @@ use std::collections::{HashMap, HashSet};
 use std::io::{BufReader, Error, ErrorKind, Read};
 use std::process::{Command, Stdio};
 
+use crate::config::{DatabaseSubsetConfig, DatabaseSubsetConfigStrategy};
 use crate::connector::Connector;
-use crate::source::{Explain, Source};
-use crate::transformer::Transformer;
+use crate::source::{check_extra_args, Explain, Source};
+use crate::transformer::{Transformer, TransformerMetrics};
 use crate::types::{Column, OriginalQuery, Query};
 use crate::utils::{binary_exists, table, wait_for_command};
 use crate::SourceOptions;
 
 use bson::{Bson, Document};
-use dump_parser::mongodb::Archive;
+use dump_parser::mongodb::{Archive, PrefixedCollections};
+use log::info;
 use mongodb_schema_parser::SchemaParser;
+use subset::mongodb::MongoDBSubset;
+use subset::{
+    PassthroughTable, Subset, SubsetOptions, SubsetStrategy, SubsetTable, SubsetTableRelation,
+};
+
+/// mongodump arguments set by Replibyte - the dump can't be parsed otherwise
+const MONGODUMP_MANAGED_ARGS: &[&str] =
+    &["--uri", "--db", "-d", "--archive", "--out", "-o", "--gzip"];
+
+/// keys of the lines of an extended JSON dump - `{"ns": "<db_name>.<collection_name>", "document": {...}}`
+pub const EXTENDED_JSON_NAMESPACE_KEY: &str = "ns";
+pub const EXTENDED_JSON_DOCUMENT_KEY: &str = "document";
+
+/// format of the MongoDB dumps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MongoDBFormat {
+    /// mongodump archive (BSON)
+    Archive,
+    /// one document by line, serialized as MongoDB Extended JSON (canonical mode)
+    ExtendedJson,
+}
+
+impl Default for MongoDBFormat {
+    fn default() -> Self {
+        MongoDBFormat::Archive
+    }
+}
 
 pub struct MongoDB<'a> {
     uri: &'a str,
     database: &'a str,
+    extra_args: Vec<String>,
+    format: MongoDBFormat,
 }
 
 impl<'a> MongoDB<'a> {
     pub fn new(uri: &'a str, database: &'a str) -> Self {
-        MongoDB { uri, database }
+        MongoDB {
+            uri,
+            database,
+            extra_args: vec![],
+            format: MongoDBFormat::default(),
+        }
+    }
+
+    /// extra mongodump arguments (e.g. `--readPreference=secondary`) - they must not conflict with the ones set by Replibyte
+    pub fn set_extra_args(&mut self, extra_args: Vec<String>) {
+        self.extra_args = extra_args;
+    }
+
+    pub fn set_format(&mut self, format: MongoDBFormat) {
+        self.format = format;
+    }
+
+    /// Estimate the number of documents by collection the configured database subset produces
+    pub fn subset_estimate(
+        &self,
+        subset_config: &DatabaseSubsetConfig,
+    ) -> Result<HashMap<String, usize>, Error> {
+        let dump_args = vec![
+            "--uri",
+            self.uri,
+            "--db",
+            self.database,
+            "--archive", // dump to stdin
+        ];
+
+        let mut process = Command::new("mongodump")
+            .args(dump_args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdout = process
+            .stdout
+            .take()
+            .ok_or_else(|| Error::new(ErrorKind::Other, "Could not capture standard output."))?;
+
+        let mut archive = Archive::from_reader(BufReader::new(stdout))?;
+
+        let mut estimate = Ok(HashMap::new());
+        archive.alter_docs(|prefixed_collections| {
+            estimate = with_mongodb_subset(prefixed_collections, subset_config, |subset| {
+                subset.estimate()
+            });
+        });
+
+        wait_for_command(&mut process)?;
+
+        estimate
     }
 }
 
@@ -69,11 +152,9 @@ impl<'a> Source for MongoDB<'a> {
         options: SourceOptions,
         query_callback: F,
     ) -> Result<(), Error> {
-        if let Some(_database_subset) = &options.database_subset {
-            todo!("database subset not supported yet for MongoDB source")
-        }
+        check_extra_args("mongodump", &self.extra_args, MONGODUMP_MANAGED_ARGS)?;
 
-        let dump_args = vec![
+        let mut dump_args = vec![
             "--uri",
             self.uri,
             "--db",
@@ -81,6 +162,10 @@ impl<'a> Source for MongoDB<'a> {
             "--archive", // dump to stdin
         ];
 
+        let mut extra_args: Vec<&str> = self.extra_args.iter().map(String::as_str).collect();
+
+        dump_args.append(&mut extra_args);
+
         let mut process = Command::new("mongodump")
             .args(dump_args)
             .stdout(Stdio::piped())
@@ -94,7 +179,7 @@ impl<'a> Source for MongoDB<'a> {
 
         let reader = BufReader::new(stdout);
 
-        read_and_transform(reader, options, query_callback)?;
+        read_and_transform(reader, options, self.format, query_callback)?;
 
         wait_for_command(&mut process)
     }
@@ -129,22 +214,29 @@ pub fn recursively_transform_bson(
     bson: Bson,
     transformers: &HashMap<String, &Box<dyn Transformer + '_>>,
     wildcard_keys: &HashSet<String>,
+    transformer_metrics: &TransformerMetrics,
 ) -> Bson {
     let mut column;
     match bson {
         Bson::String(value) => {
             column = Column::StringValue(key.clone(), value.clone());
             column = match transformers.get(key.as_str()) {
-                Some(transformer) => transformer.transform(column), // apply transformation on the column
-                None => column,
+                Some(transformer) if transformer.should_transform() => {
+                    transformer_metrics.increment(transformer.as_ref());
+                    transformer.transform(column) // apply transformation on the column
+                }
+                _ => column,
             };
             Bson::String((*column.string_value().unwrap()).to_string())
         }
         Bson::Double(value) => {
             column = Column::FloatNumberValue(key.clone(), value);
             column = match transformers.get(key.as_str()) {
-                Some(transformer) => transformer.transform(column), // apply transformation on the column
-                None => column,
+                Some(transformer) if transformer.should_transform() => {
+                    transformer_metrics.increment(transformer.as_ref());
+                    transformer.transform(column) // apply transformation on the column
+                }
+                _ => column,
             };
             Bson::Double(*column.float_number_value().unwrap())
         }
@@ -163,6 +255,7 @@ pub fn recursively_transform_bson(
                         bson.clone(),
                         transformers,
                         wildcard_keys,
+                        transformer_metrics,
                     )
                 })
                 .collect::<Vec<Bson>>();
@@ -173,21 +266,28 @@ pub fn recursively_transform_bson(
             nested_doc,
             transformers,
             wildcard_keys,
+            transformer_metrics,
         )),
         Bson::Null => Bson::Null,
         Bson::Int32(value) => {
             column = Column::NumberValue(key.clone(), value as i128);
             column = match transformers.get(key.as_str()) {
-                Some(transformer) => transformer.transform(column), // apply transformation on the column
-                None => column,
+                Some(transformer) if transformer.should_transform() => {
+                    transformer_metrics.increment(transformer.as_ref());
+                    transformer.transform(column) // apply transformation on the column
+                }
+                _ => column,
             };
             Bson::Int32(column.number_value().map(|&n| n as i32).unwrap())
         }
         Bson::Int64(value) => {
             column = Column::NumberValue(key.clone(), value as i128);
             column = match transformers.get(key.as_str()) {
-                Some(transformer) => transformer.transform(column), // apply transformation on the column
-                None => column,
+                Some(transformer) if transformer.should_transform() => {
+                    transformer_metrics.increment(transformer.as_ref());
+                    transformer.transform(column) // apply transformation on the column
+                }
+                _ => column,
             };
             Bson::Int64(column.number_value().map(|&n| n as i64).unwrap())
         }
@@ -214,6 +314,7 @@ pub fn recursively_transform_document(
     mut original_doc: Document,
     transformers: &HashMap<String, &Box<dyn Transformer + '_>>,
     wildcard_keys: &HashSet<String>,
+    transformer_metrics: &TransformerMetrics,
 ) -> Document {
     for (key, bson) in original_doc.clone() {
         original_doc.insert(
@@ -223,6 +324,7 @@ pub fn recursively_transform_document(
                 bson,
                 transformers,
                 wildcard_keys,
+                transformer_metrics,
             ),
         );
     }
@@ -257,6 +359,7 @@ pub(crate) fn find_all_keys_with_array_wildcard_op(
 pub fn read_and_transform<R: Read, F: FnMut(OriginalQuery, Query)>(
     reader: BufReader<R>,
     source_options: SourceOptions,
+    format: MongoDBFormat,
     mut query_callback: F,
 ) -> Result<(), Error> {
     let transformers = source_options.transformers;
@@ -275,7 +378,11 @@ pub fn read_and_transform<R: Read, F: FnMut(OriginalQuery, Query)>(
     // init archive from reader
     let mut archive = Archive::from_reader(reader)?;
 
-    let original_query = Query(archive.clone().into_bytes()?);
+    if let Some(subset_config) = source_options.database_subset {
+        let _ = subset(&mut archive, subset_config)?;
+    }
+
+    let original_archive = archive.clone();
 
     archive.alter_docs(|prefixed_collections| {
         for (prefix, collection) in prefixed_collections.to_owned() {
@@ -286,6 +393,7 @@ pub fn read_and_transform<R: Read, F: FnMut(OriginalQuery, Query)>(
                     doc,
                     &transformer_by_db_and_table_and_column_name,
                     &wildcard_keys,
+                    source_options.transformer_metrics,
                 );
                 new_collection.push(new_doc);
             }
@@ -293,12 +401,150 @@ pub fn read_and_transform<R: Read, F: FnMut(OriginalQuery, Query)>(
         }
     });
 
-    let query = Query(archive.into_bytes()?);
+    match format {
+        MongoDBFormat::Archive => {
+            query_callback(
+                Query(original_archive.into_bytes()?),
+                Query(archive.into_bytes()?),
+            );
+        }
+        MongoDBFormat::ExtendedJson => {
+            let original_lines = to_extended_json_lines(original_archive)?;
+            let lines = to_extended_json_lines(archive)?;
+
+            for (original_line, line) in original_lines.into_iter().zip(lines) {
+                query_callback(Query(original_line), Query(line));
+            }
+        }
+    }
 
-    query_callback(original_query, query);
     Ok(())
 }
 
+/// keep the documents of the database subset only - the reference documents and the documents they reference
+fn subset(archive: &mut Archive, subset_config: &DatabaseSubsetConfig) -> Result<(), Error> {
+    let mut result = Ok(());
+
+    archive.alter_docs(|prefixed_collections| {
+        let subset_collections =
+            with_mongodb_subset(prefixed_collections, subset_config, |subset| {
+                subset.subset_collections(|progress| {
+                    info!("Database subset completion: {}%", progress.percent());
+                })
+            });
+
+        result = subset_collections.map(|subset_collections| {
+            *prefixed_collections = subset_collections;
+        });
+    });
+
+    result
+}
+
+/// build the subset of the collections from the configuration
+fn with_mongodb_subset<T, F: FnOnce(&MongoDBSubset) -> Result<T, Error>>(
+    prefixed_collections: &PrefixedCollections,
+    subset_config: &DatabaseSubsetConfig,
+    f: F,
+) -> Result<T, Error> {
+    let database = subset_config.database.as_str();
+
+    let strategy = match subset_config.strategy {
+        DatabaseSubsetConfigStrategy::Random(opt) => SubsetStrategy::RandomPercent {
+            database,
+            table: subset_config.table.as_str(),
+            percent: opt.percent,
+        },
+    };
+
+    let empty_vec = Vec::new();
+    let passthrough_tables = subset_config
+        .passthrough_tables
+        .as_ref()
+        .unwrap_or(&empty_vec)
+        .iter()
+        .map(|table| PassthroughTable::new(database, table.as_str()))
+        .collect::<HashSet<_>>();
+
+    // the references of a collection are the relations of its subset table
+    let mut subset_table_by_table = HashMap::<&str, SubsetTable>::new();
+    for reference in subset_config.references.iter().flatten() {
+        subset_table_by_table
+            .entry(reference.table.as_str())
+            .or_insert_with(|| SubsetTable::new(database, reference.table.as_str(), vec![]))
+            .relations
+            .push(SubsetTableRelation::new(
+                database,
+                reference.referenced_table.as_str(),
+                reference.column.as_str(),
+                reference.referenced_column.as_str(),
+            ));
+    }
+
+    let subset = MongoDBSubset::new(
+        prefixed_collections,
+        subset_table_by_table.into_values().collect(),
+        strategy,
+        SubsetOptions::new(&passthrough_tables),
+    )?;
+
+    f(&subset)
+}
+
+/// serialize the documents of an archive as extended JSON lines - sorted by collection
+fn to_extended_json_lines(mut archive: Archive) -> Result<Vec<Vec<u8>>, Error> {
+    let mut prefixed_collections = vec![];
+    archive.alter_docs(|collections| {
+        prefixed_collections = collections.drain().collect::<Vec<_>>();
+    });
+    prefixed_collections.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut lines = vec![];
+    for (prefix, collection) in prefixed_collections {
+        for doc in collection {
+            lines.push(to_extended_json_line(prefix.as_str(), doc)?);
+        }
+    }
+
+    Ok(lines)
+}
+
+/// serialize a document of the collection `<db_name>.<collection_name>` as MongoDB Extended JSON (canonical mode)
+pub fn to_extended_json_line(prefix: &str, doc: Document) -> Result<Vec<u8>, Error> {
+    // the bson crate does not support Decimal128 in extended JSON (and panics on it)
+    if doc.values().any(has_decimal128) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "a document of '{}' contains a Decimal128 value which can't be serialized as extended JSON - use the archive format instead",
+                prefix
+            ),
+        ));
+    }
+
+    let mut line = serde_json::Map::new();
+    line.insert(
+        EXTENDED_JSON_NAMESPACE_KEY.to_string(),
+        serde_json::Value::String(prefix.to_string()),
+    );
+    line.insert(
+        EXTENDED_JSON_DOCUMENT_KEY.to_string(),
+        Bson::Document(doc).into_canonical_extjson(),
+    );
+
+    serde_json::to_vec(&line).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+}
+
+fn has_decimal128(bson: &Bson) -> bool {
+    match bson {
+        Bson::Decimal128(_) => true,
+        Bson::Array(arr) => arr.iter().any(has_decimal128),
+        Bson::Document(doc) => doc.values().any(has_decimal128),
+        Bson::JavaScriptCodeWithScope(jsc) => jsc.scope.values().any(has_decimal128),
+        _ => false,
+    }
+}
+
 pub fn read_and_parse_schema<R: Read>(reader: BufReader<R>) -> Result<(), Error> {
     let mut archive = Archive::from_reader(reader)?;
 
@@ -339,7 +585,7 @@ mod tests {
 
     use crate::source::mongodb::{find_all_keys_with_array_wildcard_op, MongoDB};
     use crate::transformer::transient::TransientTransformer;
-    use crate::transformer::Transformer;
+    use crate::transformer::{Transformer, TransformerMetrics};
 
     use super::recursively_transform_document;
 
@@ -368,6 +614,19 @@ mod tests {
             skip_config: &vec![],
             database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
 
         assert!(p.read(source_options, |_, _| {}).is_ok());
@@ -380,6 +639,19 @@ mod tests {
             skip_config: &vec![],
             database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
 
         assert!(p.read(source_options, |_, _| {}).is_err());
@@ -395,6 +667,19 @@ mod tests {
             skip_config: &vec![],
             database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
 
         p.read(source_options, |original_query, query| {
@@ -438,13 +723,23 @@ mod tests {
                 .zip(transformers_vec.iter()),
         );
         // Recursively transform the document
+        let transformer_metrics = TransformerMetrics::default();
         let transformed_doc = recursively_transform_document(
             "test.users".to_string(),
             doc,
             &transformers,
             &HashSet::new(),
+            &transformer_metrics,
         );
 
+        // each transformed value is counted
+        for column in columns {
+            assert_eq!(
+                transformer_metrics.count("random", format!("test.users.{}", column).as_str()),
+                1
+            );
+        }
+
         // Assert transformed values are not equal to original values
         // no_nest
         assert_ne!(transformed_doc.get("no_nest").unwrap(), &Bson::Int32(5));
@@ -514,11 +809,19 @@ mod tests {
             );
         }
         // Recursively transform the document
+        let transformer_metrics = TransformerMetrics::default();
         let transformed_doc = recursively_transform_document(
             "test.users".to_string(),
             doc,
             &transformers,
             &wildcard_keys,
+            &transformer_metrics,
+        );
+
+        // a value of each element of the array
+        assert_eq!(
+            transformer_metrics.count("random", "test.users.a.b.$[].c.0"),
+            2
         );
 
         // Assert transformed values are not equal to original values
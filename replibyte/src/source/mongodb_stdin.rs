@@ -1,16 +1,26 @@
 use std::io::{stdin, BufReader, Error};
 
 use crate::connector::Connector;
-use crate::source::mongodb::read_and_transform;
+use crate::source::mongodb::{read_and_transform, MongoDBFormat};
 use crate::types::{OriginalQuery, Query};
 use crate::Source;
 use crate::SourceOptions;
 
-pub struct MongoDBStdin {}
+pub struct MongoDBStdin {
+    format: MongoDBFormat,
+}
+
+impl MongoDBStdin {
+    pub fn set_format(&mut self, format: MongoDBFormat) {
+        self.format = format;
+    }
+}
 
 impl Default for MongoDBStdin {
     fn default() -> Self {
-        Self {}
+        Self {
+            format: MongoDBFormat::default(),
+        }
     }
 }
 
@@ -28,11 +38,7 @@ impl Source for MongoDBStdin {
     ) -> Result<(), Error> {
         let reader = BufReader::new(stdin());
 
-        if let Some(_database_subset) = &options.database_subset {
-            todo!("database subset not supported yet for MongoDB source")
-        }
-
-        let _ = read_and_transform(reader, options, query_callback)?;
+        let _ = read_and_transform(reader, options, self.format, query_callback)?;
         Ok(())
     }
 }
@@ -1,8 +1,12 @@
 use std::borrow::BorrowMut;
-use std::collections::HashMap;
-use std::io::{BufReader, Error, ErrorKind, Read};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Error, ErrorKind, Read, Write};
 use std::process::{Command, Stdio};
 
+use log::info;
+
 use dump_parser::mysql::Keyword::NoKeyword;
 use dump_parser::mysql::{
     get_column_names_from_insert_into_query, get_column_names_from_create_query,
@@ -10,10 +14,14 @@ use dump_parser::mysql::{
     get_tokens_from_query_str, match_keyword_at_position, Keyword, Token,
 };
 use dump_parser::utils::{list_sql_queries_from_dump_reader, ListQueryResult};
+use subset::mysql::MysqlSubset;
+use subset::{PassthroughTable, Subset, SubsetOptions, SubsetStrategy};
 
+use crate::config::{DatabaseSubsetConfig, DatabaseSubsetConfigStrategy, StringEscapingConfig};
 use crate::connector::Connector;
-use crate::source::{Explain, Source};
-use crate::transformer::Transformer;
+use crate::datastore::DumpContent;
+use crate::source::{check_extra_args, row_size, Explain, Source, TableStats, TablesStats};
+use crate::transformer::{Transformer, TransformerMetrics};
 use crate::types::{Column, InsertIntoQuery, OriginalQuery, Query};
 use crate::utils::{binary_exists, table, wait_for_command};
 
@@ -26,12 +34,42 @@ enum RowType {
     Others,
 }
 
+/// mysqldump arguments set by Replibyte - the dump can't be parsed otherwise
+const MYSQLDUMP_MANAGED_ARGS: &[&str] = &[
+    "-h",
+    "--host",
+    "-P",
+    "--port",
+    "-u",
+    "--user",
+    "-p",
+    "--password",
+    "--skip-extended-insert",
+    "--extended-insert",
+    "-e",
+    "--complete-insert",
+    "-c",
+    "--hex-blob",
+    "--tab",
+    "-T",
+    "--xml",
+    "-X",
+    "--result-file",
+    "-r",
+    "--no-data",
+    "-d",
+    "--no-create-info",
+    "-t",
+];
+
 pub struct Mysql<'a> {
     host: &'a str,
     port: u16,
     database: &'a str,
     username: &'a str,
     password: &'a str,
+    extra_args: Vec<String>,
+    single_transaction: bool,
 }
 
 impl<'a> Mysql<'a> {
@@ -48,8 +86,82 @@ impl<'a> Mysql<'a> {
             database,
             username,
             password,
+            extra_args: vec![],
+            single_transaction: true,
         }
     }
+
+    /// extra mysqldump arguments (e.g. `--no-tablespaces`) - they must not conflict with the ones set by Replibyte
+    pub fn set_extra_args(&mut self, extra_args: Vec<String>) {
+        self.extra_args = extra_args;
+    }
+
+    /// dump the InnoDB tables within a single transaction - disable it for MyISAM tables and lock them instead
+    pub fn set_single_transaction(&mut self, single_transaction: bool) {
+        self.single_transaction = single_transaction;
+    }
+
+    fn dump_args(&self, options: &SourceOptions) -> Result<Vec<String>, Error> {
+        check_extra_args("mysqldump", &self.extra_args, MYSQLDUMP_MANAGED_ARGS)?;
+
+        if self.single_transaction
+            && self
+                .extra_args
+                .iter()
+                .any(|arg| arg == "--lock-tables" || arg == "-l")
+        {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "mysqldump argument '--lock-tables' can't be used with a single transaction - set 'single_transaction: false' to lock the tables instead",
+            ));
+        }
+
+        let s_port = self.port.to_string();
+        let password = format!("-p{}", self.password);
+
+        let mut dump_args = vec![
+            "-h",
+            self.host,
+            "-P",
+            s_port.as_str(),
+            "-u",
+            self.username,
+            password.as_str(),
+            "--add-drop-database", // add DROP DATABASE statement before each CREATE DATABASE statement
+            "--add-drop-table",    // add DROP TABLE statement before each CREATE TABLE statement
+            "--skip-extended-insert", // have a row by INSERT INTO statement
+            "--complete-insert",   // have column names in INSERT INTO rows
+        ];
+
+        if self.single_transaction {
+            dump_args.push("--single-transaction"); // https://dev.mysql.com/doc/refman/8.0/en/mysqldump.html#option_mysqldump_single-transaction
+        }
+
+        match options.content {
+            Some(DumpContent::SchemaOnly) => dump_args.push("--no-data"),
+            Some(DumpContent::DataOnly) => dump_args.push("--no-create-info"),
+            None => {}
+        }
+
+        dump_args.append(&mut vec![
+            "--quick", // reads out large tables in a way that doesn't require having enough RAM to fit the full table in memory
+            "--hex-blob",
+            self.database,
+        ]);
+
+        let mut dump_args: Vec<String> = dump_args.into_iter().map(String::from).collect();
+
+        dump_args.extend(
+            options
+                .skip_config
+                .iter()
+                .map(|cfg| format!("--ignore-table={}.{}", cfg.database, cfg.table)),
+        );
+        dump_args.extend(options.only_tables.iter().map(|cfg| cfg.table.clone()));
+        dump_args.extend(self.extra_args.iter().cloned());
+
+        Ok(dump_args)
+    }
 }
 
 impl<'a> Connector for Mysql<'a> {
@@ -104,45 +216,59 @@ impl<'a> Source for Mysql<'a> {
         options: SourceOptions,
         query_callback: F,
     ) -> Result<(), Error> {
+        let dump_args = self.dump_args(&options)?;
+
+        let mut process = Command::new("mysqldump")
+            .args(dump_args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdout = process
+            .stdout
+            .take()
+            .ok_or_else(|| Error::new(ErrorKind::Other, "Could not capture standard output."))?;
+
+        match &options.database_subset {
+            None => {
+                let reader = BufReader::new(stdout);
+                read_and_transform(reader, options, query_callback);
+            }
+            Some(subset_config) => {
+                let dump_reader = BufReader::new(stdout);
+                let reader = subset(dump_reader, subset_config)?;
+                read_and_transform(reader, options, query_callback);
+            }
+        };
+
+        wait_for_command(&mut process)
+    }
+}
+
+impl<'a> Mysql<'a> {
+    /// Estimate the number of rows by table the configured database subset produces
+    pub fn subset_estimate(
+        &self,
+        subset_config: &DatabaseSubsetConfig,
+    ) -> Result<HashMap<String, usize>, Error> {
         let s_port = self.port.to_string();
-        let password = &format!("-p{}", self.password);
+        let password = format!("-p{}", self.password);
 
-        let mut dump_args = vec![
+        let dump_args = vec![
             "-h",
             self.host,
             "-P",
             s_port.as_str(),
             "-u",
             self.username,
-            password,
-            "--add-drop-database", // add DROP DATABASE statement before each CREATE DATABASE statement
-            "--add-drop-table",    // add DROP TABLE statement before each CREATE TABLE statement
+            password.as_str(),
             "--skip-extended-insert", // have a row by INSERT INTO statement
-            "--complete-insert",   // have column names in INSERT INTO rows
-            "--single-transaction", // https://dev.mysql.com/doc/refman/8.0/en/mysqldump.html#option_mysqldump_single-transaction
+            "--complete-insert",      // have column names in INSERT INTO rows
             "--quick", // reads out large tables in a way that doesn't require having enough RAM to fit the full table in memory
             "--hex-blob",
             self.database,
         ];
 
-        let ignore_tables_args: Vec<String> = options
-            .skip_config
-            .iter()
-            .map(|cfg| format!("--ignore-table={}.{}", cfg.database, cfg.table))
-            .collect();
-        let mut ignore_tables_args: Vec<&str> =
-            ignore_tables_args.iter().map(String::as_str).collect();
-
-        dump_args.append(&mut ignore_tables_args);
-
-        let mut only_tables_args: Vec<&str> = options
-            .only_tables
-            .iter()
-            .map(|cfg| String::as_str(&cfg.table))
-            .collect();
-
-        dump_args.append(&mut only_tables_args);
-
         let mut process = Command::new("mysqldump")
             .args(dump_args)
             .stdout(Stdio::piped())
@@ -154,14 +280,88 @@ impl<'a> Source for Mysql<'a> {
             .take()
             .ok_or_else(|| Error::new(ErrorKind::Other, "Could not capture standard output."))?;
 
-        let reader = BufReader::new(stdout);
+        let estimate = with_mysql_subset(BufReader::new(stdout), subset_config, |subset| {
+            subset.estimate()
+        })?;
 
-        read_and_transform(reader, options, query_callback);
+        wait_for_command(&mut process)?;
 
-        wait_for_command(&mut process)
+        Ok(estimate)
     }
 }
 
+pub fn subset<R: Read>(
+    dump_reader: BufReader<R>,
+    subset_config: &DatabaseSubsetConfig,
+) -> Result<BufReader<File>, Error> {
+    with_mysql_subset(dump_reader, subset_config, |subset| {
+        let named_subset_file = tempfile::NamedTempFile::new()?;
+        let mut subset_file = named_subset_file.as_file();
+
+        let _ = subset.read(
+            |row| {
+                match subset_file.write(format!("{}\n", row).as_bytes()) {
+                    Ok(_) => {}
+                    Err(err) => {
+                        panic!("{}", err)
+                    }
+                };
+            },
+            |progress| {
+                info!("Database subset completion: {}%", progress.percent());
+            },
+        )?;
+
+        Ok(BufReader::new(
+            File::open(named_subset_file.path()).unwrap(),
+        ))
+    })
+}
+
+/// copy the dump into a temp file and build the subset from the configuration
+fn with_mysql_subset<R: Read, T, F: FnOnce(&MysqlSubset) -> Result<T, Error>>(
+    mut dump_reader: BufReader<R>,
+    subset_config: &DatabaseSubsetConfig,
+    f: F,
+) -> Result<T, Error> {
+    if subset_config.references.is_some() {
+        return Err(Error::new(
+            ErrorKind::Other,
+            "config error: `references` of `database_subset` is only supported with MongoDB - the MySQL subset follows the foreign keys of the dump",
+        ));
+    }
+
+    let mut named_temp_file = tempfile::NamedTempFile::new()?;
+    let mut temp_dump_file = named_temp_file.as_file_mut();
+    let _ = io::copy(&mut dump_reader, &mut temp_dump_file)?;
+
+    let strategy = match subset_config.strategy {
+        DatabaseSubsetConfigStrategy::Random(opt) => SubsetStrategy::RandomPercent {
+            database: subset_config.database.as_str(),
+            table: subset_config.table.as_str(),
+            percent: opt.percent,
+        },
+    };
+
+    let empty_vec = Vec::new();
+    let passthrough_tables = subset_config
+        .passthrough_tables
+        .as_ref()
+        .unwrap_or(&empty_vec)
+        .iter()
+        .map(|table| PassthroughTable::new(subset_config.database.as_str(), table.as_str()))
+        .collect::<HashSet<_>>();
+
+    // the rows are visited serially - `parallel_tables` is not supported by the MySQL subset
+    let subset = MysqlSubset::new(
+        named_temp_file.path(),
+        strategy,
+        SubsetOptions::new(&passthrough_tables),
+    )?;
+
+    f(&subset)
+}
+
 pub fn read_and_transform<R: Read, F: FnMut(OriginalQuery, Query)>(
     reader: BufReader<R>,
     options: SourceOptions,
@@ -176,15 +376,26 @@ pub fn read_and_transform<R: Read, F: FnMut(OriginalQuery, Query)>(
             .insert(transformer.table_and_column_name(), transformer);
     }
 
+    let schema_only = options.content == Some(DumpContent::SchemaOnly);
+    let data_only = options.content == Some(DumpContent::DataOnly);
+    // the strings written by mysqldump are escaped by a backslash
+    let string_escaping = options
+        .string_escaping
+        .unwrap_or(StringEscapingConfig::Backslash);
+
     match list_sql_queries_from_dump_reader(reader, |query| {
         let tokens = get_tokens_from_query_str(query);
 
         match get_row_type(&tokens) {
+            RowType::InsertInto { table_name: _ } if schema_only => {
+                // not part of the dump content
+            }
             RowType::InsertInto { table_name } => {
                 let (original_columns, columns) = transform_columns(
                     table_name.as_str(),
                     &tokens,
                     &transformer_by_db_and_table_and_column_name,
+                    options.transformer_metrics,
                 );
 
                 query_callback(
@@ -194,6 +405,7 @@ pub fn read_and_transform<R: Read, F: FnMut(OriginalQuery, Query)>(
                             table_name: table_name.to_string(),
                             columns: original_columns,
                         },
+                        string_escaping,
                     ),
                     to_query(
                         None,
@@ -201,15 +413,20 @@ pub fn read_and_transform<R: Read, F: FnMut(OriginalQuery, Query)>(
                             table_name: table_name.to_string(),
                             columns,
                         },
+                        string_escaping,
                     ),
                 )
             }
             RowType::CreateTable { table_name: _ } => {
-                no_change_query_callback(query_callback.borrow_mut(), query);
+                if !data_only {
+                    no_change_query_callback(query_callback.borrow_mut(), query);
+                }
             }
             RowType::Others => {
                 // other rows than `INSERT INTO ...` and `CREATE TABLE ...`
-                no_change_query_callback(query_callback.borrow_mut(), query);
+                if !(data_only && is_schema_statement(&tokens)) {
+                    no_change_query_callback(query_callback.borrow_mut(), query);
+                }
             }
         }
 
@@ -220,6 +437,24 @@ pub fn read_and_transform<R: Read, F: FnMut(OriginalQuery, Query)>(
     }
 }
 
+/// count the rows and bytes of every table of a dump - one row by `INSERT INTO` statement
+pub fn table_stats<R: Read>(reader: BufReader<R>) -> Result<Vec<TableStats>, Error> {
+    let mut stats = TablesStats::default();
+
+    match list_sql_queries_from_dump_reader(reader, |query| {
+        let tokens = get_tokens_from_query_str(query);
+
+        if let RowType::InsertInto { table_name } = get_row_type(&tokens) {
+            stats.add_row(table_name.as_str(), row_size(query));
+        }
+
+        ListQueryResult::Continue
+    }) {
+        Ok(_) => Ok(stats.into_sorted_vec()),
+        Err(err) => Err(Error::new(ErrorKind::Other, format!("{:?}", err))),
+    }
+}
+
 pub fn read_and_parse_schema<R: Read>(reader: BufReader<R>) -> Result<(), Error> {
     match list_sql_queries_from_dump_reader(reader, |query| {
         let tokens = get_tokens_from_query_str(query.clone());
@@ -262,6 +497,7 @@ fn transform_columns(
     table_name: &str,
     tokens: &Vec<Token>,
     transformer_by_db_and_table_and_column_name: &HashMap<String, &Box<dyn Transformer>>,
+    transformer_metrics: &TransformerMetrics,
 ) -> (Vec<Column>, Vec<Column>) {
     // find database name by filtering out all queries starting with
     // INSERT INTO `<table>` (...)
@@ -303,11 +539,12 @@ fn transform_columns(
             Token::Char(column_value) => {
                 Column::CharValue(column_name.to_string(), column_value.clone())
             }
+            // the tokenizer keeps the escape sequences - they are escaped again by `to_query`
             Token::SingleQuotedString(column_value) => {
-                Column::StringValue(column_name.to_string(), column_value.clone())
+                Column::StringValue(column_name.to_string(), unescape(column_value))
             }
             Token::NationalStringLiteral(column_value) => {
-                Column::StringValue(column_name.to_string(), column_value.clone())
+                Column::StringValue(column_name.to_string(), unescape(column_value))
             }
             Token::HexStringLiteral(column_value) => {
                 Column::StringValue(column_name.to_string(), column_value.clone())
@@ -329,8 +566,11 @@ fn transform_columns(
 
         let column =
             match transformer_by_db_and_table_and_column_name.get(table_and_column_name.as_str()) {
-                Some(transformer) => transformer.transform(column), // apply transformation on the column
-                None => column,
+                Some(transformer) if transformer.should_transform() => {
+                    transformer_metrics.increment(transformer.as_ref());
+                    transformer.transform(column) // apply transformation on the column
+                }
+                _ => column,
             };
 
         original_columns.push(original_column);
@@ -345,6 +585,16 @@ fn is_insert_into_statement(tokens: &Vec<Token>) -> bool {
         && match_keyword_at_position(Keyword::Into, &tokens, 2)
 }
 
+/// `CREATE ...`, `DROP ...` or `ALTER ...` statement - the rows of a dropped table would be lost
+fn is_schema_statement(tokens: &Vec<Token>) -> bool {
+    match tokens.first() {
+        Some(Token::Word(word)) => ["CREATE", "DROP", "ALTER"]
+            .iter()
+            .any(|keyword| word.value.eq_ignore_ascii_case(keyword)),
+        _ => false,
+    }
+}
+
 fn is_create_table_statement(tokens: &Vec<Token>) -> bool {
     match_keyword_at_position(Keyword::Create, &tokens, 0)
         && match_keyword_at_position(Keyword::Table, &tokens, 2)
@@ -372,7 +622,68 @@ fn get_row_type(tokens: &Vec<Token>) -> RowType {
     row_type
 }
 
-fn to_query(database: Option<&str>, query: InsertIntoQuery) -> Query {
+/// the value of a string literal - https://dev.mysql.com/doc/refman/8.0/en/string-literals.html#character-escape-sequences
+fn unescape(literal: &str) -> String {
+    let mut value = String::with_capacity(literal.len());
+    let mut chars = literal.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('0') => value.push('\0'),
+            Some('b') => value.push('\x08'),
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('t') => value.push('\t'),
+            Some('Z') => value.push('\x1a'),
+            // kept with their backslash - for the patterns
+            Some(c @ ('%' | '_')) => {
+                value.push('\\');
+                value.push(c);
+            }
+            Some(c) => value.push(c),
+            None => value.push('\\'),
+        }
+    }
+
+    value
+}
+
+/// a quoted string literal - the backslash escaping writes the same escape sequences as mysqldump
+fn to_string_literal(value: &str, string_escaping: StringEscapingConfig) -> String {
+    match string_escaping {
+        StringEscapingConfig::Ansi => format!("'{}'", value.replace('\'', "''")),
+        StringEscapingConfig::Backslash => {
+            let mut literal = String::with_capacity(value.len() + 2);
+            literal.push('\'');
+            for c in value.chars() {
+                match c {
+                    '\0' => literal.push_str("\\0"),
+                    '\n' => literal.push_str("\\n"),
+                    '\r' => literal.push_str("\\r"),
+                    '\x1a' => literal.push_str("\\Z"),
+                    '\\' | '\'' | '"' => {
+                        literal.push('\\');
+                        literal.push(c);
+                    }
+                    c => literal.push(c),
+                }
+            }
+            literal.push('\'');
+            literal
+        }
+    }
+}
+
+fn to_query(
+    database: Option<&str>,
+    query: InsertIntoQuery,
+    string_escaping: StringEscapingConfig,
+) -> Query {
     let mut column_names = Vec::with_capacity(query.columns.len());
     let mut values = Vec::with_capacity(query.columns.len());
 
@@ -388,7 +699,7 @@ fn to_query(database: Option<&str>, query: InsertIntoQuery) -> Query {
             }
             Column::StringValue(column_name, value) => {
                 column_names.push(column_name);
-                values.push(format!("'{}'", value));
+                values.push(to_string_literal(value.as_str(), string_escaping));
             }
             Column::CharValue(column_name, value) => {
                 column_names.push(column_name);
@@ -427,10 +738,16 @@ fn to_query(database: Option<&str>, query: InsertIntoQuery) -> Query {
 
 #[cfg(test)]
 mod tests {
+    use std::io::BufReader;
+
+    use crate::config::StringEscapingConfig;
     use crate::connector::Connector;
-    use crate::source::mysql::{is_create_table_statement, is_insert_into_statement, RowType};
-    use crate::source::SourceOptions;
-    use crate::transformer::{transient::TransientTransformer, Transformer};
+    use crate::datastore::DumpContent;
+    use crate::source::mysql::{
+        is_create_table_statement, is_insert_into_statement, read_and_transform, RowType,
+    };
+    use crate::source::{SourceOptions, TableStats};
+    use crate::transformer::{transient::TransientTransformer, Transformer, TransformerMetrics};
     use crate::Source;
     use dump_parser::mysql::Tokenizer;
 
@@ -456,6 +773,19 @@ mod tests {
             skip_config: &vec![],
             database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
 
         assert!(p.read(source_options, |_original_query, _query| {}).is_ok());
@@ -468,6 +798,19 @@ mod tests {
             skip_config: &vec![],
             database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
         assert!(p
             .read(source_options, |_original_query, _query| {})
@@ -484,6 +827,19 @@ mod tests {
             skip_config: &vec![],
             database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
         let _ = p.read(source_options, |original_query, query| {
             assert!(original_query.data().len() > 0);
@@ -491,6 +847,37 @@ mod tests {
         });
     }
 
+    #[test]
+    fn table_stats() {
+        let dump = "CREATE TABLE `city` (`ID` int NOT NULL, `Name` char(35) NOT NULL);
+
+INSERT INTO `city` (`ID`, `Name`) VALUES (1,'Kabul');
+INSERT INTO `country` (`Code`) VALUES ('AFG');
+INSERT INTO `city` (`ID`, `Name`) VALUES (2,'Qandahar');
+INSERT INTO `city` (`ID`, `Name`) VALUES (3,'Herat');
+";
+
+        let stats = super::table_stats(BufReader::new(dump.as_bytes())).unwrap();
+
+        assert_eq!(
+            stats,
+            vec![
+                TableStats {
+                    table: "city".to_string(),
+                    rows: 3,
+                    bytes: "INSERT INTO `city` (`ID`, `Name`) VALUES (1,'Kabul');\n".len()
+                        + "INSERT INTO `city` (`ID`, `Name`) VALUES (2,'Qandahar');\n".len()
+                        + "INSERT INTO `city` (`ID`, `Name`) VALUES (3,'Herat');\n".len(),
+                },
+                TableStats {
+                    table: "country".to_string(),
+                    rows: 1,
+                    bytes: "INSERT INTO `country` (`Code`) VALUES ('AFG');\n".len(),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_is_insert_into_statement() {
         let q = "INSERT INTO `customers` (`first_name`, `is_valid`) VALUES ('Romaric', true);";
@@ -590,4 +977,154 @@ CONSTRAINT `city_ibfk_1` FOREIGN KEY (`CountryCode`) REFERENCES `country` (`Code
         let tokens = tokenizer.tokenize().unwrap();
         assert_eq!(is_create_table_statement(&tokens), true);
     }
+
+    #[test]
+    fn dump_args_single_transaction() {
+        let transformers = vec![];
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut p = get_mysql();
+        let dump_args = p.dump_args(&source_options).unwrap();
+        assert!(dump_args.contains(&"--single-transaction".to_string()));
+        assert!(dump_args.contains(&"--quick".to_string()));
+
+        // --lock-tables is only allowed without the single transaction
+        p.set_extra_args(vec!["--lock-tables".to_string()]);
+        assert!(p.dump_args(&source_options).is_err());
+
+        p.set_single_transaction(false);
+        let dump_args = p.dump_args(&source_options).unwrap();
+        assert!(!dump_args.contains(&"--single-transaction".to_string()));
+        assert!(dump_args.contains(&"--quick".to_string()));
+        assert_eq!(dump_args.last().unwrap(), "--lock-tables");
+    }
+
+    #[test]
+    fn read_and_transform_schema_or_data_only() {
+        let dump = "DROP TABLE IF EXISTS `customers`;
+CREATE TABLE `customers` (
+    `id` int NOT NULL AUTO_INCREMENT,
+    `first_name` char(35) NOT NULL DEFAULT '',
+    PRIMARY KEY (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+LOCK TABLES `customers` WRITE;
+INSERT INTO `customers` (`id`, `first_name`) VALUES (1, 'Romaric');
+UNLOCK TABLES;
+";
+
+        let read = |content: Option<DumpContent>| {
+            let transformers = vec![];
+            let source_options = SourceOptions {
+                transformers: &transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let mut queries = vec![];
+            read_and_transform(
+                BufReader::new(dump.as_bytes()),
+                source_options,
+                |_, query| {
+                    queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+                },
+            );
+
+            queries.join("")
+        };
+
+        let queries = read(Some(DumpContent::SchemaOnly));
+        assert!(!queries.contains("INSERT INTO"));
+        assert!(queries.contains("CREATE TABLE `customers`"));
+
+        let queries = read(Some(DumpContent::DataOnly));
+        assert!(!queries.contains("CREATE"));
+        assert!(!queries.contains("DROP TABLE"));
+        assert!(queries.contains("INSERT INTO `customers`"));
+        assert!(queries.contains("LOCK TABLES `customers` WRITE;"));
+
+        let queries = read(None);
+        assert!(queries.contains("CREATE TABLE `customers`"));
+        assert!(queries.contains("INSERT INTO `customers`"));
+    }
+
+    #[test]
+    fn read_and_transform_string_escaping() {
+        let dump = "INSERT INTO `files` (`id`, `path`) VALUES (1, 'it\\'s C:\\\\temp\\\\\\n');\n";
+
+        let read = |string_escaping: Option<StringEscapingConfig>| {
+            let transformers = vec![];
+            let source_options = SourceOptions {
+                transformers: &transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let mut queries = vec![];
+            read_and_transform(
+                BufReader::new(dump.as_bytes()),
+                source_options,
+                |_, query| {
+                    queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+                },
+            );
+
+            queries.join("")
+        };
+
+        // the escape sequences of mysqldump are kept
+        assert_eq!(
+            read(None).trim_end(),
+            "INSERT INTO `files` (`id`, `path`) VALUES (1, 'it\\'s C:\\\\temp\\\\\\n');"
+        );
+        assert_eq!(
+            read(Some(StringEscapingConfig::Ansi)).trim_end(),
+            "INSERT INTO `files` (`id`, `path`) VALUES (1, 'it''s C:\\temp\\\n');"
+        );
+    }
 }
@@ -1,7 +1,7 @@
 use std::io::{stdin, BufReader, Error};
 
 use crate::connector::Connector;
-use crate::source::mysql::read_and_transform;
+use crate::source::mysql::{read_and_transform, subset};
 use crate::types::{OriginalQuery, Query};
 use crate::Source;
 use crate::SourceOptions;
@@ -28,7 +28,16 @@ impl Source for MysqlStdin {
         query_callback: F,
     ) -> Result<(), Error> {
         let reader = BufReader::new(stdin());
-        read_and_transform(reader, options, query_callback);
+
+        match &options.database_subset {
+            None => {
+                read_and_transform(reader, options, query_callback);
+            }
+            Some(subset_config) => {
+                let reader = subset(reader, subset_config)?;
+                read_and_transform(reader, options, query_callback);
+            }
+        };
 
         Ok(())
     }
@@ -0,0 +1,270 @@
+use std::collections::{BTreeMap, HashMap};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// share of the sampled values of a column that must match a data class - from 1 to 100
+const MIN_MATCH_PERCENT: usize = 80;
+
+lazy_static! {
+    static ref EMAIL: Regex =
+        Regex::new(r"^[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}$").unwrap();
+    // international (+33 6 12 34 56 78), north american ((555) 123-4567) and national (06 12 34 56 78) formats.
+    // Plain numbers and dates (2022-01-05) are not phone numbers
+    static ref PHONE_NUMBER: Regex = Regex::new(
+        r"^(\+\d{1,3}[ .-]?(\(\d{1,4}\)[ .-]?)?\d{1,4}([ .-]?\d{1,4}){1,5}|(\(\d{3}\) ?|\d{3}[ .-])\d{3}[ .-]\d{4}|0\d([ .-]?\d{2}){4})$"
+    )
+    .unwrap();
+    // 13 to 19 digits - optionally grouped with spaces or dashes
+    static ref CREDIT_CARD: Regex = Regex::new(r"^\d([ -]?\d){12,18}$").unwrap();
+}
+
+/// kind of personal data held by a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DataClass {
+    Email,
+    PhoneNumber,
+    CreditCard,
+}
+
+impl DataClass {
+    /// name of the transformer masking this kind of data
+    pub fn transformer_name(&self) -> &'static str {
+        match self {
+            DataClass::Email => "email",
+            DataClass::PhoneNumber => "phone-number",
+            DataClass::CreditCard => "credit-card",
+        }
+    }
+}
+
+/// data class of a single value - `None` if it does not look like personal data
+pub fn classify(value: &str) -> Option<DataClass> {
+    let value = value.trim();
+
+    if EMAIL.is_match(value) {
+        return Some(DataClass::Email);
+    }
+
+    // a card number looks like a phone number - it is checked first
+    if CREDIT_CARD.is_match(value) && is_luhn_valid(value) {
+        return Some(DataClass::CreditCard);
+    }
+
+    let digits = value.chars().filter(|c| c.is_ascii_digit()).count();
+    if PHONE_NUMBER.is_match(value) && (7..=15).contains(&digits) {
+        return Some(DataClass::PhoneNumber);
+    }
+
+    None
+}
+
+/// checksum of the card numbers
+pub(crate) fn is_luhn_valid(value: &str) -> bool {
+    let digits = value
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .collect::<Vec<_>>();
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| match (i % 2, digit * 2) {
+            (0, _) => *digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+
+    sum % 10 == 0
+}
+
+/// column whose sampled values look like personal data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedColumn {
+    pub database: String,
+    pub table: String,
+    pub column: String,
+    pub data_class: DataClass,
+}
+
+#[derive(Default)]
+struct ColumnSamples {
+    samples: usize,
+    matches: HashMap<DataClass, usize>,
+}
+
+/// sample the values of the columns to find the ones holding personal data
+pub struct PiiDetector {
+    max_samples: usize,
+    columns: BTreeMap<(String, String, String), ColumnSamples>,
+}
+
+impl PiiDetector {
+    pub fn new(max_samples: usize) -> Self {
+        PiiDetector {
+            max_samples,
+            columns: BTreeMap::new(),
+        }
+    }
+
+    /// sample a value of a column - empty values and the values after `max_samples` are ignored
+    pub fn add_value(&mut self, database: &str, table: &str, column: &str, value: &str) {
+        if value.trim().is_empty() {
+            return;
+        }
+
+        let samples = self
+            .columns
+            .entry((database.to_string(), table.to_string(), column.to_string()))
+            .or_default();
+
+        if samples.samples >= self.max_samples {
+            return;
+        }
+
+        samples.samples += 1;
+
+        if let Some(data_class) = classify(value) {
+            *samples.matches.entry(data_class).or_insert(0) += 1;
+        }
+    }
+
+    /// columns whose sampled values mostly match a data class - sorted by database, table and column
+    pub fn detected_columns(&self) -> Vec<DetectedColumn> {
+        self.columns
+            .iter()
+            .filter_map(|((database, table, column), samples)| {
+                let (data_class, matches) = samples
+                    .matches
+                    .iter()
+                    .max_by_key(|(data_class, matches)| (**matches, **data_class))?;
+
+                if *matches * 100 < samples.samples * MIN_MATCH_PERCENT {
+                    return None;
+                }
+
+                Some(DetectedColumn {
+                    database: database.clone(),
+                    table: table.clone(),
+                    column: column.clone(),
+                    data_class: *data_class,
+                })
+            })
+            .collect()
+    }
+}
+
+/// `transformers` section of the configuration masking the detected columns
+pub fn to_transformers_config(columns: &Vec<DetectedColumn>) -> String {
+    let mut config = String::from("transformers:\n");
+    let mut current_table: Option<(&str, &str)> = None;
+
+    for column in columns {
+        let table = (column.database.as_str(), column.table.as_str());
+
+        if current_table != Some(table) {
+            config.push_str(&format!(
+                "  - database: {}\n    table: {}\n    columns:\n",
+                column.database, column.table
+            ));
+            current_table = Some(table);
+        }
+
+        config.push_str(&format!(
+            "      - name: {}\n        transformer_name: {}\n",
+            column.column,
+            column.data_class.transformer_name()
+        ));
+    }
+
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{TransformerConfig, TransformerTypeConfig};
+    use crate::source::pii_detector::{classify, to_transformers_config, DataClass, PiiDetector};
+
+    #[test]
+    fn classify_values() {
+        assert_eq!(classify("john.doe@example.com"), Some(DataClass::Email));
+        assert_eq!(classify("j+tag@mail.example.co.uk"), Some(DataClass::Email));
+        assert_eq!(classify("+33 6 12 34 56 78"), Some(DataClass::PhoneNumber));
+        assert_eq!(classify("(555) 123-4567"), Some(DataClass::PhoneNumber));
+        assert_eq!(classify("555.123.4567"), Some(DataClass::PhoneNumber));
+        assert_eq!(classify("06 12 34 56 78"), Some(DataClass::PhoneNumber));
+        assert_eq!(classify("4242 4242 4242 4242"), Some(DataClass::CreditCard));
+        assert_eq!(classify("4111-1111-1111-1111"), Some(DataClass::CreditCard));
+
+        // not personal data
+        assert_eq!(classify("john.doe"), None);
+        assert_eq!(classify("@example.com"), None);
+        assert_eq!(classify("2022-01-05"), None);
+        assert_eq!(classify("123456"), None);
+        // wrong checksum
+        assert_eq!(classify("4242 4242 4242 4241"), None);
+    }
+
+    #[test]
+    fn detect_email_column() {
+        let mut detector = PiiDetector::new(100);
+
+        for i in 0..10 {
+            detector.add_value("public", "users", "id", i.to_string().as_str());
+            detector.add_value(
+                "public",
+                "users",
+                "email",
+                format!("user-{}@example.com", i).as_str(),
+            );
+            // a few values do not match
+            detector.add_value(
+                "public",
+                "users",
+                "contact",
+                if i < 5 { "user@example.com" } else { "n/a" },
+            );
+        }
+        // empty values are not sampled
+        detector.add_value("public", "users", "email", "");
+
+        let columns = detector.detected_columns();
+        assert_eq!(columns.len(), 1);
+        assert_eq!(columns[0].column, "email");
+        assert_eq!(columns[0].data_class, DataClass::Email);
+    }
+
+    #[test]
+    fn suggest_transformers_config() {
+        let mut detector = PiiDetector::new(100);
+        detector.add_value("public", "users", "email", "john.doe@example.com");
+        detector.add_value("public", "users", "phone", "+33 6 12 34 56 78");
+        detector.add_value("public", "orders", "card", "4242 4242 4242 4242");
+
+        let config = to_transformers_config(&detector.detected_columns());
+
+        #[derive(serde::Deserialize)]
+        struct Source {
+            transformers: Vec<TransformerConfig>,
+        }
+
+        let source: Source = serde_yaml::from_str(config.as_str()).unwrap();
+        assert_eq!(source.transformers.len(), 2);
+        assert_eq!(source.transformers[0].table, "orders");
+        assert_eq!(
+            source.transformers[0].columns[0].transformer,
+            TransformerTypeConfig::CreditCard
+        );
+        assert_eq!(source.transformers[1].table, "users");
+        assert_eq!(
+            source.transformers[1].columns[0].transformer,
+            TransformerTypeConfig::Email
+        );
+        assert_eq!(
+            source.transformers[1].columns[1].transformer,
+            TransformerTypeConfig::PhoneNumber
+        );
+    }
+}
@@ -1,28 +1,46 @@
 use std::borrow::BorrowMut;
+use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io;
-use std::io::{BufReader, Error, ErrorKind, Read, Write};
-use std::process::{Command, Stdio};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Write};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::thread;
+use std::thread::JoinHandle;
 
-use log::info;
+use log::{info, warn};
 
 use dump_parser::postgres::Keyword::NoKeyword;
 use dump_parser::postgres::{
-    get_column_names_from_create_query, get_column_names_from_insert_into_query,
-    get_column_values_from_insert_into_query, get_tokens_from_query_str,
-    get_word_value_at_position, match_keyword_at_position, Keyword, Token,
+    get_column_names_from_copy_query, get_column_names_from_create_query,
+    get_column_names_from_insert_into_query, get_column_types_from_create_query,
+    get_column_values_from_copy_row, get_column_values_from_insert_into_query,
+    get_not_null_column_names_from_create_query, get_tokens_from_query_str,
+    get_word_value_at_position, match_keyword_at_position, to_copy_row, Keyword, Token, Word,
 };
-use dump_parser::utils::{list_sql_queries_from_dump_reader, ListQueryResult};
-use subset::postgres::{PostgresSubset, SubsetStrategy};
-use subset::{PassthroughTable, Subset, SubsetOptions};
+use dump_parser::utils::{
+    list_sql_queries_from_dump_reader, ListQueryResult, COPY_PAYLOAD_TERMINATOR,
+};
+use subset::postgres::{
+    get_alter_table_foreign_key, list_foreign_keys, ForeignKey, PostgresSubset,
+};
+use subset::{PassthroughTable, Subset, SubsetOptions, SubsetStrategy};
 
-use crate::config::DatabaseSubsetConfigStrategy;
+use crate::config::{
+    ColumnTagConfig, DatabaseSubsetConfigStrategy, OnlyTablesConfig, SequencesConfig, SkipConfig,
+    SkipReferencesConfig, SortRowsConfig, StringEscapingConfig,
+};
 use crate::connector::Connector;
-use crate::source::{Explain, Source};
-use crate::transformer::Transformer;
+use crate::datastore::DumpContent;
+use crate::source::cpu_limiter::CpuLimiter;
+use crate::source::ddl_rewriter::DdlRewriter;
+use crate::source::sequences::SequenceTracker;
+use crate::source::{check_extra_args, row_size, Explain, Source, TableStats, TablesStats};
+use crate::transformer::{Transformer, TransformerMetrics};
 use crate::types::{Column, InsertIntoQuery, OriginalQuery, Query};
-use crate::utils::{binary_exists, table, wait_for_command};
+use crate::utils::{binary_exists, glob_match, table, wait_for_command};
 use crate::DatabaseSubsetConfig;
 
 use super::SourceOptions;
@@ -40,15 +58,313 @@ enum RowType {
         database_name: String,
         table_name: String,
     },
+    Copy {
+        database_name: String,
+        table_name: String,
+    },
+    CommentOnColumn {
+        database_name: String,
+        table_name: String,
+        column_name: String,
+        comment: String,
+    },
     Others,
 }
 
+#[derive(Debug, PartialEq)]
+enum LargeObjectStatement {
+    /// `SELECT pg_catalog.lo_create(...)`, `ALTER LARGE OBJECT ...`, `COMMENT ON LARGE OBJECT ...`...
+    Definition,
+    /// `SELECT pg_catalog.lo_open(...)`, `SELECT pg_catalog.lowrite(...)` and `SELECT pg_catalog.lo_close(...)` -
+    /// the descriptor opened is only valid until the end of the transaction
+    Data,
+}
+
+/// Transformers by `<database>.<table>.<column>`, from the configuration or found via the column comment tags.
+///
+/// When several transformers match a column, the most specific one is applied: a transformer naming the column
+/// exactly, then the transformers with glob patterns (`*` and `?`) in their names - the ones with the fewest
+/// patterned names and then the most literal characters first - and then the column tags. Transformers as specific
+/// as each other are applied in the order of the configuration: the first one wins.
+struct Transformers<'a> {
+    configured: HashMap<String, &'a Box<dyn Transformer>>,
+    // transformers with a glob pattern in their names - sorted from the most specific one
+    patterns: Vec<&'a Box<dyn Transformer>>,
+    // index of the pattern matching each column met - to match the patterns once per column
+    matched_patterns: RefCell<HashMap<String, Option<usize>>>,
+    tagged: HashMap<String, Box<dyn Transformer>>,
+}
+
+impl<'a> Transformers<'a> {
+    fn new(transformers: &'a [Box<dyn Transformer>]) -> Self {
+        let mut configured = HashMap::with_capacity(transformers.len());
+        let mut patterns = vec![];
+
+        for transformer in transformers {
+            if is_transformer_pattern(transformer.as_ref()) {
+                patterns.push(transformer);
+            } else {
+                // the first transformer of the configuration wins
+                let _ = configured
+                    .entry(transformer.database_and_quoted_table_and_column_name())
+                    .or_insert(transformer);
+            }
+        }
+
+        // a stable sort - the transformers as specific as each other stay in the configuration order
+        patterns.sort_by(|a, b| {
+            transformer_specificity(b.as_ref()).cmp(&transformer_specificity(a.as_ref()))
+        });
+
+        Transformers {
+            configured,
+            patterns,
+            matched_patterns: RefCell::new(HashMap::new()),
+            tagged: HashMap::new(),
+        }
+    }
+
+    fn get(&self, db_and_table_and_column_name: &str) -> Option<&dyn Transformer> {
+        if let Some(transformer) = self.configured.get(db_and_table_and_column_name) {
+            return Some(transformer.as_ref());
+        }
+
+        if let Some(transformer) = self.get_pattern(db_and_table_and_column_name) {
+            return Some(transformer);
+        }
+
+        self.tagged
+            .get(db_and_table_and_column_name)
+            .map(|transformer| transformer.as_ref())
+    }
+
+    fn get_pattern(&self, db_and_table_and_column_name: &str) -> Option<&dyn Transformer> {
+        if self.patterns.is_empty() {
+            return None;
+        }
+
+        let mut matched_patterns = self.matched_patterns.borrow_mut();
+        let idx = *matched_patterns
+            .entry(db_and_table_and_column_name.to_string())
+            .or_insert_with(|| {
+                self.patterns.iter().position(|transformer| {
+                    glob_match(
+                        transformer
+                            .database_and_quoted_table_and_column_name()
+                            .as_str(),
+                        db_and_table_and_column_name,
+                    )
+                })
+            });
+
+        idx.map(|idx| self.patterns[idx].as_ref())
+    }
+
+    fn contains_key(&self, db_and_table_and_column_name: &str) -> bool {
+        self.get(db_and_table_and_column_name).is_some()
+    }
+}
+
+/// `COPY ... FROM stdin;` statement for which the payload rows are being read
+struct CopyPayload {
+    database_name: String,
+    table_name: String,
+    column_names: Vec<String>,
+    skip: bool,
+    has_transformers: bool,
+    // index of the columns referencing a skipped table
+    dangling_references: Vec<(usize, DanglingReference)>,
+    // index of the columns whose transformer needs all the values at once
+    all_values_columns: Vec<usize>,
+    // rows kept in memory until the end of the payload - if there are such columns
+    rows: Vec<(OriginalQuery, Query)>,
+}
+
+impl CopyPayload {
+    /// write the rows kept in memory - the columns transformed all at once are transformed first
+    fn flush<F: FnMut(OriginalQuery, Query)>(
+        &mut self,
+        transformer_by_db_and_table_and_column_name: &Transformers,
+        transformer_metrics: &TransformerMetrics,
+        query_callback: &mut F,
+    ) {
+        let rows = std::mem::take(&mut self.rows);
+        let mut rows_values = rows
+            .iter()
+            .map(|(_, row)| get_column_values_from_copy_row(&String::from_utf8_lossy(row.data())))
+            .collect::<Vec<_>>();
+
+        for idx in &self.all_values_columns {
+            let column_name = self.column_names[*idx].as_str();
+            let transformer = match transformer_by_db_and_table_and_column_name
+                .get(format!("{}.{}.{}", self.database_name, self.table_name, column_name).as_str())
+            {
+                Some(transformer) => transformer,
+                None => continue,
+            };
+
+            // COPY values are not typed - they are all given as strings
+            let columns = rows_values
+                .iter_mut()
+                .filter_map(|values| values.get_mut(*idx))
+                .map(|value| match value.take() {
+                    Some(value) => Column::StringValue(column_name.to_string(), value),
+                    None => Column::None(column_name.to_string()),
+                })
+                .collect::<Vec<_>>();
+
+            let mut columns =
+                transform_all_values(transformer, columns, transformer_metrics).into_iter();
+
+            for value in rows_values
+                .iter_mut()
+                .filter_map(|values| values.get_mut(*idx))
+            {
+                *value = columns.next().and_then(to_copy_value);
+            }
+        }
+
+        for ((original_query, _), values) in rows.into_iter().zip(rows_values) {
+            query_callback(original_query, Query(to_copy_row(&values).into_bytes()));
+        }
+    }
+}
+
+/// what is done with the value of a column referencing a skipped table
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DanglingReference {
+    SetNull,
+    DeleteRow,
+}
+
+/// `INSERT INTO` rows of a table kept in memory to be written sorted by a column, or to transform all
+/// the values of a column at once
+struct BufferedRows {
+    database_name: String,
+    table_name: String,
+    sort_column_name: Option<String>,
+    // columns whose transformer needs all the values at once
+    all_values_column_names: Vec<String>,
+    string_escaping: StringEscapingConfig,
+    rows: Vec<(OriginalQuery, Vec<Column>)>,
+}
+
+impl BufferedRows {
+    fn is_table(&self, database_name: &str, table_name: &str) -> bool {
+        self.database_name == database_name && self.table_name == table_name
+    }
+
+    fn flush<F: FnMut(OriginalQuery, Query)>(
+        self,
+        transformer_by_db_and_table_and_column_name: &Transformers,
+        transformer_metrics: &TransformerMetrics,
+        query_callback: &mut F,
+    ) {
+        let mut rows = self.rows;
+
+        for column_name in &self.all_values_column_names {
+            let transformer = match transformer_by_db_and_table_and_column_name
+                .get(format!("{}.{}.{}", self.database_name, self.table_name, column_name).as_str())
+            {
+                Some(transformer) => transformer,
+                None => continue,
+            };
+
+            let columns = rows
+                .iter()
+                .filter_map(|(_, columns)| columns.iter().find(|c| c.name() == column_name))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let mut columns =
+                transform_all_values(transformer, columns, transformer_metrics).into_iter();
+
+            for column in rows
+                .iter_mut()
+                .filter_map(|(_, columns)| columns.iter_mut().find(|c| c.name() == column_name))
+            {
+                if let Some(transformed_column) = columns.next() {
+                    *column = transformed_column;
+                }
+            }
+        }
+
+        let mut rows = rows
+            .into_iter()
+            .map(|(original_query, columns)| {
+                let sort_value = self
+                    .sort_column_name
+                    .as_ref()
+                    .and_then(|sort_column_name| {
+                        columns
+                            .iter()
+                            .find(|column| unquote(column.name()) == sort_column_name)
+                            .cloned()
+                    })
+                    .unwrap_or_else(|| Column::None(String::new()));
+
+                (sort_value, original_query, columns)
+            })
+            .collect::<Vec<_>>();
+
+        if self.sort_column_name.is_some() {
+            // stable sort - the rows with the same value keep their dump order
+            rows.sort_by(|(a, _, _), (b, _, _)| compare_sort_values(a, b));
+        }
+
+        for (_, original_query, columns) in rows {
+            let query = to_query(
+                Some(self.database_name.as_str()),
+                InsertIntoQuery {
+                    table_name: self.table_name.clone(),
+                    columns,
+                },
+                self.string_escaping,
+            );
+
+            query_callback(original_query, query);
+        }
+    }
+}
+
+/// pg_dump arguments set by Replibyte - the dump can't be parsed or the options are configured otherwise
+const PG_DUMP_MANAGED_ARGS: &[&str] = &[
+    "--column-inserts",
+    "--inserts",
+    "--rows-per-insert",
+    "-h",
+    "--host",
+    "-p",
+    "--port",
+    "-U",
+    "--username",
+    "-d",
+    "--dbname",
+    "-F",
+    "--format",
+    "-f",
+    "--file",
+    "-O",
+    "--no-owner",
+    "-x",
+    "--no-privileges",
+    "--no-acl",
+    "-s",
+    "--schema-only",
+    "-a",
+    "--data-only",
+];
+
 pub struct Postgres<'a> {
     host: &'a str,
     port: u16,
     database: &'a str,
     username: &'a str,
     password: &'a str,
+    privileges: bool,
+    owner: bool,
+    use_copy: bool,
+    extra_args: Vec<String>,
 }
 
 impl<'a> Postgres<'a> {
@@ -65,7 +381,99 @@ impl<'a> Postgres<'a> {
             database,
             username,
             password,
+            privileges: true,
+            owner: false,
+            use_copy: false,
+            extra_args: vec![],
+        }
+    }
+
+    /// dump the privileges (GRANT/REVOKE statements) - true by default
+    pub fn set_privileges(&mut self, privileges: bool) {
+        self.privileges = privileges;
+    }
+
+    /// dump the object ownership (ALTER ... OWNER TO statements) - false by default
+    pub fn set_owner(&mut self, owner: bool) {
+        self.owner = owner;
+    }
+
+    /// dump the rows as `COPY ... FROM stdin;` payloads instead of `INSERT INTO` statements - false by default
+    pub fn set_use_copy(&mut self, use_copy: bool) {
+        self.use_copy = use_copy;
+    }
+
+    /// extra pg_dump arguments (e.g. `--disable-triggers`) - they must not conflict with the ones set by Replibyte
+    pub fn set_extra_args(&mut self, extra_args: Vec<String>) {
+        self.extra_args = extra_args;
+    }
+
+    fn dump_args(&self, options: &SourceOptions) -> Result<Vec<String>, Error> {
+        check_extra_args("pg_dump", &self.extra_args, PG_DUMP_MANAGED_ARGS)?;
+
+        if self.use_copy && options.database_subset.is_some() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "config error: `use_copy` can't be used with `database_subset` - the subset reads the INSERT INTO rows",
+            ));
+        }
+
+        if self.use_copy && !options.sort_rows.is_empty() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "config error: `use_copy` can't be used with `sort_rows` - only the INSERT INTO rows are sorted",
+            ));
+        }
+
+        let s_port = self.port.to_string();
+
+        let mut dump_args = vec!["-h", self.host, "-p", s_port.as_str(), "-U", self.username];
+
+        if !self.use_copy {
+            dump_args.insert(0, "--column-inserts"); // dump data as INSERT commands with column names
+        }
+
+        dump_args.append(&mut self.privileges_args());
+
+        match options.content {
+            Some(DumpContent::SchemaOnly) => dump_args.push("--schema-only"),
+            // the subset and the skipped references rely on the relationships declared in the schema -
+            // the schema statements are dropped afterwards
+            Some(DumpContent::DataOnly)
+                if options.database_subset.is_none()
+                    && options.skip_config.iter().all(|skip| skip.references.is_none()) =>
+            {
+                dump_args.push("--data-only")
+            }
+            _ => {}
+        }
+
+        if options.skip_large_objects {
+            dump_args.push("--no-blobs");
         }
+
+        let mut dump_args: Vec<String> = dump_args.into_iter().map(String::from).collect();
+
+        dump_args.append(&mut only_tables_args(options.only_tables));
+        dump_args.append(&mut exclude_schemas_args(options.exclude_schemas));
+        dump_args.extend(self.extra_args.iter().cloned());
+        dump_args.push(self.database.to_string());
+
+        Ok(dump_args)
+    }
+
+    fn privileges_args(&self) -> Vec<&'static str> {
+        let mut args = vec![];
+
+        if !self.owner {
+            args.push("--no-owner"); // skip restoration of object ownership
+        }
+
+        if !self.privileges {
+            args.push("--no-privileges"); // skip GRANT/REVOKE statements (a.k.a. --no-acl)
+        }
+
+        args
     }
 }
 
@@ -116,29 +524,7 @@ impl<'a> Source for Postgres<'a> {
         options: SourceOptions,
         query_callback: F,
     ) -> Result<(), Error> {
-        let s_port = self.port.to_string();
-
-        let mut dump_args = vec![
-            "--column-inserts", // dump data as INSERT commands with column names
-            "--no-owner",       // skip restoration of object ownership
-            "-h",
-            self.host,
-            "-p",
-            s_port.as_str(),
-            "-U",
-            self.username,
-        ];
-
-        let only_tables_args: Vec<String> = options
-            .only_tables
-            .iter()
-            .map(|cfg| format!("--table={}.{}", cfg.database, cfg.table))
-            .collect();
-        let mut only_tables_args: Vec<&str> = only_tables_args.iter().map(String::as_str).collect();
-
-        dump_args.append(&mut only_tables_args);
-
-        dump_args.push(self.database);
+        let dump_args = self.dump_args(&options)?;
 
         // TODO: as for mysql we can exclude tables directly here so we can remove the skip_tables_map checks
         let mut process = Command::new("pg_dump")
@@ -156,12 +542,12 @@ impl<'a> Source for Postgres<'a> {
         match &options.database_subset {
             None => {
                 let reader = BufReader::new(stdout);
-                read_and_transform(reader, options, query_callback);
+                read_and_transform(reader, options, query_callback)?;
             }
             Some(subset_config) => {
                 let dump_reader = BufReader::new(stdout);
                 let reader = subset(dump_reader, subset_config)?;
-                read_and_transform(reader, options, query_callback);
+                read_and_transform(reader, options, query_callback)?;
             }
         };
 
@@ -169,10 +555,194 @@ impl<'a> Source for Postgres<'a> {
     }
 }
 
+/// `*` and `?` wildcards are part of the pg_dump patterns - globs are expanded by pg_dump itself
+fn only_tables_args(only_tables: &Vec<OnlyTablesConfig>) -> Vec<String> {
+    only_tables
+        .iter()
+        .map(|cfg| format!("--table={}.{}", cfg.database, cfg.table))
+        .collect()
+}
+
+fn exclude_schemas_args(exclude_schemas: &Vec<String>) -> Vec<String> {
+    exclude_schemas
+        .iter()
+        .map(|schema| format!("--exclude-schema={}", schema))
+        .collect()
+}
+
+impl<'a> Postgres<'a> {
+    /// Estimate the number of rows by table the configured database subset produces - pg_dump runs with the dump arguments
+    pub fn subset_estimate(
+        &self,
+        options: &SourceOptions,
+    ) -> Result<HashMap<String, usize>, Error> {
+        let subset_config = match options.database_subset {
+            Some(subset_config) => subset_config,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    "missing <source.database_subset> object in the configuration file",
+                ));
+            }
+        };
+
+        let dump_args = self.dump_args(options)?;
+
+        let mut process = Command::new("pg_dump")
+            .env("PGPASSWORD", self.password)
+            .args(dump_args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdout = process
+            .stdout
+            .take()
+            .ok_or_else(|| Error::new(ErrorKind::Other, "Could not capture standard output."))?;
+
+        let estimate = with_postgres_subset(BufReader::new(stdout), subset_config, |subset| {
+            subset.estimate()
+        })?;
+
+        wait_for_command(&mut process)?;
+
+        Ok(estimate)
+    }
+}
+
+/// custom-format dumps (`pg_dump -Fc`) start with these bytes
+const CUSTOM_FORMAT_MAGIC_BYTES: &[u8] = b"PGDMP";
+
+/// true if the dump is a custom-format dump (`pg_dump -Fc`) - nothing is consumed from the reader
+pub fn is_custom_format<R: Read>(reader: &mut BufReader<R>) -> Result<bool, Error> {
+    Ok(reader.fill_buf()?.starts_with(CUSTOM_FORMAT_MAGIC_BYTES))
+}
+
+/// `pg_restore` process converting a custom-format dump into a plain SQL dump
+pub struct PgRestore {
+    process: Child,
+    input: JoinHandle<Result<u64, Error>>,
+    stderr: JoinHandle<Result<String, Error>>,
+}
+
+impl PgRestore {
+    /// the plain SQL dump written by `pg_restore`
+    pub fn stdout(&mut self) -> Result<ChildStdout, Error> {
+        self.process
+            .stdout
+            .take()
+            .ok_or_else(|| Error::new(ErrorKind::Other, "Could not capture standard output."))
+    }
+
+    /// wait for `pg_restore` to exit - the error holds its standard error output, or the error of the dump copy
+    pub fn wait(mut self) -> Result<(), Error> {
+        let exit_status = self.process.wait()?;
+        let input = join_thread(self.input)?;
+        let stderr = join_thread(self.stderr)?;
+
+        if !exit_status.success() {
+            let reason = match stderr {
+                Ok(stderr) if !stderr.trim().is_empty() => stderr,
+                _ => exit_status.to_string(),
+            };
+
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!("command error: {}", reason),
+            ));
+        }
+
+        let _ = input?;
+
+        Ok(())
+    }
+}
+
+fn join_thread<T>(handle: JoinHandle<T>) -> Result<T, Error> {
+    handle
+        .join()
+        .map_err(|_| Error::new(ErrorKind::Other, "pg_restore thread panicked"))
+}
+
+/// spawn `pg_restore` to convert a custom-format dump into a plain SQL dump - read on [`PgRestore::stdout`]
+pub fn pg_restore<R: Read + Send + 'static>(mut reader: R) -> Result<PgRestore, Error> {
+    binary_exists("pg_restore")?;
+
+    let mut process = Command::new("pg_restore")
+        .args(["--no-owner", "-f", "-"]) // skip restoration of object ownership and write on stdout
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut stdin = process
+        .stdin
+        .take()
+        .ok_or_else(|| Error::new(ErrorKind::Other, "Could not capture standard input."))?;
+
+    let mut stderr = process
+        .stderr
+        .take()
+        .ok_or_else(|| Error::new(ErrorKind::Other, "Could not capture standard error."))?;
+
+    // the dump is written from another thread while the plain SQL dump is read -
+    // stdin is closed when the copy is over
+    let input = thread::spawn(move || io::copy(&mut reader, &mut stdin));
+
+    // stderr is drained so pg_restore never blocks on a full pipe
+    let stderr = thread::spawn(move || {
+        let mut buffer = String::new();
+        stderr.read_to_string(&mut buffer).map(|_| buffer)
+    });
+
+    Ok(PgRestore {
+        process,
+        input,
+        stderr,
+    })
+}
+
 pub fn subset<R: Read>(
-    mut dump_reader: BufReader<R>,
+    dump_reader: BufReader<R>,
     subset_config: &DatabaseSubsetConfig,
 ) -> Result<BufReader<File>, Error> {
+    with_postgres_subset(dump_reader, subset_config, |subset| {
+        let named_subset_file = tempfile::NamedTempFile::new()?;
+        let mut subset_file = named_subset_file.as_file();
+
+        let _ = subset.read(
+            |row| {
+                match subset_file.write(format!("{}\n", row).as_bytes()) {
+                    Ok(_) => {}
+                    Err(err) => {
+                        panic!("{}", err)
+                    }
+                };
+            },
+            |progress| {
+                info!("Database subset completion: {}%", progress.percent());
+            },
+        )?;
+
+        Ok(BufReader::new(
+            File::open(named_subset_file.path()).unwrap(),
+        ))
+    })
+}
+
+/// copy the dump into a temp file and build the subset from the configuration
+fn with_postgres_subset<R: Read, T, F: FnOnce(&PostgresSubset) -> Result<T, Error>>(
+    mut dump_reader: BufReader<R>,
+    subset_config: &DatabaseSubsetConfig,
+    f: F,
+) -> Result<T, Error> {
+    if subset_config.references.is_some() {
+        return Err(Error::new(
+            ErrorKind::Other,
+            "config error: `references` of `database_subset` is only supported with MongoDB - the PostgreSQL subset follows the foreign keys of the dump",
+        ));
+    }
+
     let mut named_temp_file = tempfile::NamedTempFile::new()?;
     let mut temp_dump_file = named_temp_file.as_file_mut();
     let _ = io::copy(&mut dump_reader, &mut temp_dump_file)?;
@@ -194,697 +764,5166 @@ pub fn subset<R: Read>(
         .map(|table| PassthroughTable::new(subset_config.database.as_str(), table.as_str()))
         .collect::<HashSet<_>>();
 
-    let subset_options = SubsetOptions::new(&passthrough_tables);
-    let subset = PostgresSubset::new(named_temp_file.path(), strategy, subset_options)?;
-
-    let named_subset_file = tempfile::NamedTempFile::new()?;
-    let mut subset_file = named_subset_file.as_file();
+    let mut subset_options = SubsetOptions::new(&passthrough_tables);
+    if let Some(workers) = subset_config.parallel_tables {
+        subset_options.set_parallel_tables(workers);
+    }
 
-    let _ = subset.read(
-        |row| {
-            match subset_file.write(format!("{}\n", row).as_bytes()) {
-                Ok(_) => {}
-                Err(err) => {
-                    panic!("{}", err)
-                }
-            };
-        },
-        |progress| {
-            info!("Database subset completion: {}%", progress.percent());
-        },
-    )?;
+    let subset = PostgresSubset::new(named_temp_file.path(), strategy, subset_options)?;
 
-    Ok(BufReader::new(
-        File::open(named_subset_file.path()).unwrap(),
-    ))
+    f(&subset)
 }
 
 /// consume reader and apply transformation on INSERT INTO queries if needed
 pub fn read_and_transform<R: Read, F: FnMut(OriginalQuery, Query)>(
+    mut reader: BufReader<R>,
+    options: SourceOptions,
+    query_callback: F,
+) -> Result<(), Error> {
+    if options
+        .skip_config
+        .iter()
+        .all(|skip| skip.references.is_none())
+    {
+        return transform(reader, options, &vec![], query_callback);
+    }
+
+    // the foreign keys are declared after the rows - the dump is read a first time to list them
+    let mut named_temp_file = tempfile::NamedTempFile::new()?;
+    let _ = io::copy(&mut reader, named_temp_file.as_file_mut())?;
+    let foreign_keys = list_foreign_keys(BufReader::new(File::open(named_temp_file.path())?))?;
+
+    transform(
+        BufReader::new(File::open(named_temp_file.path())?),
+        options,
+        &foreign_keys,
+        query_callback,
+    )
+}
+
+fn transform<R: Read, F: FnMut(OriginalQuery, Query)>(
     reader: BufReader<R>,
     options: SourceOptions,
+    foreign_keys: &Vec<ForeignKey>,
     mut query_callback: F,
-) {
+) -> Result<(), Error> {
     // create a map variable with Transformer by column_name
-    let mut transformer_by_db_and_table_and_column_name: HashMap<String, &Box<dyn Transformer>> =
-        HashMap::with_capacity(options.transformers.len());
+    let mut transformer_by_db_and_table_and_column_name = Transformers::new(options.transformers);
 
-    for transformer in options.transformers {
-        let _ = transformer_by_db_and_table_and_column_name.insert(
-            transformer.database_and_quoted_table_and_column_name(),
-            transformer,
-        );
-    }
+    // skipped tables may be glob patterns - they are expanded against the tables met in the dump
+    // (the CREATE TABLE statement first), and the result is kept to match each table only once
+    let mut skip_tables_map: HashMap<String, bool> = HashMap::new();
 
-    let mut skip_tables_map: HashMap<String, bool> =
-        HashMap::with_capacity(options.skip_config.len());
-    for skip in options.skip_config {
-        let _ = skip_tables_map.insert(format!("{}.{}", skip.database, skip.table), true);
-    }
+    let exclude_schemas: HashSet<&str> = options
+        .exclude_schemas
+        .iter()
+        .map(String::as_str)
+        .collect();
 
-    match list_sql_queries_from_dump_reader(reader, |query| {
-        let tokens = get_tokens_from_query_str(query);
+    let mut skip_table = |database_name: &str, table_name: &str| -> bool {
+        if exclude_schemas.contains(database_name) {
+            return true;
+        }
 
-        match get_row_type(&tokens) {
-            RowType::InsertInto {
-                database_name,
-                table_name,
-            } => {
-                if !skip_tables_map.contains_key(&format!("{}.{}", database_name, table_name)) {
-                    let (original_columns, columns) = transform_columns(
-                        database_name.as_str(),
-                        table_name.as_str(),
-                        &tokens,
-                        &transformer_by_db_and_table_and_column_name,
+        *skip_tables_map
+            .entry(format!("{}.{}", database_name, table_name))
+            .or_insert_with(|| {
+                options.skip_config.iter().any(|skip| {
+                    glob_match(skip.database.as_str(), database_name)
+                        && glob_match(skip.table.as_str(), table_name)
+                })
+            })
+    };
+
+    // the columns referencing a skipped table, by "<database>.<table>"
+    let mut dangling_references: HashMap<String, Vec<(String, DanglingReference)>> = HashMap::new();
+
+    // the boolean columns ("<database>.<table>.<column>") - their values are not always written as
+    // `true`/`false` (e.g. `t`/`f` in a COPY payload, '1'/'0' in a column-insert)
+    let mut boolean_columns: HashSet<String> = HashSet::new();
+
+    // the numeric columns ("<database>.<table>.<column>") with their type - their values are validated
+    let mut numeric_columns: HashMap<String, String> = HashMap::new();
+
+    let mut copy_payload: Option<CopyPayload> = None;
+    let mut buffered_rows: Option<BufferedRows> = None;
+    let mut cpu_limiter = options.max_cpu_percent.map(CpuLimiter::new);
+    let ddl_rewriter = DdlRewriter::new(options.ddl_rewrites);
+    let schema_only = options.content == Some(DumpContent::SchemaOnly);
+    let data_only = options.content == Some(DumpContent::DataOnly);
+    let string_escaping = options
+        .string_escaping
+        .unwrap_or(StringEscapingConfig::Ansi);
+    let mut sequence_tracker = match options.sequences {
+        Some(SequencesConfig::MaxId) => Some(SequenceTracker::default()),
+        _ => None,
+    };
+
+    // the large objects are data - like `pg_dump`, they are kept by a data-only dump
+    let keep_large_objects = !schema_only && !options.skip_large_objects;
+    // the data of the large objects is written in a transaction (`BEGIN; SELECT pg_catalog.lo_open(...); ...
+    // COMMIT;`) - a `BEGIN;` is held until the next statement tells if it opens this transaction
+    let mut pending_begin: Option<String> = None;
+    let mut is_large_objects_transaction = false;
+
+    list_sql_queries_from_dump_reader(reader, |query| {
+        if options.fail_fast && options.transformer_metrics.has_errors() {
+            return ListQueryResult::Break;
+        }
+
+        if let Some(cpu_limiter) = cpu_limiter.as_mut() {
+            cpu_limiter.throttle();
+        }
+
+        if let Some(payload) = copy_payload.as_mut() {
+            if let Some(sequence_tracker) = sequence_tracker.as_mut() {
+                if !payload.skip && query != COPY_PAYLOAD_TERMINATOR {
+                    sequence_tracker.track_copy_row(
+                        payload.database_name.as_str(),
+                        payload.table_name.as_str(),
+                        &payload.column_names,
+                        query,
                     );
+                }
+            }
 
-                    query_callback(
-                        to_query(
-                            Some(database_name.as_str()),
-                            InsertIntoQuery {
-                                table_name: table_name.to_string(),
-                                columns: original_columns,
-                            },
-                        ),
-                        to_query(
+            if query == COPY_PAYLOAD_TERMINATOR {
+                if !payload.skip {
+                    payload.flush(
+                        &transformer_by_db_and_table_and_column_name,
+                        options.transformer_metrics,
+                        query_callback.borrow_mut(),
+                    );
+
+                    no_change_query_callback(query_callback.borrow_mut(), query);
+                }
+
+                copy_payload = None;
+                return ListQueryResult::Continue;
+            }
+
+            let row = if payload.skip {
+                // the table is skipped - so are its rows
+                None
+            } else if !payload.dangling_references.is_empty() {
+                let row = if payload.has_transformers {
+                    transform_copy_row(
+                        payload,
+                        query,
+                        &transformer_by_db_and_table_and_column_name,
+                        &boolean_columns,
+                        &numeric_columns,
+                        options.transformer_metrics,
+                    )
+                } else {
+                    Query(query.as_bytes().to_vec())
+                };
+
+                let row = String::from_utf8_lossy(row.data()).to_string();
+                remove_dangling_copy_references(&payload.dangling_references, row.as_str())
+                    .map(|row| Query(row.into_bytes()))
+            } else if payload.has_transformers {
+                Some(transform_copy_row(
+                    payload,
+                    query,
+                    &transformer_by_db_and_table_and_column_name,
+                    &boolean_columns,
+                    &numeric_columns,
+                    options.transformer_metrics,
+                ))
+            } else {
+                // no transformer for this table - the row is kept byte-for-byte
+                Some(Query(query.as_bytes().to_vec()))
+            };
+
+            if let Some(row) = row {
+                let original_query = Query(query.as_bytes().to_vec());
+
+                if payload.all_values_columns.is_empty() {
+                    query_callback(original_query, row);
+                } else {
+                    payload.rows.push((original_query, row));
+                }
+            }
+
+            return ListQueryResult::Continue;
+        }
+
+        let tokens = get_tokens_from_query_str(query);
+        let row_type = get_row_type(&tokens);
+
+        // the buffered rows are written once all the rows of their table have been read
+        // (the line breaks between the rows do not count)
+        let is_buffered_table = match (&buffered_rows, &row_type) {
+            (
+                Some(rows),
+                RowType::InsertInto {
+                    database_name,
+                    table_name,
+                },
+            ) => rows.is_table(database_name, table_name),
+            (Some(_), RowType::Others) => query.trim().is_empty(),
+            _ => false,
+        };
+
+        if !is_buffered_table {
+            if let Some(rows) = buffered_rows.take() {
+                rows.flush(
+                    &transformer_by_db_and_table_and_column_name,
+                    options.transformer_metrics,
+                    query_callback.borrow_mut(),
+                );
+            }
+        }
+
+        let large_object_statement = get_large_object_statement(&tokens);
+
+        if let Some(begin) = pending_begin.take() {
+            if large_object_statement == Some(LargeObjectStatement::Data) {
+                is_large_objects_transaction = true;
+                if keep_large_objects {
+                    no_change_query_callback(query_callback.borrow_mut(), begin.as_str());
+                }
+            } else if !data_only {
+                no_change_query_callback(query_callback.borrow_mut(), begin.as_str());
+            }
+        }
+
+        if is_transaction_statement(&tokens, "BEGIN") && !is_large_objects_transaction {
+            pending_begin = Some(query.to_string());
+            return ListQueryResult::Continue;
+        }
+
+        if is_large_objects_transaction && is_transaction_statement(&tokens, "COMMIT") {
+            is_large_objects_transaction = false;
+            if keep_large_objects {
+                no_change_query_callback(query_callback.borrow_mut(), query);
+            }
+            return ListQueryResult::Continue;
+        }
+
+        if large_object_statement.is_some() {
+            // passed through as they are, in the order of the dump
+            if keep_large_objects {
+                no_change_query_callback(query_callback.borrow_mut(), query);
+            }
+            return ListQueryResult::Continue;
+        }
+
+        match row_type {
+            RowType::Copy {
+                database_name,
+                table_name,
+            } => {
+                let skip = schema_only || skip_table(database_name.as_str(), table_name.as_str());
+
+                if !skip {
+                    no_change_query_callback(query_callback.borrow_mut(), query);
+                }
+
+                let column_names = get_column_names_from_copy_query(&tokens);
+                let has_transformers = column_names.iter().any(|column_name| {
+                    transformer_by_db_and_table_and_column_name.contains_key(
+                        format!("{}.{}.{}", database_name, table_name, column_name).as_str(),
+                    )
+                });
+
+                let copy_dangling_references = dangling_references
+                    .get(format!("{}.{}", database_name, table_name).as_str())
+                    .map(|references| {
+                        references
+                            .iter()
+                            .filter_map(|(column_name, reference)| {
+                                column_names
+                                    .iter()
+                                    .position(|name| unquote(name) == unquote(column_name))
+                                    .map(|idx| (idx, *reference))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+
+                let all_values_columns = column_names
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, column_name)| {
+                        matches!(
+                            transformer_by_db_and_table_and_column_name.get(
+                                format!("{}.{}.{}", database_name, table_name, column_name)
+                                    .as_str()
+                            ),
+                            Some(transformer) if transformer.transforms_all_values()
+                        )
+                    })
+                    .map(|(idx, _)| idx)
+                    .collect::<Vec<_>>();
+
+                if !skip && !all_values_columns.is_empty() {
+                    warn!(
+                        "the rows of the table '{}.{}' are kept in memory to transform all the values of some columns at once",
+                        database_name, table_name
+                    );
+                }
+
+                copy_payload = Some(CopyPayload {
+                    database_name,
+                    table_name,
+                    column_names,
+                    skip,
+                    has_transformers,
+                    dangling_references: copy_dangling_references,
+                    all_values_columns,
+                    rows: vec![],
+                });
+            }
+            RowType::InsertInto {
+                database_name,
+                table_name,
+            } => {
+                if schema_only || skip_table(database_name.as_str(), table_name.as_str()) {
+                    // the table is skipped
+                    return ListQueryResult::Continue;
+                }
+
+                if let Some(sequence_tracker) = sequence_tracker.as_mut() {
+                    sequence_tracker.track_insert_into(
+                        database_name.as_str(),
+                        table_name.as_str(),
+                        &tokens,
+                    );
+                }
+
+                if !has_literal_values(&tokens) {
+                    // `DEFAULT VALUES`, expressions, sub-selects... can't be transformed safely
+                    no_change_query_callback(query_callback.borrow_mut(), query);
+                } else {
+                    let (original_columns, columns) = transform_columns(
+                        database_name.as_str(),
+                        table_name.as_str(),
+                        &tokens,
+                        &transformer_by_db_and_table_and_column_name,
+                        &boolean_columns,
+                        &numeric_columns,
+                        options.transformer_metrics,
+                    );
+
+                    let columns = match dangling_references
+                        .get(format!("{}.{}", database_name, table_name).as_str())
+                    {
+                        Some(references) => {
+                            match remove_dangling_references(references, columns) {
+                                Some(columns) => columns,
+                                // the row references a skipped table
+                                None => return ListQueryResult::Continue,
+                            }
+                        }
+                        None => columns,
+                    };
+
+                    let sort_column_name = find_sort_column_name(
+                        options.sort_rows,
+                        database_name.as_str(),
+                        table_name.as_str(),
+                    );
+
+                    let all_values_column_names = columns
+                        .iter()
+                        .map(|column| column.name())
+                        .filter(|column_name| {
+                            matches!(
+                                transformer_by_db_and_table_and_column_name.get(
+                                    format!("{}.{}.{}", database_name, table_name, column_name)
+                                        .as_str()
+                                ),
+                                Some(transformer) if transformer.transforms_all_values()
+                            )
+                        })
+                        .map(|column_name| column_name.to_string())
+                        .collect::<Vec<_>>();
+
+                    let original_query = to_query(
+                        Some(database_name.as_str()),
+                        InsertIntoQuery {
+                            table_name: table_name.to_string(),
+                            columns: original_columns,
+                        },
+                        string_escaping,
+                    );
+
+                    if sort_column_name.is_none() && all_values_column_names.is_empty() {
+                        let query = to_query(
                             Some(database_name.as_str()),
                             InsertIntoQuery {
                                 table_name: table_name.to_string(),
                                 columns,
                             },
-                        ),
-                    )
+                            string_escaping,
+                        );
+
+                        query_callback(original_query, query);
+                    } else {
+                        let rows = buffered_rows.get_or_insert_with(|| {
+                            if let Some(sort_column_name) = sort_column_name {
+                                warn!(
+                                    "the rows of the table '{}.{}' are kept in memory to be sorted by '{}'",
+                                    database_name, table_name, sort_column_name
+                                );
+                            }
+
+                            if !all_values_column_names.is_empty() {
+                                warn!(
+                                    "the rows of the table '{}.{}' are kept in memory to transform all the values of some columns at once",
+                                    database_name, table_name
+                                );
+                            }
+
+                            BufferedRows {
+                                database_name: database_name.clone(),
+                                table_name: table_name.clone(),
+                                sort_column_name: sort_column_name.map(str::to_string),
+                                all_values_column_names,
+                                string_escaping,
+                                rows: vec![],
+                            }
+                        });
+
+                        rows.rows.push((original_query, columns));
+                    }
                 }
             }
             RowType::CreateTable {
                 database_name,
                 table_name,
             } => {
-                if !skip_tables_map.contains_key(&format!("{}.{}", database_name, table_name)) {
-                    no_change_query_callback(query_callback.borrow_mut(), query);
+                let skip = skip_table(database_name.as_str(), table_name.as_str());
+
+                if !skip {
+                    for (column_name, column_type) in get_column_types_from_create_query(&tokens) {
+                        let db_and_table_and_column_name =
+                            format!("{}.{}.{}", database_name, table_name, column_name);
+
+                        if is_boolean_type(column_type.as_str()) {
+                            let _ = boolean_columns.insert(db_and_table_and_column_name);
+                        } else if is_numeric_type(column_type.as_str()) {
+                            let _ = numeric_columns
+                                .insert(db_and_table_and_column_name, column_type.to_lowercase());
+                        }
+                    }
+
+                    let references = find_dangling_references(
+                        options.skip_config,
+                        foreign_keys,
+                        database_name.as_str(),
+                        table_name.as_str(),
+                        &get_not_null_column_names_from_create_query(&tokens),
+                    );
+
+                    if !references.is_empty() {
+                        let _ = dangling_references
+                            .insert(format!("{}.{}", database_name, table_name), references);
+                    }
+                }
+
+                if !skip && !data_only {
+                    let transformed_query = if options.transform_default_values {
+                        transform_default_values(
+                            database_name.as_str(),
+                            table_name.as_str(),
+                            query,
+                            &tokens,
+                            &transformer_by_db_and_table_and_column_name,
+                            options.transformer_metrics,
+                        )
+                    } else {
+                        None
+                    };
+
+                    let transformed_query = ddl_rewriter
+                        .rewrite(transformed_query.as_deref().unwrap_or(query))
+                        .or(transformed_query);
+
+                    match transformed_query {
+                        Some(transformed_query) => query_callback(
+                            Query(query.as_bytes().to_vec()),
+                            Query(transformed_query.into_bytes()),
+                        ),
+                        None => no_change_query_callback(query_callback.borrow_mut(), query),
+                    }
                 }
             }
             RowType::AlterTable {
                 database_name,
                 table_name,
             } => {
-                if !skip_tables_map.contains_key(&format!("{}.{}", database_name, table_name)) {
+                // the foreign keys to a table skipped with its references would not be valid anymore
+                let is_dangling_foreign_key = match get_alter_table_foreign_key(&tokens) {
+                    Some(fk) => find_skip_references(
+                        options.skip_config,
+                        fk.to_database.as_str(),
+                        fk.to_table.as_str(),
+                    )
+                    .is_some(),
+                    None => false,
+                };
+
+                if !data_only
+                    && !skip_table(database_name.as_str(), table_name.as_str())
+                    && !is_dangling_foreign_key
+                {
+                    match ddl_rewriter.rewrite(query) {
+                        Some(rewritten_query) => query_callback(
+                            Query(query.as_bytes().to_vec()),
+                            Query(rewritten_query.into_bytes()),
+                        ),
+                        None => no_change_query_callback(query_callback.borrow_mut(), query),
+                    }
+                }
+            }
+            RowType::CommentOnColumn {
+                database_name,
+                table_name,
+                column_name,
+                comment,
+            } => {
+                let db_and_table_and_column_name =
+                    format!("{}.{}.{}", database_name, table_name, column_name);
+
+                // a transformer from the configuration always takes precedence over a tag
+                if !transformer_by_db_and_table_and_column_name.contains_key(
+                    db_and_table_and_column_name.as_str(),
+                ) {
+                    if let Some(column_tag) = find_column_tag(options.column_tags, &comment) {
+                        let transformer = column_tag.transformer.transformer(
+                            database_name.as_str(),
+                            table_name.as_str(),
+                            column_name.as_str(),
+                        );
+
+                        let _ = transformer_by_db_and_table_and_column_name
+                            .tagged
+                            .insert(db_and_table_and_column_name, transformer);
+                    }
+                }
+
+                if !data_only {
                     no_change_query_callback(query_callback.borrow_mut(), query);
                 }
             }
             RowType::Others => {
                 // other rows than `INSERT INTO ...` and `CREATE TABLE ...`
-                no_change_query_callback(query_callback.borrow_mut(), query);
+                let setval = match sequence_tracker.as_mut() {
+                    Some(sequence_tracker) => match sequence_tracker.add_owned_by(&tokens) {
+                        true => None,
+                        false => sequence_tracker.setval(&tokens),
+                    },
+                    None => None,
+                };
+
+                if (schema_only && is_setval_statement(&tokens))
+                    || (data_only && is_schema_statement(&tokens))
+                {
+                    // not part of the dump content
+                } else if let Some(setval) = setval {
+                    query_callback(Query(query.as_bytes().to_vec()), Query(setval.into_bytes()));
+                } else if !(options.minimal && is_session_noise(&tokens)) {
+                    no_change_query_callback(query_callback.borrow_mut(), query);
+                }
             }
         }
 
         ListQueryResult::Continue
-    }) {
-        Ok(_) => {}
-        Err(err) => panic!("{:?}", err),
+    })?;
+
+    if let Some(rows) = buffered_rows {
+        rows.flush(
+            &transformer_by_db_and_table_and_column_name,
+            options.transformer_metrics,
+            query_callback.borrow_mut(),
+        );
+    }
+
+    if let Some(begin) = pending_begin {
+        if !data_only {
+            no_change_query_callback(query_callback.borrow_mut(), begin.as_str());
+        }
     }
+
+    report_errors(options.transformer_metrics, options.fail_fast)
 }
 
-pub fn read_and_parse_schema<R: Read>(reader: BufReader<R>) -> Result<(), Error> {
-    match list_sql_queries_from_dump_reader(reader, |query| {
-        let tokens = get_tokens_from_query_str(query.clone());
-        match get_row_type(&tokens) {
-            RowType::CreateTable {
-                database_name,
-                table_name,
-            } => {
-                let column_schema = get_column_names_from_create_query(&tokens);
+/// true if the database, table or column name of the transformer is a glob pattern
+fn is_transformer_pattern(transformer: &dyn Transformer) -> bool {
+    [
+        transformer.database_name(),
+        transformer.table_name(),
+        transformer.column_name(),
+    ]
+    .iter()
+    .any(|name| name.contains(|c| c == '*' || c == '?'))
+}
 
-                let mut table = table();
-                table.set_titles(row!["Field"]);
+/// how specific the names of a transformer are - the number of names without a glob pattern, then the number of
+/// literal characters of its names
+fn transformer_specificity(transformer: &dyn Transformer) -> (usize, usize) {
+    let names = [
+        transformer.database_name(),
+        transformer.table_name(),
+        transformer.column_name(),
+    ];
 
-                column_schema.iter().for_each(|column_name| {
-                    table.add_row(row![column_name]);
-                });
+    let literal_names = names
+        .iter()
+        .filter(|name| !name.contains(|c| c == '*' || c == '?'))
+        .count();
 
-                println!(" Table {}", table_name);
+    let literal_chars = names
+        .iter()
+        .map(|name| name.chars().filter(|c| *c != '*' && *c != '?').count())
+        .sum();
 
-                let _ = table.printstd();
+    (literal_names, literal_chars)
+}
 
-                println!();
-            }
-            _ => {}
+/// fail on the first transformation error with `fail_fast` - the errors are logged otherwise
+fn report_errors(transformer_metrics: &TransformerMetrics, fail_fast: bool) -> Result<(), Error> {
+    let errors = transformer_metrics.errors();
+
+    if fail_fast && !errors.is_empty() {
+        return Err(Error::new(
+            ErrorKind::Other,
+            format!("transformation error: {}", errors[0]),
+        ));
+    }
+
+    if !errors.is_empty() {
+        warn!(
+            "{} transformation errors - the original values are kept:",
+            errors.len()
+        );
+
+        for error in errors {
+            warn!("  {}", error);
         }
+    }
 
-        ListQueryResult::Continue
-    }) {
-        Ok(_) => Ok(()),
-        Err(err) => panic!("{:?}", err),
+    // the values are written as they are in the dump - they are not transformation errors
+    let warnings = transformer_metrics.warnings();
+
+    if !warnings.is_empty() {
+        warn!(
+            "{} values do not match the type of their column - they are written as they are:",
+            warnings.len()
+        );
+
+        for warning in warnings {
+            warn!("  {}", warning);
+        }
     }
+
+    Ok(())
 }
 
-fn no_change_query_callback<F: FnMut(OriginalQuery, Query)>(query_callback: &mut F, query: &str) {
-    query_callback(
-        // there is no diff between the original and the modified one
-        Query(query.as_bytes().to_vec()),
-        Query(query.as_bytes().to_vec()),
-    );
+/// how the rows referencing a skipped table are handled - `None` if they are kept as they are
+fn find_skip_references(
+    skip_config: &Vec<SkipConfig>,
+    database_name: &str,
+    table_name: &str,
+) -> Option<SkipReferencesConfig> {
+    skip_config
+        .iter()
+        .find(|skip| {
+            skip.references.is_some()
+                && glob_match(skip.database.as_str(), database_name)
+                && glob_match(skip.table.as_str(), table_name)
+        })
+        .and_then(|skip| skip.references)
 }
 
-fn transform_columns(
+/// columns of a table referencing a table skipped with its references
+fn find_dangling_references(
+    skip_config: &Vec<SkipConfig>,
+    foreign_keys: &Vec<ForeignKey>,
     database_name: &str,
     table_name: &str,
-    tokens: &Vec<Token>,
-    transformer_by_db_and_table_and_column_name: &HashMap<String, &Box<dyn Transformer>>,
-) -> (Vec<Column>, Vec<Column>) {
-    // find database name by filtering out all queries starting with
-    // INSERT INTO <database>.<table> (...)
-    // INSERT       -> position 0
-    // INTO         -> position 2
-    // <table>      -> position 6
-    // L Paren      -> position X?
-    // R Paren      -> position X?
-    let column_names = get_column_names_from_insert_into_query(&tokens);
-    let column_values = get_column_values_from_insert_into_query(&tokens);
-    assert_eq!(
-        column_names.len(),
-        column_values.len(),
-        "Column names do not match values: got {} names and {} values",
-        column_names.len(),
-        column_values.len()
-    );
+    not_null_column_names: &Vec<String>,
+) -> Vec<(String, DanglingReference)> {
+    foreign_keys
+        .iter()
+        .filter(|fk| fk.from_database == database_name && fk.from_table == table_name)
+        .filter_map(|fk| {
+            let references =
+                find_skip_references(skip_config, fk.to_database.as_str(), fk.to_table.as_str())?;
 
-    let mut original_columns = vec![];
-    let mut columns = vec![];
+            let is_not_null = not_null_column_names
+                .iter()
+                .any(|name| unquote(name) == unquote(fk.from_property.as_str()));
 
-    for (i, column_name) in column_names.iter().enumerate() {
-        let value_token = column_values.get(i).unwrap();
-
-        let column = match value_token {
-            Token::Number(column_value, _) => {
-                if column_value.contains(".") {
-                    Column::FloatNumberValue(
-                        column_name.to_string(),
-                        column_value.parse::<f64>().unwrap(),
-                    )
-                } else {
-                    Column::NumberValue(
-                        column_name.to_string(),
-                        column_value.parse::<i128>().unwrap(),
-                    )
-                }
-            }
-            Token::Char(column_value) => {
-                Column::CharValue(column_name.to_string(), column_value.clone())
-            }
-            Token::SingleQuotedString(column_value) => {
-                Column::StringValue(column_name.to_string(), column_value.clone())
-            }
-            Token::NationalStringLiteral(column_value) => {
-                Column::StringValue(column_name.to_string(), column_value.clone())
-            }
-            Token::HexStringLiteral(column_value) => {
-                Column::StringValue(column_name.to_string(), column_value.clone())
-            }
-            Token::Word(w)
-                if (w.value == "true" || w.value == "false")
-                    && w.quote_style == None
-                    && w.keyword == NoKeyword =>
-            {
-                Column::BooleanValue(column_name.to_string(), w.value.parse::<bool>().unwrap())
+            let reference = match references {
+                SkipReferencesConfig::SetNull if !is_not_null => DanglingReference::SetNull,
+                _ => DanglingReference::DeleteRow,
+            };
+
+            Some((fk.from_property.clone(), reference))
+        })
+        .collect()
+}
+
+/// columns of an `INSERT INTO` row without their references to skipped tables - `None` if the row must be dropped
+fn remove_dangling_references(
+    references: &Vec<(String, DanglingReference)>,
+    columns: Vec<Column>,
+) -> Option<Vec<Column>> {
+    let mut new_columns = Vec::with_capacity(columns.len());
+
+    for column in columns {
+        let reference = references
+            .iter()
+            .find(|(column_name, _)| unquote(column_name) == unquote(column.name()))
+            .map(|(_, reference)| *reference);
+
+        match (reference, &column) {
+            // a NULL value does not reference anything
+            (_, Column::None(_)) | (None, _) => new_columns.push(column),
+            (Some(DanglingReference::SetNull), _) => {
+                new_columns.push(Column::None(column.name().to_string()))
             }
-            _ => Column::None(column_name.to_string()),
-        };
+            (Some(DanglingReference::DeleteRow), _) => return None,
+        }
+    }
 
-        // get the right transformer for the right column name
-        let original_column = column.clone();
+    Some(new_columns)
+}
 
-        let db_and_table_and_column_name =
-            format!("{}.{}.{}", database_name, table_name, *column_name);
-        let column = match transformer_by_db_and_table_and_column_name
-            .get(db_and_table_and_column_name.as_str())
-        {
-            Some(transformer) => transformer.transform(column), // apply transformation on the column
-            None => column,
-        };
+/// same as `remove_dangling_references` for a `COPY` payload row
+fn remove_dangling_copy_references(
+    references: &Vec<(usize, DanglingReference)>,
+    row: &str,
+) -> Option<String> {
+    let mut column_values = get_column_values_from_copy_row(row);
 
-        original_columns.push(original_column);
-        columns.push(column);
+    for (idx, reference) in references {
+        match (reference, column_values.get(*idx)) {
+            (_, None) | (_, Some(None)) => {}
+            (DanglingReference::SetNull, _) => column_values[*idx] = None,
+            (DanglingReference::DeleteRow, _) => return None,
+        }
     }
 
-    (original_columns, columns)
+    Some(to_copy_row(&column_values))
 }
 
-fn is_insert_into_statement(tokens: &Vec<Token>) -> bool {
-    match_keyword_at_position(Keyword::Insert, &tokens, 0)
-        && match_keyword_at_position(Keyword::Into, &tokens, 2)
+/// the column to sort the rows of a table by - if configured
+fn find_sort_column_name<'a>(
+    sort_rows: &'a Vec<SortRowsConfig>,
+    database_name: &str,
+    table_name: &str,
+) -> Option<&'a str> {
+    sort_rows
+        .iter()
+        .find(|sort| sort.database == unquote(database_name) && sort.table == unquote(table_name))
+        .map(|sort| sort.column.as_str())
 }
 
-fn is_create_table_statement(tokens: &Vec<Token>) -> bool {
-    match_keyword_at_position(Keyword::Create, &tokens, 0)
-        && match_keyword_at_position(Keyword::Table, &tokens, 2)
+fn unquote(name: &str) -> &str {
+    name.trim_matches('"')
 }
 
-fn is_alter_table_statement(tokens: &Vec<Token>) -> bool {
-    match_keyword_at_position(Keyword::Alter, &tokens, 0)
-        && match_keyword_at_position(Keyword::Table, &tokens, 2)
+/// order of two values of the sort column - NULL first, numbers by value and the others as strings
+fn compare_sort_values(a: &Column, b: &Column) -> Ordering {
+    match (a, b) {
+        (Column::None(_), Column::None(_)) => Ordering::Equal,
+        (Column::None(_), _) => Ordering::Less,
+        (_, Column::None(_)) => Ordering::Greater,
+        (Column::NumberValue(_, a), Column::NumberValue(_, b)) => a.cmp(b),
+        (a, b) => match (to_f64(a), to_f64(b)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            _ => sort_value_string(a).cmp(&sort_value_string(b)),
+        },
+    }
 }
 
-fn get_row_type(tokens: &Vec<Token>) -> RowType {
-    let mut row_type = RowType::Others;
+fn to_f64(column: &Column) -> Option<f64> {
+    match column {
+        Column::NumberValue(_, value) => Some(*value as f64),
+        Column::FloatNumberValue(_, value) => Some(*value),
+        _ => None,
+    }
+}
 
-    if is_insert_into_statement(&tokens) {
-        if let Some(database_name) = get_word_value_at_position(&tokens, 4) {
-            if let Some(table_name) = get_word_value_at_position(&tokens, 6) {
-                row_type = RowType::InsertInto {
-                    database_name: database_name.to_string(),
-                    table_name: table_name.to_string(),
-                };
+fn sort_value_string(column: &Column) -> String {
+    match column {
+        Column::NumberValue(_, value) => value.to_string(),
+        Column::FloatNumberValue(_, value) => value.to_string(),
+        Column::StringValue(_, value) => value.clone(),
+        Column::CharValue(_, value) => value.to_string(),
+        Column::BooleanValue(_, value) => value.to_string(),
+        Column::None(_) => String::new(),
+    }
+}
+
+/// count the rows and bytes of every table of a dump - `INSERT INTO` statements and `COPY` payload rows
+pub fn table_stats<R: Read>(reader: BufReader<R>) -> Result<Vec<TableStats>, Error> {
+    let mut stats = TablesStats::default();
+    let mut copy_table: Option<String> = None;
+
+    list_sql_queries_from_dump_reader(reader, |query| {
+        if let Some(table) = &copy_table {
+            if query == COPY_PAYLOAD_TERMINATOR {
+                copy_table = None;
+            } else {
+                // a payload row is a line as it is - its tabs must not be trimmed
+                stats.add_row(table.as_str(), query.len() + 1);
+            }
+
+            return ListQueryResult::Continue;
+        }
+
+        let tokens = get_tokens_from_query_str(query);
+
+        match get_row_type(&tokens) {
+            RowType::InsertInto {
+                database_name,
+                table_name,
+            } => {
+                stats.add_row(
+                    format!("{}.{}", database_name, table_name).as_str(),
+                    row_size(query),
+                );
+            }
+            RowType::Copy {
+                database_name,
+                table_name,
+            } => {
+                copy_table = Some(format!("{}.{}", database_name, table_name));
+            }
+            _ => {}
+        }
+
+        ListQueryResult::Continue
+    })?;
+
+    Ok(stats.into_sorted_vec())
+}
+
+/// what a query of a dump is to the rows of the tables
+#[derive(Debug, PartialEq)]
+pub enum TableRow {
+    /// `INSERT INTO` statement of the table `<database>.<table>`
+    Insert(String),
+    /// `COPY ... FROM stdin;` statement of the table `<database>.<table>` - its payload rows follow
+    CopyStart(String),
+    CopyRow,
+    /// `\.` - the end of the payload
+    CopyEnd,
+    Other,
+}
+
+/// follow the tables of the rows of a dump read one query at a time - the queries are not tokenized
+#[derive(Default)]
+pub struct RowTables {
+    in_copy_payload: bool,
+}
+
+impl RowTables {
+    pub fn next(&mut self, query: &str) -> TableRow {
+        if self.in_copy_payload {
+            if query == COPY_PAYLOAD_TERMINATOR {
+                self.in_copy_payload = false;
+                return TableRow::CopyEnd;
+            }
+
+            return TableRow::CopyRow;
+        }
+
+        if let Some(table) = query.strip_prefix("INSERT INTO ").and_then(qualified_name) {
+            return TableRow::Insert(table);
+        }
+
+        if query.trim_end().ends_with("FROM stdin;") {
+            if let Some(table) = query.strip_prefix("COPY ").and_then(qualified_name) {
+                self.in_copy_payload = true;
+                return TableRow::CopyStart(table);
             }
         }
+
+        TableRow::Other
     }
+}
 
-    if is_create_table_statement(&tokens) {
-        if let Some(database_name) = get_word_value_at_position(&tokens, 4) {
-            if let Some(table_name) = get_word_value_at_position(&tokens, 6) {
-                row_type = RowType::CreateTable {
-                    database_name: database_name.to_string(),
-                    table_name: table_name.to_string(),
-                };
+/// `<database>.<table>` at the start of `query` - the names keep their quotes
+fn qualified_name(query: &str) -> Option<String> {
+    let mut in_quotes = false;
+    let end = query
+        .char_indices()
+        .find(|(_, c)| {
+            if *c == '"' {
+                in_quotes = !in_quotes;
             }
+
+            !in_quotes && (c.is_whitespace() || *c == '(')
+        })
+        .map(|(idx, _)| idx)
+        .unwrap_or(query.len());
+
+    match &query[..end] {
+        name if name.contains('.') => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+/// database, table and columns of an `INSERT INTO` query - `None` for the other queries
+pub fn insert_into_columns(query: &str) -> Option<(String, String, Vec<Column>)> {
+    let tokens = get_tokens_from_query_str(query);
+
+    match get_row_type(&tokens) {
+        RowType::InsertInto {
+            database_name,
+            table_name,
+        } if has_literal_values(&tokens) => {
+            let column_names = get_column_names_from_insert_into_query(&tokens);
+            let column_values = get_column_values_from_insert_into_query(&tokens);
+
+            let columns = column_names
+                .iter()
+                .zip(column_values)
+                .map(|(column_name, value_token)| to_column(column_name, value_token))
+                .collect();
+
+            Some((database_name, table_name, columns))
         }
+        _ => None,
     }
+}
 
-    if is_alter_table_statement(&tokens) {
-        let database_name_pos = if match_keyword_at_position(Keyword::Only, &tokens, 4) {
-            6
-        } else {
-            4
-        };
+pub fn read_and_parse_schema<R: Read>(reader: BufReader<R>) -> Result<(), Error> {
+    match list_sql_queries_from_dump_reader(reader, |query| {
+        let tokens = get_tokens_from_query_str(query.clone());
+        match get_row_type(&tokens) {
+            RowType::CreateTable {
+                database_name,
+                table_name,
+            } => {
+                let column_schema = get_column_names_from_create_query(&tokens);
 
-        let table_name_pos = if match_keyword_at_position(Keyword::Only, &tokens, 4) {
-            8
-        } else {
-            6
-        };
+                let mut table = table();
+                table.set_titles(row!["Field"]);
 
-        if let Some(database_name) = get_word_value_at_position(&tokens, database_name_pos) {
-            if let Some(table_name) = get_word_value_at_position(&tokens, table_name_pos) {
-                row_type = RowType::AlterTable {
-                    database_name: database_name.to_string(),
-                    table_name: table_name.to_string(),
-                };
+                column_schema.iter().for_each(|column_name| {
+                    table.add_row(row![column_name]);
+                });
+
+                println!(" Table {}", table_name);
+
+                let _ = table.printstd();
+
+                println!();
             }
+            _ => {}
         }
+
+        ListQueryResult::Continue
+    }) {
+        Ok(_) => Ok(()),
+        Err(err) => panic!("{:?}", err),
     }
+}
 
-    row_type
+fn no_change_query_callback<F: FnMut(OriginalQuery, Query)>(query_callback: &mut F, query: &str) {
+    query_callback(
+        // there is no diff between the original and the modified one
+        Query(query.as_bytes().to_vec()),
+        Query(query.as_bytes().to_vec()),
+    );
 }
 
-fn to_query(database: Option<&str>, query: InsertIntoQuery) -> Query {
-    let mut column_names = Vec::with_capacity(query.columns.len());
-    let mut values = Vec::with_capacity(query.columns.len());
+fn transform_columns(
+    database_name: &str,
+    table_name: &str,
+    tokens: &Vec<Token>,
+    transformer_by_db_and_table_and_column_name: &Transformers,
+    boolean_columns: &HashSet<String>,
+    numeric_columns: &HashMap<String, String>,
+    transformer_metrics: &TransformerMetrics,
+) -> (Vec<Column>, Vec<Column>) {
+    // find database name by filtering out all queries starting with
+    // INSERT INTO <database>.<table> (...)
+    // INSERT       -> position 0
+    // INTO         -> position 2
+    // <table>      -> position 6
+    // L Paren      -> position X?
+    // R Paren      -> position X?
+    let column_names = get_column_names_from_insert_into_query(&tokens);
+    let column_values = get_column_values_from_insert_into_query(&tokens);
+    assert_eq!(
+        column_names.len(),
+        column_values.len(),
+        "Column names do not match values: got {} names and {} values",
+        column_names.len(),
+        column_values.len()
+    );
 
-    for column in query.columns {
-        match column {
-            Column::NumberValue(column_name, value) => {
-                column_names.push(column_name);
-                values.push(value.to_string());
-            }
-            Column::FloatNumberValue(column_name, value) => {
-                column_names.push(column_name);
-                values.push(value.to_string());
-            }
-            Column::StringValue(column_name, value) => {
-                column_names.push(column_name);
-                values.push(format!("'{}'", value.replace("'", "''")));
+    let mut original_columns = vec![];
+    let mut columns = vec![];
+
+    for (i, column_name) in column_names.iter().enumerate() {
+        let db_and_table_and_column_name =
+            format!("{}.{}.{}", database_name, table_name, *column_name);
+
+        let column = to_column(column_name, column_values.get(i).unwrap());
+        let column = if boolean_columns.contains(db_and_table_and_column_name.as_str()) {
+            to_boolean_column(column)
+        } else {
+            column
+        };
+
+        // get the right transformer for the right column name
+        let original_column = column.clone();
+        let column = match transformer_by_db_and_table_and_column_name
+            .get(db_and_table_and_column_name.as_str())
+        {
+            // apply transformation on the column - unless all its values are transformed at once,
+            // it is derived from another column of the row or keyed by another column of the row
+            Some(transformer)
+                if !transformer.transforms_all_values()
+                    && transformer.source_column().is_none()
+                    && transformer.key_column().is_none() =>
+            {
+                transform_column(transformer, column, None, transformer_metrics)
             }
-            Column::CharValue(column_name, value) => {
-                column_names.push(column_name);
-                values.push(format!("'{}'", value));
+            _ => column,
+        };
+
+        original_columns.push(original_column);
+        columns.push(column);
+    }
+
+    // the keyed columns are transformed from the original value of their key
+    for (i, column_name) in column_names.iter().enumerate() {
+        let db_and_table_and_column_name =
+            format!("{}.{}.{}", database_name, table_name, column_name);
+
+        if let Some(transformer) =
+            transformer_by_db_and_table_and_column_name.get(db_and_table_and_column_name.as_str())
+        {
+            if transformer.key_column().is_some() {
+                let key = find_key(
+                    transformer,
+                    &column_names,
+                    |idx| to_copy_value(original_columns[idx].clone()),
+                    transformer_metrics,
+                );
+
+                columns[i] = transform_column(
+                    transformer,
+                    columns[i].clone(),
+                    key.as_deref(),
+                    transformer_metrics,
+                );
             }
-            Column::BooleanValue(column_name, value) => {
-                column_names.push(column_name);
-                values.push(value.to_string());
+        }
+    }
+
+    // the derived columns are set once the other columns of the row are transformed
+    for (i, column_name) in column_names.iter().enumerate() {
+        let db_and_table_and_column_name =
+            format!("{}.{}.{}", database_name, table_name, column_name);
+
+        if let Some(transformer) =
+            transformer_by_db_and_table_and_column_name.get(db_and_table_and_column_name.as_str())
+        {
+            if let Some(source_column) =
+                find_source_column(transformer, &column_names, &columns, transformer_metrics)
+            {
+                columns[i] =
+                    transform_column(transformer, source_column, None, transformer_metrics);
             }
-            Column::None(column_name) => {
-                column_names.push(column_name);
-                values.push("NULL".to_string());
+        }
+    }
+
+    for (column_name, column) in column_names.iter().zip(&columns) {
+        let db_and_table_and_column_name =
+            format!("{}.{}.{}", database_name, table_name, column_name);
+
+        if let Some(column_type) = numeric_columns.get(db_and_table_and_column_name.as_str()) {
+            validate_numeric_value(
+                db_and_table_and_column_name.as_str(),
+                column_type.as_str(),
+                to_copy_value(column.clone()).as_deref(),
+                transformer_metrics,
+            );
+        }
+    }
+
+    (original_columns, columns)
+}
+
+/// column of the row a transformer derives its value from - `None` if the transformer does not derive its value
+/// from another column, or if the row has no such column (the value is kept as it is and the error is reported)
+fn find_source_column<S: AsRef<str>>(
+    transformer: &dyn Transformer,
+    column_names: &[S],
+    columns: &[Column],
+    transformer_metrics: &TransformerMetrics,
+) -> Option<Column> {
+    let source_column = transformer.source_column()?;
+
+    match column_names
+        .iter()
+        .position(|column_name| unquote(column_name.as_ref()) == unquote(source_column))
+    {
+        Some(idx) => columns.get(idx).cloned(),
+        None => {
+            transformer_metrics.add_error(format!(
+                "transformer '{}' can't find the column '{}' to derive the column '{}' from",
+                transformer.id(),
+                source_column,
+                transformer.database_and_table_and_column_name()
+            ));
+
+            None
+        }
+    }
+}
+
+/// value of the key column of the row a transformer is keyed by, as written in a COPY payload - `None` if the key is
+/// NULL, or if the row has no such column (the error is reported): the value is transformed without its key
+fn find_key<S: AsRef<str>, V: Fn(usize) -> Option<String>>(
+    transformer: &dyn Transformer,
+    column_names: &[S],
+    key_value: V,
+    transformer_metrics: &TransformerMetrics,
+) -> Option<String> {
+    let key_column = transformer.key_column()?;
+
+    match column_names
+        .iter()
+        .position(|column_name| unquote(column_name.as_ref()) == unquote(key_column))
+    {
+        Some(idx) => key_value(idx),
+        None => {
+            transformer_metrics.add_error(format!(
+                "transformer '{}' can't find the key column '{}' of the column '{}'",
+                transformer.id(),
+                key_column,
+                transformer.database_and_table_and_column_name()
+            ));
+
+            None
+        }
+    }
+}
+
+/// column of an `INSERT INTO ...` query from its name and its value token
+pub fn to_column(column_name: &str, value_token: &Token) -> Column {
+    match value_token {
+        Token::Number(column_value, _) => {
+            if column_value.contains(".") {
+                Column::FloatNumberValue(
+                    column_name.to_string(),
+                    column_value.parse::<f64>().unwrap(),
+                )
+            } else {
+                Column::NumberValue(
+                    column_name.to_string(),
+                    column_value.parse::<i128>().unwrap(),
+                )
             }
         }
+        Token::Char(column_value) => {
+            Column::CharValue(column_name.to_string(), column_value.clone())
+        }
+        // the tokenizer keeps the quotes escaped ('') - they are escaped again by `to_query`
+        Token::SingleQuotedString(column_value) => {
+            Column::StringValue(column_name.to_string(), column_value.replace("''", "'"))
+        }
+        Token::NationalStringLiteral(column_value) => {
+            Column::StringValue(column_name.to_string(), column_value.replace("''", "'"))
+        }
+        Token::HexStringLiteral(column_value) => {
+            Column::StringValue(column_name.to_string(), column_value.clone())
+        }
+        Token::Word(w) if is_boolean_word(w) => Column::BooleanValue(
+            column_name.to_string(),
+            w.value.eq_ignore_ascii_case("true"),
+        ),
+        _ => Column::None(column_name.to_string()),
     }
+}
 
-    let query_prefix = match database {
-        Some(database) => format!("INSERT INTO {}.", database),
-        None => "INSERT INTO ".to_string(),
+/// `true`/`false` keywords - whatever their case
+fn is_boolean_word(word: &Word) -> bool {
+    word.quote_style == None
+        && word.keyword == NoKeyword
+        && (word.value.eq_ignore_ascii_case("true") || word.value.eq_ignore_ascii_case("false"))
+}
+
+fn is_boolean_type(column_type: &str) -> bool {
+    column_type.eq_ignore_ascii_case("boolean") || column_type.eq_ignore_ascii_case("bool")
+}
+
+fn is_integer_type(column_type: &str) -> bool {
+    [
+        "smallint",
+        "integer",
+        "int",
+        "int2",
+        "int4",
+        "int8",
+        "bigint",
+        "smallserial",
+        "serial",
+        "serial2",
+        "serial4",
+        "serial8",
+        "bigserial",
+    ]
+    .iter()
+    .any(|integer_type| column_type.eq_ignore_ascii_case(integer_type))
+}
+
+fn is_numeric_type(column_type: &str) -> bool {
+    is_integer_type(column_type)
+        || [
+            "numeric", "decimal", "real", "float", "float4", "float8", "double",
+        ]
+        .iter()
+        .any(|numeric_type| column_type.eq_ignore_ascii_case(numeric_type))
+}
+
+/// flag a value which can't be restored in its numeric column - e.g. a text in a `bigint` column is
+/// the symptom of values written in the wrong columns. The value is kept as it is.
+fn validate_numeric_value(
+    db_and_table_and_column_name: &str,
+    column_type: &str,
+    value: Option<&str>,
+    transformer_metrics: &TransformerMetrics,
+) {
+    let value = match value {
+        Some(value) => value.trim(),
+        // NULL
+        None => return,
     };
 
-    let query_string = format!(
-        "{}{} ({}) VALUES ({});",
-        query_prefix,
-        query.table_name.as_str(),
+    let is_valid = if is_integer_type(column_type) {
+        value.parse::<i128>().is_ok()
+    } else {
+        // `NaN` and `Infinity` included
+        value.parse::<f64>().is_ok()
+    };
+
+    if !is_valid {
+        transformer_metrics.add_warning(format!(
+            "a value of the {} column '{}' is not a number",
+            column_type, db_and_table_and_column_name
+        ));
+    }
+}
+
+/// boolean value of a boolean column, as accepted by PostgreSQL: `t`/`f` (COPY), `true`/`false`
+/// (column-inserts) and `1`/`0` - `None` for anything else
+fn parse_boolean(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "t" | "true" | "1" => Some(true),
+        "f" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// the value of a boolean column written as a string or a number (e.g. '1') is turned into a boolean
+fn to_boolean_column(column: Column) -> Column {
+    let value = match &column {
+        Column::StringValue(_, value) => parse_boolean(value.as_str()),
+        Column::NumberValue(_, value) => parse_boolean(value.to_string().as_str()),
+        _ => None,
+    };
+
+    match value {
+        Some(value) => Column::BooleanValue(column.name().to_string(), value),
+        None => column,
+    }
+}
+
+/// apply the transformers on the string literals of the `DEFAULT` expressions of a `CREATE TABLE` query.
+/// Only the literals are replaced, the rest of the query is kept as it is - `None` if nothing is transformed
+fn transform_default_values(
+    database_name: &str,
+    table_name: &str,
+    query: &str,
+    tokens: &Vec<Token>,
+    transformer_by_db_and_table_and_column_name: &Transformers,
+    transformer_metrics: &TransformerMetrics,
+) -> Option<String> {
+    let literal_tokens = tokens
+        .iter()
+        .filter(|token| is_quoted_literal(token))
+        .collect::<Vec<_>>();
+
+    let spans = quoted_literal_spans(query);
+    if spans.len() != literal_tokens.len() {
+        warn!(
+            "the default values of the table '{}.{}' can't be located - they are kept as they are",
+            database_name, table_name
+        );
+        return None;
+    }
+
+    let mut transformed_query = query.to_string();
+    let mut is_transformed = false;
+
+    // from the last literal to the first one, to keep the positions of the previous ones valid
+    for (column_name, literal_idx) in get_default_value_literals_from_create_query(tokens)
+        .into_iter()
+        .rev()
+    {
+        let db_and_table_and_column_name =
+            format!("{}.{}.{}", database_name, table_name, column_name);
+
+        let transformer = match transformer_by_db_and_table_and_column_name
+            .get(db_and_table_and_column_name.as_str())
+        {
+            Some(transformer) => transformer,
+            None => continue,
+        };
+
+        let value = match literal_tokens[literal_idx] {
+            Token::SingleQuotedString(value) | Token::NationalStringLiteral(value) => {
+                value.replace("''", "'")
+            }
+            _ => continue,
+        };
+
+        let column = Column::StringValue(column_name, value);
+        if let Column::StringValue(_, value) =
+            transform_column(transformer, column, None, transformer_metrics)
+        {
+            // the content between the quotes is replaced
+            let (start, end) = spans[literal_idx];
+            transformed_query.replace_range(start + 1..end - 1, value.replace('\'', "''").as_str());
+            is_transformed = true;
+        }
+    }
+
+    if is_transformed {
+        Some(transformed_query)
+    } else {
+        None
+    }
+}
+
+fn is_quoted_literal(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::SingleQuotedString(_) | Token::NationalStringLiteral(_) | Token::HexStringLiteral(_)
+    )
+}
+
+/// string literals of the `DEFAULT` expressions of a `CREATE TABLE` query by column name -
+/// a literal is referenced by its position among all the quoted literals of the query
+fn get_default_value_literals_from_create_query(tokens: &Vec<Token>) -> Vec<(String, usize)> {
+    let mut literals = vec![];
+    let mut depth = 0;
+    let mut literal_idx = 0;
+    let mut column_name: Option<String> = None;
+    let mut in_default = false;
+
+    for token in tokens {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => {
+                depth -= 1;
+                if depth == 0 {
+                    in_default = false;
+                }
+            }
+            // next column definition
+            Token::Comma if depth == 1 => {
+                column_name = None;
+                in_default = false;
+            }
+            Token::Word(word) if depth == 1 => {
+                match word.value.to_uppercase().as_str() {
+                    _ if column_name.is_none() => column_name = Some(word.value.clone()),
+                    "DEFAULT" => in_default = true,
+                    // the default expression is over - the constraints are kept as they are
+                    "NOT" | "NULL" | "CONSTRAINT" | "CHECK" | "REFERENCES" | "PRIMARY"
+                    | "UNIQUE" | "COLLATE" | "GENERATED" => in_default = false,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        if is_quoted_literal(token) {
+            if let (true, Some(column_name)) = (in_default, &column_name) {
+                literals.push((column_name.clone(), literal_idx));
+            }
+
+            literal_idx += 1;
+        }
+    }
+
+    literals
+}
+
+/// positions (start and end, quotes included) of the quoted literals of a query -
+/// quoted identifiers and comments are skipped
+fn quoted_literal_spans(query: &str) -> Vec<(usize, usize)> {
+    let mut spans = vec![];
+    let mut chars = query.char_indices().peekable();
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                // the quote is escaped by doubling it
+                let mut end = query.len();
+                while let Some((quote_idx, quote)) = chars.next() {
+                    if quote == c {
+                        if chars.peek().map(|(_, next)| *next) == Some(c) {
+                            let _ = chars.next();
+                        } else {
+                            end = quote_idx + 1;
+                            break;
+                        }
+                    }
+                }
+
+                if c == '\'' {
+                    spans.push((idx, end));
+                }
+            }
+            '-' if chars.peek().map(|(_, next)| *next) == Some('-') => {
+                for (_, comment) in chars.by_ref() {
+                    if comment == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek().map(|(_, next)| *next) == Some('*') => {
+                let _ = chars.next();
+                let mut previous = ' ';
+                for (_, comment) in chars.by_ref() {
+                    if previous == '*' && comment == '/' {
+                        break;
+                    }
+                    previous = comment;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    spans
+}
+
+/// apply a transformer on a column - if the transformer panics, the failure is reported and the
+/// original value is kept, instead of crashing the whole dump
+fn transform_column(
+    transformer: &dyn Transformer,
+    column: Column,
+    key: Option<&str>,
+    transformer_metrics: &TransformerMetrics,
+) -> Column {
+    if !transformer.should_transform() {
+        return column;
+    }
+
+    transformer_metrics.increment(transformer);
+
+    let original_column = column.clone();
+    match catch_unwind(AssertUnwindSafe(|| match key {
+        Some(key) => transformer.transform_with_key(column, key),
+        None => transformer.transform(column),
+    })) {
+        Ok(column) => column,
+        Err(err) => {
+            let reason = match err.downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => match err.downcast_ref::<String>() {
+                    Some(message) => message.clone(),
+                    None => "unknown error".to_string(),
+                },
+            };
+
+            transformer_metrics.add_error(format!(
+                "transformer '{}' failed on column '{}' ({})",
+                transformer.id(),
+                transformer.database_and_table_and_column_name(),
+                reason
+            ));
+
+            transformer_metrics.increment_failure(transformer);
+            original_column
+        }
+    }
+}
+
+/// apply a transformer on all the values of a column at once - the original values are kept if it fails
+fn transform_all_values(
+    transformer: &dyn Transformer,
+    columns: Vec<Column>,
+    transformer_metrics: &TransformerMetrics,
+) -> Vec<Column> {
+    for _ in 0..columns.len() {
+        transformer_metrics.increment(transformer);
+    }
+
+    let original_columns = columns.clone();
+    let reason = match catch_unwind(AssertUnwindSafe(|| transformer.transform_all(columns))) {
+        Ok(columns) if columns.len() == original_columns.len() => return columns,
+        Ok(columns) => format!(
+            "got {} values instead of {}",
+            columns.len(),
+            original_columns.len()
+        ),
+        Err(err) => match err.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match err.downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "unknown error".to_string(),
+            },
+        },
+    };
+
+    transformer_metrics.add_error(format!(
+        "transformer '{}' failed on all the values of column '{}' ({})",
+        transformer.id(),
+        transformer.database_and_table_and_column_name(),
+        reason
+    ));
+
+    for _ in 0..original_columns.len() {
+        transformer_metrics.increment_failure(transformer);
+    }
+
+    original_columns
+}
+
+/// apply the transformers on a row of a COPY payload.
+/// Only the columns with a transformer are parsed, the other values are kept as they are.
+fn transform_copy_row(
+    copy_payload: &CopyPayload,
+    row: &str,
+    transformer_by_db_and_table_and_column_name: &Transformers,
+    boolean_columns: &HashSet<String>,
+    numeric_columns: &HashMap<String, String>,
+    transformer_metrics: &TransformerMetrics,
+) -> Query {
+    let mut column_values = get_column_values_from_copy_row(row);
+    if copy_payload.column_names.len() != column_values.len() {
+        transformer_metrics.add_error(format!(
+            "a row of the table '{}.{}' has {} values instead of {}",
+            copy_payload.database_name,
+            copy_payload.table_name,
+            column_values.len(),
+            copy_payload.column_names.len()
+        ));
+
+        return Query(row.as_bytes().to_vec());
+    }
+
+    // the keyed columns are transformed first - while their key has its original value
+    for (i, column_name) in copy_payload.column_names.iter().enumerate() {
+        let db_and_table_and_column_name = format!(
+            "{}.{}.{}",
+            copy_payload.database_name, copy_payload.table_name, column_name
+        );
+
+        let transformer = match transformer_by_db_and_table_and_column_name
+            .get(db_and_table_and_column_name.as_str())
+        {
+            Some(transformer) if transformer.key_column().is_some() => transformer,
+            _ => continue,
+        };
+
+        let key = find_key(
+            transformer,
+            &copy_payload.column_names,
+            |idx| column_values[idx].clone(),
+            transformer_metrics,
+        );
+
+        let is_boolean_column = boolean_columns.contains(db_and_table_and_column_name.as_str());
+        let column = to_copy_column(column_name, column_values[i].take(), is_boolean_column);
+
+        column_values[i] = to_copy_value(transform_column(
+            transformer,
+            column,
+            key.as_deref(),
+            transformer_metrics,
+        ));
+    }
+
+    for (i, column_name) in copy_payload.column_names.iter().enumerate() {
+        let db_and_table_and_column_name = format!(
+            "{}.{}.{}",
+            copy_payload.database_name, copy_payload.table_name, column_name
+        );
+
+        let transformer = match transformer_by_db_and_table_and_column_name
+            .get(db_and_table_and_column_name.as_str())
+        {
+            Some(transformer)
+                if !transformer.transforms_all_values()
+                    && transformer.source_column().is_none()
+                    && transformer.key_column().is_none() =>
+            {
+                transformer
+            }
+            _ => continue,
+        };
+
+        let is_boolean_column = boolean_columns.contains(db_and_table_and_column_name.as_str());
+        let column = to_copy_column(column_name, column_values[i].take(), is_boolean_column);
+
+        column_values[i] = to_copy_value(transform_column(
+            transformer,
+            column,
+            None,
+            transformer_metrics,
+        ));
+    }
+
+    // the derived columns are set once the other columns of the row are transformed
+    for (i, column_name) in copy_payload.column_names.iter().enumerate() {
+        let db_and_table_and_column_name = format!(
+            "{}.{}.{}",
+            copy_payload.database_name, copy_payload.table_name, column_name
+        );
+
+        let transformer = match transformer_by_db_and_table_and_column_name
+            .get(db_and_table_and_column_name.as_str())
+        {
+            Some(transformer) if transformer.source_column().is_some() => transformer,
+            _ => continue,
+        };
+
+        // the values are copied as they are written - not typed
+        let columns = copy_payload
+            .column_names
+            .iter()
+            .zip(&column_values)
+            .map(|(column_name, value)| match value {
+                Some(value) => Column::StringValue(column_name.to_string(), value.clone()),
+                None => Column::None(column_name.to_string()),
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(source_column) = find_source_column(
+            transformer,
+            &copy_payload.column_names,
+            &columns,
+            transformer_metrics,
+        ) {
+            column_values[i] = to_copy_value(transform_column(
+                transformer,
+                source_column,
+                None,
+                transformer_metrics,
+            ));
+        }
+    }
+
+    for (column_name, value) in copy_payload.column_names.iter().zip(&column_values) {
+        let db_and_table_and_column_name = format!(
+            "{}.{}.{}",
+            copy_payload.database_name, copy_payload.table_name, column_name
+        );
+
+        if let Some(column_type) = numeric_columns.get(db_and_table_and_column_name.as_str()) {
+            validate_numeric_value(
+                db_and_table_and_column_name.as_str(),
+                column_type.as_str(),
+                value.as_deref(),
+                transformer_metrics,
+            );
+        }
+    }
+
+    Query(to_copy_row(&column_values).into_bytes())
+}
+
+/// column of a COPY payload row from its name and its value - `None` is a NULL value
+fn to_copy_column(column_name: &str, value: Option<String>, is_boolean_column: bool) -> Column {
+    match value {
+        Some(value) if is_boolean_column && parse_boolean(value.as_str()).is_some() => {
+            Column::BooleanValue(
+                column_name.to_string(),
+                parse_boolean(value.as_str()).unwrap(),
+            )
+        }
+        // COPY values are not typed - numbers are guessed from the value itself
+        Some(value) => match value.parse::<i128>() {
+            Ok(number) => Column::NumberValue(column_name.to_string(), number),
+            Err(_) => match value.parse::<f64>() {
+                Ok(number) if value.contains(".") => {
+                    Column::FloatNumberValue(column_name.to_string(), number)
+                }
+                _ => Column::StringValue(column_name.to_string(), value),
+            },
+        },
+        None => Column::None(column_name.to_string()),
+    }
+}
+
+/// value of a COPY payload row - `None` is a NULL value
+fn to_copy_value(column: Column) -> Option<String> {
+    match column {
+        Column::NumberValue(_, value) => Some(value.to_string()),
+        Column::FloatNumberValue(_, value) => Some(value.to_string()),
+        Column::StringValue(_, value) => Some(value),
+        Column::CharValue(_, value) => Some(value.to_string()),
+        Column::BooleanValue(_, value) => Some(if value { "t" } else { "f" }.to_string()),
+        Column::None(_) => None,
+    }
+}
+
+fn is_insert_into_statement(tokens: &Vec<Token>) -> bool {
+    match_keyword_at_position(Keyword::Insert, &tokens, 0)
+        && match_keyword_at_position(Keyword::Into, &tokens, 2)
+}
+
+/// check that an `INSERT INTO ...` has one value per column and that every value is a literal
+/// e.g. `INSERT INTO public.t (a, b) VALUES (1, 'x');` -> true
+/// `INSERT INTO public.t DEFAULT VALUES;` or `INSERT INTO public.t (a) VALUES (now());` -> false
+pub fn has_literal_values(tokens: &Vec<Token>) -> bool {
+    let column_names = get_column_names_from_insert_into_query(&tokens);
+    if column_names.is_empty() {
+        return false;
+    }
+
+    let mut tokens = tokens
+        .iter()
+        .skip_while(|token| match token {
+            Token::Word(word) => word.keyword != Keyword::Values,
+            _ => true,
+        })
+        .skip(1) // VALUES
+        .filter(|token| !matches!(token, Token::Whitespace(_)));
+
+    if tokens.next() != Some(&Token::LParen) {
+        return false;
+    }
+
+    let mut total_values = 0usize;
+    loop {
+        let is_literal = match tokens.next() {
+            Some(Token::Number(_, _))
+            | Some(Token::SingleQuotedString(_))
+            | Some(Token::NationalStringLiteral(_))
+            | Some(Token::HexStringLiteral(_)) => true,
+            Some(Token::Word(w)) => {
+                (w.quote_style == None && w.keyword == Keyword::Null) || is_boolean_word(w)
+            }
+            _ => false,
+        };
+
+        if !is_literal {
+            return false;
+        }
+
+        total_values += 1;
+
+        match tokens.next() {
+            Some(Token::Comma) => continue,
+            Some(Token::RParen) => break,
+            _ => return false,
+        }
+    }
+
+    // nothing but the end of the statement is expected after the values
+    total_values == column_names.len() && tokens.all(|token| *token == Token::SemiColon)
+}
+
+/// session settings changing how the dump is read and executed - they are kept by a minimal dump
+const RESTORE_SETTINGS: &[&str] = &[
+    "client_encoding",
+    "standard_conforming_strings",
+    "check_function_bodies",
+];
+
+/// comments, blank lines, `SET ...` and `SELECT pg_catalog.set_config(...)` statements that can be
+/// dropped without changing the restored database - `SELECT pg_catalog.setval(...)` and the settings
+/// of `RESTORE_SETTINGS` are kept
+fn is_session_noise(tokens: &Vec<Token>) -> bool {
+    let tokens = tokens
+        .iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_)))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let first_word = match get_word_value_at_position(&tokens, 0) {
+        Some(word) => word,
+        // nothing but comments and whitespaces
+        None => return tokens.is_empty(),
+    };
+
+    if first_word.eq_ignore_ascii_case("SET") {
+        return match get_word_value_at_position(&tokens, 1) {
+            Some(setting) => !RESTORE_SETTINGS
+                .iter()
+                .any(|kept| setting.eq_ignore_ascii_case(kept)),
+            None => false,
+        };
+    }
+
+    first_word.eq_ignore_ascii_case("SELECT")
+        && get_word_value_at_position(&tokens, 1) == Some("pg_catalog")
+        && tokens.get(2) == Some(&Token::Period)
+        && get_word_value_at_position(&tokens, 3) == Some("set_config")
+}
+
+/// `SELECT pg_catalog.setval(...)` statement - the value of a sequence is part of the data
+fn is_setval_statement(tokens: &Vec<Token>) -> bool {
+    let tokens = tokens
+        .iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_)))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    match get_word_value_at_position(&tokens, 0) {
+        Some(first_word) => {
+            first_word.eq_ignore_ascii_case("SELECT")
+                && get_word_value_at_position(&tokens, 1) == Some("pg_catalog")
+                && tokens.get(2) == Some(&Token::Period)
+                && get_word_value_at_position(&tokens, 3) == Some("setval")
+        }
+        None => false,
+    }
+}
+
+/// statement changing the schema (e.g. `CREATE INDEX`, `GRANT`) - the comments, the session settings and the
+/// `SELECT` statements are not
+fn is_schema_statement(tokens: &Vec<Token>) -> bool {
+    match tokens
+        .iter()
+        .find(|token| !matches!(token, Token::Whitespace(_)))
+    {
+        Some(Token::Word(word)) => {
+            !word.value.eq_ignore_ascii_case("SET") && !word.value.eq_ignore_ascii_case("SELECT")
+        }
+        Some(_) => true,
+        None => false,
+    }
+}
+
+/// `BEGIN;` or `COMMIT;` statement - `keyword` is the first and only word of the statement
+fn is_transaction_statement(tokens: &Vec<Token>, keyword: &str) -> bool {
+    let mut words = tokens.iter().filter_map(|token| match token {
+        Token::Word(word) => Some(word.value.as_str()),
+        Token::Whitespace(_) | Token::SemiColon => None,
+        _ => Some(""),
+    });
+
+    matches!(words.next(), Some(word) if word.eq_ignore_ascii_case(keyword))
+        && words.next().is_none()
+}
+
+/// statement creating or filling a large object (`pg_largeobject`) - see `LargeObjectStatement`
+fn get_large_object_statement(tokens: &Vec<Token>) -> Option<LargeObjectStatement> {
+    let tokens = tokens
+        .iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_)))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let first_word = get_word_value_at_position(&tokens, 0)?;
+
+    if first_word.eq_ignore_ascii_case("SELECT") {
+        if get_word_value_at_position(&tokens, 1) != Some("pg_catalog")
+            || tokens.get(2) != Some(&Token::Period)
+        {
+            return None;
+        }
+
+        return match get_word_value_at_position(&tokens, 3) {
+            Some("lo_open") | Some("lowrite") | Some("lo_close") => {
+                Some(LargeObjectStatement::Data)
+            }
+            Some("lo_create") | Some("lo_unlink") => Some(LargeObjectStatement::Definition),
+            _ => None,
+        };
+    }
+
+    // `ALTER LARGE OBJECT ...`, `COMMENT ON LARGE OBJECT ...`, `GRANT ... ON LARGE OBJECT ...`
+    if !["ALTER", "COMMENT", "GRANT", "REVOKE", "SECURITY"]
+        .iter()
+        .any(|keyword| first_word.eq_ignore_ascii_case(keyword))
+    {
+        return None;
+    }
+
+    let is_large_object = tokens.windows(2).any(|words| match words {
+        [Token::Word(large), Token::Word(object)] => {
+            large.quote_style.is_none()
+                && large.value.eq_ignore_ascii_case("LARGE")
+                && object.quote_style.is_none()
+                && object.value.eq_ignore_ascii_case("OBJECT")
+        }
+        _ => false,
+    });
+
+    match is_large_object {
+        true => Some(LargeObjectStatement::Definition),
+        false => None,
+    }
+}
+
+fn is_create_table_statement(tokens: &Vec<Token>) -> bool {
+    match_keyword_at_position(Keyword::Create, &tokens, 0)
+        && match_keyword_at_position(Keyword::Table, &tokens, 2)
+}
+
+fn is_copy_statement(tokens: &Vec<Token>) -> bool {
+    match_keyword_at_position(Keyword::Copy, &tokens, 0)
+}
+
+fn is_alter_table_statement(tokens: &Vec<Token>) -> bool {
+    match_keyword_at_position(Keyword::Alter, &tokens, 0)
+        && match_keyword_at_position(Keyword::Table, &tokens, 2)
+}
+
+fn is_comment_on_column_statement(tokens: &Vec<Token>) -> bool {
+    match_keyword_at_position(Keyword::Comment, &tokens, 0)
+        && match_keyword_at_position(Keyword::On, &tokens, 2)
+        && match_keyword_at_position(Keyword::Column, &tokens, 4)
+        && match_keyword_at_position(Keyword::Is, &tokens, 12)
+}
+
+fn get_row_type(tokens: &Vec<Token>) -> RowType {
+    let mut row_type = RowType::Others;
+
+    if is_insert_into_statement(&tokens) {
+        if let Some(database_name) = get_word_value_at_position(&tokens, 4) {
+            if let Some(table_name) = get_word_value_at_position(&tokens, 6) {
+                row_type = RowType::InsertInto {
+                    database_name: database_name.to_string(),
+                    table_name: table_name.to_string(),
+                };
+            }
+        }
+    }
+
+    if is_create_table_statement(&tokens) {
+        if let Some(database_name) = get_word_value_at_position(&tokens, 4) {
+            if let Some(table_name) = get_word_value_at_position(&tokens, 6) {
+                row_type = RowType::CreateTable {
+                    database_name: database_name.to_string(),
+                    table_name: table_name.to_string(),
+                };
+            }
+        }
+    }
+
+    if is_copy_statement(&tokens) {
+        if let Some(database_name) = get_word_value_at_position(&tokens, 2) {
+            if let Some(table_name) = get_word_value_at_position(&tokens, 4) {
+                row_type = RowType::Copy {
+                    database_name: database_name.to_string(),
+                    table_name: table_name.to_string(),
+                };
+            }
+        }
+    }
+
+    if is_alter_table_statement(&tokens) {
+        let database_name_pos = if match_keyword_at_position(Keyword::Only, &tokens, 4) {
+            6
+        } else {
+            4
+        };
+
+        let table_name_pos = if match_keyword_at_position(Keyword::Only, &tokens, 4) {
+            8
+        } else {
+            6
+        };
+
+        if let Some(database_name) = get_word_value_at_position(&tokens, database_name_pos) {
+            if let Some(table_name) = get_word_value_at_position(&tokens, table_name_pos) {
+                row_type = RowType::AlterTable {
+                    database_name: database_name.to_string(),
+                    table_name: table_name.to_string(),
+                };
+            }
+        }
+    }
+
+    if is_comment_on_column_statement(&tokens) {
+        if let Some(database_name) = get_word_value_at_position(&tokens, 6) {
+            if let Some(table_name) = get_word_value_at_position(&tokens, 8) {
+                // keep the column name escaping - like the column names of `INSERT INTO ...`
+                let column_name = match tokens.get(10) {
+                    Some(Token::Word(word)) => match word.quote_style {
+                        Some(quote) => Some(format!("{}{}{}", quote, word.value, quote)),
+                        None => Some(word.value.clone()),
+                    },
+                    _ => None,
+                };
+
+                // `COMMENT ON COLUMN ... IS NULL` removes the comment
+                let comment = match tokens.get(14) {
+                    Some(Token::SingleQuotedString(comment)) => Some(comment.clone()),
+                    _ => None,
+                };
+
+                if let (Some(column_name), Some(comment)) = (column_name, comment) {
+                    row_type = RowType::CommentOnColumn {
+                        database_name: database_name.to_string(),
+                        table_name: table_name.to_string(),
+                        column_name,
+                        comment,
+                    };
+                }
+            }
+        }
+    }
+
+    row_type
+}
+
+/// find the first configured tag present in a column comment - e.g. `pii:email` in `'contact address, pii:email'`
+fn find_column_tag<'a>(
+    column_tags: &'a Vec<ColumnTagConfig>,
+    comment: &str,
+) -> Option<&'a ColumnTagConfig> {
+    let words = comment
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|word| !word.is_empty())
+        .collect::<HashSet<_>>();
+
+    column_tags
+        .iter()
+        .find(|column_tag| words.contains(column_tag.tag.as_str()))
+}
+
+pub fn to_query(
+    database: Option<&str>,
+    query: InsertIntoQuery,
+    string_escaping: StringEscapingConfig,
+) -> Query {
+    let mut column_names = Vec::with_capacity(query.columns.len());
+    let mut values = Vec::with_capacity(query.columns.len());
+
+    for column in query.columns {
+        match column {
+            Column::NumberValue(column_name, value) => {
+                column_names.push(column_name);
+                values.push(value.to_string());
+            }
+            Column::FloatNumberValue(column_name, value) => {
+                column_names.push(column_name);
+                values.push(value.to_string());
+            }
+            Column::StringValue(column_name, value) => {
+                column_names.push(column_name);
+                values.push(to_string_literal(value.as_str(), string_escaping));
+            }
+            Column::CharValue(column_name, value) => {
+                column_names.push(column_name);
+                values.push(format!("'{}'", value));
+            }
+            Column::BooleanValue(column_name, value) => {
+                column_names.push(column_name);
+                values.push(value.to_string());
+            }
+            Column::None(column_name) => {
+                column_names.push(column_name);
+                values.push("NULL".to_string());
+            }
+        }
+    }
+
+    let query_prefix = match database {
+        Some(database) => format!("INSERT INTO {}.", database),
+        None => "INSERT INTO ".to_string(),
+    };
+
+    let query_string = format!(
+        "{}{} ({}) VALUES ({});",
+        query_prefix,
+        query.table_name.as_str(),
         column_names.join(", "),
         values.join(", "),
     );
 
-    Query(query_string.into_bytes())
-}
+    Query(query_string.into_bytes())
+}
+
+/// a quoted string literal - its quotes (and backslashes) escaped the configured way. The backslash escaped
+/// strings are `E'...'` literals, whatever the `standard_conforming_strings` setting of the session
+fn to_string_literal(value: &str, string_escaping: StringEscapingConfig) -> String {
+    match string_escaping {
+        StringEscapingConfig::Ansi => format!("'{}'", value.replace('\'', "''")),
+        StringEscapingConfig::Backslash => {
+            format!("E'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::io::BufReader;
+    use std::str;
+    use std::time::Instant;
+    use std::vec;
+
+    use crate::config::{
+        ColumnTagConfig, DatabaseSubsetConfig, DatabaseSubsetConfigStrategy,
+        DatabaseSubsetConfigStrategyRandom, DdlRewriteConfig, DdlRewriteReplaceTypeConfig,
+        OnlyTablesConfig, SequencesConfig, SkipConfig, SkipReferencesConfig, SortRowsConfig,
+        StringEscapingConfig, TransformerTypeConfig,
+    };
+    use crate::datastore::DumpContent;
+    use crate::source::pii_detector::{DataClass, PiiDetector};
+    use crate::source::postgres::{
+        insert_into_columns, read_and_transform, to_query, Postgres, RowTables, TableRow,
+    };
+    use crate::source::{SourceOptions, TableStats};
+    use crate::transformer::consistent_fake::{
+        ConsistentFakeKind, ConsistentFakeTransformer, ConsistentFakeTransformerOptions,
+    };
+    use crate::transformer::derive_from_column::{
+        DeriveFromColumnTransformer, DeriveFromColumnTransformerOptions,
+    };
+    use crate::transformer::keep_first_char::KeepFirstCharTransformer;
+    use crate::transformer::random::RandomTransformer;
+    use crate::transformer::shuffle::ShuffleTransformerOptions;
+    use crate::transformer::transient::TransientTransformer;
+    use crate::transformer::{Transformer, TransformerMetrics};
+    use crate::types::{to_bytes, Column, InsertIntoQuery};
+    use crate::Source;
+
+    const COPY_DUMP: &str = "COPY public.categories (category_id, category_name, description, picture) FROM stdin;
+1\tBeverages\tSoft drinks, coffees; teas\\tbeers\t\\N
+2\tCondiments\tit's \"sweet\" and (savory\t\\N
+\\.
+";
+
+    fn get_postgres() -> Postgres<'static> {
+        Postgres::new("localhost", 5432, "root", "root", "password")
+    }
+
+    fn get_invalid_postgres() -> Postgres<'static> {
+        Postgres::new("localhost", 5432, "root", "root", "wrongpassword")
+    }
+
+    #[test]
+    fn connect() {
+        let p = get_postgres();
+        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
+        let transformers = vec![t1];
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        assert!(p.read(source_options, |original_query, query| {}).is_ok());
+
+        let p = get_invalid_postgres();
+        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
+        let transformers = vec![t1];
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        assert!(p.read(source_options, |original_query, query| {}).is_err());
+    }
+
+    #[test]
+    fn list_rows() {
+        let p = get_postgres();
+        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
+        let transformers = vec![t1];
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let _ = p.read(source_options, |original_query, query| {
+            assert!(original_query.data().len() > 0);
+            assert!(query.data().len() > 0);
+        });
+    }
+
+    #[test]
+    fn test_to_row() {
+        let query = to_query(
+            None,
+            InsertIntoQuery {
+                table_name: "test".to_string(),
+                columns: vec![Column::StringValue(
+                    "first_name".to_string(),
+                    "romaric".to_string(),
+                )],
+            },
+            StringEscapingConfig::Ansi,
+        );
+
+        assert_eq!(
+            query.data(),
+            b"INSERT INTO test (first_name) VALUES ('romaric');"
+        );
+
+        let query = to_query(
+            None,
+            InsertIntoQuery {
+                table_name: "test".to_string(),
+                columns: vec![Column::StringValue(
+                    r#""firstName""#.to_string(),
+                    "romaric".to_string(),
+                )],
+            },
+            StringEscapingConfig::Ansi,
+        );
+        assert_eq!(
+            query.data(),
+            b"INSERT INTO test (\"firstName\") VALUES ('romaric');"
+        );
+
+        let query = to_query(
+            None,
+            InsertIntoQuery {
+                table_name: "test".to_string(),
+                columns: vec![Column::BooleanValue("is_valid".to_string(), true)],
+            },
+            StringEscapingConfig::Ansi,
+        );
+
+        assert_eq!(query.data(), b"INSERT INTO test (is_valid) VALUES (true);");
+
+        let query = to_query(
+            Some("public"),
+            InsertIntoQuery {
+                table_name: "test".to_string(),
+                columns: vec![
+                    Column::StringValue("first_name".to_string(), "romaric".to_string()),
+                    Column::FloatNumberValue("height_in_meters".to_string(), 1.78),
+                ],
+            },
+            StringEscapingConfig::Ansi,
+        );
+
+        assert_eq!(
+            query.data(),
+            b"INSERT INTO public.test (first_name, height_in_meters) VALUES ('romaric', 1.78);"
+        );
+
+        let query = to_query(
+            Some("public"),
+            InsertIntoQuery {
+                table_name: "test".to_string(),
+                columns: vec![
+                    Column::None("first_name".to_string()),
+                    Column::FloatNumberValue("height_in_meters".to_string(), 1.78),
+                ],
+            },
+            StringEscapingConfig::Ansi,
+        );
+
+        assert_eq!(
+            query.data(),
+            b"INSERT INTO public.test (first_name, height_in_meters) VALUES (NULL, 1.78);"
+        );
+
+        let query = to_query(
+            Some("public"),
+            InsertIntoQuery {
+                table_name: "test".to_string(),
+                columns: vec![
+                    Column::StringValue("first_name".to_string(), "romaric".to_string()),
+                    Column::FloatNumberValue("height_in_meters".to_string(), 1.78),
+                    Column::StringValue(
+                        "description".to_string(),
+                        "I'd like to say... I don't know.".to_string(),
+                    ),
+                ],
+            },
+            StringEscapingConfig::Ansi,
+        );
+
+        assert_eq!(
+            query.data(),
+            b"INSERT INTO public.test (first_name, height_in_meters, description) \
+            VALUES ('romaric', 1.78, 'I''d like to say... I don''t know.');"
+        );
+    }
+
+    #[test]
+    fn list_rows_and_hide_last_name() {
+        let p = get_postgres();
+
+        let database_name = "public";
+        let table_name = "employees";
+        let column_name_to_obfuscate = "last_name";
+
+        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
+        let t2: Box<dyn Transformer> = Box::new(RandomTransformer::new(
+            database_name,
+            table_name,
+            column_name_to_obfuscate,
+        ));
+
+        let transformers = vec![t1, t2];
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let _ = p.read(source_options, |original_query, query| {
+            assert!(query.data().len() > 0);
+            assert!(query.data().len() > 0);
+
+            let query_str = str::from_utf8(query.data()).unwrap();
+
+            if query_str.contains(database_name)
+                && query_str.contains(table_name)
+                && query_str.starts_with("INSERT INTO")
+            {
+                assert_ne!(query.data(), original_query.data());
+                // TODO to complete to better check the column change only
+            } else {
+                assert_eq!(query.data(), original_query.data());
+            }
+        });
+    }
+
+    #[test]
+    fn skip_table() {
+        let p = get_postgres();
+
+        let database_name = "public";
+        let table_name = "employees";
+
+        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
+        let skip_employees_table = SkipConfig {
+            database: database_name.to_string(),
+            table: table_name.to_string(),
+            references: None,
+        };
+
+        let transformers = vec![t1];
+        let skip_config = vec![skip_employees_table];
+
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &skip_config,
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let _ = p.read(source_options, |_original_query, query| {
+            assert!(query.data().len() > 0);
+            assert!(query.data().len() > 0);
+
+            let query_str = str::from_utf8(query.data()).unwrap();
+            let unexpected_insert_into = format!("INSERT INTO {}.{}", database_name, table_name);
+            let unexpected_create_table = format!("CREATE TABLE {}.{}", database_name, table_name);
+            let unexpected_alter_table = format!("ALTER TABLE {}.{}", database_name, table_name);
+            let unexpected_alter_table_only =
+                format!("ALTER TABLE ONLY {}.{}", database_name, table_name);
+
+            if query_str.contains(unexpected_insert_into.as_str()) {
+                panic!("unexpected insert into: {}", unexpected_insert_into);
+            }
+
+            if query_str.contains(unexpected_create_table.as_str()) {
+                panic!("unexpected create table: {}", unexpected_create_table);
+            }
+
+            if query_str.contains(unexpected_alter_table.as_str()) {
+                panic!("unexpected alter table: {}", unexpected_alter_table);
+            }
+
+            if query_str.contains(unexpected_alter_table_only.as_str()) {
+                panic!(
+                    "unexpected alter table only: {}",
+                    unexpected_alter_table_only
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn subset_estimate_with_dump_args() {
+        let database_subset = Some(DatabaseSubsetConfig {
+            database: "public".to_string(),
+            table: "orders".to_string(),
+            strategy: DatabaseSubsetConfigStrategy::Random(DatabaseSubsetConfigStrategyRandom {
+                percent: 50,
+            }),
+            passthrough_tables: None,
+            parallel_tables: None,
+            references: None,
+        });
+        let only_tables = vec![OnlyTablesConfig {
+            database: "public".to_string(),
+            table: "orders".to_string(),
+        }];
+        let transformers = vec![];
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &database_subset,
+            only_tables: &only_tables,
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut p = get_postgres();
+
+        // only the tables of the dump are estimated
+        let estimate = p.subset_estimate(&source_options).unwrap();
+        assert!(estimate.keys().all(|table| table == "public.orders"));
+
+        // the dump arguments are checked as for the dump
+        p.set_extra_args(vec!["--column-inserts".to_string()]);
+        assert!(p.subset_estimate(&source_options).is_err());
+
+        p.set_extra_args(vec![]);
+        p.set_use_copy(true);
+        assert!(p.subset_estimate(&source_options).is_err());
+    }
+
+    #[test]
+    fn subset_options() {
+        let p = get_postgres();
+        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
+
+        let source_options = SourceOptions {
+            transformers: &vec![t1],
+            skip_config: &vec![],
+            database_subset: &Some(DatabaseSubsetConfig {
+                database: "public".to_string(),
+                table: "orders".to_string(),
+                strategy: DatabaseSubsetConfigStrategy::Random(
+                    DatabaseSubsetConfigStrategyRandom { percent: 50 },
+                ),
+                passthrough_tables: None,
+                parallel_tables: None,
+                references: None,
+            }),
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut rows_percent_50 = vec![];
+        let _ = p.read(source_options, |_original_query, query| {
+            assert!(query.data().len() > 0);
+            rows_percent_50.push(String::from_utf8_lossy(query.data().as_slice()).to_string());
+        });
+
+        let x = rows_percent_50
+            .iter()
+            .filter(|x| x.contains("INSERT INTO"))
+            .map(|x| x.as_str())
+            .collect::<HashSet<_>>();
+
+        let y = rows_percent_50
+            .iter()
+            .filter(|x| x.contains("INSERT INTO"))
+            .map(|x| x.as_str())
+            .collect::<Vec<_>>();
+
+        // check that there is no duplicated rows
+        assert_eq!(x.len(), y.len());
+
+        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
+
+        let source_options = SourceOptions {
+            transformers: &vec![t1],
+            skip_config: &vec![],
+            database_subset: &Some(DatabaseSubsetConfig {
+                database: "public".to_string(),
+                table: "orders".to_string(),
+                strategy: DatabaseSubsetConfigStrategy::Random(
+                    DatabaseSubsetConfigStrategyRandom { percent: 30 },
+                ),
+                passthrough_tables: None,
+                parallel_tables: None,
+                references: None,
+            }),
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut rows_percent_30 = vec![];
+        let _ = p.read(source_options, |_original_query, query| {
+            assert!(query.data().len() > 0);
+            rows_percent_30.push(String::from_utf8_lossy(query.data().as_slice()).to_string());
+        });
+
+        // check that there is no duplicated rows
+        assert_eq!(
+            rows_percent_30
+                .iter()
+                .filter(|x| x.contains("INSERT INTO"))
+                .collect::<HashSet<_>>()
+                .len(),
+            rows_percent_30
+                .iter()
+                .filter(|x| x.contains("INSERT INTO"))
+                .collect::<Vec<_>>()
+                .len(),
+        );
+
+        assert!(rows_percent_30.len() < rows_percent_50.len());
+    }
+
+    #[test]
+    fn read_and_transform_copy_payload_without_transformer() {
+        let t1: Box<dyn Transformer> = Box::new(KeepFirstCharTransformer::new(
+            "public",
+            "employees",
+            "last_name",
+        ));
+
+        let transformers = vec![t1];
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(COPY_DUMP.as_bytes()),
+            source_options,
+            |original_query, query| {
+                assert_eq!(original_query, query);
+                queries.push(query);
+            },
+        )
+        .unwrap();
+
+        // the COPY table has no transformer - it must be passed through byte-for-byte
+        assert_eq!(to_bytes(queries), COPY_DUMP.as_bytes());
+    }
+
+    #[test]
+    fn read_and_transform_binary_copy_payload() {
+        let mut dump = b"COPY public.categories (category_id, category_name) FROM stdin WITH (FORMAT binary);\n".to_vec();
+        dump.extend_from_slice(b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0\xff\xff");
+
+        let source_options = SourceOptions {
+            transformers: &vec![],
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let err = read_and_transform(BufReader::new(dump.as_slice()), source_options, |_, _| {})
+            .unwrap_err();
+
+        assert!(err.to_string().contains("binary format are not supported"));
+        assert!(err.to_string().contains("WITH (FORMAT binary)"));
+    }
+
+    #[test]
+    fn read_and_transform_copy_payload_with_transformer() {
+        let t1: Box<dyn Transformer> = Box::new(KeepFirstCharTransformer::new(
+            "public",
+            "categories",
+            "category_name",
+        ));
+
+        let transformers = vec![t1];
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(COPY_DUMP.as_bytes()),
+            source_options,
+            |_original_query, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            queries,
+            vec![
+                "COPY public.categories (category_id, category_name, description, picture) FROM stdin;",
+                "1\tB\tSoft drinks, coffees; teas\\tbeers\t\\N",
+                "2\tC\tit's \"sweet\" and (savory\t\\N",
+                "\\.",
+            ]
+        );
+    }
+
+    #[test]
+    fn exclude_schemas_args() {
+        let exclude_schemas = vec!["cron".to_string(), "audit".to_string()];
+
+        assert_eq!(
+            super::exclude_schemas_args(&exclude_schemas),
+            vec!["--exclude-schema=cron", "--exclude-schema=audit"]
+        );
+        assert!(super::exclude_schemas_args(&vec![]).is_empty());
+    }
+
+    #[test]
+    fn privileges_args() {
+        let mut p = get_postgres();
+        assert_eq!(p.privileges_args(), vec!["--no-owner"]);
+
+        p.set_privileges(false);
+        assert_eq!(p.privileges_args(), vec!["--no-owner", "--no-privileges"]);
+
+        p.set_owner(true);
+        assert_eq!(p.privileges_args(), vec!["--no-privileges"]);
+
+        p.set_privileges(true);
+        assert!(p.privileges_args().is_empty());
+    }
+
+    #[test]
+    fn dump_args_with_extra_args() {
+        let only_tables = vec![OnlyTablesConfig {
+            database: "public".to_string(),
+            table: "orders".to_string(),
+        }];
+        let transformers = vec![];
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &only_tables,
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut p = get_postgres();
+        p.set_extra_args(vec![
+            "--disable-triggers".to_string(),
+            "--serializable-deferrable".to_string(),
+        ]);
+
+        assert_eq!(
+            p.dump_args(&source_options).unwrap(),
+            vec![
+                "--column-inserts",
+                "-h",
+                "localhost",
+                "-p",
+                "5432",
+                "-U",
+                "root",
+                "--no-owner",
+                "--table=public.orders",
+                "--disable-triggers",
+                "--serializable-deferrable",
+                "root",
+            ]
+        );
+
+        // the managed arguments can't be overridden
+        for extra_arg in ["--column-inserts", "--format=custom", "-Fc", "--no-acl", "-p5433"] {
+            p.set_extra_args(vec![extra_arg.to_string()]);
+            assert!(p.dump_args(&source_options).is_err());
+        }
+
+        p.set_extra_args(vec!["--disable-triggers".to_string()]);
+        assert!(p.read(source_options, |_, _| {}).is_ok());
+    }
+
+    #[test]
+    fn dump_args_with_copy() {
+        let read = |p: &Postgres, sort_rows: &Vec<SortRowsConfig>, read: bool| {
+            let transformers = vec![];
+            let source_options = SourceOptions {
+                transformers: &transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows,
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let dump_args = p.dump_args(&source_options);
+
+            let mut queries = vec![];
+            if read {
+                p.read(source_options, |_, query| {
+                    queries.push(str::from_utf8(query.data()).unwrap().to_string())
+                })
+                .unwrap();
+            }
+
+            (dump_args, queries)
+        };
+
+        let mut p = get_postgres();
+        p.set_use_copy(true);
+
+        let (dump_args, queries) = read(&p, &vec![], true);
+        assert_eq!(
+            dump_args.unwrap(),
+            vec![
+                "-h",
+                "localhost",
+                "-p",
+                "5432",
+                "-U",
+                "root",
+                "--no-owner",
+                "root"
+            ]
+        );
+        assert!(queries.iter().any(|query| query.starts_with("COPY ")));
+        assert!(queries
+            .iter()
+            .all(|query| !query.starts_with("INSERT INTO ")));
+
+        // only the INSERT INTO rows are sorted
+        let sort_rows = vec![SortRowsConfig {
+            database: "public".to_string(),
+            table: "orders".to_string(),
+            column: "order_id".to_string(),
+        }];
+        let (dump_args, _) = read(&p, &sort_rows, false);
+        assert!(dump_args.is_err());
+    }
+
+    #[test]
+    fn dump_without_privileges_and_owner() {
+        let queries = |p: &Postgres| {
+            let transformers = vec![];
+            let source_options = SourceOptions {
+                transformers: &transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let mut queries = vec![];
+            p.read(source_options, |_, query| {
+                queries.push(str::from_utf8(query.data()).unwrap().to_string())
+            })
+            .unwrap();
+
+            queries
+        };
+
+        let mut p = get_postgres();
+        p.set_privileges(false);
+        let dump = queries(&p);
+        assert!(!dump.is_empty());
+        assert!(dump
+            .iter()
+            .all(|query| !query.starts_with("GRANT ") && !query.starts_with("REVOKE ")));
+        assert!(dump.iter().all(|query| !query.contains(" OWNER TO ")));
+
+        p.set_owner(true);
+        let dump = queries(&p);
+        assert!(dump.iter().any(|query| query.contains(" OWNER TO ")));
+    }
+
+    #[test]
+    fn only_tables_args() {
+        let only_tables = vec![
+            OnlyTablesConfig {
+                database: "public".to_string(),
+                table: "orders".to_string(),
+            },
+            OnlyTablesConfig {
+                database: "public".to_string(),
+                table: "*_log".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            super::only_tables_args(&only_tables),
+            vec!["--table=public.orders", "--table=public.*_log"]
+        );
+    }
+
+    #[test]
+    fn read_and_transform_nulls_references_to_skipped_tables() {
+        let dump = "CREATE TABLE public.customers (id integer NOT NULL);
+CREATE TABLE public.orders (id integer NOT NULL, customer_id integer);
+CREATE TABLE public.invoices (id integer NOT NULL, customer_id integer NOT NULL);
+CREATE TABLE public.notes (id integer NOT NULL, customer_id integer, body text);
+
+INSERT INTO public.customers (id) VALUES (1);
+INSERT INTO public.orders (id, customer_id) VALUES (1, 1);
+INSERT INTO public.orders (id, customer_id) VALUES (2, NULL);
+INSERT INTO public.invoices (id, customer_id) VALUES (1, 1);
+
+COPY public.notes (id, customer_id, body) FROM stdin;
+1\t1\tcall back
+2\t\\N\tnew lead
+\\.
+
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT fk_orders_customers FOREIGN KEY (customer_id) REFERENCES public.customers(id);
+ALTER TABLE ONLY public.invoices
+    ADD CONSTRAINT fk_invoices_customers FOREIGN KEY (customer_id) REFERENCES public.customers(id);
+ALTER TABLE ONLY public.notes
+    ADD CONSTRAINT fk_notes_customers FOREIGN KEY (customer_id) REFERENCES public.customers(id);
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT orders_pkey PRIMARY KEY (id);
+";
+
+        let skip_config = vec![SkipConfig {
+            database: "public".to_string(),
+            table: "customers".to_string(),
+            references: Some(SkipReferencesConfig::SetNull),
+        }];
+        let source_options = SourceOptions {
+            transformers: &vec![],
+            skip_config: &skip_config,
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+            },
+        )
+        .unwrap();
+
+        let queries = queries
+            .iter()
+            .map(|query| query.trim())
+            .filter(|query| !query.is_empty() && !query.starts_with("CREATE TABLE"))
+            .collect::<Vec<_>>();
+
+        // the nullable references are set to NULL, the rows with a NOT NULL reference are dropped
+        assert_eq!(
+            queries,
+            vec![
+                "INSERT INTO public.orders (id, customer_id) VALUES (1, NULL);",
+                "INSERT INTO public.orders (id, customer_id) VALUES (2, NULL);",
+                "COPY public.notes (id, customer_id, body) FROM stdin;",
+                "1\t\\N\tcall back",
+                "2\t\\N\tnew lead",
+                "\\.",
+                "ALTER TABLE ONLY public.orders\n    ADD CONSTRAINT orders_pkey PRIMARY KEY (id);",
+            ]
+        );
+    }
+
+    #[test]
+    fn read_and_transform_skips_tables_matching_a_glob() {
+        let dump = "CREATE TABLE public.users (id integer NOT NULL);
+CREATE TABLE public.users_log (id integer NOT NULL);
+CREATE TABLE audit.login_log (id integer NOT NULL);
+
+INSERT INTO public.users (id) VALUES (1);
+INSERT INTO public.users_log (id) VALUES (1);
+INSERT INTO audit.login_log (id) VALUES (1);
+INSERT INTO public.log_settings (id) VALUES (1);
+
+COPY public.orders_log (id) FROM stdin;
+1
+\\.
+ALTER TABLE ONLY public.users_log ADD CONSTRAINT users_log_pkey PRIMARY KEY (id);
+";
+
+        let skip_config = vec![SkipConfig {
+            database: "*".to_string(),
+            table: "*_log".to_string(),
+            references: None,
+        }];
+        let source_options = SourceOptions {
+            transformers: &vec![],
+            skip_config: &skip_config,
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+            },
+        )
+        .unwrap();
+
+        let queries = queries
+            .iter()
+            .map(|query| query.trim())
+            .filter(|query| !query.is_empty())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            queries,
+            vec![
+                "CREATE TABLE public.users (id integer NOT NULL);",
+                "INSERT INTO public.users (id) VALUES (1);",
+                "INSERT INTO public.log_settings (id) VALUES (1);",
+            ]
+        );
+    }
+
+    #[test]
+    fn row_tables() {
+        let mut row_tables = RowTables::default();
+
+        for (query, expected) in [
+            (
+                "CREATE TABLE public.users (id integer NOT NULL);",
+                TableRow::Other,
+            ),
+            (
+                "INSERT INTO public.users (id) VALUES (1);",
+                TableRow::Insert("public.users".to_string()),
+            ),
+            (
+                "INSERT INTO public.\"Order Items\" (id) VALUES (1);",
+                TableRow::Insert("public.\"Order Items\"".to_string()),
+            ),
+            (
+                "COPY public.categories (id, name) FROM stdin;",
+                TableRow::CopyStart("public.categories".to_string()),
+            ),
+            (
+                "INSERT INTO public.users (id) VALUES (1);",
+                TableRow::CopyRow,
+            ),
+            ("\\.", TableRow::CopyEnd),
+            (
+                "SELECT pg_catalog.setval('public.users_id_seq', 1, true);",
+                TableRow::Other,
+            ),
+        ] {
+            assert_eq!(row_tables.next(query), expected);
+        }
+    }
+
+    #[test]
+    fn table_stats() {
+        let dump = "CREATE TABLE public.users (id integer NOT NULL, name text);
+
+INSERT INTO public.users (id, name) VALUES (1, 'a');
+INSERT INTO public.orders (id) VALUES (1);
+INSERT INTO public.users (id, name) VALUES (2, 'b');
+
+COPY public.categories (id, name) FROM stdin;
+1\tBeverages
+2\tCondiments
+3\tConfections
+\\.
+
+ALTER TABLE ONLY public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+";
+
+        let stats = super::table_stats(BufReader::new(dump.as_bytes())).unwrap();
+
+        assert_eq!(
+            stats,
+            vec![
+                TableStats {
+                    table: "public.users".to_string(),
+                    rows: 2,
+                    bytes: 2 * "INSERT INTO public.users (id, name) VALUES (1, 'a');\n".len(),
+                },
+                TableStats {
+                    table: "public.orders".to_string(),
+                    rows: 1,
+                    bytes: "INSERT INTO public.orders (id) VALUES (1);\n".len(),
+                },
+                TableStats {
+                    table: "public.categories".to_string(),
+                    rows: 3,
+                    bytes: "1\tBeverages\n2\tCondiments\n3\tConfections\n".len(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn read_and_transform_sorts_rows() {
+        let dump = "CREATE TABLE public.users (id integer NOT NULL, name text);
+
+INSERT INTO public.users (id, name) VALUES (3, 'c');
+INSERT INTO public.users (id, name) VALUES (10, 'j');
+INSERT INTO public.users (id, name) VALUES (NULL, 'null');
+INSERT INTO public.users (id, name) VALUES (1, 'a');
+INSERT INTO public.orders (id) VALUES (2);
+INSERT INTO public.orders (id) VALUES (1);
+INSERT INTO public.cities (id, name) VALUES (2, 'Paris');
+INSERT INTO public.cities (id, name) VALUES (1, 'Lyon');
+
+ALTER TABLE ONLY public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+";
+
+        let sort_rows = vec![
+            SortRowsConfig {
+                database: "public".to_string(),
+                table: "users".to_string(),
+                column: "id".to_string(),
+            },
+            SortRowsConfig {
+                database: "public".to_string(),
+                table: "cities".to_string(),
+                column: "name".to_string(),
+            },
+        ];
+        let source_options = SourceOptions {
+            transformers: &vec![],
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &sort_rows,
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+            },
+        )
+        .unwrap();
+
+        let queries = queries
+            .iter()
+            .map(|query| query.trim())
+            .filter(|query| !query.is_empty())
+            .collect::<Vec<_>>();
+
+        // the rows of the tables without sort column keep the dump order
+        assert_eq!(
+            queries,
+            vec![
+                "CREATE TABLE public.users (id integer NOT NULL, name text);",
+                "INSERT INTO public.users (id, name) VALUES (NULL, 'null');",
+                "INSERT INTO public.users (id, name) VALUES (1, 'a');",
+                "INSERT INTO public.users (id, name) VALUES (3, 'c');",
+                "INSERT INTO public.users (id, name) VALUES (10, 'j');",
+                "INSERT INTO public.orders (id) VALUES (2);",
+                "INSERT INTO public.orders (id) VALUES (1);",
+                "INSERT INTO public.cities (id, name) VALUES (1, 'Lyon');",
+                "INSERT INTO public.cities (id, name) VALUES (2, 'Paris');",
+                "ALTER TABLE ONLY public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id);",
+            ]
+        );
+    }
+
+    #[test]
+    fn read_and_transform_shuffles_columns() {
+        let mut dump =
+            "CREATE TABLE public.users (id integer NOT NULL, name text, age integer);\n\n"
+                .to_string();
+        for id in 0..20 {
+            dump.push_str(
+                format!(
+                    "INSERT INTO public.users (id, name, age) VALUES ({}, 'name-{}', {});\n",
+                    id,
+                    id,
+                    id + 20
+                )
+                .as_str(),
+            );
+        }
+        dump.push_str("INSERT INTO public.cities (id, name) VALUES (1, 'Lyon');\n\n");
+        dump.push_str("COPY public.orders (id, amount) FROM stdin;\n");
+        for id in 0..20 {
+            dump.push_str(format!("{}\t{}.50\n", id, id).as_str());
+        }
+        dump.push_str(
+            "\\.\n\nALTER TABLE ONLY public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id);\n",
+        );
+
+        let shuffle = |table: &str, column: &str| {
+            TransformerTypeConfig::Shuffle(Some(ShuffleTransformerOptions { seed: Some(42) }))
+                .transformer("public", table, column)
+        };
+
+        let transformers = vec![
+            shuffle("users", "name"),
+            shuffle("orders", "amount"),
+            Box::new(KeepFirstCharTransformer::new("public", "users", "age"))
+                as Box<dyn Transformer>,
+        ];
+        let transformer_metrics = TransformerMetrics::default();
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &transformer_metrics,
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+            },
+        )
+        .unwrap();
+
+        let queries = queries
+            .iter()
+            .map(|query| query.trim())
+            .filter(|query| !query.is_empty())
+            .collect::<Vec<_>>();
+
+        assert_eq!(queries.len(), 45);
+        assert_eq!(
+            queries[21],
+            "INSERT INTO public.cities (id, name) VALUES (1, 'Lyon');"
+        );
+        assert_eq!(queries[43], "\\.");
+        assert!(queries[44].starts_with("ALTER TABLE ONLY public.users"));
+
+        // the rows keep their id, the names are attached to other rows
+        let mut names = vec![];
+        for (id, query) in queries[1..21].iter().enumerate() {
+            let (_, _, columns) = insert_into_columns(query).unwrap();
+            assert_eq!(columns[0].number_value(), Some(&(id as i128)));
+            // the other transformers still apply
+            assert_eq!(columns[2].number_value(), Some(&((id as i128 + 20) / 10)));
+
+            let name = columns[1].string_value().unwrap().to_string();
+            assert_ne!(name, format!("name-{}", id));
+            names.push(name);
+        }
+
+        names.sort();
+        let mut original_names = (0..20).map(|id| format!("name-{}", id)).collect::<Vec<_>>();
+        original_names.sort();
+        assert_eq!(names, original_names);
+
+        // COPY payload
+        let mut amounts = vec![];
+        for (id, row) in queries[23..43].iter().enumerate() {
+            let (row_id, amount) = row.split_once('\t').unwrap();
+            assert_eq!(row_id, id.to_string());
+            assert_ne!(amount, format!("{}.50", id));
+            amounts.push(amount.to_string());
+        }
+
+        amounts.sort();
+        let mut original_amounts = (0..20).map(|id| format!("{}.50", id)).collect::<Vec<_>>();
+        original_amounts.sort();
+        assert_eq!(amounts, original_amounts);
+
+        assert_eq!(
+            transformer_metrics.count("shuffle", "public.users.name"),
+            20
+        );
+        assert_eq!(
+            transformer_metrics.count("shuffle", "public.orders.amount"),
+            20
+        );
+    }
+
+    #[test]
+    fn read_and_transform_with_excluded_schemas() {
+        let dump = "CREATE TABLE cron.job (jobid bigint NOT NULL, command text NOT NULL);
+
+INSERT INTO cron.job (jobid, command) VALUES (1, 'VACUUM');
+INSERT INTO public.employees (employee_id, last_name) VALUES (1, 'Davolio');
+
+COPY cron.job_run_details (jobid, status) FROM stdin;
+1\tsucceeded
+\\.
+";
+
+        let exclude_schemas = vec!["cron".to_string()];
+        let source_options = SourceOptions {
+            transformers: &vec![],
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &exclude_schemas,
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+            },
+        )
+        .unwrap();
+
+        assert!(queries.iter().all(|query| !query.contains("cron.")));
+        assert!(!queries.iter().any(|query| query.contains("succeeded")));
+        assert!(queries
+            .iter()
+            .any(|query| query.starts_with("INSERT INTO public.employees")));
+    }
+
+    #[test]
+    fn read_and_transform_counts_transformed_values() {
+        let dump = "INSERT INTO public.employees (employee_id, last_name, first_name) VALUES (1, 'Davolio', 'Nancy');
+INSERT INTO public.employees (employee_id, last_name, first_name) VALUES (2, 'Fuller', 'Andrew');
+INSERT INTO public.customers (customer_id, last_name) VALUES (1, 'Dupont');
+
+";
+        let dump = format!("{}{}", dump, COPY_DUMP);
+
+        let t1: Box<dyn Transformer> = Box::new(KeepFirstCharTransformer::new(
+            "public",
+            "employees",
+            "last_name",
+        ));
+        let t2: Box<dyn Transformer> = Box::new(KeepFirstCharTransformer::new(
+            "public",
+            "categories",
+            "category_name",
+        ));
+        let t3: Box<dyn Transformer> = Box::new(RandomTransformer::new(
+            "public",
+            "suppliers",
+            "company_name",
+        ));
+
+        let transformers = vec![t1, t2, t3];
+        let transformer_metrics = TransformerMetrics::default();
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &transformer_metrics,
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        read_and_transform(BufReader::new(dump.as_bytes()), source_options, |_, _| {}).unwrap();
+
+        let id = KeepFirstCharTransformer::default().id().to_string();
+        let count = |column: &str| transformer_metrics.count(id.as_str(), column);
+        assert_eq!(count("public.employees.last_name"), 2);
+        assert_eq!(count("public.categories.category_name"), 2);
+        // no row for this table - nothing is reported
+        assert_eq!(transformer_metrics.counters().len(), 2);
+    }
+
+    /// transformer panicking on the `Fuller` value
+    struct PanickingTransformer;
+
+    impl Transformer for PanickingTransformer {
+        fn id(&self) -> &str {
+            "panicking"
+        }
+
+        fn description(&self) -> &str {
+            "panic on the Fuller value"
+        }
+
+        fn database_name(&self) -> &str {
+            "public"
+        }
+
+        fn table_name(&self) -> &str {
+            "employees"
+        }
+
+        fn column_name(&self) -> &str {
+            "last_name"
+        }
+
+        fn transform(&self, column: Column) -> Column {
+            match column {
+                Column::StringValue(column_name, value) if value == "Fuller" => {
+                    panic!("unexpected value for {}", column_name)
+                }
+                Column::StringValue(column_name, _) => {
+                    Column::StringValue(column_name, "Doe".to_string())
+                }
+                column => column,
+            }
+        }
+    }
+
+    /// transformer replacing the values of a column by an empty string
+    struct EmptyStringTransformer {
+        column_name: &'static str,
+    }
+
+    impl Transformer for EmptyStringTransformer {
+        fn id(&self) -> &str {
+            "empty-string"
+        }
+
+        fn description(&self) -> &str {
+            "replace the value by an empty string"
+        }
+
+        fn database_name(&self) -> &str {
+            "public"
+        }
+
+        fn table_name(&self) -> &str {
+            "employees"
+        }
+
+        fn column_name(&self) -> &str {
+            self.column_name
+        }
+
+        fn transform(&self, column: Column) -> Column {
+            match column {
+                Column::StringValue(column_name, _) => {
+                    Column::StringValue(column_name, String::new())
+                }
+                column => column,
+            }
+        }
+    }
+
+    #[test]
+    fn null_and_empty_string_values() {
+        let query = to_query(
+            Some("public"),
+            InsertIntoQuery {
+                table_name: "employees".to_string(),
+                columns: vec![
+                    Column::None("region".to_string()),
+                    Column::StringValue("notes".to_string(), String::new()),
+                ],
+            },
+            StringEscapingConfig::Ansi,
+        );
+
+        assert_eq!(
+            query.data(),
+            b"INSERT INTO public.employees (region, notes) VALUES (NULL, '');"
+        );
+
+        let dump = "INSERT INTO public.employees (employee_id, region, title, notes, address) VALUES (1, NULL, '', 'Sales', 'Rue de l''Eglise');
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashSet;
-    use std::str;
-    use std::vec;
+COPY public.employees (employee_id, region, title, notes, address) FROM stdin;
+2\t\\N\t\tSales\tRue de l'Eglise
+\\.
+";
 
-    use crate::config::{
-        DatabaseSubsetConfig, DatabaseSubsetConfigStrategy, DatabaseSubsetConfigStrategyRandom,
-        SkipConfig,
-    };
-    use crate::source::postgres::{to_query, Postgres};
-    use crate::source::SourceOptions;
-    use crate::transformer::random::RandomTransformer;
-    use crate::transformer::transient::TransientTransformer;
-    use crate::transformer::Transformer;
-    use crate::types::{Column, InsertIntoQuery};
-    use crate::Source;
+        // NULL values are kept NULL by a transformer - the other values become empty strings
+        let transformers: Vec<Box<dyn Transformer>> = vec![
+            Box::new(EmptyStringTransformer {
+                column_name: "region",
+            }),
+            Box::new(EmptyStringTransformer {
+                column_name: "notes",
+            }),
+        ];
+        let transformer_metrics = TransformerMetrics::default();
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &transformer_metrics,
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
 
-    fn get_postgres() -> Postgres<'static> {
-        Postgres::new("localhost", 5432, "root", "root", "password")
-    }
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+            },
+        )
+        .unwrap();
 
-    fn get_invalid_postgres() -> Postgres<'static> {
-        Postgres::new("localhost", 5432, "root", "root", "wrongpassword")
+        let queries = queries
+            .iter()
+            .map(|query| query.trim())
+            .filter(|query| !query.is_empty())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            queries,
+            vec![
+                "INSERT INTO public.employees (employee_id, region, title, notes, address) VALUES (1, NULL, '', '', 'Rue de l''Eglise');",
+                "COPY public.employees (employee_id, region, title, notes, address) FROM stdin;",
+                "2\t\\N\t\t\tRue de l'Eglise",
+                "\\.",
+            ]
+        );
     }
 
     #[test]
-    fn connect() {
-        let p = get_postgres();
-        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
+    fn read_and_transform_contains_panicking_transformer() {
+        let dump = "INSERT INTO public.employees (employee_id, last_name) VALUES (1, 'Davolio');
+INSERT INTO public.employees (employee_id, last_name) VALUES (2, 'Fuller');
+INSERT INTO public.employees (employee_id, last_name) VALUES (3, 'Leverling');
+
+COPY public.employees (employee_id, last_name) FROM stdin;
+4\tFuller
+5\tPeacock
+\\.
+";
+
+        let t1: Box<dyn Transformer> = Box::new(PanickingTransformer);
         let transformers = vec![t1];
+        let transformer_metrics = TransformerMetrics::default();
         let source_options = SourceOptions {
             transformers: &transformers,
             skip_config: &vec![],
             database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &transformer_metrics,
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
 
-        assert!(p.read(source_options, |original_query, query| {}).is_ok());
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+            },
+        )
+        .unwrap();
 
-        let p = get_invalid_postgres();
-        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
-        let transformers = vec![t1];
+        let queries = queries
+            .iter()
+            .map(|query| query.trim())
+            .filter(|query| !query.is_empty())
+            .collect::<Vec<_>>();
+
+        // the dump goes on - the values the transformer failed on are kept as they are
+        assert_eq!(
+            queries,
+            vec![
+                "INSERT INTO public.employees (employee_id, last_name) VALUES (1, 'Doe');",
+                "INSERT INTO public.employees (employee_id, last_name) VALUES (2, 'Fuller');",
+                "INSERT INTO public.employees (employee_id, last_name) VALUES (3, 'Doe');",
+                "COPY public.employees (employee_id, last_name) FROM stdin;",
+                "4\tFuller",
+                "5\tDoe",
+                "\\.",
+            ]
+        );
+
+        // and the failures are reported
+        let column = "public.employees.last_name";
+        assert_eq!(transformer_metrics.count("panicking", column), 5);
+        assert_eq!(transformer_metrics.failure_count("panicking", column), 2);
+    }
+
+    #[test]
+    fn read_and_transform_fail_fast_or_collect_errors() {
+        // the second row has a missing value
+        let dump = "COPY public.employees (employee_id, last_name) FROM stdin;
+1\tDoe
+2
+3\tFuller
+\\.
+";
+
+        let read = |fail_fast: bool| {
+            let transformers: Vec<Box<dyn Transformer>> = vec![Box::new(
+                KeepFirstCharTransformer::new("public", "employees", "last_name"),
+            )];
+            let transformer_metrics = TransformerMetrics::default();
+            let source_options = SourceOptions {
+                transformers: &transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &transformer_metrics,
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast,
+                skip_large_objects: false,
+            };
+
+            let mut queries = vec![];
+            let result = read_and_transform(
+                BufReader::new(dump.as_bytes()),
+                source_options,
+                |_, query| {
+                    queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+                },
+            );
+
+            (result, queries, transformer_metrics.errors())
+        };
+
+        // the bad row is kept as it is and the dump goes on
+        let (result, queries, errors) = read(false);
+        assert!(result.is_ok());
+        assert_eq!(
+            queries,
+            vec![
+                "COPY public.employees (employee_id, last_name) FROM stdin;",
+                "1\tD",
+                "2",
+                "3\tF",
+                "\\.",
+            ]
+        );
+        assert_eq!(
+            errors,
+            vec!["a row of the table 'public.employees' has 1 values instead of 2".to_string()]
+        );
+
+        // the dump stops on the bad row
+        let (result, queries, _) = read(true);
+        let err = result.unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("a row of the table 'public.employees'"));
+        assert!(!queries.contains(&"3\tF".to_string()));
+    }
+
+    #[test]
+    fn read_and_transform_flags_non_numeric_values() {
+        // the values of the second rows are shifted by one column
+        let dump = "CREATE TABLE public.employees (employee_id bigint NOT NULL, last_name character varying(20), salary numeric(10,2));
+
+INSERT INTO public.employees (employee_id, last_name, salary) VALUES (1, 'Doe', 1200.5);
+INSERT INTO public.employees (employee_id, last_name, salary) VALUES ('Fuller', '1500', NULL);
+
+COPY public.employees (employee_id, last_name, salary) FROM stdin;
+3\tDavolio\t900
+Leverling\t1100\tNaN
+5\tPeacock\t\\N
+\\.
+";
+
+        let transformers: Vec<Box<dyn Transformer>> = vec![Box::new(
+            KeepFirstCharTransformer::new("public", "employees", "last_name"),
+        )];
+        let transformer_metrics = TransformerMetrics::default();
         let source_options = SourceOptions {
             transformers: &transformers,
             skip_config: &vec![],
             database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &transformer_metrics,
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: true,
+            skip_large_objects: false,
         };
 
-        assert!(p.read(source_options, |original_query, query| {}).is_err());
+        let mut queries = vec![];
+        let result = read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+            },
+        );
+
+        // the rows are kept - the text values in the numeric columns are flagged, even with `fail_fast`
+        assert!(result.is_ok());
+        assert!(queries.contains(&"Leverling\t1\tNaN".to_string()));
+        assert!(transformer_metrics.errors().is_empty());
+        assert_eq!(
+            transformer_metrics.warnings(),
+            vec![
+                "a value of the bigint column 'public.employees.employee_id' is not a number"
+                    .to_string(),
+                "a value of the bigint column 'public.employees.employee_id' is not a number"
+                    .to_string(),
+            ]
+        );
     }
 
     #[test]
-    fn list_rows() {
-        let p = get_postgres();
-        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
+    fn read_and_transform_with_column_tags() {
+        let dump = "CREATE TABLE public.employees (employee_id smallint NOT NULL, last_name character varying(20) NOT NULL, email character varying(40));
+
+COMMENT ON COLUMN public.employees.email IS 'contact address, pii:email';
+COMMENT ON COLUMN public.employees.last_name IS 'pii:name';
+
+INSERT INTO public.employees (employee_id, last_name, email) VALUES (1, 'Davolio', 'nancy@davolio.com');
+INSERT INTO public.employees (employee_id, last_name, email) VALUES (2, 'Fuller', 'andrew@fuller.com');
+";
+
+        let column_tags = vec![
+            ColumnTagConfig {
+                tag: "pii:email".to_string(),
+                transformer: TransformerTypeConfig::KeepFirstChar,
+            },
+            ColumnTagConfig {
+                tag: "pii:name".to_string(),
+                transformer: TransformerTypeConfig::KeepFirstChar,
+            },
+        ];
+
+        // a configured transformer takes precedence over the tag
+        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::new(
+            "public",
+            "employees",
+            "last_name",
+        ));
         let transformers = vec![t1];
+
         let source_options = SourceOptions {
             transformers: &transformers,
             skip_config: &vec![],
             database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &column_tags,
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
 
-        let _ = p.read(source_options, |original_query, query| {
-            assert!(original_query.data().len() > 0);
-            assert!(query.data().len() > 0);
-        });
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+            },
+        )
+        .unwrap();
+
+        assert!(queries
+            .iter()
+            .any(|query| query.contains("COMMENT ON COLUMN public.employees.email")));
+
+        let inserts = queries
+            .iter()
+            .filter(|query| query.starts_with("INSERT INTO"))
+            .collect::<Vec<_>>();
+
+        assert_eq!(inserts.len(), 2);
+        assert!(inserts[0].contains("'Davolio'"));
+        assert!(inserts[0].contains("'n'"));
+        assert!(!inserts[0].contains("nancy@davolio.com"));
+        assert!(inserts[1].contains("'Fuller'"));
+        assert!(inserts[1].contains("'a'"));
+        assert!(!inserts[1].contains("andrew@fuller.com"));
     }
 
     #[test]
-    fn test_to_row() {
-        let query = to_query(
-            None,
-            InsertIntoQuery {
-                table_name: "test".to_string(),
-                columns: vec![Column::StringValue(
-                    "first_name".to_string(),
-                    "romaric".to_string(),
-                )],
-            },
-        );
+    fn read_and_transform_with_overlapping_transformers() {
+        fn transform(transformers: &Vec<Box<dyn Transformer>>) -> String {
+            let dump = "INSERT INTO public.employees (employee_id, last_name, first_name, email) VALUES (1, 'Davolio', 'Nancy', 'nancy@davolio.com');\n";
 
-        assert_eq!(
-            query.data(),
-            b"INSERT INTO test (first_name) VALUES ('romaric');"
-        );
+            let source_options = SourceOptions {
+                transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
 
-        let query = to_query(
-            None,
-            InsertIntoQuery {
-                table_name: "test".to_string(),
-                columns: vec![Column::StringValue(
-                    r#""firstName""#.to_string(),
-                    "romaric".to_string(),
-                )],
+            let mut queries = vec![];
+            read_and_transform(
+                BufReader::new(dump.as_bytes()),
+                source_options,
+                |_, query| {
+                    queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+                },
+            )
+            .unwrap();
+
+            assert_eq!(queries.len(), 1);
+            queries.remove(0)
+        }
+
+        let transformers: Vec<Box<dyn Transformer>> = vec![
+            Box::new(KeepFirstCharTransformer::new("public", "*", "*")),
+            Box::new(TransientTransformer::new("public", "employees", "*_name")),
+            Box::new(KeepFirstCharTransformer::new(
+                "public",
+                "employees",
+                "last_name",
+            )),
+            Box::new(TransientTransformer::new("public", "employees", "*")),
+            // as specific as each other - the first one of the configuration wins
+            Box::new(KeepFirstCharTransformer::new(
+                "public",
+                "employees",
+                "*mail",
+            )),
+            Box::new(TransientTransformer::new("public", "employees", "emai*")),
+        ];
+
+        let query = transform(&transformers);
+        // the exact column name wins over the patterns
+        assert!(query.contains("'D'"));
+        // a pattern naming the table wins over a pattern matching any table
+        assert!(query.contains("'Nancy'"));
+        assert!(query.contains("'n'"));
+        assert!(!query.contains("nancy@davolio.com"));
+
+        // the same result whatever the order of the configuration, except for the transformers as specific as each other
+        let transformers = transformers.into_iter().rev().collect::<Vec<_>>();
+        let query = transform(&transformers);
+        assert!(query.contains("'D'"));
+        assert!(query.contains("'Nancy'"));
+        assert!(query.contains("'nancy@davolio.com'"));
+
+        // the first transformer of the configuration wins for the same column
+        let transformers: Vec<Box<dyn Transformer>> = vec![
+            Box::new(TransientTransformer::new(
+                "public",
+                "employees",
+                "last_name",
+            )),
+            Box::new(KeepFirstCharTransformer::new(
+                "public",
+                "employees",
+                "last_name",
+            )),
+        ];
+
+        assert!(transform(&transformers).contains("'Davolio'"));
+    }
+
+    #[test]
+    fn read_and_transform_derived_columns() {
+        let dump = "INSERT INTO public.employees (employee_id, first_name, display_name, nickname) VALUES (1, 'Nancy', 'Nancy D.', 'nancy');
+COPY public.employees (employee_id, first_name, display_name, nickname) FROM stdin;
+2\tAndrew\tAndrew F.\t\\N
+\\.
+";
+
+        let derive_from_column = |column_name: &str, source_column: &str| {
+            let transformer: Box<dyn Transformer> = Box::new(DeriveFromColumnTransformer::new(
+                "public",
+                "employees",
+                column_name,
+                DeriveFromColumnTransformerOptions {
+                    source_column: source_column.to_string(),
+                },
+            ));
+
+            transformer
+        };
+
+        // the column is derived from the transformed value of its source column
+        let transformers: Vec<Box<dyn Transformer>> = vec![
+            derive_from_column("display_name", "first_name"),
+            Box::new(KeepFirstCharTransformer::new(
+                "public",
+                "employees",
+                "first_name",
+            )),
+            derive_from_column("nickname", "employee_id"),
+        ];
+
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
             },
-        );
+        )
+        .unwrap();
+
         assert_eq!(
-            query.data(),
-            b"INSERT INTO test (\"firstName\") VALUES ('romaric');"
+            queries,
+            vec![
+                "INSERT INTO public.employees (employee_id, first_name, display_name, nickname) VALUES (1, 'N', 'N', 1);",
+                "COPY public.employees (employee_id, first_name, display_name, nickname) FROM stdin;",
+                "2\tA\tA\t2",
+                "\\.",
+            ]
         );
+    }
 
-        let query = to_query(
-            None,
-            InsertIntoQuery {
-                table_name: "test".to_string(),
-                columns: vec![Column::BooleanValue("is_valid".to_string(), true)],
-            },
-        );
+    #[test]
+    fn read_and_transform_with_key_column() {
+        let read = |dump: &str| {
+            // a new transformer for each dump
+            let transformers: Vec<Box<dyn Transformer>> =
+                vec![Box::new(ConsistentFakeTransformer::new(
+                    "public",
+                    "users",
+                    "email",
+                    ConsistentFakeTransformerOptions {
+                        kind: ConsistentFakeKind::Email,
+                        key_column: Some("id".to_string()),
+                    },
+                ))];
 
-        assert_eq!(query.data(), b"INSERT INTO test (is_valid) VALUES (true);");
+            let source_options = SourceOptions {
+                transformers: &transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
 
-        let query = to_query(
-            Some("public"),
-            InsertIntoQuery {
-                table_name: "test".to_string(),
-                columns: vec![
-                    Column::StringValue("first_name".to_string(), "romaric".to_string()),
-                    Column::FloatNumberValue("height_in_meters".to_string(), 1.78),
-                ],
-            },
-        );
+            // the fake email of each row
+            let mut emails = vec![];
+            read_and_transform(
+                BufReader::new(dump.as_bytes()),
+                source_options,
+                |_, query| {
+                    let query = String::from_utf8(query.data().to_vec()).unwrap();
+                    if let Some((_, _, columns)) = insert_into_columns(query.as_str()) {
+                        emails.push(columns[1].string_value().unwrap().to_string());
+                    } else if let Some((email, _)) = query.split_once('\t') {
+                        emails.push(email.to_string());
+                    }
+                },
+            )
+            .unwrap();
+
+            emails
+        };
+
+        let dump = "INSERT INTO public.users (id, email) VALUES (1, 'alice@example.com');
+INSERT INTO public.users (id, email) VALUES (2, 'bob@example.com');
+";
+
+        let emails = read(dump);
+        assert_eq!(emails.len(), 2);
+        assert_ne!(emails[0], "alice@example.com");
+        assert_ne!(emails[0], emails[1]);
 
+        // the same key gets the same fake in another dump - whatever its original value or the format of the rows
+        let other_dump = "COPY public.users (email, id) FROM stdin;
+bob@example.com\t2
+alice@acme.com\t1
+\\.
+";
+
+        let other_emails = read(other_dump);
+        assert_eq!(other_emails, vec![emails[1].clone(), emails[0].clone()]);
+    }
+
+    #[test]
+    fn read_and_transform_default_values() {
+        let create_table = "CREATE TABLE public.employees (
+    employee_id smallint NOT NULL,
+    email character varying(40) DEFAULT 'admin@company.com'::character varying NOT NULL CHECK (email <> 'root@company.com'),
+    title character varying(30) DEFAULT 'Sales Representative'::character varying,
+    \"Notes\" text DEFAULT 'it''s'
+);";
+
+        let t1: Box<dyn Transformer> = Box::new(KeepFirstCharTransformer::new(
+            "public",
+            "employees",
+            "email",
+        ));
+        let t2: Box<dyn Transformer> = Box::new(KeepFirstCharTransformer::new(
+            "public",
+            "employees",
+            "\"Notes\"",
+        ));
+        let transformers = vec![t1, t2];
+
+        let transform = |transform_default_values: bool| {
+            let source_options = SourceOptions {
+                transformers: &transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let mut queries = vec![];
+            read_and_transform(
+                BufReader::new(create_table.as_bytes()),
+                source_options,
+                |_, query| {
+                    queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+                },
+            )
+            .unwrap();
+
+            queries
+        };
+
+        // the default values are kept by default
+        assert_eq!(transform(false), vec![create_table]);
+
+        // only the literals of the default values with a transformer are replaced
         assert_eq!(
-            query.data(),
-            b"INSERT INTO public.test (first_name, height_in_meters) VALUES ('romaric', 1.78);"
+            transform(true),
+            vec![create_table
+                .replace("'admin@company.com'", "'a'")
+                .replace("'it''s'", "'i'")]
         );
+    }
 
-        let query = to_query(
-            Some("public"),
-            InsertIntoQuery {
-                table_name: "test".to_string(),
-                columns: vec![
-                    Column::None("first_name".to_string()),
-                    Column::FloatNumberValue("height_in_meters".to_string(), 1.78),
-                ],
+    #[test]
+    fn read_and_transform_ddl_rewrites() {
+        let dump = "CREATE TABLE public.employees (
+    employee_id smallint NOT NULL,
+    email public.citext
+) TABLESPACE fast_ssd;
+
+INSERT INTO public.employees (employee_id, email) VALUES (1, 'nancy@northwind.com');
+
+ALTER TABLE ONLY public.employees
+    ADD CONSTRAINT pk_employees PRIMARY KEY (employee_id) USING INDEX TABLESPACE fast_ssd;
+";
+
+        let transformers = vec![];
+        let ddl_rewrites = vec![
+            DdlRewriteConfig::StripTablespace,
+            DdlRewriteConfig::ReplaceType(DdlRewriteReplaceTypeConfig {
+                from: "citext".to_string(),
+                to: "text".to_string(),
+            }),
+        ];
+        let source_options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &ddl_rewrites,
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
             },
-        );
+        )
+        .unwrap();
+
+        let queries = queries.join("");
+        assert!(!queries.contains("TABLESPACE"));
+        assert!(queries.contains(
+            "CREATE TABLE public.employees (
+    employee_id smallint NOT NULL,
+    email text
+);"
+        ));
+        assert!(queries.contains("PRIMARY KEY (employee_id);"));
+        // the rows are not rewritten
+        assert!(queries.contains("'nancy@northwind.com'"));
+    }
+
+    #[test]
+    fn read_and_transform_sets_sequences_to_max_id() {
+        let dump = "CREATE SEQUENCE public.users_id_seq
+    AS integer
+    START WITH 1
+    INCREMENT BY 1;
+
+ALTER SEQUENCE public.users_id_seq OWNED BY public.users.id;
+
+CREATE SEQUENCE public.orders_id_seq;
+
+ALTER SEQUENCE public.orders_id_seq OWNED BY public.orders.id;
+
+CREATE SEQUENCE public.invoices_id_seq;
+
+INSERT INTO public.users (id, email) VALUES (1, 'nancy@northwind.com');
+INSERT INTO public.users (id, email) VALUES (7, 'andrew@northwind.com');
+INSERT INTO public.users (id, email) VALUES (3, 'janet@northwind.com');
+
+COPY public.orders (customer, id) FROM stdin;
+ALFKI\t10248
+VINET\t10250
+\\.
+
+SELECT pg_catalog.setval('public.users_id_seq', 1, false);
+SELECT pg_catalog.setval('public.orders_id_seq', 1, false);
+SELECT pg_catalog.setval('public.invoices_id_seq', 42, true);
+";
+
+        let read = |sequences: Option<SequencesConfig>| {
+            let source_options = SourceOptions {
+                transformers: &vec![],
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let mut queries = vec![];
+            read_and_transform(
+                BufReader::new(dump.as_bytes()),
+                source_options,
+                |_, query| {
+                    queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+                },
+            )
+            .unwrap();
+
+            queries.join("")
+        };
+
+        // the values of the source database are kept by default
+        for sequences in [None, Some(SequencesConfig::Preserve)] {
+            let queries = read(sequences);
+            assert!(queries.contains("SELECT pg_catalog.setval('public.users_id_seq', 1, false);"));
+            assert!(queries.contains("SELECT pg_catalog.setval('public.orders_id_seq', 1, false);"));
+        }
+
+        // the next value of each sequence is above the greatest id of its table
+        let queries = read(Some(SequencesConfig::MaxId));
+        assert!(queries.contains("SELECT pg_catalog.setval('public.users_id_seq', 7, true);"));
+        assert!(queries.contains("SELECT pg_catalog.setval('public.orders_id_seq', 10250, true);"));
+        // a sequence without an owning column is kept as it is
+        assert!(queries.contains("SELECT pg_catalog.setval('public.invoices_id_seq', 42, true);"));
+    }
+
+    #[test]
+    fn read_and_transform_schema_or_data_only() {
+        let dump = "SET client_encoding = 'UTF8';
+
+CREATE TABLE public.users (
+    id integer NOT NULL,
+    email character varying(40)
+);
+
+CREATE SEQUENCE public.users_id_seq;
+
+ALTER SEQUENCE public.users_id_seq OWNED BY public.users.id;
+
+COMMENT ON COLUMN public.users.email IS 'pii:email';
+
+INSERT INTO public.users (id, email) VALUES (1, 'nancy@northwind.com');
+
+COPY public.orders (id, customer) FROM stdin;
+10248\tALFKI
+\\.
+
+SELECT pg_catalog.setval('public.users_id_seq', 1, true);
+
+ALTER TABLE ONLY public.users
+    ADD CONSTRAINT pk_users PRIMARY KEY (id);
+
+CREATE INDEX users_email ON public.users USING btree (email);
+";
+
+        let read = |content: Option<DumpContent>| {
+            let source_options = SourceOptions {
+                transformers: &vec![],
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let mut queries = vec![];
+            read_and_transform(
+                BufReader::new(dump.as_bytes()),
+                source_options,
+                |_, query| {
+                    queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+                },
+            )
+            .unwrap();
+
+            queries.join("")
+        };
+
+        let queries = read(Some(DumpContent::SchemaOnly));
+        assert!(!queries.contains("INSERT INTO"));
+        assert!(!queries.contains("COPY"));
+        assert!(!queries.contains("ALFKI"));
+        assert!(!queries.contains("setval"));
+        assert!(queries.contains("CREATE TABLE public.users"));
+        assert!(queries.contains("CREATE INDEX users_email"));
+        assert!(queries.contains("ADD CONSTRAINT pk_users"));
+
+        let queries = read(Some(DumpContent::DataOnly));
+        assert!(!queries.contains("CREATE"));
+        assert!(!queries.contains("ALTER"));
+        assert!(!queries.contains("COMMENT"));
+        assert!(queries.contains("SET client_encoding"));
+        assert!(queries.contains("INSERT INTO public.users"));
+        assert!(queries.contains("COPY public.orders"));
+        assert!(queries.contains("10248\tALFKI"));
+        assert!(queries.contains("setval"));
+
+        // both by default
+        let queries = read(None);
+        assert!(queries.contains("CREATE TABLE public.users"));
+        assert!(queries.contains("INSERT INTO public.users"));
+    }
 
-        assert_eq!(
-            query.data(),
-            b"INSERT INTO public.test (first_name, height_in_meters) VALUES (NULL, 1.78);"
-        );
+    #[test]
+    fn to_query_string_escaping() {
+        let to_query_with = |string_escaping: StringEscapingConfig| {
+            let query = to_query(
+                Some("public"),
+                InsertIntoQuery {
+                    table_name: "test".to_string(),
+                    columns: vec![Column::StringValue(
+                        "path".to_string(),
+                        "it's C:\\temp\\".to_string(),
+                    )],
+                },
+                string_escaping,
+            );
 
-        let query = to_query(
-            Some("public"),
-            InsertIntoQuery {
-                table_name: "test".to_string(),
-                columns: vec![
-                    Column::StringValue("first_name".to_string(), "romaric".to_string()),
-                    Column::FloatNumberValue("height_in_meters".to_string(), 1.78),
-                    Column::StringValue(
-                        "description".to_string(),
-                        "I'd like to say... I don't know.".to_string(),
-                    ),
-                ],
-            },
-        );
+            String::from_utf8(query.data().to_vec()).unwrap()
+        };
 
         assert_eq!(
-            query.data(),
-            b"INSERT INTO public.test (first_name, height_in_meters, description) \
-            VALUES ('romaric', 1.78, 'I''d like to say... I don''t know.');"
+            to_query_with(StringEscapingConfig::Ansi),
+            "INSERT INTO public.test (path) VALUES ('it''s C:\\temp\\');"
+        );
+        assert_eq!(
+            to_query_with(StringEscapingConfig::Backslash),
+            "INSERT INTO public.test (path) VALUES (E'it\\'s C:\\\\temp\\\\');"
         );
     }
 
     #[test]
-    fn list_rows_and_hide_last_name() {
-        let p = get_postgres();
+    fn read_and_transform_with_backslash_escaping() {
+        let dump = "SET standard_conforming_strings = on;
 
-        let database_name = "public";
-        let table_name = "employees";
-        let column_name_to_obfuscate = "last_name";
+CREATE FUNCTION public.temp_path() RETURNS text
+    LANGUAGE sql
+    AS 'SELECT ''C:\\temp''';
 
-        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
-        let t2: Box<dyn Transformer> = Box::new(RandomTransformer::new(
-            database_name,
-            table_name,
-            column_name_to_obfuscate,
-        ));
+INSERT INTO public.test (path) VALUES ('it''s C:\\temp\\files');
+";
 
-        let transformers = vec![t1, t2];
         let source_options = SourceOptions {
-            transformers: &transformers,
+            transformers: &vec![],
             skip_config: &vec![],
             database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: Some(StringEscapingConfig::Backslash),
+            fail_fast: false,
+            skip_large_objects: false,
         };
 
-        let _ = p.read(source_options, |original_query, query| {
-            assert!(query.data().len() > 0);
-            assert!(query.data().len() > 0);
-
-            let query_str = str::from_utf8(query.data()).unwrap();
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                queries.push(String::from_utf8(query.data().to_vec()).unwrap());
+            },
+        )
+        .unwrap();
 
-            if query_str.contains(database_name)
-                && query_str.contains(table_name)
-                && query_str.starts_with("INSERT INTO")
-            {
-                assert_ne!(query.data(), original_query.data());
-                // TODO to complete to better check the column change only
-            } else {
-                assert_eq!(query.data(), original_query.data());
-            }
-        });
+        // the session setting and the strings which are not rewritten are kept as they are
+        let queries = queries.join("");
+        assert!(queries.contains("SET standard_conforming_strings = on;"));
+        assert!(queries.contains("AS 'SELECT ''C:\\temp''';"));
+        assert!(queries.contains("VALUES (E'it\\'s C:\\\\temp\\\\files');"));
     }
 
     #[test]
-    fn skip_table() {
+    fn dump_args_schema_or_data_only() {
         let p = get_postgres();
+        let transformers = vec![];
+        let dump_args = |content: Option<DumpContent>| {
+            let source_options = SourceOptions {
+                transformers: &transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
 
-        let database_name = "public";
-        let table_name = "employees";
-
-        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
-        let skip_employees_table = SkipConfig {
-            database: database_name.to_string(),
-            table: table_name.to_string(),
+            p.dump_args(&source_options).unwrap()
         };
 
+        let args = dump_args(Some(DumpContent::SchemaOnly));
+        assert!(args.contains(&"--schema-only".to_string()));
+        assert!(!args.contains(&"--data-only".to_string()));
+
+        let args = dump_args(Some(DumpContent::DataOnly));
+        assert!(args.contains(&"--data-only".to_string()));
+        assert!(!args.contains(&"--schema-only".to_string()));
+
+        let args = dump_args(None);
+        assert!(!args.contains(&"--schema-only".to_string()));
+        assert!(!args.contains(&"--data-only".to_string()));
+    }
+
+    #[test]
+    fn read_and_transform_keeps_non_literal_values() {
+        let queries_to_keep = vec![
+            "INSERT INTO public.employees DEFAULT VALUES;",
+            "INSERT INTO public.employees (employee_id, last_name) VALUES (1, upper('Davolio'));",
+            "INSERT INTO public.employees (employee_id, last_name) VALUES (2, (SELECT last_name FROM public.customers LIMIT 1));",
+            "INSERT INTO public.employees (employee_id, last_name) VALUES (3, 'Fuller'), (4, 'Buchanan');",
+        ];
+
+        let dump = format!(
+            "{}\nINSERT INTO public.employees (employee_id, last_name) VALUES (5, 'Peacock');\n",
+            queries_to_keep.join("\n")
+        );
+
+        let t1: Box<dyn Transformer> = Box::new(KeepFirstCharTransformer::new(
+            "public",
+            "employees",
+            "last_name",
+        ));
         let transformers = vec![t1];
-        let skip_config = vec![skip_employees_table];
 
         let source_options = SourceOptions {
             transformers: &transformers,
-            skip_config: &skip_config,
+            skip_config: &vec![],
             database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
 
-        let _ = p.read(source_options, |_original_query, query| {
-            assert!(query.data().len() > 0);
-            assert!(query.data().len() > 0);
+        let mut queries = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |_, query| {
+                let query = String::from_utf8(query.data().to_vec()).unwrap();
+                if !query.trim().is_empty() {
+                    queries.push(query.trim().to_string());
+                }
+            },
+        )
+        .unwrap();
 
-            let query_str = str::from_utf8(query.data()).unwrap();
-            let unexpected_insert_into = format!("INSERT INTO {}.{}", database_name, table_name);
-            let unexpected_create_table = format!("CREATE TABLE {}.{}", database_name, table_name);
-            let unexpected_alter_table = format!("ALTER TABLE {}.{}", database_name, table_name);
-            let unexpected_alter_table_only =
-                format!("ALTER TABLE ONLY {}.{}", database_name, table_name);
+        assert_eq!(queries.len(), 5);
 
-            if query_str.contains(unexpected_insert_into.as_str()) {
-                panic!("unexpected insert into: {}", unexpected_insert_into);
-            }
+        for (query, expected_query) in queries.iter().zip(queries_to_keep) {
+            assert_eq!(query, expected_query);
+        }
 
-            if query_str.contains(unexpected_create_table.as_str()) {
-                panic!("unexpected create table: {}", unexpected_create_table);
-            }
+        assert!(queries[4].contains("'P'"));
+    }
 
-            if query_str.contains(unexpected_alter_table.as_str()) {
-                panic!("unexpected alter table: {}", unexpected_alter_table);
-            }
+    #[test]
+    fn read_and_transform_with_max_cpu_percent() {
+        let dump = (0..5000)
+            .map(|i| {
+                format!(
+                    "INSERT INTO public.employees (employee_id, last_name) VALUES ({}, 'Davolio');\n",
+                    i
+                )
+            })
+            .collect::<String>();
 
-            if query_str.contains(unexpected_alter_table_only.as_str()) {
-                panic!(
-                    "unexpected alter table only: {}",
-                    unexpected_alter_table_only
-                );
+        let t1: Box<dyn Transformer> = Box::new(KeepFirstCharTransformer::new(
+            "public",
+            "employees",
+            "last_name",
+        ));
+        let transformers = vec![t1];
+
+        let transform = |max_cpu_percent: Option<u8>| {
+            let source_options = SourceOptions {
+                transformers: &transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let mut rows = 0;
+            let start = Instant::now();
+            read_and_transform(BufReader::new(dump.as_bytes()), source_options, |_, _| {
+                rows += 1
+            })
+            .unwrap();
+
+            (rows, start.elapsed())
+        };
+
+        let (rows, elapsed) = transform(None);
+        let (throttled_rows, throttled_elapsed) = transform(Some(10));
+
+        // the same rows are written - just slower (about 10 times)
+        assert_eq!(throttled_rows, rows);
+        assert!(throttled_elapsed > elapsed * 3);
+    }
+
+    /// transformer negating the `active` and `verified` booleans
+    struct NegateBooleanTransformer {
+        column_name: String,
+    }
+
+    impl Transformer for NegateBooleanTransformer {
+        fn id(&self) -> &str {
+            "negate-boolean"
+        }
+
+        fn description(&self) -> &str {
+            "negate a boolean"
+        }
+
+        fn database_name(&self) -> &str {
+            "public"
+        }
+
+        fn table_name(&self) -> &str {
+            "users"
+        }
+
+        fn column_name(&self) -> &str {
+            self.column_name.as_str()
+        }
+
+        fn transform(&self, column: Column) -> Column {
+            match column {
+                Column::BooleanValue(column_name, value) => {
+                    Column::BooleanValue(column_name, !value)
+                }
+                column => column,
             }
-        });
+        }
     }
 
     #[test]
-    fn subset_options() {
-        let p = get_postgres();
-        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
+    fn read_and_transform_boolean_values() {
+        let dump = r#"
+CREATE TABLE public.users (
+    id integer NOT NULL,
+    active boolean DEFAULT true,
+    verified bool,
+    score smallint
+);
+
+INSERT INTO public.users (id, active, verified, score) VALUES (1, true, FALSE, 1);
+INSERT INTO public.users (id, active, verified, score) VALUES (2, 't', 'f', 0);
+INSERT INTO public.users (id, active, verified, score) VALUES (3, '1', '0', 1);
+INSERT INTO public.users (id, active, verified, score) VALUES (4, 0, 1, 0);
+INSERT INTO public.users (id, active, verified, score) VALUES (5, NULL, 'TRUE', 1);
+
+COPY public.users (id, active, verified, score) FROM stdin;
+6	t	f	1
+7	true	false	0
+8	1	0	1
+9	\N	T	0
+\.
+"#;
+
+        let transformers: Vec<Box<dyn Transformer>> = vec!["active", "verified"]
+            .into_iter()
+            .map(|column_name| {
+                Box::new(NegateBooleanTransformer {
+                    column_name: column_name.to_string(),
+                }) as Box<dyn Transformer>
+            })
+            .collect();
 
         let source_options = SourceOptions {
-            transformers: &vec![t1],
+            transformers: &transformers,
             skip_config: &vec![],
-            database_subset: &Some(DatabaseSubsetConfig {
-                database: "public".to_string(),
-                table: "orders".to_string(),
-                strategy: DatabaseSubsetConfigStrategy::Random(
-                    DatabaseSubsetConfigStrategyRandom { percent: 50 },
-                ),
-                passthrough_tables: None,
-            }),
+            database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
 
-        let mut rows_percent_50 = vec![];
-        let _ = p.read(source_options, |_original_query, query| {
-            assert!(query.data().len() > 0);
-            rows_percent_50.push(String::from_utf8_lossy(query.data().as_slice()).to_string());
-        });
+        let mut rows = vec![];
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |original_query, query| {
+                let original_query = str::from_utf8(original_query.data()).unwrap().trim();
+                let query = str::from_utf8(query.data()).unwrap().trim();
+                if original_query.starts_with("INSERT INTO")
+                    || original_query.starts_with(char::is_numeric)
+                {
+                    rows.push((original_query.to_string(), query.to_string()));
+                }
+            },
+        )
+        .unwrap();
 
-        let x = rows_percent_50
-            .iter()
-            .filter(|x| x.contains("INSERT INTO"))
-            .map(|x| x.as_str())
-            .collect::<HashSet<_>>();
+        let expected_rows = vec![
+            // column-inserts: the values are parsed and written back as `true`/`false`
+            (
+                "INSERT INTO public.users (id, active, verified, score) VALUES (1, true, false, 1);",
+                "INSERT INTO public.users (id, active, verified, score) VALUES (1, false, true, 1);",
+            ),
+            (
+                "INSERT INTO public.users (id, active, verified, score) VALUES (2, true, false, 0);",
+                "INSERT INTO public.users (id, active, verified, score) VALUES (2, false, true, 0);",
+            ),
+            (
+                "INSERT INTO public.users (id, active, verified, score) VALUES (3, true, false, 1);",
+                "INSERT INTO public.users (id, active, verified, score) VALUES (3, false, true, 1);",
+            ),
+            (
+                "INSERT INTO public.users (id, active, verified, score) VALUES (4, false, true, 0);",
+                "INSERT INTO public.users (id, active, verified, score) VALUES (4, true, false, 0);",
+            ),
+            (
+                "INSERT INTO public.users (id, active, verified, score) VALUES (5, NULL, true, 1);",
+                "INSERT INTO public.users (id, active, verified, score) VALUES (5, NULL, false, 1);",
+            ),
+            // COPY payload: the values are written back as `t`/`f`
+            ("6\tt\tf\t1", "6\tf\tt\t1"),
+            ("7\ttrue\tfalse\t0", "7\tf\tt\t0"),
+            ("8\t1\t0\t1", "8\tf\tt\t1"),
+            ("9\t\\N\tT\t0", "9\t\\N\tf\t0"),
+        ];
 
-        let y = rows_percent_50
-            .iter()
-            .filter(|x| x.contains("INSERT INTO"))
-            .map(|x| x.as_str())
-            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            expected_rows
+                .into_iter()
+                .map(|(original_query, query)| (original_query.to_string(), query.to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
 
-        // check that there is no duplicated rows
-        assert_eq!(x.len(), y.len());
+    #[test]
+    fn sample_pii_columns() {
+        let dump = r#"
+CREATE TABLE public.users (
+    id integer NOT NULL,
+    login character varying(255),
+    contact character varying(255)
+);
 
-        let t1: Box<dyn Transformer> = Box::new(TransientTransformer::default());
+INSERT INTO public.users (id, login, contact) VALUES (1, 'john', 'john.doe@example.com');
+INSERT INTO public.users (id, login, contact) VALUES (2, 'jane', 'jane.doe@example.com');
+INSERT INTO public.users (id, login, contact) VALUES (3, 'joe', NULL);
+INSERT INTO public.users (id, login, contact) VALUES (4, 'jim', 'jim@example.org');
+"#;
 
         let source_options = SourceOptions {
-            transformers: &vec![t1],
+            transformers: &vec![],
             skip_config: &vec![],
-            database_subset: &Some(DatabaseSubsetConfig {
-                database: "public".to_string(),
-                table: "orders".to_string(),
-                strategy: DatabaseSubsetConfigStrategy::Random(
-                    DatabaseSubsetConfigStrategyRandom { percent: 30 },
-                ),
-                passthrough_tables: None,
-            }),
+            database_subset: &None,
             only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &TransformerMetrics::default(),
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
 
-        let mut rows_percent_30 = vec![];
-        let _ = p.read(source_options, |_original_query, query| {
-            assert!(query.data().len() > 0);
-            rows_percent_30.push(String::from_utf8_lossy(query.data().as_slice()).to_string());
-        });
+        let mut detector = PiiDetector::new(100);
+        read_and_transform(
+            BufReader::new(dump.as_bytes()),
+            source_options,
+            |original_query, _| {
+                let query = str::from_utf8(original_query.data()).unwrap();
+                if let Some((database, table, columns)) = insert_into_columns(query) {
+                    for column in columns {
+                        if let Some(value) = column.string_value() {
+                            detector.add_value(&database, &table, column.name(), value);
+                        }
+                    }
+                }
+            },
+        )
+        .unwrap();
 
-        // check that there is no duplicated rows
+        let columns = detector.detected_columns();
+        assert_eq!(columns.len(), 1);
+        assert_eq!(columns[0].database, "public");
+        assert_eq!(columns[0].table, "users");
+        assert_eq!(columns[0].column, "contact");
+        assert_eq!(columns[0].data_class, DataClass::Email);
+    }
+
+    #[test]
+    fn read_and_transform_minimal() {
+        let dump = r#"--
+-- PostgreSQL database dump
+--
+
+SET statement_timeout = 0;
+SET lock_timeout = 0;
+SET client_encoding = 'UTF8';
+SET standard_conforming_strings = on;
+SELECT pg_catalog.set_config('search_path', '', false);
+SET check_function_bodies = false;
+SET xmloption = content;
+SET client_min_messages = warning;
+SET row_security = off;
+
+CREATE EXTENSION IF NOT EXISTS pgcrypto WITH SCHEMA public;
+
+SET default_tablespace = '';
+
+SET default_table_access_method = heap;
+
+--
+-- Name: users; Type: TABLE; Schema: public; Owner: root
+--
+
+CREATE TABLE public.users (
+    id integer NOT NULL,
+    name character varying(255)
+);
+
+CREATE SEQUENCE public.users_id_seq
+    AS integer
+    START WITH 1
+    INCREMENT BY 1
+    NO MINVALUE
+    NO MAXVALUE
+    CACHE 1;
+
+ALTER SEQUENCE public.users_id_seq OWNED BY public.users.id;
+
+INSERT INTO public.users (id, name) VALUES (1, 'Alice');
+
+SELECT pg_catalog.setval('public.users_id_seq', 1, true);
+
+ALTER TABLE ONLY public.users
+    ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+
+--
+-- PostgreSQL database dump complete
+--
+
+"#;
+
+        let read = |minimal: bool| {
+            let source_options = SourceOptions {
+                transformers: &vec![],
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let mut queries = vec![];
+            read_and_transform(
+                BufReader::new(dump.as_bytes()),
+                source_options,
+                |_, query| {
+                    queries.push(str::from_utf8(query.data()).unwrap().to_string());
+                },
+            )
+            .unwrap();
+
+            queries
+        };
+
+        let queries = read(false);
+        assert!(queries.iter().any(|query| query.starts_with("--")));
+        assert!(queries
+            .iter()
+            .any(|query| query.starts_with("SET statement_timeout")));
+
+        let queries = read(true);
         assert_eq!(
-            rows_percent_30
-                .iter()
-                .filter(|x| x.contains("INSERT INTO"))
-                .collect::<HashSet<_>>()
-                .len(),
-            rows_percent_30
+            queries
                 .iter()
-                .filter(|x| x.contains("INSERT INTO"))
-                .collect::<Vec<_>>()
-                .len(),
+                .map(|query| query.trim().lines().next().unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                "SET client_encoding = 'UTF8';",
+                "SET standard_conforming_strings = on;",
+                "SET check_function_bodies = false;",
+                "CREATE EXTENSION IF NOT EXISTS pgcrypto WITH SCHEMA public;",
+                "CREATE TABLE public.users (",
+                "CREATE SEQUENCE public.users_id_seq",
+                "ALTER SEQUENCE public.users_id_seq OWNED BY public.users.id;",
+                "INSERT INTO public.users (id, name) VALUES (1, 'Alice');",
+                "SELECT pg_catalog.setval('public.users_id_seq', 1, true);",
+                "ALTER TABLE ONLY public.users",
+            ]
         );
+    }
 
-        assert!(rows_percent_30.len() < rows_percent_50.len());
+    #[test]
+    fn read_and_transform_large_objects() {
+        let dump = r#"SET standard_conforming_strings = on;
+
+CREATE TABLE public.users (
+    id integer NOT NULL,
+    name character varying(255),
+    avatar oid
+);
+
+SELECT pg_catalog.lo_create('16385');
+
+ALTER LARGE OBJECT 16385 OWNER TO root;
+
+INSERT INTO public.users (id, name, avatar) VALUES (1, 'Alice', 16385);
+
+--
+-- Data for Name: BLOBS; Type: BLOBS; Schema: -; Owner: -
+--
+
+BEGIN;
+
+SELECT pg_catalog.lo_open('16385', 131072);
+SELECT pg_catalog.lowrite(0, '\x48656c6c6f');
+SELECT pg_catalog.lo_close(0);
+
+COMMIT;
+
+BEGIN;
+
+CREATE INDEX users_name_idx ON public.users USING btree (name);
+
+COMMIT;
+
+ALTER TABLE ONLY public.users
+    ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+"#;
+
+        let read = |content: Option<DumpContent>, skip_large_objects: bool| {
+            let transformers: Vec<Box<dyn Transformer>> = vec![Box::new(
+                TransientTransformer::new("public", "users", "name"),
+            )];
+
+            let source_options = SourceOptions {
+                transformers: &transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &TransformerMetrics::default(),
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: true,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects,
+            };
+
+            let mut queries = vec![];
+            read_and_transform(
+                BufReader::new(dump.as_bytes()),
+                source_options,
+                |_, query| {
+                    let query = str::from_utf8(query.data()).unwrap().trim().to_string();
+                    queries.push(query.lines().next().unwrap().to_string());
+                },
+            )
+            .unwrap();
+
+            queries
+        };
+
+        // the large objects are passed through in the order of the dump - their data in its transaction
+        assert_eq!(
+            read(None, false),
+            vec![
+                "SET standard_conforming_strings = on;",
+                "CREATE TABLE public.users (",
+                "SELECT pg_catalog.lo_create('16385');",
+                "ALTER LARGE OBJECT 16385 OWNER TO root;",
+                "INSERT INTO public.users (id, name, avatar) VALUES (1, 'Alice', 16385);",
+                "BEGIN;",
+                "SELECT pg_catalog.lo_open('16385', 131072);",
+                "SELECT pg_catalog.lowrite(0, '\\x48656c6c6f');",
+                "SELECT pg_catalog.lo_close(0);",
+                "COMMIT;",
+                "BEGIN;",
+                "CREATE INDEX users_name_idx ON public.users USING btree (name);",
+                "COMMIT;",
+                "ALTER TABLE ONLY public.users",
+            ]
+        );
+
+        // the large objects are data - their transaction is kept by a data-only dump
+        assert_eq!(
+            read(Some(DumpContent::DataOnly), false),
+            vec![
+                "SET standard_conforming_strings = on;",
+                "SELECT pg_catalog.lo_create('16385');",
+                "ALTER LARGE OBJECT 16385 OWNER TO root;",
+                "INSERT INTO public.users (id, name, avatar) VALUES (1, 'Alice', 16385);",
+                "BEGIN;",
+                "SELECT pg_catalog.lo_open('16385', 131072);",
+                "SELECT pg_catalog.lowrite(0, '\\x48656c6c6f');",
+                "SELECT pg_catalog.lo_close(0);",
+                "COMMIT;",
+            ]
+        );
+
+        // and dropped with their transaction by a schema-only dump, or when they are skipped
+        let without_large_objects = vec![
+            "SET standard_conforming_strings = on;",
+            "CREATE TABLE public.users (",
+            "INSERT INTO public.users (id, name, avatar) VALUES (1, 'Alice', 16385);",
+            "BEGIN;",
+            "CREATE INDEX users_name_idx ON public.users USING btree (name);",
+            "COMMIT;",
+            "ALTER TABLE ONLY public.users",
+        ];
+        assert_eq!(read(None, true), without_large_objects);
+
+        let mut schema = without_large_objects.clone();
+        let _ = schema.remove(2);
+        assert_eq!(read(Some(DumpContent::SchemaOnly), false), schema);
     }
 }
@@ -1,12 +1,12 @@
-use std::io::{stdin, BufReader, Error};
+use std::io::{stdin, BufReader, Error, ErrorKind, Read};
 
 use crate::connector::Connector;
-use crate::source::postgres::{read_and_transform, subset};
+use crate::source::postgres::{is_custom_format, pg_restore, read_and_transform, subset};
 use crate::types::{OriginalQuery, Query};
 use crate::Source;
 use crate::SourceOptions;
 
-/// Source Postgres dump from STDIN
+/// Source Postgres dump from STDIN - plain SQL or custom-format (`pg_dump -Fc`)
 pub struct PostgresStdin {}
 
 impl Default for PostgresStdin {
@@ -27,18 +27,151 @@ impl Source for PostgresStdin {
         options: SourceOptions,
         query_callback: F,
     ) -> Result<(), Error> {
-        match &options.database_subset {
-            None => {
-                let reader = BufReader::new(stdin());
-                read_and_transform(reader, options, query_callback);
-            }
-            Some(subset_config) => {
-                let dump_reader = BufReader::new(stdin());
-                let reader = subset(dump_reader, subset_config)?;
-                read_and_transform(reader, options, query_callback);
-            }
+        read_dump(BufReader::new(stdin()), options, query_callback)
+    }
+}
+
+/// read a plain SQL dump, or a custom-format dump converted on the fly by `pg_restore`
+fn read_dump<R: Read + Send + 'static, F: FnMut(OriginalQuery, Query)>(
+    mut reader: BufReader<R>,
+    options: SourceOptions,
+    query_callback: F,
+) -> Result<(), Error> {
+    if !is_custom_format(&mut reader)? {
+        return read_plain_dump(reader, options, query_callback);
+    }
+
+    if options.database_subset.is_some() {
+        // pg_restore writes the rows as COPY statements, which the subset does not support
+        return Err(Error::new(
+            ErrorKind::Other,
+            "database_subset is not supported with a custom-format dump - use `pg_dump --column-inserts` instead",
+        ));
+    }
+
+    let mut process = pg_restore(reader)?;
+    let stdout = process.stdout()?;
+
+    read_plain_dump(BufReader::new(stdout), options, query_callback)?;
+
+    process.wait()
+}
+
+fn read_plain_dump<R: Read, F: FnMut(OriginalQuery, Query)>(
+    reader: BufReader<R>,
+    options: SourceOptions,
+    query_callback: F,
+) -> Result<(), Error> {
+    match &options.database_subset {
+        None => {
+            read_and_transform(reader, options, query_callback)?;
+        }
+        Some(subset_config) => {
+            let reader = subset(reader, subset_config)?;
+            read_and_transform(reader, options, query_callback)?;
+        }
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::process::Command;
+
+    use crate::source::postgres_stdin::read_dump;
+    use crate::source::SourceOptions;
+    use crate::transformer::TransformerMetrics;
+
+    fn read_queries<R: std::io::Read + Send + 'static>(reader: R) -> Vec<String> {
+        try_read_queries(reader).unwrap()
+    }
+
+    fn try_read_queries<R: std::io::Read + Send + 'static>(
+        reader: R,
+    ) -> Result<Vec<String>, std::io::Error> {
+        let transformer_metrics = TransformerMetrics::default();
+        let source_options = SourceOptions {
+            transformers: &vec![],
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &transformer_metrics,
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
         };
 
-        Ok(())
+        let mut queries = vec![];
+        read_dump(BufReader::new(reader), source_options, |_, query| {
+            let query = String::from_utf8(query.data().to_vec()).unwrap();
+            // `\restrict` lines hold a random key
+            if !query.trim().is_empty() && !query.starts_with('\\') {
+                queries.push(query);
+            }
+        })?;
+
+        Ok(queries)
+    }
+
+    fn pg_dump(extra_args: &[&str]) -> tempfile::NamedTempFile {
+        let dump_file = tempfile::NamedTempFile::new().unwrap();
+        let status = Command::new("pg_dump")
+            .env("PGPASSWORD", "password")
+            .args(["--no-owner", "-h", "localhost", "-p", "5432", "-U", "root"])
+            .args(extra_args)
+            .args(["-f", dump_file.path().to_str().unwrap(), "root"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        dump_file
+    }
+
+    #[test]
+    fn read_plain_dump() {
+        let dump = "INSERT INTO public.users (id) VALUES (1);\n".as_bytes();
+
+        assert_eq!(
+            read_queries(dump),
+            vec!["INSERT INTO public.users (id) VALUES (1);".to_string()]
+        );
+    }
+
+    #[test]
+    fn read_custom_format_dump() {
+        let plain_dump = pg_dump(&[]);
+        let custom_format_dump = pg_dump(&["-Fc"]);
+
+        let queries = read_queries(File::open(custom_format_dump.path()).unwrap());
+        assert!(queries
+            .iter()
+            .any(|query| query.starts_with("COPY public.employees")));
+
+        // the custom-format dump is read as the plain SQL dump
+        assert_eq!(
+            queries,
+            read_queries(File::open(plain_dump.path()).unwrap())
+        );
+    }
+
+    #[test]
+    fn read_invalid_custom_format_dump() {
+        // the magic bytes of a custom-format dump followed by garbage
+        let dump = "PGDMP not a custom-format dump".as_bytes();
+
+        let err = try_read_queries(dump).unwrap_err();
+        assert!(err.to_string().contains("pg_restore"));
     }
 }
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use dump_parser::postgres::{
+    get_column_names_from_insert_into_query, get_column_values_from_copy_row,
+    get_column_values_from_insert_into_query, get_word_value_at_position, Token,
+};
+
+/// Track the greatest value of the columns owning a sequence - to set the sequences above the restored rows
+/// instead of keeping the values of the source database (e.g. for a subset).
+#[derive(Default)]
+pub struct SequenceTracker {
+    // "<database>.<table>" -> (column, "<database>.<sequence>")
+    owned_columns: HashMap<String, Vec<(String, String)>>,
+    // "<database>.<sequence>" -> greatest value of its column
+    max_values: HashMap<String, i128>,
+}
+
+impl SequenceTracker {
+    /// keep the column owning the sequence of an `ALTER SEQUENCE <sequence> OWNED BY <table>.<column>;` statement -
+    /// false if the statement is not one
+    pub fn add_owned_by(&mut self, tokens: &Vec<Token>) -> bool {
+        let words = words(tokens);
+
+        let is_owned_by = match words.as_slice() {
+            [alter, sequence, _, owned, by, _] => {
+                alter.eq_ignore_ascii_case("ALTER")
+                    && sequence.eq_ignore_ascii_case("SEQUENCE")
+                    && owned.eq_ignore_ascii_case("OWNED")
+                    && by.eq_ignore_ascii_case("BY")
+            }
+            _ => false,
+        };
+
+        if !is_owned_by {
+            return false;
+        }
+
+        // `OWNED BY NONE` or a column without its schema - not written by pg_dump
+        let (table_name, column_name) = match words[5].rsplit_once('.') {
+            Some((table_name, column_name)) if table_name.contains('.') => {
+                (table_name, column_name)
+            }
+            _ => return false,
+        };
+
+        self.owned_columns
+            .entry(table_name.to_string())
+            .or_default()
+            .push((column_name.to_string(), words[2].to_string()));
+
+        true
+    }
+
+    fn is_tracked(&self, database_name: &str, table_name: &str) -> bool {
+        self.owned_columns
+            .contains_key(format!("{}.{}", database_name, table_name).as_str())
+    }
+
+    /// keep the greatest values of the columns owning a sequence of an `INSERT INTO` row
+    pub fn track_insert_into(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+        tokens: &Vec<Token>,
+    ) {
+        if !self.is_tracked(database_name, table_name) {
+            return;
+        }
+
+        let column_names = get_column_names_from_insert_into_query(tokens);
+        let values = get_column_values_from_insert_into_query(tokens)
+            .into_iter()
+            .map(|value| match value {
+                Token::Number(value, _) => value.parse::<i128>().ok(),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        self.track(database_name, table_name, &column_names, values);
+    }
+
+    /// same as `track_insert_into` for a `COPY` payload row
+    pub fn track_copy_row(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+        column_names: &Vec<String>,
+        row: &str,
+    ) {
+        if !self.is_tracked(database_name, table_name) {
+            return;
+        }
+
+        let values = get_column_values_from_copy_row(row)
+            .into_iter()
+            .map(|value| value.and_then(|value| value.parse::<i128>().ok()))
+            .collect::<Vec<_>>();
+
+        self.track(database_name, table_name, column_names, values);
+    }
+
+    fn track(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+        column_names: &Vec<String>,
+        values: Vec<Option<i128>>,
+    ) {
+        let owned_columns = match self
+            .owned_columns
+            .get(format!("{}.{}", database_name, table_name).as_str())
+        {
+            Some(owned_columns) => owned_columns,
+            None => return,
+        };
+
+        for (column_name, sequence_name) in owned_columns {
+            let value = column_names
+                .iter()
+                .position(|name| name.trim_matches('"') == column_name)
+                .and_then(|idx| values.get(idx).copied().flatten());
+
+            if let Some(value) = value {
+                let max_value = self
+                    .max_values
+                    .entry(sequence_name.clone())
+                    .or_insert(value);
+                *max_value = (*max_value).max(value);
+            }
+        }
+    }
+
+    /// a `SELECT pg_catalog.setval(...)` statement setting the sequence to the greatest value of its column -
+    /// `None` if the statement is not a `setval` of a tracked sequence
+    pub fn setval(&self, tokens: &Vec<Token>) -> Option<String> {
+        let tokens = tokens
+            .iter()
+            .filter(|token| !matches!(token, Token::Whitespace(_)))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let first_word = get_word_value_at_position(&tokens, 0).unwrap_or_default();
+
+        let is_setval = first_word.eq_ignore_ascii_case("SELECT")
+            && get_word_value_at_position(&tokens, 1) == Some("pg_catalog")
+            && tokens.get(2) == Some(&Token::Period)
+            && get_word_value_at_position(&tokens, 3) == Some("setval");
+
+        let sequence_literal = match tokens.get(5) {
+            Some(Token::SingleQuotedString(sequence_literal)) if is_setval => sequence_literal,
+            _ => return None,
+        };
+
+        let sequence_name = sequence_literal.replace('"', "");
+        let is_tracked = self
+            .owned_columns
+            .values()
+            .flatten()
+            .any(|(_, name)| *name == sequence_name);
+
+        if !is_tracked {
+            return None;
+        }
+
+        let setval = match self.max_values.get(sequence_name.as_str()) {
+            // the next value is the greatest one plus one
+            Some(max_value) => format!(
+                "SELECT pg_catalog.setval('{}', {}, true);",
+                sequence_literal.replace('\'', "''"),
+                max_value
+            ),
+            // no row - the next value is the first one
+            None => format!(
+                "SELECT pg_catalog.setval('{}', 1, false);",
+                sequence_literal.replace('\'', "''")
+            ),
+        };
+
+        Some(setval)
+    }
+}
+
+/// the words of a statement - the qualified names are joined by a period (e.g. `public.users.id`)
+fn words(tokens: &Vec<Token>) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+    let mut is_qualified = false;
+
+    for token in tokens {
+        match token {
+            Token::Word(word) if is_qualified => {
+                if let Some(last_word) = words.last_mut() {
+                    last_word.push('.');
+                    last_word.push_str(word.value.as_str());
+                }
+                is_qualified = false;
+            }
+            Token::Word(word) => words.push(word.value.clone()),
+            Token::Period => is_qualified = true,
+            _ => {}
+        }
+    }
+
+    words
+}
@@ -1,15 +1,27 @@
-use std::io::{Error, ErrorKind};
-use std::sync::mpsc;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
+use std::time::Duration;
 
-use crate::datastore::Datastore;
+use log::{info, warn};
+
+use crate::datastore::{Datastore, DumpManifest, DumpManifestPart, ReadOptions};
+use crate::interruption::{interrupted, InterruptionListener};
+use crate::source::postgres::{RowTables, TableRow};
 use crate::source::SourceOptions;
 use crate::tasks::{MaxBytes, Message, Task, TransferredBytes};
-use crate::types::{to_bytes, Queries};
+use crate::types::{to_bytes, Queries, Query};
+use crate::utils::{get_replibyte_version, stable_hash};
 use crate::Source;
 
 type DataMessage = (u16, Queries);
 
+/// the index file is written once all the parts are stored - a transient failure must not lose them
+const INDEX_FILE_WRITE_ATTEMPTS: u64 = 3;
+
 /// FullDumpTask is a wrapping struct to execute the synchronization between a *Source* and a *Datastore*
 pub struct FullDumpTask<'a, S>
 where
@@ -18,6 +30,13 @@ where
     source: S,
     datastore: Box<dyn Datastore>,
     options: SourceOptions<'a>,
+    interrupted: Arc<AtomicBool>,
+    buffer_size: usize,
+    min_part_size: usize,
+    max_parts: Option<u16>,
+    ttl_days: Option<i64>,
+    hash_tables: bool,
+    since_table_hashes: Option<BTreeMap<String, String>>,
 }
 
 impl<'a, S> FullDumpTask<'a, S>
@@ -29,8 +48,41 @@ where
             source,
             datastore,
             options,
+            interrupted: interrupted(),
+            // buffer of 100MB in memory to use and re-use to upload data into datastore
+            buffer_size: 100 * 1024 * 1024,
+            min_part_size: 0,
+            max_parts: None,
+            ttl_days: None,
+            hash_tables: false,
+            since_table_hashes: None,
         }
     }
+
+    /// Merge the queries into parts of `min_part_size` bytes at least - except the last part
+    pub fn set_min_part_size(&mut self, min_part_size: usize) {
+        self.min_part_size = min_part_size;
+    }
+
+    /// Write `max_parts` parts at most - the last part holds the rest of the dump, it is kept in memory until the end
+    pub fn set_max_parts(&mut self, max_parts: Option<u16>) {
+        self.max_parts = max_parts;
+    }
+
+    /// Delete the dumps older than `ttl_days` days once the dump is created
+    pub fn set_ttl_days(&mut self, ttl_days: Option<i64>) {
+        self.ttl_days = ttl_days;
+    }
+
+    /// Record a hash of the rows of every table in the index file - the source must be PostgreSQL
+    pub fn set_hash_tables(&mut self, hash_tables: bool) {
+        self.hash_tables = hash_tables;
+    }
+
+    /// Skip the rows of the tables whose hash is the one recorded by a previous dump - the source must be PostgreSQL
+    pub fn set_since_table_hashes(&mut self, since_table_hashes: Option<BTreeMap<String, String>>) {
+        self.since_table_hashes = since_table_hashes;
+    }
 }
 
 impl<'a, S> Task for FullDumpTask<'a, S>
@@ -44,12 +96,32 @@ where
         // initialize the source
         let _ = self.source.init()?;
 
+        // on Ctrl-C, stop the dump and remove what has already been uploaded
+        let _interruption_listener = InterruptionListener::new();
+        let interrupted = self.interrupted;
+
+        // the parts can be appended to an existing dump - which must not be removed on Ctrl-C
+        let dump_exists = self
+            .datastore
+            .index_file()
+            .map(|index_file| {
+                index_file
+                    .dumps
+                    .iter()
+                    .any(|dump| dump.directory_name.as_str() == self.datastore.dump_name())
+            })
+            .unwrap_or(false);
+
         let (tx, rx) = mpsc::sync_channel::<Message<DataMessage>>(1);
         let datastore = self.datastore;
 
-        let join_handle = thread::spawn(move || -> Result<(), Error> {
+        // the index file is not updated by the upload - the dump is only visible once all its parts are stored
+        let join_handle = thread::spawn(move || {
             // managing Datastore (S3) upload here
             let datastore = datastore;
+            let mut size = 0usize;
+            let mut raw_size = 0usize;
+            let mut parts = vec![];
 
             loop {
                 let result = match rx.recv() {
@@ -59,18 +131,30 @@ where
                 };
 
                 if let Ok((chunk_part, queries)) = result {
-                    let _ = match datastore.write(chunk_part, to_bytes(queries)) {
-                        Ok(_) => {}
-                        Err(err) => return Err(Error::new(ErrorKind::Other, format!("{}", err))),
+                    let data = to_bytes(queries);
+                    raw_size += data.len();
+                    parts.push(DumpManifestPart {
+                        part: chunk_part,
+                        raw_size: data.len(),
+                        checksum: format!("{:016x}", stable_hash(&data)),
+                    });
+
+                    match datastore.write_part(chunk_part, data) {
+                        Ok(part_size) => size += part_size,
+                        Err(err) => {
+                            let err = Error::new(ErrorKind::Other, format!("{}", err));
+                            return (datastore, Err(err));
+                        }
                     };
                 }
             }
 
-            Ok(())
+            (datastore, Ok((size, raw_size, parts)))
         });
 
-        // buffer of 100MB in memory to use and re-use to upload data into datastore
-        let buffer_size = 100 * 1024 * 1024;
+        let buffer_size = self.buffer_size;
+        let min_part_size = self.min_part_size;
+        let max_parts = self.max_parts;
         let mut queries = vec![];
         let mut consumed_buffer_size = 0usize;
         let mut total_transferred_bytes = 0usize;
@@ -82,8 +166,18 @@ where
             buffer_size * (chunk_part as usize + 1),
         );
 
-        let _ = self.source.read(self.options, |_original_query, query| {
-            if consumed_buffer_size + query.data().len() > buffer_size {
+        let mut push_query = |query: Query| {
+            let is_last_part = match max_parts {
+                Some(max_parts) => chunk_part + 1 >= max_parts,
+                None => false,
+            };
+
+            // small (or empty) parts are merged, and the last allowed part is not sent before the end of the dump
+            if consumed_buffer_size + query.data().len() > buffer_size
+                && !queries.is_empty()
+                && consumed_buffer_size >= min_part_size
+                && !is_last_part
+            {
                 chunk_part += 1;
                 consumed_buffer_size = 0;
                 // TODO .clone() - look if we do not consume more mem
@@ -101,7 +195,96 @@ where
                 buffer_size * (chunk_part as usize + 1),
             );
             queries.push(query);
-        })?;
+        };
+
+        // recorded in the manifest of the dump
+        let transformers = self
+            .options
+            .transformers
+            .iter()
+            .map(|transformer| {
+                let column = format!(
+                    "{}.{}.{}",
+                    transformer.database_name(),
+                    transformer.table_name(),
+                    transformer.column_name()
+                );
+
+                (column, transformer.id().to_string())
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let hash_tables = self.hash_tables || self.since_table_hashes.is_some();
+        let mut row_tables = RowTables::default();
+        let mut table_hashes = TableHashes::default();
+        let mut copy_table: Option<String> = None;
+        let mut unchanged_tables = self.since_table_hashes.map(UnchangedTables::new);
+        let mut unchanged_tables_error: Option<Error> = None;
+
+        let read_result = self.source.read(self.options, |original_query, query| {
+            if interrupted.load(Ordering::SeqCst) || unchanged_tables_error.is_some() {
+                // drop the remaining queries
+                return;
+            }
+
+            if !hash_tables {
+                push_query(query);
+                return;
+            }
+
+            let row_table = row_tables.next(&String::from_utf8_lossy(original_query.data()));
+
+            match &row_table {
+                TableRow::Insert(table) => table_hashes.add_row(table, original_query.data()),
+                TableRow::CopyStart(table) => copy_table = Some(table.clone()),
+                TableRow::CopyRow => {
+                    if let Some(table) = &copy_table {
+                        table_hashes.add_row(table, original_query.data());
+                    }
+                }
+                TableRow::CopyEnd | TableRow::Other => {}
+            }
+
+            match unchanged_tables.as_mut() {
+                Some(unchanged_tables) => {
+                    if let Err(err) =
+                        unchanged_tables.push(&row_table, query, &table_hashes, &mut push_query)
+                    {
+                        unchanged_tables_error = Some(err);
+                    }
+                }
+                None => push_query(query),
+            }
+        });
+
+        if interrupted.load(Ordering::SeqCst) {
+            let _ = tx.send(Message::EOF);
+            // wait for the parts being uploaded before removing them
+            let (datastore, _) = join_handle.join().unwrap();
+            remove_partial_dump(datastore.as_ref(), dump_exists);
+
+            return Err(Error::new(ErrorKind::Interrupted, "dump interrupted"));
+        }
+
+        let read_result = read_result
+            .and_then(|_| match unchanged_tables_error {
+                Some(err) => Err(err),
+                None => Ok(()),
+            })
+            // the rows of the last table are all read
+            .and_then(|_| match unchanged_tables.as_mut() {
+                Some(unchanged_tables) => unchanged_tables.finish(&table_hashes, &mut push_query),
+                None => Ok(()),
+            });
+
+        if let Err(err) = read_result {
+            let _ = tx.send(Message::EOF);
+            // the parts already uploaded are useless without the rest of the dump
+            let (datastore, _) = join_handle.join().unwrap();
+            remove_partial_dump(datastore.as_ref(), dump_exists);
+
+            return Err(err);
+        }
 
         progress_callback(total_transferred_bytes, total_transferred_bytes);
 
@@ -109,8 +292,1059 @@ where
         let _ = tx.send(Message::Data((chunk_part, queries)));
         let _ = tx.send(Message::EOF);
         // wait for end of upload execution
-        join_handle.join().unwrap()?;
+        let (mut datastore, upload_result) = join_handle.join().unwrap();
+
+        let (size, raw_size, parts) = match upload_result {
+            Ok(sizes) => sizes,
+            Err(err) => {
+                remove_partial_dump(datastore.as_ref(), dump_exists);
+                return Err(err);
+            }
+        };
+
+        let rows = table_hashes.rows();
+        if hash_tables {
+            datastore.set_table_hashes(table_hashes.into_hashes());
+        }
+
+        if let Err(err) = write_dump_entry(datastore.as_ref(), size, raw_size) {
+            remove_partial_dump(datastore.as_ref(), dump_exists);
+            return Err(err);
+        }
+
+        // cheap integrity check - the size recorded in the index file must be the size of the written parts
+        match datastore.size_mismatch(datastore.dump_name()) {
+            Ok(Some((recorded_size, parts_size))) => warn!(
+                "the size of the dump '{}' recorded in the index file ({} bytes) does not match the size of its parts ({} bytes)",
+                datastore.dump_name(),
+                recorded_size,
+                parts_size
+            ),
+            Ok(None) => {}
+            Err(err) => warn!("error while checking the size of the dump: {}", err),
+        }
+
+        // the dump is usable without its manifest - failing to write it must not fail the dump
+        if let Err(err) =
+            write_dump_manifest(datastore.as_ref(), dump_exists, transformers, rows, parts)
+        {
+            warn!("error while writing the manifest of the dump: {}", err);
+        }
+
+        if let Some(ttl_days) = self.ttl_days {
+            info!("deleting the dumps older than {} days", ttl_days);
+            // the dump is created - failing to prune the expired ones must not fail it
+            if let Err(err) = datastore.delete_older_than(ttl_days) {
+                warn!("error while deleting the expired dumps: {}", err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// order-independent hash of the original rows of every table - with their count
+#[derive(Default)]
+struct TableHashes {
+    tables: BTreeMap<String, (u64, u64)>,
+}
+
+impl TableHashes {
+    fn add_row(&mut self, table: &str, row: &[u8]) {
+        let hash = stable_hash(row);
+
+        match self.tables.get_mut(table) {
+            Some((sum, rows)) => {
+                *sum = sum.wrapping_add(hash);
+                *rows += 1;
+            }
+            None => {
+                let _ = self.tables.insert(table.to_string(), (hash, 1));
+            }
+        }
+    }
+
+    fn rows(&self) -> BTreeMap<String, u64> {
+        self.tables
+            .iter()
+            .map(|(table, (_, rows))| (table.clone(), *rows))
+            .collect()
+    }
+
+    fn hash(&self, table: &str) -> Option<String> {
+        self.tables
+            .get(table)
+            .map(|(sum, rows)| to_table_hash(*sum, *rows))
+    }
+
+    fn into_hashes(self) -> BTreeMap<String, String> {
+        self.tables
+            .into_iter()
+            .map(|(table, (sum, rows))| (table, to_table_hash(sum, rows)))
+            .collect()
+    }
+}
+
+fn to_table_hash(sum: u64, rows: u64) -> String {
+    format!("{:016x}-{}", sum, rows)
+}
+
+/// drop the rows of the tables unchanged since a previous dump - the rows of the table being read are kept aside
+/// until all of them are read
+struct UnchangedTables {
+    since_table_hashes: BTreeMap<String, String>,
+    table_block: Option<TableBlock>,
+    read_tables: HashSet<String>,
+}
+
+impl UnchangedTables {
+    fn new(since_table_hashes: BTreeMap<String, String>) -> Self {
+        UnchangedTables {
+            since_table_hashes,
+            table_block: None,
+            read_tables: HashSet::new(),
+        }
+    }
+
+    fn push<F: FnMut(Query)>(
+        &mut self,
+        row_table: &TableRow,
+        query: Query,
+        table_hashes: &TableHashes,
+        mut push_query: F,
+    ) -> Result<(), Error> {
+        let is_same_table = match (row_table, &self.table_block) {
+            (TableRow::CopyRow | TableRow::CopyEnd, _) => true,
+            (TableRow::Insert(table), Some(table_block)) => table_block.table == *table,
+            _ => false,
+        };
+
+        if !is_same_table {
+            let _ = self.finish(table_hashes, &mut push_query)?;
+
+            if let TableRow::Insert(table) | TableRow::CopyStart(table) = row_table {
+                self.table_block = Some(TableBlock::new(table.clone())?);
+            }
+        }
+
+        match self.table_block.as_mut() {
+            Some(table_block) => table_block.write(&query)?,
+            None => push_query(query),
+        }
+
+        if *row_table == TableRow::CopyEnd {
+            let _ = self.finish(table_hashes, &mut push_query)?;
+        }
 
         Ok(())
     }
+
+    /// write the rows of the table being read - unless they are the same as in the previous dump
+    fn finish<F: FnMut(Query)>(
+        &mut self,
+        table_hashes: &TableHashes,
+        push_query: F,
+    ) -> Result<(), Error> {
+        let table_block = match self.table_block.take() {
+            Some(table_block) => table_block,
+            None => return Ok(()),
+        };
+
+        // a table whose rows are not contiguous is kept - the hash of its first rows is not the one of the table
+        let is_first_block = self.read_tables.insert(table_block.table.clone());
+        let hash = table_hashes.hash(table_block.table.as_str());
+
+        if is_first_block
+            && hash.is_some()
+            && hash.as_ref() == self.since_table_hashes.get(&table_block.table)
+        {
+            info!(
+                "table '{}' unchanged since the previous dump - skipped",
+                table_block.table
+            );
+
+            return Ok(());
+        }
+
+        table_block.replay(push_query)
+    }
+}
+
+/// queries of a table written into a temporary file - a table can be bigger than the memory
+struct TableBlock {
+    table: String,
+    writer: BufWriter<File>,
+}
+
+impl TableBlock {
+    fn new(table: String) -> Result<Self, Error> {
+        Ok(TableBlock {
+            table,
+            writer: BufWriter::new(tempfile::tempfile()?),
+        })
+    }
+
+    fn write(&mut self, query: &Query) -> Result<(), Error> {
+        self.writer
+            .write_all(&(query.data().len() as u64).to_le_bytes())?;
+        self.writer.write_all(query.data())
+    }
+
+    /// read the queries back in the order they have been written
+    fn replay<F: FnMut(Query)>(self, mut push_query: F) -> Result<(), Error> {
+        let mut file = self.writer.into_inner().map_err(|err| err.into_error())?;
+        let _ = file.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(file);
+        let mut len = [0u8; 8];
+
+        loop {
+            match reader.read_exact(&mut len) {
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+                Err(err) => return Err(err),
+            }
+
+            let mut data = vec![0u8; u64::from_le_bytes(len) as usize];
+            reader.read_exact(&mut data)?;
+            push_query(Query(data));
+        }
+    }
+}
+
+/// best effort - remove the parts of a dump which has not been fully written
+fn remove_partial_dump(datastore: &dyn Datastore, dump_exists: bool) {
+    let dump_name = datastore.dump_name().to_string();
+
+    if dump_exists {
+        warn!(
+            "the dump '{}' existed before - the parts already appended are kept",
+            dump_name
+        );
+    } else {
+        info!("removing partial dump '{}'", dump_name);
+        let _ = datastore.delete_by_name(dump_name);
+    }
+}
+
+/// write the manifest of the dump once it is recorded in the index file - the parts of an appended dump are added to
+/// its manifest
+fn write_dump_manifest(
+    datastore: &dyn Datastore,
+    dump_exists: bool,
+    transformers: BTreeMap<String, String>,
+    rows: BTreeMap<String, u64>,
+    parts: Vec<DumpManifestPart>,
+) -> Result<(), Error> {
+    let dump_name = datastore.dump_name().to_string();
+    let dump = datastore
+        .index_file()?
+        .find_dump(&ReadOptions::Dump {
+            name: dump_name.clone(),
+        })?
+        .clone();
+
+    if !dump_exists {
+        return datastore.write_dump_manifest(&DumpManifest {
+            replibyte_version: get_replibyte_version().to_string(),
+            dump,
+            transformers,
+            rows,
+            parts,
+        });
+    }
+
+    let mut manifest = match datastore.read_dump_manifest(dump_name.as_str()) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            // e.g. the dump has been created before the manifests - a manifest of the appended parts would be wrong
+            info!(
+                "the manifest of the dump '{}' is not updated - it can't be read: {}",
+                dump_name, err
+            );
+            return Ok(());
+        }
+    };
+
+    // the parts are numbered after the ones of the dump
+    let part_offset = manifest.parts.last().map(|part| part.part).unwrap_or(0);
+    manifest
+        .parts
+        .extend(parts.into_iter().map(|part| DumpManifestPart {
+            part: part.part + part_offset,
+            ..part
+        }));
+    manifest.transformers.extend(transformers);
+    // the rows of the appended parts are not counted
+    manifest.rows.clear();
+    manifest.dump = dump;
+
+    datastore.write_dump_manifest(&manifest)
+}
+
+/// record the dump in the index file - retried as its parts are already stored
+fn write_dump_entry(datastore: &dyn Datastore, size: usize, raw_size: usize) -> Result<(), Error> {
+    let mut attempt = 1;
+
+    loop {
+        match datastore.write_dump_entry(size, raw_size) {
+            Ok(_) => return Ok(()),
+            Err(err) if attempt < INDEX_FILE_WRITE_ATTEMPTS => {
+                warn!(
+                    "error while writing the index file (attempt {}/{}): {}",
+                    attempt, INDEX_FILE_WRITE_ATTEMPTS, err
+                );
+
+                thread::sleep(Duration::from_millis(500 * attempt));
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::io::{Error, ErrorKind};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use tempfile::tempdir;
+
+    use std::cell::RefCell;
+
+    use crate::connector::Connector;
+    use crate::datastore::local_disk::LocalDisk;
+    use crate::datastore::{Datastore, DumpManifest, ReadOptions};
+    use crate::destination::Destination;
+    use crate::source::{Source, SourceOptions};
+    use crate::tasks::full_dump::FullDumpTask;
+    use crate::tasks::full_restore::FullRestoreTask;
+    use crate::tasks::Task;
+    use crate::transformer::keep_first_char::KeepFirstCharTransformer;
+    use crate::transformer::{Transformer, TransformerMetrics};
+    use crate::types::{Bytes, OriginalQuery, Query};
+    use crate::utils::stable_hash;
+
+    /// source raising the interruption flag in the middle of the dump
+    struct InterruptedSource {
+        interrupted: Arc<AtomicBool>,
+    }
+
+    impl Connector for InterruptedSource {
+        fn init(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Source for InterruptedSource {
+        fn read<F: FnMut(OriginalQuery, Query)>(
+            &self,
+            _options: SourceOptions,
+            mut query_callback: F,
+        ) -> Result<(), Error> {
+            for i in 0..10 {
+                if i == 5 {
+                    self.interrupted.store(true, Ordering::SeqCst);
+                }
+
+                let query = format!("INSERT INTO public.users (id) VALUES ({});", i);
+                query_callback(
+                    Query(query.as_bytes().to_vec()),
+                    Query(query.as_bytes().to_vec()),
+                );
+            }
+
+            Ok(())
+        }
+    }
+
+    /// source failing in the middle of the dump
+    struct FailingSource {}
+
+    impl Connector for FailingSource {
+        fn init(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Source for FailingSource {
+        fn read<F: FnMut(OriginalQuery, Query)>(
+            &self,
+            _options: SourceOptions,
+            mut query_callback: F,
+        ) -> Result<(), Error> {
+            for i in 0..5 {
+                let query = format!("INSERT INTO public.users (id) VALUES ({});", i);
+                query_callback(
+                    Query(query.as_bytes().to_vec()),
+                    Query(query.as_bytes().to_vec()),
+                );
+            }
+
+            Err(Error::new(ErrorKind::Other, "connection lost"))
+        }
+    }
+
+    /// source sending the rows of a single table
+    struct TableSource {
+        table: &'static str,
+    }
+
+    impl Connector for TableSource {
+        fn init(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Source for TableSource {
+        fn read<F: FnMut(OriginalQuery, Query)>(
+            &self,
+            _options: SourceOptions,
+            mut query_callback: F,
+        ) -> Result<(), Error> {
+            for i in 0..5 {
+                let query = format!("INSERT INTO public.{} (id) VALUES ({});", self.table, i);
+                query_callback(
+                    Query(query.as_bytes().to_vec()),
+                    Query(query.as_bytes().to_vec()),
+                );
+            }
+
+            Ok(())
+        }
+    }
+
+    /// source sending the rows of `users` by `COPY` and the ones of `orders` by `INSERT INTO`
+    struct UsersAndOrdersSource {
+        order_amount: u32,
+    }
+
+    impl Connector for UsersAndOrdersSource {
+        fn init(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Source for UsersAndOrdersSource {
+        fn read<F: FnMut(OriginalQuery, Query)>(
+            &self,
+            _options: SourceOptions,
+            mut query_callback: F,
+        ) -> Result<(), Error> {
+            let mut queries = vec![
+                "CREATE TABLE public.users (id integer, name text);".to_string(),
+                "COPY public.users (id, name) FROM stdin;".to_string(),
+                "1\tjane".to_string(),
+                "2\tjohn".to_string(),
+                "\\.".to_string(),
+            ];
+
+            for i in 0..2 {
+                queries.push(format!(
+                    "INSERT INTO public.orders (id, amount) VALUES ({}, {});",
+                    i, self.order_amount
+                ));
+            }
+
+            queries.push(
+                "ALTER TABLE ONLY public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id);"
+                    .to_string(),
+            );
+
+            for query in queries {
+                query_callback(
+                    Query(query.as_bytes().to_vec()),
+                    Query(query.as_bytes().to_vec()),
+                );
+            }
+
+            Ok(())
+        }
+    }
+
+    /// source sending many small queries
+    struct SmallQueriesSource {}
+
+    impl Connector for SmallQueriesSource {
+        fn init(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Source for SmallQueriesSource {
+        fn read<F: FnMut(OriginalQuery, Query)>(
+            &self,
+            _options: SourceOptions,
+            mut query_callback: F,
+        ) -> Result<(), Error> {
+            for i in 0..200 {
+                let query = format!("INSERT INTO public.events (id) VALUES ({});", i);
+                query_callback(
+                    Query(query.as_bytes().to_vec()),
+                    Query(query.as_bytes().to_vec()),
+                );
+            }
+
+            Ok(())
+        }
+    }
+
+    /// destination keeping the restored data in memory
+    #[derive(Default)]
+    struct InMemoryDestination {
+        data: RefCell<Bytes>,
+    }
+
+    impl Connector for InMemoryDestination {
+        fn init(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Destination for InMemoryDestination {
+        fn write(&self, data: Bytes) -> Result<(), Error> {
+            self.data.borrow_mut().extend(data);
+            Ok(())
+        }
+    }
+
+    fn dump_table(
+        datastore: Box<dyn Datastore>,
+        table: &'static str,
+        ttl_days: Option<i64>,
+    ) -> Result<(), Error> {
+        let transformer_metrics = TransformerMetrics::default();
+        let options = SourceOptions {
+            transformers: &vec![],
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &transformer_metrics,
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut task = FullDumpTask::new(TableSource { table }, datastore, options);
+        task.interrupted = Arc::new(AtomicBool::new(false));
+        // force several parts by dump
+        task.buffer_size = 100;
+        task.set_ttl_days(ttl_days);
+        task.run(|_, _| {})
+    }
+
+    #[test]
+    fn append_to_existing_dump() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let new_local_disk = || {
+            let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+            local_disk.set_source_type("postgresql".to_string());
+            let _ = local_disk.init().expect("local_disk init failed");
+            local_disk
+        };
+
+        let mut local_disk = new_local_disk();
+        local_disk.set_dump_name("dump-1".to_string());
+        assert!(dump_table(Box::new(local_disk), "users", None).is_ok());
+
+        let last_part = new_local_disk().last_part("dump-1").unwrap();
+        assert!(last_part > 1);
+
+        // a dump can't be appended to a dump of another engine
+        let mut local_disk = new_local_disk();
+        assert!(local_disk.append_to("dump-1".to_string(), "mysql").is_err());
+
+        let mut local_disk = new_local_disk();
+        assert!(local_disk
+            .append_to("dump-1".to_string(), "postgresql")
+            .is_ok());
+        assert!(dump_table(Box::new(local_disk), "orders", None).is_ok());
+
+        // the parts have been added to the same dump
+        let local_disk = new_local_disk();
+        let index_file = local_disk.index_file().unwrap();
+        assert_eq!(index_file.dumps.len(), 1);
+        assert_eq!(
+            index_file.dumps[0].source_type,
+            Some("postgresql".to_string())
+        );
+        assert_eq!(local_disk.last_part("dump-1").unwrap(), last_part * 2);
+
+        // and to its manifest
+        let manifest = local_disk.read_dump_manifest("dump-1").unwrap();
+        assert_eq!(
+            manifest
+                .parts
+                .iter()
+                .map(|part| part.part)
+                .collect::<Vec<_>>(),
+            (1..=last_part * 2).collect::<Vec<_>>()
+        );
+        assert_eq!(manifest.dump.size, index_file.dumps[0].size);
+
+        // and the whole dump is restored
+        let mut destination = InMemoryDestination::default();
+        let task = FullRestoreTask::new(
+            &mut destination,
+            Box::new(local_disk),
+            ReadOptions::Dump {
+                name: "dump-1".to_string(),
+            },
+        );
+        assert!(task.run(|_, _| {}).is_ok());
+
+        let restored = String::from_utf8(destination.data.into_inner()).unwrap();
+        let mut expected = String::new();
+        for table in ["users", "orders"] {
+            for i in 0..5 {
+                expected.push_str(&format!(
+                    "INSERT INTO public.{} (id) VALUES ({});\n",
+                    table, i
+                ));
+            }
+        }
+        assert_eq!(restored, expected);
+    }
+
+    #[test]
+    fn since_dump_skips_unchanged_tables() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let new_local_disk = |dump_name: &str| {
+            let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+            local_disk.set_dump_name(dump_name.to_string());
+            let _ = local_disk.init().expect("local_disk init failed");
+            local_disk
+        };
+
+        let table_hashes = |dump_name: &str| {
+            new_local_disk(dump_name)
+                .index_file()
+                .unwrap()
+                .find_dump(&ReadOptions::Dump {
+                    name: dump_name.to_string(),
+                })
+                .unwrap()
+                .table_hashes
+                .clone()
+        };
+
+        let restore = |dump_name: &str| {
+            let mut destination = InMemoryDestination::default();
+            let task = FullRestoreTask::new(
+                &mut destination,
+                Box::new(new_local_disk(dump_name)),
+                ReadOptions::Dump {
+                    name: dump_name.to_string(),
+                },
+            );
+            assert!(task.run(|_, _| {}).is_ok());
+            String::from_utf8(destination.data.into_inner()).unwrap()
+        };
+
+        for (dump_name, order_amount, since_dump) in
+            [("dump-1", 10, None), ("dump-2", 20, Some("dump-1"))]
+        {
+            let transformer_metrics = TransformerMetrics::default();
+            let options = SourceOptions {
+                transformers: &vec![],
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &transformer_metrics,
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let source = UsersAndOrdersSource { order_amount };
+            let mut task = FullDumpTask::new(source, Box::new(new_local_disk(dump_name)), options);
+            task.interrupted = Arc::new(AtomicBool::new(false));
+            task.set_hash_tables(true);
+            task.set_since_table_hashes(since_dump.map(table_hashes));
+            assert!(task.run(|_, _| {}).is_ok());
+        }
+
+        // the rows of users are the same - only the ones of orders have changed
+        let hashes_1 = table_hashes("dump-1");
+        let hashes_2 = table_hashes("dump-2");
+        assert_eq!(
+            hashes_1.keys().collect::<Vec<_>>(),
+            vec!["public.orders", "public.users"]
+        );
+        assert_eq!(hashes_1["public.users"], hashes_2["public.users"]);
+        assert_ne!(hashes_1["public.orders"], hashes_2["public.orders"]);
+
+        assert!(restore("dump-1").contains("1\tjane\n"));
+
+        // the unchanged table is omitted from the new dump - not its schema
+        assert_eq!(
+            restore("dump-2"),
+            "CREATE TABLE public.users (id integer, name text);
+INSERT INTO public.orders (id, amount) VALUES (0, 20);
+INSERT INTO public.orders (id, amount) VALUES (1, 20);
+ALTER TABLE ONLY public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+"
+        );
+    }
+
+    #[test]
+    fn write_dump_manifest() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let new_local_disk = |dump_name: &str| {
+            let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+            local_disk.set_dump_name(dump_name.to_string());
+            local_disk.set_source_type("postgresql".to_string());
+            let _ = local_disk.init().expect("local_disk init failed");
+            local_disk
+        };
+
+        let transformers: Vec<Box<dyn Transformer>> = vec![Box::new(
+            KeepFirstCharTransformer::new("public", "orders", "amount"),
+        )];
+
+        for (dump_name, order_amount) in [("dump-1", 10), ("dump-2", 20)] {
+            let transformer_metrics = TransformerMetrics::default();
+            let options = SourceOptions {
+                transformers: &transformers,
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &transformer_metrics,
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let source = UsersAndOrdersSource { order_amount };
+            let mut task = FullDumpTask::new(source, Box::new(new_local_disk(dump_name)), options);
+            task.interrupted = Arc::new(AtomicBool::new(false));
+            // force several parts by dump
+            task.buffer_size = 100;
+            task.set_hash_tables(true);
+            assert!(task.run(|_, _| {}).is_ok());
+        }
+
+        let local_disk = new_local_disk("dump-3");
+        let index_file = local_disk.index_file().unwrap();
+
+        for dump in &index_file.dumps {
+            // the manifest is a JSON file next to the parts
+            let manifest_path = dir.path().join(&dump.directory_name).join("manifest.json");
+            let manifest: DumpManifest =
+                serde_json::from_slice(&fs::read(manifest_path).unwrap()).unwrap();
+
+            assert_eq!(manifest.dump, *dump);
+            assert_eq!(manifest.dump.source_type, Some("postgresql".to_string()));
+            assert_eq!(
+                manifest.transformers,
+                BTreeMap::from([(
+                    "public.orders.amount".to_string(),
+                    "keep-first-char".to_string()
+                )])
+            );
+            assert_eq!(
+                manifest.rows,
+                BTreeMap::from([
+                    ("public.orders".to_string(), 2),
+                    ("public.users".to_string(), 2)
+                ])
+            );
+
+            let last_part = local_disk.last_part(&dump.directory_name).unwrap();
+            assert!(last_part > 1);
+            assert_eq!(
+                manifest
+                    .parts
+                    .iter()
+                    .map(|part| part.part)
+                    .collect::<Vec<_>>(),
+                (1..=last_part).collect::<Vec<_>>()
+            );
+
+            // the checksums are the ones of the parts before their compression
+            let mut parts = manifest.parts.iter();
+            let _ = local_disk
+                .read(
+                    &ReadOptions::Dump {
+                        name: dump.directory_name.clone(),
+                    },
+                    &mut |data| {
+                        let part = parts.next().unwrap();
+                        assert_eq!(part.raw_size, data.len());
+                        assert_eq!(part.checksum, format!("{:016x}", stable_hash(&data)));
+                    },
+                )
+                .unwrap();
+            assert!(parts.next().is_none());
+            assert_eq!(
+                manifest
+                    .parts
+                    .iter()
+                    .map(|part| part.raw_size)
+                    .sum::<usize>(),
+                dump.raw_size.unwrap()
+            );
+        }
+
+        // the manifest is deleted with the dump
+        local_disk.delete_by_name("dump-1".to_string()).unwrap();
+        assert!(local_disk.read_dump_manifest("dump-1").is_err());
+        assert!(local_disk.read_dump_manifest("dump-2").is_ok());
+    }
+
+    #[test]
+    fn interrupted_dump_leaves_no_orphaned_parts() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        local_disk.set_dump_name("dump-1".to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let source = InterruptedSource {
+            interrupted: interrupted.clone(),
+        };
+
+        let transformer_metrics = TransformerMetrics::default();
+        let options = SourceOptions {
+            transformers: &vec![],
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &transformer_metrics,
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut task = FullDumpTask::new(source, Box::new(local_disk), options);
+        task.interrupted = interrupted;
+        // force an upload for each query
+        task.buffer_size = 10;
+
+        assert!(task.run(|_, _| {}).is_err());
+
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+        assert!(local_disk.index_file().unwrap().dumps.is_empty());
+        assert!(!Path::new(&format!("{}/dump-1", dir.path().to_str().unwrap())).exists());
+    }
+
+    #[test]
+    fn failed_source_read_leaves_no_orphaned_parts() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        local_disk.set_dump_name("dump-1".to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+
+        let transformer_metrics = TransformerMetrics::default();
+        let options = SourceOptions {
+            transformers: &vec![],
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &transformer_metrics,
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut task = FullDumpTask::new(FailingSource {}, Box::new(local_disk), options);
+        task.interrupted = Arc::new(AtomicBool::new(false));
+        // the first queries are flushed into parts before the read fails
+        task.buffer_size = 10;
+
+        assert_eq!(
+            task.run(|_, _| {}).unwrap_err().to_string(),
+            "connection lost"
+        );
+
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+        assert!(local_disk.index_file().unwrap().dumps.is_empty());
+        assert!(!local_disk
+            .list_all_objects()
+            .unwrap()
+            .iter()
+            .any(|object| object.starts_with("dump-1/")));
+    }
+
+    #[test]
+    fn failed_part_upload_leaves_no_dump_entry() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let dir_path = dir.path().to_str().unwrap().to_string();
+        let new_local_disk = |dump_name: &str| {
+            let mut local_disk = LocalDisk::new(dir_path.clone());
+            local_disk.set_dump_name(dump_name.to_string());
+            let _ = local_disk.init().expect("local_disk init failed");
+            local_disk
+        };
+
+        assert!(dump_table(Box::new(new_local_disk("dump-1")), "users", None).is_ok());
+
+        // the second part of the next dump can't be written
+        fs::create_dir_all(format!("{}/dump-2/2.dump", dir_path)).unwrap();
+        assert!(dump_table(Box::new(new_local_disk("dump-2")), "users", None).is_err());
+
+        // the first part has been written but the dump is not in the index file
+        let index_file = new_local_disk("dump-3").index_file().unwrap();
+        assert_eq!(index_file.dumps.len(), 1);
+        assert_eq!(index_file.dumps[0].directory_name, "dump-1");
+        assert!(!Path::new(&format!("{}/dump-2", dir_path)).exists());
+    }
+
+    #[test]
+    fn dump_with_ttl_deletes_expired_dumps() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let new_local_disk = |dump_name: &str| {
+            let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+            local_disk.set_dump_name(dump_name.to_string());
+            let _ = local_disk.init().expect("local_disk init failed");
+            local_disk
+        };
+
+        assert!(dump_table(Box::new(new_local_disk("dump-1")), "users", None).is_ok());
+        assert!(dump_table(Box::new(new_local_disk("dump-2")), "users", None).is_ok());
+
+        // dump-1 has been created 31 days ago
+        let local_disk = new_local_disk("dump-1");
+        let mut index_file = local_disk.index_file().unwrap();
+        let days_31 = 31 * 24 * 60 * 60 * 1000;
+        index_file.dumps[0].created_at -= days_31;
+        local_disk.write_index_file(&index_file).unwrap();
+
+        assert!(dump_table(Box::new(new_local_disk("dump-3")), "users", Some(30)).is_ok());
+
+        let index_file = new_local_disk("dump-3").index_file().unwrap();
+        let dump_names: Vec<&str> = index_file
+            .dumps
+            .iter()
+            .map(|dump| dump.directory_name.as_str())
+            .collect();
+        assert_eq!(dump_names, vec!["dump-2", "dump-3"]);
+        assert!(!Path::new(&format!("{}/dump-1", dir.path().to_str().unwrap())).exists());
+    }
+
+    #[test]
+    fn merge_small_parts() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let new_local_disk = |dump_name: &str| {
+            let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+            local_disk.set_dump_name(dump_name.to_string());
+            let _ = local_disk.init().expect("local_disk init failed");
+            local_disk
+        };
+
+        let expected = (0..200)
+            .map(|i| format!("INSERT INTO public.events (id) VALUES ({});\n", i))
+            .collect::<String>();
+
+        for (dump_name, min_part_size, max_parts) in [
+            ("dump-1", 0, None),
+            ("dump-2", 0, Some(5)),
+            ("dump-3", 2000, None),
+            ("dump-4", 2000, Some(3)),
+        ] {
+            let transformer_metrics = TransformerMetrics::default();
+            let options = SourceOptions {
+                transformers: &vec![],
+                skip_config: &vec![],
+                database_subset: &None,
+                only_tables: &vec![],
+                exclude_schemas: &vec![],
+                column_tags: &vec![],
+                transform_default_values: false,
+                transformer_metrics: &transformer_metrics,
+                sort_rows: &vec![],
+                max_cpu_percent: None,
+                minimal: false,
+                ddl_rewrites: &vec![],
+                sequences: None,
+                content: None,
+                string_escaping: None,
+                fail_fast: false,
+                skip_large_objects: false,
+            };
+
+            let local_disk = new_local_disk(dump_name);
+            let mut task = FullDumpTask::new(SmallQueriesSource {}, Box::new(local_disk), options);
+            task.interrupted = Arc::new(AtomicBool::new(false));
+            // a part for each query by default
+            task.buffer_size = 10;
+            task.set_min_part_size(min_part_size);
+            task.set_max_parts(max_parts);
+            assert!(task.run(|_, _| {}).is_ok());
+
+            let local_disk = new_local_disk(dump_name);
+            let last_part = local_disk.last_part(dump_name).unwrap();
+            match (min_part_size, max_parts) {
+                (0, None) => assert_eq!(last_part, 200),
+                (_, Some(max_parts)) => assert_eq!(last_part, max_parts),
+                // about 9KB of queries
+                _ => assert!(last_part > 1 && last_part <= 5),
+            }
+
+            // the dump is restored the same way whatever its parts
+            let mut destination = InMemoryDestination::default();
+            let task = FullRestoreTask::new(
+                &mut destination,
+                Box::new(local_disk),
+                ReadOptions::Dump {
+                    name: dump_name.to_string(),
+                },
+            );
+            assert!(task.run(|_, _| {}).is_ok());
+            assert_eq!(
+                String::from_utf8(destination.data.into_inner()).unwrap(),
+                expected
+            );
+        }
+    }
 }
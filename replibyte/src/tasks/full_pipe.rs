@@ -0,0 +1,231 @@
+use std::io::Error;
+
+use crate::destination::Destination;
+use crate::source::SourceOptions;
+use crate::tasks::{MaxBytes, Task, TransferredBytes};
+use crate::types::to_bytes;
+use crate::Source;
+
+/// FullPipeTask is a wrapping struct to stream the queries of a *Source* straight into a *Destination* - without any *Datastore*
+pub struct FullPipeTask<'a, S, D>
+where
+    S: Source,
+    D: Destination,
+{
+    source: S,
+    destination: &'a mut D,
+    options: SourceOptions<'a>,
+    buffer_size: usize,
+}
+
+impl<'a, S, D> FullPipeTask<'a, S, D>
+where
+    S: Source,
+    D: Destination,
+{
+    pub fn new(source: S, destination: &'a mut D, options: SourceOptions<'a>) -> Self {
+        FullPipeTask {
+            source,
+            destination,
+            options,
+            // buffer of 100MB in memory - the same size as a dump part
+            buffer_size: 100 * 1024 * 1024,
+        }
+    }
+}
+
+impl<'a, S, D> Task for FullPipeTask<'a, S, D>
+where
+    S: Source,
+    D: Destination,
+{
+    fn run<F: FnMut(TransferredBytes, MaxBytes)>(
+        mut self,
+        mut progress_callback: F,
+    ) -> Result<(), Error> {
+        // initialize the source and the destination
+        self.source.init()?;
+        self.destination.init()?;
+
+        let destination = &*self.destination;
+        let buffer_size = self.buffer_size;
+        let mut queries = vec![];
+        let mut consumed_buffer_size = 0usize;
+        let mut total_transferred_bytes = 0usize;
+        let mut chunk_part = 0usize;
+        let mut write_result = Ok(());
+
+        // init progress
+        progress_callback(total_transferred_bytes, buffer_size);
+
+        let read_result = self.source.read(self.options, |_original_query, query| {
+            if write_result.is_err() {
+                // the destination failed - the remaining queries are dropped
+                return;
+            }
+
+            if consumed_buffer_size + query.data().len() > buffer_size {
+                chunk_part += 1;
+                consumed_buffer_size = 0;
+
+                let chunk = std::mem::take(&mut queries);
+                write_result = destination.write(to_bytes(chunk));
+            }
+
+            consumed_buffer_size += query.data().len();
+            total_transferred_bytes += query.data().len();
+            progress_callback(total_transferred_bytes, buffer_size * (chunk_part + 1));
+            queries.push(query);
+        });
+
+        read_result?;
+        write_result?;
+
+        destination.write(to_bytes(queries))?;
+
+        progress_callback(total_transferred_bytes, total_transferred_bytes);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{BufReader, Error, ErrorKind};
+
+    use crate::connector::Connector;
+    use crate::destination::Destination;
+    use crate::source::postgres::read_and_transform;
+    use crate::source::{Source, SourceOptions};
+    use crate::tasks::full_pipe::FullPipeTask;
+    use crate::tasks::Task;
+    use crate::transformer::keep_first_char::KeepFirstCharTransformer;
+    use crate::transformer::{Transformer, TransformerMetrics};
+    use crate::types::{Bytes, OriginalQuery, Query};
+
+    const DUMP: &str = "CREATE TABLE public.employees (employee_id smallint NOT NULL, last_name character varying(20) NOT NULL);
+
+INSERT INTO public.employees (employee_id, last_name) VALUES (1, 'Davolio');
+INSERT INTO public.employees (employee_id, last_name) VALUES (2, 'Fuller');
+
+COPY public.employees (employee_id, last_name) FROM stdin;
+3\tLeverling
+4\tPeacock
+\\.
+";
+
+    /// source reading a PostgreSQL dump from memory
+    struct DumpSource;
+
+    impl Connector for DumpSource {
+        fn init(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Source for DumpSource {
+        fn read<F: FnMut(OriginalQuery, Query)>(
+            &self,
+            options: SourceOptions,
+            query_callback: F,
+        ) -> Result<(), Error> {
+            read_and_transform(BufReader::new(DUMP.as_bytes()), options, query_callback)
+        }
+    }
+
+    /// destination keeping each write in memory - or failing them
+    #[derive(Default)]
+    struct InMemoryDestination {
+        writes: RefCell<Vec<Bytes>>,
+        fail: bool,
+    }
+
+    impl Connector for InMemoryDestination {
+        fn init(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Destination for InMemoryDestination {
+        fn write(&self, data: Bytes) -> Result<(), Error> {
+            if self.fail {
+                return Err(Error::new(ErrorKind::Other, "destination unavailable"));
+            }
+
+            self.writes.borrow_mut().push(data);
+            Ok(())
+        }
+    }
+
+    fn pipe(destination: &mut InMemoryDestination, buffer_size: usize) -> Result<(), Error> {
+        let t1: Box<dyn Transformer> = Box::new(KeepFirstCharTransformer::new(
+            "public",
+            "employees",
+            "last_name",
+        ));
+        let transformers = vec![t1];
+        let transformer_metrics = TransformerMetrics::default();
+
+        let options = SourceOptions {
+            transformers: &transformers,
+            skip_config: &vec![],
+            database_subset: &None,
+            only_tables: &vec![],
+            exclude_schemas: &vec![],
+            column_tags: &vec![],
+            transform_default_values: false,
+            transformer_metrics: &transformer_metrics,
+            sort_rows: &vec![],
+            max_cpu_percent: None,
+            minimal: false,
+            ddl_rewrites: &vec![],
+            sequences: None,
+            content: None,
+            string_escaping: None,
+            fail_fast: false,
+            skip_large_objects: false,
+        };
+
+        let mut task = FullPipeTask::new(DumpSource, destination, options);
+        task.buffer_size = buffer_size;
+        task.run(|_, _| {})
+    }
+
+    #[test]
+    fn pipe_source_into_destination() {
+        let mut destination = InMemoryDestination::default();
+        assert!(pipe(&mut destination, 100 * 1024 * 1024).is_ok());
+
+        let writes = destination.writes.into_inner();
+        assert_eq!(writes.len(), 1);
+
+        let restored = String::from_utf8(writes[0].clone()).unwrap();
+        assert!(restored.starts_with("CREATE TABLE public.employees"));
+        assert!(restored.contains("VALUES (1, 'D');"));
+        assert!(restored.contains("VALUES (2, 'F');"));
+        assert!(restored.contains("3\tL\n4\tP\n\\.\n"));
+        assert!(!restored.contains("Davolio"));
+        assert!(!restored.contains("Peacock"));
+    }
+
+    #[test]
+    fn pipe_in_several_writes() {
+        let mut destination = InMemoryDestination::default();
+        assert!(pipe(&mut destination, 100).is_ok());
+
+        let writes = destination.writes.into_inner();
+        assert!(writes.len() > 1);
+
+        // the writes are in order and complete
+        let mut piped = InMemoryDestination::default();
+        assert!(pipe(&mut piped, 100 * 1024 * 1024).is_ok());
+        assert_eq!(writes.concat(), piped.writes.into_inner().concat());
+
+        let mut destination = InMemoryDestination {
+            fail: true,
+            ..Default::default()
+        };
+        assert!(pipe(&mut destination, 100).is_err());
+    }
+}
@@ -1,10 +1,11 @@
-use std::io::Error;
+use std::io::{Error, ErrorKind, Read};
 use std::sync::mpsc;
 use std::thread;
 
 use crate::datastore::{Datastore, ReadOptions};
-use crate::destination::Destination;
-use crate::tasks::{MaxBytes, Message, Task, TransferredBytes};
+use crate::destination::postgres::{ColumnMapping, CopyBatching};
+use crate::destination::{Destination, RestoreReport};
+use crate::tasks::{read_dump, MaxBytes, Message, MessageReader, Task, TransferredBytes};
 use crate::types::Bytes;
 
 /// FullRestoreTask is a wrapping struct to execute the synchronization between a *Datastore* and a *Source*.
@@ -15,6 +16,11 @@ where
     destination: &'a mut D,
     datastore: Box<dyn Datastore>,
     read_options: ReadOptions,
+    truncate_target: bool,
+    column_mapping: ColumnMapping,
+    use_copy: bool,
+    concurrency: usize,
+    max_errors: Option<(usize, &'a mut RestoreReport)>,
 }
 
 impl<'a, D> FullRestoreTask<'a, D>
@@ -30,8 +36,39 @@ where
             destination,
             datastore,
             read_options,
+            truncate_target: false,
+            column_mapping: ColumnMapping::default(),
+            use_copy: false,
+            concurrency: 1,
+            max_errors: None,
         }
     }
+
+    /// empty the tables of the dump before restoring it
+    pub fn set_truncate_target(&mut self, truncate_target: bool) {
+        self.truncate_target = truncate_target;
+    }
+
+    /// rename or drop columns of the dump to match the destination (PostgreSQL only)
+    pub fn set_column_mapping(&mut self, column_mapping: ColumnMapping) {
+        self.column_mapping = column_mapping;
+    }
+
+    /// restore the `INSERT INTO` rows as `COPY ... FROM stdin;` payloads (PostgreSQL only)
+    pub fn set_use_copy(&mut self, use_copy: bool) {
+        self.use_copy = use_copy;
+    }
+
+    /// number of connections restoring the dump (PostgreSQL only)
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        self.concurrency = concurrency;
+    }
+
+    /// skip the failed statements until the `max_errors`th one, which aborts the restore (PostgreSQL only) -
+    /// the restored statements and the errors are recorded into `report`
+    pub fn set_max_errors(&mut self, max_errors: usize, report: &'a mut RestoreReport) {
+        self.max_errors = Some((max_errors, report));
+    }
 }
 
 impl<'a, D> Task for FullRestoreTask<'a, D>
@@ -39,56 +76,344 @@ where
     D: Destination,
 {
     fn run<F: FnMut(TransferredBytes, MaxBytes)>(
-        mut self,
+        self,
         mut progress_callback: F,
     ) -> Result<(), Error> {
+        let mut datastore = self.datastore;
+
+        // the tables are listed before touching the destination
+        let truncated_tables = if self.truncate_target {
+            let (read_datastore, tables) =
+                read_dump_tables(&*self.destination, datastore, &self.read_options)?;
+            datastore = read_datastore;
+            Some(tables)
+        } else {
+            None
+        };
+
         // initialize the destination
         let _ = self.destination.init()?;
 
+        if let Some(tables) = truncated_tables {
+            let _ = self.destination.truncate_tables(&tables)?;
+        }
+
         // bound to 1 to avoid eating too much memory if we download the dump faster than we ingest it
         let (tx, rx) = mpsc::sync_channel::<Message<Bytes>>(1);
-        let datastore = self.datastore;
 
         let mut index_file = datastore.index_file()?;
         let dump = index_file.find_dump(&self.read_options)?;
 
+        // the restored bytes are not comparable with the stored size of a compressed or encrypted dump -
+        // a spinner is shown if its size before compression is not recorded
+        let max_bytes = dump.restored_size().unwrap_or(0);
+
         // init progress
-        progress_callback(0, dump.size);
+        progress_callback(0, max_bytes);
 
         let read_options = self.read_options.clone();
+        let mut column_mapping = self.column_mapping;
+        let mut copy_batching = if self.use_copy {
+            Some(CopyBatching::default())
+        } else {
+            None
+        };
 
-        let join_handle = thread::spawn(move || {
+        let join_handle = thread::spawn(move || -> Result<(), Error> {
             // managing Datastore (S3) download here
             let datastore = datastore;
             let read_options = read_options;
+            let mut disconnected = false;
 
-            let _ = match datastore.read(&read_options, &mut |data| {
-                let _ = tx.send(Message::Data(data));
-            }) {
-                Ok(_) => {}
-                Err(err) => panic!("{:?}", err),
-            };
+            let _ = datastore.read(&read_options, &mut |data| {
+                // the destination stopped restoring the dump (e.g. after an error) - the remaining parts are dropped
+                if disconnected {
+                    return;
+                }
+
+                let data = column_mapping.map_columns(data);
+                let data = match copy_batching.as_mut() {
+                    Some(copy_batching) => copy_batching.to_copy(data),
+                    None => data,
+                };
+
+                disconnected = tx.send(Message::Data(data)).is_err();
+            })?;
 
             let _ = tx.send(Message::EOF);
+            Ok(())
         });
 
-        loop {
-            let data = match rx.recv() {
-                Ok(Message::Data(data)) => data,
-                Ok(Message::EOF) => break,
-                Err(err) => panic!("{:?}", err), // FIXME what should I do here?
+        let restored_bytes = if let Some((max_errors, report)) = self.max_errors {
+            let mut dump_reader = ProgressReader {
+                reader: MessageReader::new(rx),
+                progress_callback: &mut progress_callback,
+                max_bytes,
+                read_bytes: 0,
+                reported_bytes: 0,
             };
 
-            progress_callback(data.len(), dump.size);
+            let _ = self
+                .destination
+                .write_with_max_errors(&mut dump_reader, max_errors, report)?;
 
-            let _ = self.destination.write(data)?;
-        }
+            dump_reader.read_bytes
+        } else if self.concurrency > 1 {
+            let mut dump_reader = ProgressReader {
+                reader: MessageReader::new(rx),
+                progress_callback: &mut progress_callback,
+                max_bytes,
+                read_bytes: 0,
+                reported_bytes: 0,
+            };
 
-        // wait for end of download execution
-        let _ = join_handle.join(); // FIXME catch result here
+            let _ = self
+                .destination
+                .write_concurrently(&mut dump_reader, self.concurrency)?;
 
-        progress_callback(dump.size, dump.size);
+            dump_reader.read_bytes
+        } else {
+            let mut restored_bytes = 0usize;
+
+            loop {
+                let data = match rx.recv() {
+                    Ok(Message::Data(data)) => data,
+                    // the datastore thread stopped before the end of the dump - its error is returned below
+                    Ok(Message::EOF) | Err(_) => break,
+                };
+
+                restored_bytes += data.len();
+                let _ = self.destination.write(data)?;
+
+                // reported once applied by the destination
+                progress_callback(restored_bytes, max_bytes);
+            }
+
+            restored_bytes
+        };
+
+        // wait for end of download execution - a dump partially read must not be reported as restored
+        match join_handle.join() {
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => return Err(err),
+            Err(_) => return Err(Error::new(ErrorKind::Other, "failed to read the dump")),
+        }
+
+        progress_callback(restored_bytes, restored_bytes);
 
         Ok(())
     }
 }
+
+const PROGRESS_STEP_BYTES: usize = 1024 * 1024;
+
+/// report the progress of a dump restored from a reader - by steps of at least `PROGRESS_STEP_BYTES`
+struct ProgressReader<'a, R, F> {
+    reader: R,
+    progress_callback: &'a mut F,
+    max_bytes: MaxBytes,
+    read_bytes: TransferredBytes,
+    reported_bytes: TransferredBytes,
+}
+
+impl<'a, R, F> Read for ProgressReader<'a, R, F>
+where
+    R: Read,
+    F: FnMut(TransferredBytes, MaxBytes),
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read_bytes = self.reader.read(buf)?;
+        self.read_bytes += read_bytes;
+
+        let unreported_bytes = self.read_bytes - self.reported_bytes;
+        if unreported_bytes >= PROGRESS_STEP_BYTES || (read_bytes == 0 && unreported_bytes > 0) {
+            (self.progress_callback)(self.read_bytes, self.max_bytes);
+            self.reported_bytes = self.read_bytes;
+        }
+
+        Ok(read_bytes)
+    }
+}
+
+/// read the whole dump a first time to list its tables
+fn read_dump_tables<D: Destination>(
+    destination: &D,
+    datastore: Box<dyn Datastore>,
+    read_options: &ReadOptions,
+) -> Result<(Box<dyn Datastore>, Vec<String>), Error> {
+    read_dump(datastore, read_options, |dump| {
+        destination.dump_tables(dump)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::fs;
+    use std::io::{Error, Read};
+
+    use tempfile::tempdir;
+
+    use crate::connector::Connector;
+    use crate::datastore::local_disk::LocalDisk;
+    use crate::datastore::{Datastore, ReadOptions};
+    use crate::destination::Destination;
+    use crate::tasks::full_restore::FullRestoreTask;
+    use crate::tasks::Task;
+    use crate::types::Bytes;
+
+    /// destination recording the truncated tables and the written data
+    #[derive(Default)]
+    struct RecordingDestination {
+        dump: RefCell<String>,
+        writes: RefCell<Vec<String>>,
+    }
+
+    impl Connector for RecordingDestination {
+        fn init(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Destination for RecordingDestination {
+        fn write(&self, data: Bytes) -> Result<(), Error> {
+            self.writes
+                .borrow_mut()
+                .push(String::from_utf8(data).unwrap());
+            Ok(())
+        }
+
+        fn dump_tables(&self, dump: &mut dyn Read) -> Result<Vec<String>, Error> {
+            let _ = dump.read_to_string(&mut self.dump.borrow_mut())?;
+            Ok(vec![
+                "public.users".to_string(),
+                "public.orders".to_string(),
+            ])
+        }
+
+        fn truncate_tables(&self, tables: &Vec<String>) -> Result<(), Error> {
+            for table in tables {
+                self.writes
+                    .borrow_mut()
+                    .push(format!("TRUNCATE TABLE {} CASCADE;", table));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn truncate_tables_before_restore() {
+        let parts = [
+            "INSERT INTO public.users (id) VALUES (1);\nCOPY public.orders (id) FROM stdin;\n1\n",
+            "2\n\\.\nINSERT INTO public.users (id) VALUES (2);\n",
+        ];
+
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+        local_disk.set_dump_name("dump-1".to_string());
+        for (i, part) in parts.iter().enumerate() {
+            let _ = local_disk
+                .write(i as u16 + 1, part.as_bytes().to_vec())
+                .unwrap();
+        }
+
+        let read_options = ReadOptions::Dump {
+            name: "dump-1".to_string(),
+        };
+
+        let mut destination = RecordingDestination::default();
+        let mut task = FullRestoreTask::new(&mut destination, Box::new(local_disk), read_options);
+        task.set_truncate_target(true);
+        assert!(task.run(|_, _| {}).is_ok());
+
+        // the whole dump has been read to list the tables
+        assert_eq!(destination.dump.into_inner(), parts.concat());
+
+        // and the tables are truncated before the data is restored
+        assert_eq!(
+            destination.writes.into_inner(),
+            vec![
+                "TRUNCATE TABLE public.users CASCADE;".to_string(),
+                "TRUNCATE TABLE public.orders CASCADE;".to_string(),
+                parts[0].to_string(),
+                parts[1].to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn fail_on_datastore_read_error() {
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+        local_disk.set_dump_name("dump-1".to_string());
+        for part in 1..=2 {
+            let _ = local_disk
+                .write(
+                    part,
+                    b"INSERT INTO public.users (id) VALUES (1);\n".to_vec(),
+                )
+                .unwrap();
+        }
+
+        // the second part is a corrupted gzip archive
+        fs::write(
+            dir.path().join("dump-1/2.dump"),
+            vec![0x1f, 0x8b, 0x00, 0x01, 0x02, 0x03],
+        )
+        .unwrap();
+
+        let read_options = ReadOptions::Dump {
+            name: "dump-1".to_string(),
+        };
+
+        let mut destination = RecordingDestination::default();
+        let task = FullRestoreTask::new(&mut destination, Box::new(local_disk), read_options);
+        assert!(task.run(|_, _| {}).is_err());
+
+        // the first part has been restored before the error
+        assert_eq!(destination.writes.into_inner().len(), 1);
+    }
+
+    #[test]
+    fn report_restored_bytes() {
+        let parts = [
+            "INSERT INTO public.users (id) VALUES (1);\n",
+            "INSERT INTO public.users (id) VALUES (2);\n",
+        ];
+        let total_bytes = parts.concat().len();
+
+        let dir = tempdir().expect("cannot create tempdir");
+        let mut local_disk = LocalDisk::new(dir.path().to_str().unwrap().to_string());
+        let _ = local_disk.init().expect("local_disk init failed");
+        local_disk.set_dump_name("dump-1".to_string());
+        for (i, part) in parts.iter().enumerate() {
+            let _ = local_disk
+                .write(i as u16 + 1, part.as_bytes().to_vec())
+                .unwrap();
+        }
+
+        let read_options = ReadOptions::Dump {
+            name: "dump-1".to_string(),
+        };
+
+        let mut destination = RecordingDestination::default();
+        let task = FullRestoreTask::new(&mut destination, Box::new(local_disk), read_options);
+        let mut progress = vec![];
+        assert!(task
+            .run(|bytes, max_bytes| progress.push((bytes, max_bytes)))
+            .is_ok());
+
+        // the size before compression is the size of the restored data
+        assert_eq!(
+            progress,
+            vec![
+                (0, total_bytes),
+                (parts[0].len(), total_bytes),
+                (total_bytes, total_bytes),
+                (total_bytes, total_bytes),
+            ]
+        );
+    }
+}
@@ -1,6 +1,14 @@
-use std::io::Error;
+use std::cmp::min;
+use std::io::{Error, ErrorKind, Read};
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::thread;
+
+use crate::datastore::{Datastore, ReadOptions};
+use crate::types::Bytes;
 
 pub mod full_dump;
+pub mod full_pipe;
 pub mod full_restore;
 
 pub type TransferredBytes = usize;
@@ -16,3 +24,75 @@ enum Message<T> {
     Data(T),
     EOF,
 }
+
+/// stream the whole dump through a reader - the datastore is given back once the dump is read
+pub fn read_dump<T, F>(
+    datastore: Box<dyn Datastore>,
+    read_options: &ReadOptions,
+    f: F,
+) -> Result<(Box<dyn Datastore>, T), Error>
+where
+    F: FnOnce(&mut dyn Read) -> Result<T, Error>,
+{
+    let (tx, rx) = mpsc::sync_channel::<Message<Bytes>>(1);
+    let read_options = read_options.clone();
+
+    let join_handle = thread::spawn(move || {
+        let result = datastore.read(&read_options, &mut |data| {
+            let _ = tx.send(Message::Data(data));
+        });
+
+        let _ = tx.send(Message::EOF);
+        (datastore, result)
+    });
+
+    let mut reader = MessageReader::new(rx);
+    let value = f(&mut reader);
+
+    // the datastore thread must not stay blocked on a full channel if the reader stopped early
+    drop(reader);
+
+    match join_handle.join() {
+        Ok((datastore, Ok(_))) => value.map(|value| (datastore, value)),
+        Ok((_, Err(err))) => Err(err),
+        Err(_) => Err(Error::new(ErrorKind::Other, "failed to read the dump")),
+    }
+}
+
+/// expose the dump parts sent by the datastore thread as a reader
+struct MessageReader {
+    rx: Receiver<Message<Bytes>>,
+    data: Bytes,
+    pos: usize,
+}
+
+impl MessageReader {
+    fn new(rx: Receiver<Message<Bytes>>) -> Self {
+        MessageReader {
+            rx,
+            data: Bytes::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for MessageReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos >= self.data.len() {
+            match self.rx.recv() {
+                Ok(Message::Data(data)) => {
+                    self.data = data;
+                    self.pos = 0;
+                }
+                // end of the dump
+                Ok(Message::EOF) | Err(_) => return Ok(0),
+            }
+        }
+
+        let len = min(buf.len(), self.data.len() - self.pos);
+        buf[..len].copy_from_slice(&self.data[self.pos..self.pos + len]);
+        self.pos += len;
+
+        Ok(len)
+    }
+}
@@ -1,5 +1,8 @@
 use crate::config::{ConnectionUri, TransformerTypeConfig};
-use crate::{Config, DumpCommand, RestoreCommand, SourceCommand, SubCommand, TransformerCommand};
+use crate::{
+    Config, DatastoreCommand, DumpCommand, RestoreCommand, SourceCommand, SubCommand,
+    TransformerCommand,
+};
 use chrono::{NaiveDateTime, Utc};
 use reqwest::blocking::Client as HttpClient;
 use reqwest::header::CONTENT_TYPE;
@@ -113,10 +116,18 @@ impl TelemetryClient {
                                 TransformerTypeConfig::RandomDate => "random-date",
                                 TransformerTypeConfig::FirstName => "first-name",
                                 TransformerTypeConfig::Email => "email",
+                                TransformerTypeConfig::UniqueEmail => "unique-email",
                                 TransformerTypeConfig::KeepFirstChar => "keep-first-char",
                                 TransformerTypeConfig::PhoneNumber => "phone-number",
                                 TransformerTypeConfig::CreditCard => "credit-card",
+                                TransformerTypeConfig::CreditCardScrub => "credit-card-scrub",
+                                TransformerTypeConfig::Iban => "iban",
                                 TransformerTypeConfig::Redacted(_) => "redacted",
+                                TransformerTypeConfig::Lorem => "lorem",
+                                TransformerTypeConfig::JsonScrub(_) => "json-scrub",
+                                TransformerTypeConfig::ConsistentFake(_) => "consistent-fake",
+                                TransformerTypeConfig::Shuffle(_) => "shuffle",
+                                TransformerTypeConfig::DeriveFromColumn(_) => "derive-from-column",
                                 TransformerTypeConfig::Transient => "transient",
                                 TransformerTypeConfig::CustomWasm(_) => "custom-wasm",
                             });
@@ -133,9 +144,13 @@ impl TelemetryClient {
 
         let event = match sub_command {
             SubCommand::Dump(cmd) => match cmd {
-                DumpCommand::List => "dump-list",
+                DumpCommand::List(_) => "dump-list",
                 DumpCommand::Create(_) => "dump-create",
                 DumpCommand::Delete(_) => "dump-delete",
+                DumpCommand::Pipe => "dump-pipe",
+                DumpCommand::Stats(_) => "dump-stats",
+                DumpCommand::Reencrypt(_) => "dump-reencrypt",
+                DumpCommand::Copy(_) => "dump-copy",
                 DumpCommand::Restore(restore_cmd) => match restore_cmd {
                     RestoreCommand::Local(_) => "dump-restore-local",
                     RestoreCommand::Remote(_) => "dump-restore-remote",
@@ -143,9 +158,15 @@ impl TelemetryClient {
             },
             SubCommand::Source(cmd) => match cmd {
                 SourceCommand::Schema => "source-schema",
+                SourceCommand::SubsetEstimate => "source-subset-estimate",
+                SourceCommand::DetectPii => "source-detect-pii",
             },
             SubCommand::Transformer(cmd) => match cmd {
                 TransformerCommand::List => "transformer-list",
+                TransformerCommand::Test(_) => "transformer-test",
+            },
+            SubCommand::Datastore(cmd) => match cmd {
+                DatastoreCommand::Fsck => "datastore-fsck",
             },
         };
 
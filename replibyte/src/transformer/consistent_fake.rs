@@ -0,0 +1,299 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use fake::faker::address::raw::CityName;
+use fake::faker::company::raw::CompanyName;
+use fake::faker::internet::raw::SafeEmail;
+use fake::faker::name::raw::{FirstName, LastName, Name};
+use fake::locales::EN;
+use fake::Fake;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::transformer::Transformer;
+use crate::types::Column;
+use crate::utils::stable_hash;
+
+/// number of fakes generated to find one which is not used yet, before adding a suffix to it
+const MAX_FAKE_ATTEMPTS: usize = 10;
+
+/// This struct is dedicated to replacing a string by a fake one - the same original value is always
+/// replaced by the same fake value within a dump (one dictionary by column).
+///
+/// With a `key_column`, the fake value is generated from the value of this column of the row instead (e.g. the
+/// primary key): the same key is always replaced by the same fake value, across dumps.
+///
+/// The dictionary is kept in a `RefCell`: the transformer is not `Sync` and can't be shared between
+/// threads as it is - transforming the rows in parallel requires a lock around the dictionary.
+pub struct ConsistentFakeTransformer {
+    database_name: String,
+    table_name: String,
+    column_name: String,
+    options: ConsistentFakeTransformerOptions,
+    dictionary: RefCell<Dictionary>,
+}
+
+#[derive(Default)]
+struct Dictionary {
+    fakes: HashMap<String, String>,
+    used_fakes: HashSet<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct ConsistentFakeTransformerOptions {
+    /// kind of fake value to generate
+    #[serde(default)]
+    pub kind: ConsistentFakeKind,
+    /// column of the same table the fake value is generated from (e.g. the primary key) - the original value by default
+    #[serde(default)]
+    pub key_column: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConsistentFakeKind {
+    CompanyName,
+    FirstName,
+    LastName,
+    Name,
+    Email,
+    City,
+}
+
+impl Default for ConsistentFakeKind {
+    fn default() -> Self {
+        ConsistentFakeKind::CompanyName
+    }
+}
+
+impl ConsistentFakeKind {
+    fn fake(&self) -> String {
+        self.fake_with_rng(&mut rand::thread_rng())
+    }
+
+    fn fake_with_rng<R: Rng>(&self, rng: &mut R) -> String {
+        match self {
+            ConsistentFakeKind::CompanyName => CompanyName(EN).fake_with_rng(rng),
+            ConsistentFakeKind::FirstName => FirstName(EN).fake_with_rng(rng),
+            ConsistentFakeKind::LastName => LastName(EN).fake_with_rng(rng),
+            ConsistentFakeKind::Name => Name(EN).fake_with_rng(rng),
+            ConsistentFakeKind::Email => SafeEmail(EN).fake_with_rng(rng),
+            ConsistentFakeKind::City => CityName(EN).fake_with_rng(rng),
+        }
+    }
+}
+
+
+impl ConsistentFakeTransformer {
+    pub fn new<S>(
+        database_name: S,
+        table_name: S,
+        column_name: S,
+        options: ConsistentFakeTransformerOptions,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        ConsistentFakeTransformer {
+            database_name: database_name.into(),
+            table_name: table_name.into(),
+            column_name: column_name.into(),
+            options,
+            dictionary: RefCell::new(Dictionary::default()),
+        }
+    }
+
+    /// the fake value of an original value - generated the first time the original value is met
+    fn fake(&self, value: String) -> String {
+        let mut dictionary = self.dictionary.borrow_mut();
+
+        if let Some(fake) = dictionary.fakes.get(value.as_str()) {
+            return fake.clone();
+        }
+
+        // two original values must not share the same fake value
+        let mut fake = self.options.kind.fake();
+        let mut attempts = 1;
+        while dictionary.used_fakes.contains(fake.as_str()) && attempts < MAX_FAKE_ATTEMPTS {
+            fake = self.options.kind.fake();
+            attempts += 1;
+        }
+
+        if dictionary.used_fakes.contains(fake.as_str()) {
+            fake = format!("{} {}", fake, dictionary.used_fakes.len());
+        }
+
+        dictionary.used_fakes.insert(fake.clone());
+        dictionary.fakes.insert(value, fake.clone());
+
+        fake
+    }
+}
+
+impl Default for ConsistentFakeTransformer {
+    fn default() -> Self {
+        ConsistentFakeTransformer {
+            database_name: String::default(),
+            table_name: String::default(),
+            column_name: String::default(),
+            options: ConsistentFakeTransformerOptions::default(),
+            dictionary: RefCell::new(Dictionary::default()),
+        }
+    }
+}
+
+impl Transformer for ConsistentFakeTransformer {
+    fn id(&self) -> &str {
+        "consistent-fake"
+    }
+
+    fn description(&self) -> &str {
+        "Generate a fake value - the same original value is always replaced by the same fake value (string only). [Acme]->[Schmidt LLC]"
+    }
+
+    fn database_name(&self) -> &str {
+        self.database_name.as_str()
+    }
+
+    fn table_name(&self) -> &str {
+        self.table_name.as_str()
+    }
+
+    fn column_name(&self) -> &str {
+        self.column_name.as_str()
+    }
+
+    fn transform(&self, column: Column) -> Column {
+        match column {
+            Column::StringValue(column_name, value) => {
+                Column::StringValue(column_name, self.fake(value))
+            }
+            column => column,
+        }
+    }
+
+    fn key_column(&self) -> Option<&str> {
+        self.options.key_column.as_deref()
+    }
+
+    /// the fake value is generated from the key only - the dictionary is not used
+    fn transform_with_key(&self, column: Column, key: &str) -> Column {
+        match column {
+            Column::StringValue(column_name, _) => {
+                let mut rng = StdRng::seed_from_u64(stable_hash(key.as_bytes()));
+                Column::StringValue(column_name, self.options.kind.fake_with_rng(&mut rng))
+            }
+            column => column,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{transformer::Transformer, types::Column};
+
+    use super::{ConsistentFakeKind, ConsistentFakeTransformer, ConsistentFakeTransformerOptions};
+
+    fn transform(transformer: &ConsistentFakeTransformer, value: &str) -> String {
+        let column = Column::StringValue("company_name".to_string(), value.to_string());
+        let transformed_column = transformer.transform(column);
+
+        transformed_column.string_value().unwrap().to_string()
+    }
+
+    #[test]
+    fn same_input_same_fake() {
+        let transformer = get_transformer(ConsistentFakeKind::CompanyName);
+
+        let acme = transform(&transformer, "Acme");
+        let initech = transform(&transformer, "Initech");
+
+        assert_ne!(acme, "Acme");
+        assert_ne!(initech, "Initech");
+        assert_ne!(acme, initech);
+
+        for _ in 0..10 {
+            assert_eq!(transform(&transformer, "Acme"), acme);
+            assert_eq!(transform(&transformer, "Initech"), initech);
+        }
+
+        // another dump (transformer) has its own dictionary
+        let other_transformer = get_transformer(ConsistentFakeKind::CompanyName);
+        let _ = transform(&other_transformer, "Initech");
+        assert_eq!(transform(&transformer, "Acme"), acme);
+    }
+
+    #[test]
+    fn different_inputs_different_fakes() {
+        // there are few first names - the fakes end up with a suffix instead of being shared
+        let transformer = get_transformer(ConsistentFakeKind::FirstName);
+
+        let fakes = (0..1000)
+            .map(|i| transform(&transformer, format!("name-{}", i).as_str()))
+            .collect::<HashSet<_>>();
+
+        assert_eq!(fakes.len(), 1000);
+    }
+
+    #[test]
+    fn keep_other_values() {
+        let transformer = ConsistentFakeTransformer::default();
+
+        let column = Column::NumberValue("company_name".to_string(), 42);
+        let transformed_column = transformer.transform(column);
+        assert_eq!(transformed_column.number_value().unwrap().to_owned(), 42);
+
+        let column = Column::None("company_name".to_string());
+        let transformed_column = transformer.transform(column);
+        assert!(matches!(transformed_column, Column::None(_)));
+    }
+
+    #[test]
+    fn same_key_same_fake() {
+        let transform_with_key = |key: &str, value: &str| {
+            // a new transformer for each value - like in another dump
+            let transformer = ConsistentFakeTransformer::new(
+                "public",
+                "users",
+                "email",
+                ConsistentFakeTransformerOptions {
+                    kind: ConsistentFakeKind::Email,
+                    key_column: Some("id".to_string()),
+                },
+            );
+            assert_eq!(transformer.key_column(), Some("id"));
+
+            let column = Column::StringValue("email".to_string(), value.to_string());
+            let transformed_column = transformer.transform_with_key(column, key);
+            transformed_column.string_value().unwrap().to_string()
+        };
+
+        let fake = transform_with_key("1", "alice@example.com");
+        assert_ne!(fake, "alice@example.com");
+        assert!(fake.contains('@'));
+
+        // the original value does not matter
+        assert_eq!(transform_with_key("1", "alice@example.com"), fake);
+        assert_eq!(transform_with_key("1", "alice@acme.com"), fake);
+
+        let fakes = (1..=10)
+            .map(|key| transform_with_key(key.to_string().as_str(), "alice@example.com"))
+            .collect::<HashSet<_>>();
+        assert!(fakes.len() > 1);
+    }
+
+    fn get_transformer(kind: ConsistentFakeKind) -> ConsistentFakeTransformer {
+        ConsistentFakeTransformer::new(
+            "public",
+            "customers",
+            "company_name",
+            ConsistentFakeTransformerOptions {
+                kind,
+                key_column: None,
+            },
+        )
+    }
+}
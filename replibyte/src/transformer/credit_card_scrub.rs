@@ -0,0 +1,146 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use crate::source::pii_detector::is_luhn_valid;
+use crate::transformer::Transformer;
+use crate::types::Column;
+
+const MASK: &str = "XXXX";
+
+lazy_static! {
+    // 13 to 19 digits - optionally grouped with spaces or dashes - not part of a longer number
+    static ref CARD_NUMBER: Regex = Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").unwrap();
+}
+
+/// This struct is dedicated to redacting the credit card numbers found within a free text.
+pub struct CreditCardScrubTransformer {
+    database_name: String,
+    table_name: String,
+    column_name: String,
+}
+
+impl CreditCardScrubTransformer {
+    pub fn new<S>(database_name: S, table_name: S, column_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        CreditCardScrubTransformer {
+            database_name: database_name.into(),
+            table_name: table_name.into(),
+            column_name: column_name.into(),
+        }
+    }
+}
+
+impl Default for CreditCardScrubTransformer {
+    fn default() -> Self {
+        CreditCardScrubTransformer {
+            database_name: String::default(),
+            table_name: String::default(),
+            column_name: String::default(),
+        }
+    }
+}
+
+/// replace the digit sequences passing the Luhn check - the rest of the text is kept
+fn scrub(value: &str) -> String {
+    CARD_NUMBER
+        .replace_all(value, |captures: &Captures| {
+            let number = &captures[0];
+            match is_luhn_valid(number) {
+                true => MASK.to_string(),
+                false => number.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+impl Transformer for CreditCardScrubTransformer {
+    fn id(&self) -> &str {
+        "credit-card-scrub"
+    }
+
+    fn description(&self) -> &str {
+        "Redact the credit card numbers found within a text (string only). [paid with 4242 4242 4242 4242]->[paid with XXXX]"
+    }
+
+    fn database_name(&self) -> &str {
+        self.database_name.as_str()
+    }
+
+    fn table_name(&self) -> &str {
+        self.table_name.as_str()
+    }
+
+    fn column_name(&self) -> &str {
+        self.column_name.as_str()
+    }
+
+    fn transform(&self, column: Column) -> Column {
+        match column {
+            Column::StringValue(column_name, value) => {
+                Column::StringValue(column_name, scrub(value.as_str()))
+            }
+            column => column,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{transformer::Transformer, types::Column};
+
+    use super::CreditCardScrubTransformer;
+
+    fn transform(value: &str) -> String {
+        let transformer = get_transformer();
+        let column = Column::StringValue("notes".to_string(), value.to_string());
+        transformer
+            .transform(column)
+            .string_value()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn redact_embedded_credit_cards() {
+        assert_eq!(
+            transform("paid with 4242424242424242, thanks!"),
+            "paid with XXXX, thanks!"
+        );
+        assert_eq!(
+            transform("cards: 4242 4242 4242 4242 and 5555-5555-5555-4444."),
+            "cards: XXXX and XXXX."
+        );
+        assert_eq!(transform("378282246310005"), "XXXX");
+    }
+
+    #[test]
+    fn keep_other_numbers() {
+        // fails the Luhn check
+        assert_eq!(
+            transform("order 4242424242424241 shipped"),
+            "order 4242424242424241 shipped"
+        );
+        // too short and too long to be a card number
+        assert_eq!(transform("call 0612345678"), "call 0612345678");
+        assert_eq!(
+            transform("ref 42424242424242424242424242"),
+            "ref 42424242424242424242424242"
+        );
+        assert_eq!(transform(""), "");
+    }
+
+    #[test]
+    fn transform_non_string_column() {
+        let transformer = get_transformer();
+        let column = Column::NumberValue("notes".to_string(), 4242424242424242);
+        let transformed_column = transformer.transform(column);
+
+        assert_eq!(transformed_column.number_value().unwrap(), &4242424242424242);
+    }
+
+    fn get_transformer() -> CreditCardScrubTransformer {
+        CreditCardScrubTransformer::new("github", "users", "notes")
+    }
+}
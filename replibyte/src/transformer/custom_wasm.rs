@@ -5,10 +5,26 @@ use crate::types::Column;
 
 use serde::{Deserialize, Serialize};
 use wasmer::{ImportObject, Instance, Module, Store};
-use wasmer_wasi::{Pipe, WasiEnv, WasiState};
+use wasmer_wasi::{get_wasi_version, Pipe, WasiEnv, WasiState};
 
 pub type WasmError = Box<dyn std::error::Error>;
 
+/// `transform(ptr: i32, len: i32) -> i64` - transform the bytes of the value and return the position of the
+/// transformed bytes in the memory, as `ptr << 32 | len`
+const TRANSFORM_FUNCTION: &str = "transform";
+/// `alloc(len: i32) -> i32` - reserve the bytes of the value in the memory and return their position
+const ALLOC_FUNCTION: &str = "alloc";
+const MEMORY: &str = "memory";
+
+/// how the value is exchanged with the module
+#[derive(Debug, PartialEq)]
+enum WasmAbi {
+    /// the exported `transform` function is called on the bytes of the value, written in the exported `memory`
+    Transform,
+    /// the value is written on stdin and read on stdout of the `_start` function (WASI)
+    Stdio,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct CustomWasmTransformerOptions {
     pub path: String,
@@ -17,6 +33,7 @@ struct WasmConfig {
     wasi_env: WasiEnv,
     import_object: ImportObject,
     module: Module,
+    abi: WasmAbi,
 }
 pub struct CustomWasmTransformer {
     database_name: String,
@@ -49,8 +66,20 @@ impl CustomWasmTransformer {
             .stdout(Box::new(output))
             .finalize()?;
 
-        // Import object related to WASI
-        let import_object = wasi_env.import_object(&module)?;
+        // Import object related to WASI - a module exporting `transform` does not need to import it
+        let import_object = match get_wasi_version(&module, false) {
+            Some(_) => wasi_env.import_object(&module)?,
+            None => ImportObject::new(),
+        };
+
+        let abi = if module
+            .exports()
+            .any(|export| export.name() == TRANSFORM_FUNCTION)
+        {
+            WasmAbi::Transform
+        } else {
+            WasmAbi::Stdio
+        };
 
         Ok(CustomWasmTransformer {
             database_name: database_name.into(),
@@ -60,12 +89,22 @@ impl CustomWasmTransformer {
                 wasi_env,
                 import_object,
                 module,
+                abi,
             },
         })
     }
+
     fn call_wasm_module(&self, value: &str) -> Result<String, WasmError> {
-        // Create a new wasm instance from the wasm configuration
+        // Create a new wasm instance from the wasm configuration - the memory is not shared between values
         let instance = Instance::new(&self.wasm_config.module, &self.wasm_config.import_object)?;
+
+        match self.wasm_config.abi {
+            WasmAbi::Transform => call_transform_function(&instance, value),
+            WasmAbi::Stdio => self.call_start_function(&instance, value),
+        }
+    }
+
+    fn call_start_function(&self, instance: &Instance, value: &str) -> Result<String, WasmError> {
         // Access WasiState in a nested scope to ensure we're not holding
         // the mutex after we need it.
         {
@@ -99,6 +138,53 @@ impl CustomWasmTransformer {
     }
 }
 
+/// write the value in the memory of the module, call `transform` and read the transformed value
+fn call_transform_function(instance: &Instance, value: &str) -> Result<String, WasmError> {
+    let memory = instance.exports.get_memory(MEMORY)?;
+    let alloc = instance
+        .exports
+        .get_native_function::<i32, i32>(ALLOC_FUNCTION)?;
+    let transform = instance
+        .exports
+        .get_native_function::<(i32, i32), i64>(TRANSFORM_FUNCTION)?;
+
+    let input = value.as_bytes();
+    let input_ptr = alloc.call(input.len() as i32)? as u32 as usize;
+
+    let view = memory.view::<u8>();
+    let input_cells = view
+        .get(input_ptr..input_ptr + input.len())
+        .ok_or_else(|| out_of_bounds_error(ALLOC_FUNCTION))?;
+    for (cell, byte) in input_cells.iter().zip(input) {
+        cell.set(*byte);
+    }
+
+    let output = transform.call(input_ptr as i32, input.len() as i32)? as u64;
+    let output_ptr = (output >> 32) as usize;
+    let output_len = (output & 0xFFFF_FFFF) as usize;
+
+    // the memory may have grown during the call
+    let view = memory.view::<u8>();
+    let output = view
+        .get(output_ptr..output_ptr + output_len)
+        .ok_or_else(|| out_of_bounds_error(TRANSFORM_FUNCTION))?
+        .iter()
+        .map(|cell| cell.get())
+        .collect::<Vec<u8>>();
+
+    Ok(String::from_utf8(output)?)
+}
+
+fn out_of_bounds_error(function_name: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!(
+            "the position returned by the `{}` function of the wasm module is out of its memory",
+            function_name
+        ),
+    )
+}
+
 impl Default for CustomWasmTransformer {
     fn default() -> Self {
         CustomWasmTransformer {
@@ -121,6 +207,7 @@ impl Default for CustomWasmTransformer {
                     ],
                 )
                 .unwrap(),
+                abi: WasmAbi::Stdio,
             },
         }
     }
@@ -194,11 +281,90 @@ mod tests {
         types::Column,
     };
 
+    /// reverse the bytes of the value - with the `transform` function
+    const REVERSE_BYTES_WAT: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (global $next_ptr (mut i32) (i32.const 1024))
+  (func $alloc (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next_ptr))
+    (global.set $next_ptr (i32.add (global.get $next_ptr) (local.get $len)))
+    (local.get $ptr))
+  (func (export "transform") (param $ptr i32) (param $len i32) (result i64)
+    (local $output_ptr i32)
+    (local $idx i32)
+    (local.set $output_ptr (call $alloc (local.get $len)))
+    (block $done
+      (loop $copy
+        (br_if $done (i32.ge_u (local.get $idx) (local.get $len)))
+        (i32.store8
+          (i32.add (local.get $output_ptr) (local.get $idx))
+          (i32.load8_u
+            (i32.sub
+              (i32.add (local.get $ptr) (local.get $len))
+              (i32.add (local.get $idx) (i32.const 1)))))
+        (local.set $idx (i32.add (local.get $idx) (i32.const 1)))
+        (br $copy)))
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $output_ptr)) (i64.const 32))
+      (i64.extend_i32_u (local.get $len))))
+)
+"#;
+
+    /// return a position out of the memory of the module
+    const OUT_OF_BOUNDS_WAT: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param $len i32) (result i32)
+    (i32.const 1024))
+  (func (export "transform") (param $ptr i32) (param $len i32) (result i64)
+    (i64.const -1))
+)
+"#;
+
     fn get_wasm_transformer(path: &str) -> CustomWasmTransformer {
         let wasm_bytes = std::fs::read(path).unwrap();
         CustomWasmTransformer::new("test", "users", "number", wasm_bytes).unwrap()
     }
 
+    #[test]
+    fn transform_wasm_transform_function() {
+        let transformer = CustomWasmTransformer::new(
+            "test",
+            "users",
+            "string",
+            REVERSE_BYTES_WAT.as_bytes().to_vec(),
+        )
+        .unwrap();
+
+        let column = Column::StringValue("string".to_string(), "reverse_it".to_string());
+        let transformed_column = transformer.transform(column);
+        assert_eq!(transformed_column.string_value().unwrap(), "ti_esrever");
+
+        let column = Column::NumberValue("number".to_string(), 123);
+        let transformed_column = transformer.transform(column);
+        assert_eq!(transformed_column.number_value().unwrap(), &321);
+
+        // an empty value
+        let column = Column::StringValue("string".to_string(), String::new());
+        let transformed_column = transformer.transform(column);
+        assert_eq!(transformed_column.string_value().unwrap(), "");
+    }
+
+    #[test]
+    fn transform_wasm_transform_function_out_of_bounds() {
+        let transformer = CustomWasmTransformer::new(
+            "test",
+            "users",
+            "string",
+            OUT_OF_BOUNDS_WAT.as_bytes().to_vec(),
+        )
+        .unwrap();
+
+        assert!(transformer.call_wasm_module("value").is_err());
+    }
+
     #[test]
     fn transform_wasm_reverse_string() {
         let transformer =
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+use crate::transformer::Transformer;
+use crate::types::Column;
+
+/// This struct is dedicated to copying the value of another column of the same row - after its own transformation,
+/// e.g. to set `display_name` to the fake `first_name` of the row.
+pub struct DeriveFromColumnTransformer {
+    database_name: String,
+    table_name: String,
+    column_name: String,
+    options: DeriveFromColumnTransformerOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct DeriveFromColumnTransformerOptions {
+    /// column of the same table the value is copied from
+    pub source_column: String,
+}
+
+impl DeriveFromColumnTransformer {
+    pub fn new<S>(
+        database_name: S,
+        table_name: S,
+        column_name: S,
+        options: DeriveFromColumnTransformerOptions,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        DeriveFromColumnTransformer {
+            database_name: database_name.into(),
+            table_name: table_name.into(),
+            column_name: column_name.into(),
+            options,
+        }
+    }
+}
+
+impl Default for DeriveFromColumnTransformer {
+    fn default() -> Self {
+        DeriveFromColumnTransformer {
+            database_name: String::default(),
+            table_name: String::default(),
+            column_name: String::default(),
+            options: DeriveFromColumnTransformerOptions::default(),
+        }
+    }
+}
+
+impl Transformer for DeriveFromColumnTransformer {
+    fn id(&self) -> &str {
+        "derive-from-column"
+    }
+
+    fn description(&self) -> &str {
+        "Copy the value of another column of the row, after its transformation (PostgreSQL only). [Lucas, Jane]->[Georges, Georges]"
+    }
+
+    fn database_name(&self) -> &str {
+        self.database_name.as_str()
+    }
+
+    fn table_name(&self) -> &str {
+        self.table_name.as_str()
+    }
+
+    fn column_name(&self) -> &str {
+        self.column_name.as_str()
+    }
+
+    /// `column` is the column of `source_column` - its value is written into the column of the transformer
+    fn transform(&self, column: Column) -> Column {
+        let column_name = self.column_name.clone();
+
+        match column {
+            Column::NumberValue(_, value) => Column::NumberValue(column_name, value),
+            Column::FloatNumberValue(_, value) => Column::FloatNumberValue(column_name, value),
+            Column::StringValue(_, value) => Column::StringValue(column_name, value),
+            Column::CharValue(_, value) => Column::CharValue(column_name, value),
+            Column::BooleanValue(_, value) => Column::BooleanValue(column_name, value),
+            Column::None(_) => Column::None(column_name),
+        }
+    }
+
+    fn source_column(&self) -> Option<&str> {
+        Some(self.options.source_column.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{transformer::Transformer, types::Column};
+
+    use super::{DeriveFromColumnTransformer, DeriveFromColumnTransformerOptions};
+
+    fn get_transformer() -> DeriveFromColumnTransformer {
+        DeriveFromColumnTransformer::new(
+            "github",
+            "users",
+            "display_name",
+            DeriveFromColumnTransformerOptions {
+                source_column: "first_name".to_string(),
+            },
+        )
+    }
+
+    #[test]
+    fn copy_the_value_of_the_source_column() {
+        let transformer = get_transformer();
+        assert_eq!(transformer.source_column(), Some("first_name"));
+
+        let column = transformer.transform(Column::StringValue(
+            "first_name".to_string(),
+            "Georges".to_string(),
+        ));
+        assert_eq!(column.name(), "display_name");
+        assert_eq!(column.string_value().unwrap(), "Georges");
+
+        let column = transformer.transform(Column::NumberValue("first_name".to_string(), 42));
+        assert_eq!(column.name(), "display_name");
+        assert_eq!(column.number_value().unwrap(), &42);
+
+        let column = transformer.transform(Column::None("first_name".to_string()));
+        assert_eq!(column.name(), "display_name");
+    }
+}
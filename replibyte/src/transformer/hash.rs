@@ -0,0 +1,174 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::transformer::Transformer;
+use crate::types::Column;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// number of hex characters of a SHA-256 digest
+const MAX_LENGTH: usize = 64;
+
+/// This struct is dedicated to replacing a string by its HMAC-SHA256 (hex) - the same original value is always
+/// replaced by the same hash, from one dump to another, as long as the secret does not change.
+pub struct HashTransformer {
+    database_name: String,
+    table_name: String,
+    column_name: String,
+    options: HashTransformerOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct HashTransformerOptions {
+    /// key of the HMAC - the value can't be found back from its hash without it
+    pub secret: String,
+    /// number of hex characters kept, from 1 to 64 - the whole digest by default
+    #[serde(default)]
+    pub length: Option<usize>,
+}
+
+impl HashTransformer {
+    pub fn new<S>(
+        database_name: S,
+        table_name: S,
+        column_name: S,
+        options: HashTransformerOptions,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        HashTransformer {
+            database_name: database_name.into(),
+            table_name: table_name.into(),
+            column_name: column_name.into(),
+            options,
+        }
+    }
+
+    fn hash(&self, value: &str) -> String {
+        // HMAC takes a key of any size
+        let mut mac = HmacSha256::new_from_slice(self.options.secret.as_bytes()).unwrap();
+        mac.update(value.as_bytes());
+
+        let hash = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+
+        match self.options.length {
+            Some(length) => hash[..length.clamp(1, MAX_LENGTH)].to_string(),
+            None => hash,
+        }
+    }
+}
+
+impl Default for HashTransformer {
+    fn default() -> Self {
+        HashTransformer {
+            database_name: String::default(),
+            table_name: String::default(),
+            column_name: String::default(),
+            options: HashTransformerOptions::default(),
+        }
+    }
+}
+
+impl Transformer for HashTransformer {
+    fn id(&self) -> &str {
+        "hash"
+    }
+
+    fn description(&self) -> &str {
+        "Replace the value by its keyed hash (HMAC-SHA256) - the same value always gets the same hash, across dumps (string only). [john@doe.com]->[27bc79f408328ad7]"
+    }
+
+    fn database_name(&self) -> &str {
+        self.database_name.as_str()
+    }
+
+    fn table_name(&self) -> &str {
+        self.table_name.as_str()
+    }
+
+    fn column_name(&self) -> &str {
+        self.column_name.as_str()
+    }
+
+    fn transform(&self, column: Column) -> Column {
+        match column {
+            Column::StringValue(column_name, value) => {
+                Column::StringValue(column_name, self.hash(value.as_str()))
+            }
+            column => column,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{transformer::Transformer, types::Column};
+
+    use super::{HashTransformer, HashTransformerOptions};
+
+    fn transform(secret: &str, length: Option<usize>, value: &str) -> String {
+        let transformer = HashTransformer::new(
+            "public",
+            "users",
+            "email",
+            HashTransformerOptions {
+                secret: secret.to_string(),
+                length,
+            },
+        );
+
+        let column = Column::StringValue("email".to_string(), value.to_string());
+        let transformed_column = transformer.transform(column);
+
+        transformed_column.string_value().unwrap().to_string()
+    }
+
+    #[test]
+    fn hmac_sha256() {
+        // RFC 4231 - test case 2
+        assert_eq!(
+            transform("Jefe", None, "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn same_value_same_hash() {
+        let hash = transform("secret", None, "john@doe.com");
+        assert_eq!(hash.len(), 64);
+
+        // a new transformer - like in another dump
+        assert_eq!(transform("secret", None, "john@doe.com"), hash);
+        assert_ne!(transform("secret", None, "jane@doe.com"), hash);
+        assert_ne!(transform("another secret", None, "john@doe.com"), hash);
+    }
+
+    #[test]
+    fn truncate_hash() {
+        let hash = transform("secret", None, "john@doe.com");
+
+        assert_eq!(transform("secret", Some(16), "john@doe.com"), hash[..16]);
+        assert_eq!(transform("secret", Some(100), "john@doe.com"), hash);
+        assert_eq!(transform("secret", Some(0), "john@doe.com").len(), 1);
+    }
+
+    #[test]
+    fn keep_other_values() {
+        let transformer = HashTransformer::default();
+
+        let column = Column::NumberValue("email".to_string(), 42);
+        let transformed_column = transformer.transform(column);
+        assert_eq!(transformed_column.number_value().unwrap().to_owned(), 42);
+
+        let column = Column::None("email".to_string());
+        let transformed_column = transformer.transform(column);
+        assert!(matches!(transformed_column, Column::None(_)));
+    }
+}
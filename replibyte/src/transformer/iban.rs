@@ -0,0 +1,252 @@
+use rand::rngs::ThreadRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::transformer::Transformer;
+use crate::types::Column;
+
+/// kind of characters of a part of a BBAN
+#[derive(Clone, Copy)]
+enum CharKind {
+    /// digits
+    N,
+    /// upper case letters
+    A,
+    /// digits and upper case letters
+    C,
+}
+
+use CharKind::{A, C, N};
+
+/// BBAN (Basic Bank Account Number) format by country code
+const BBAN_FORMATS: &[(&str, &[(usize, CharKind)])] = &[
+    ("AT", &[(16, N)]),
+    ("BE", &[(12, N)]),
+    ("CH", &[(5, N), (12, C)]),
+    ("DE", &[(18, N)]),
+    ("DK", &[(14, N)]),
+    ("ES", &[(20, N)]),
+    ("FI", &[(14, N)]),
+    ("FR", &[(10, N), (11, C), (2, N)]),
+    ("GB", &[(4, A), (14, N)]),
+    ("IE", &[(4, A), (14, N)]),
+    ("IT", &[(1, A), (10, N), (12, C)]),
+    ("LU", &[(3, N), (13, C)]),
+    ("NL", &[(4, A), (10, N)]),
+    ("NO", &[(11, N)]),
+    ("PL", &[(24, N)]),
+    ("PT", &[(21, N)]),
+    ("SE", &[(20, N)]),
+];
+
+/// This struct is dedicated to replacing an IBAN by a random IBAN with valid check digits.
+pub struct IbanTransformer {
+    database_name: String,
+    table_name: String,
+    column_name: String,
+}
+
+impl IbanTransformer {
+    pub fn new<S>(database_name: S, table_name: S, column_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        IbanTransformer {
+            database_name: database_name.into(),
+            table_name: table_name.into(),
+            column_name: column_name.into(),
+        }
+    }
+}
+
+impl Default for IbanTransformer {
+    fn default() -> Self {
+        IbanTransformer {
+            database_name: String::default(),
+            table_name: String::default(),
+            column_name: String::default(),
+        }
+    }
+}
+
+impl Transformer for IbanTransformer {
+    fn id(&self) -> &str {
+        "iban"
+    }
+
+    fn description(&self) -> &str {
+        "Generate an IBAN with valid check digits and the same country (string only). [DE89 3704 0044 0532 0130 00]->[DE02 1203 0000 0000 2020 51]"
+    }
+
+    fn database_name(&self) -> &str {
+        self.database_name.as_str()
+    }
+
+    fn table_name(&self) -> &str {
+        self.table_name.as_str()
+    }
+
+    fn column_name(&self) -> &str {
+        self.column_name.as_str()
+    }
+
+    fn transform(&self, column: Column) -> Column {
+        match column {
+            Column::StringValue(column_name, value) => {
+                let mut random = rand::thread_rng();
+                let compact_value = compact(value.as_str());
+
+                // keep the country of the IBAN if it is a known one - otherwise pick a random one
+                let format = BBAN_FORMATS
+                    .iter()
+                    .find(|(country_code, _)| compact_value.starts_with(country_code))
+                    .or_else(|| BBAN_FORMATS.choose(&mut random))
+                    .unwrap();
+
+                let mut iban = random_iban(format, &mut random);
+                while iban == compact_value {
+                    iban = random_iban(format, &mut random);
+                }
+
+                // keep the print format (groups of 4 characters) if the original value uses it
+                if value.trim().contains(' ') {
+                    iban = print_format(iban.as_str());
+                }
+
+                Column::StringValue(column_name, iban)
+            }
+            column => column,
+        }
+    }
+}
+
+fn compact(iban: &str) -> String {
+    iban.chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+fn print_format(iban: &str) -> String {
+    iban.chars()
+        .collect::<Vec<_>>()
+        .chunks(4)
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn random_iban(
+    (country_code, bban_format): &(&str, &[(usize, CharKind)]),
+    random: &mut ThreadRng,
+) -> String {
+    let bban = bban_format
+        .iter()
+        .flat_map(|(length, kind)| (0..*length).map(move |_| *kind))
+        .map(|kind| match kind {
+            N => random.gen_range('0'..='9'),
+            A => random.gen_range('A'..='Z'),
+            C => {
+                let idx = random.gen_range(0..36u32);
+                std::char::from_digit(idx, 36).unwrap().to_ascii_uppercase()
+            }
+        })
+        .collect::<String>();
+
+    format!(
+        "{}{:02}{}",
+        country_code,
+        check_digits(country_code, bban.as_str()),
+        bban
+    )
+}
+
+/// ISO 7064 mod 97-10 check digits of an IBAN
+fn check_digits(country_code: &str, bban: &str) -> u32 {
+    98 - mod_97(format!("{}{}00", bban, country_code).as_str())
+}
+
+/// remainder of the division by 97 of the number where each letter is replaced by two digits (A = 10, ..., Z = 35)
+fn mod_97(value: &str) -> u32 {
+    value
+        .chars()
+        .filter_map(|c| c.to_digit(36))
+        .fold(0, |remainder, digit| {
+            if digit < 10 {
+                (remainder * 10 + digit) % 97
+            } else {
+                (remainder * 100 + digit) % 97
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{transformer::Transformer, types::Column};
+
+    use super::{compact, mod_97, IbanTransformer, BBAN_FORMATS};
+
+    fn is_valid_iban(iban: &str) -> bool {
+        let iban = compact(iban);
+        let (country_code_and_check_digits, bban) = iban.split_at(4);
+
+        iban.chars().all(|c| c.is_ascii_alphanumeric())
+            && mod_97(format!("{}{}", bban, country_code_and_check_digits).as_str()) == 1
+    }
+
+    fn transform(value: &str) -> String {
+        let transformer = get_transformer();
+        let column = Column::StringValue("iban".to_string(), value.to_string());
+        let transformed_column = transformer.transform(column);
+
+        transformed_column.string_value().unwrap().to_string()
+    }
+
+    #[test]
+    fn validate_known_ibans() {
+        assert!(is_valid_iban("DE89 3704 0044 0532 0130 00"));
+        assert!(is_valid_iban("GB29NWBK60161331926819"));
+        assert!(is_valid_iban("FR1420041010050500013M02606"));
+        assert!(is_valid_iban("DE02 1203 0000 0000 2020 51"));
+        assert!(!is_valid_iban("DE88 3704 0044 0532 0130 00"));
+    }
+
+    #[test]
+    fn transform_string_with_an_iban() {
+        for iban in [
+            "DE89 3704 0044 0532 0130 00",
+            "GB29NWBK60161331926819",
+            "FR1420041010050500013M02606",
+            "CH9300762011623852957",
+        ] {
+            for _ in 0..50 {
+                let transformed_value = transform(iban);
+
+                assert!(is_valid_iban(transformed_value.as_str()));
+                assert_ne!(compact(transformed_value.as_str()), compact(iban));
+                // same country and same length
+                assert_eq!(&transformed_value[..2], &iban[..2]);
+                assert_eq!(transformed_value.len(), iban.len());
+            }
+        }
+    }
+
+    #[test]
+    fn transform_unknown_country() {
+        let transformed_value = transform("not an iban");
+
+        assert!(is_valid_iban(transformed_value.as_str()));
+        assert!(BBAN_FORMATS
+            .iter()
+            .any(|(country_code, _)| transformed_value.starts_with(country_code)));
+
+        let transformer = get_transformer();
+        let column = Column::NumberValue("iban".to_string(), 42);
+        let transformed_column = transformer.transform(column);
+        assert_eq!(transformed_column.number_value().unwrap().to_owned(), 42);
+    }
+
+    fn get_transformer() -> IbanTransformer {
+        IbanTransformer::new("github", "users", "iban")
+    }
+}
@@ -0,0 +1,282 @@
+use rand::rngs::ThreadRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+
+use crate::transformer::Transformer;
+use crate::types::Column;
+
+/// This struct is dedicated to scrubbing all the values of a JSON document while keeping its keys and structure.
+pub struct JsonScrubTransformer {
+    database_name: String,
+    table_name: String,
+    column_name: String,
+    options: JsonScrubTransformerOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+pub struct JsonScrubTransformerOptions {
+    /// replace the numbers by random numbers of the same magnitude
+    #[serde(default)]
+    pub perturb_numbers: bool,
+    /// replace the booleans by random booleans
+    #[serde(default)]
+    pub perturb_booleans: bool,
+}
+
+impl JsonScrubTransformer {
+    pub fn new<S>(
+        database_name: S,
+        table_name: S,
+        column_name: S,
+        options: JsonScrubTransformerOptions,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        JsonScrubTransformer {
+            database_name: database_name.into(),
+            table_name: table_name.into(),
+            column_name: column_name.into(),
+            options,
+        }
+    }
+
+    fn scrub(&self, value: Value, random: &mut ThreadRng) -> Value {
+        match value {
+            Value::String(value) => Value::String(scrub_string(value.as_str(), random)),
+            Value::Number(number) if self.options.perturb_numbers => {
+                Value::Number(perturb_number(number, random))
+            }
+            Value::Bool(_) if self.options.perturb_booleans => Value::Bool(random.gen()),
+            Value::Array(values) => Value::Array(
+                values
+                    .into_iter()
+                    .map(|value| self.scrub(value, random))
+                    .collect(),
+            ),
+            Value::Object(values) => Value::Object(
+                values
+                    .into_iter()
+                    .map(|(key, value)| (key, self.scrub(value, random)))
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+}
+
+impl Default for JsonScrubTransformer {
+    fn default() -> Self {
+        JsonScrubTransformer {
+            database_name: String::default(),
+            table_name: String::default(),
+            column_name: String::default(),
+            options: JsonScrubTransformerOptions::default(),
+        }
+    }
+}
+
+impl Transformer for JsonScrubTransformer {
+    fn id(&self) -> &str {
+        "json-scrub"
+    }
+
+    fn description(&self) -> &str {
+        "Scrub all the strings of a JSON document but keep its structure (JSON string only). [{\"name\":\"Lucas\"}]->[{\"name\":\"Xkpao\"}]"
+    }
+
+    fn database_name(&self) -> &str {
+        self.database_name.as_str()
+    }
+
+    fn table_name(&self) -> &str {
+        self.table_name.as_str()
+    }
+
+    fn column_name(&self) -> &str {
+        self.column_name.as_str()
+    }
+
+    fn transform(&self, column: Column) -> Column {
+        match column {
+            Column::StringValue(column_name, value) => {
+                // not a JSON document - the value is kept as it is
+                let json = match serde_json::from_str::<Value>(value.as_str()) {
+                    Ok(json) => json,
+                    Err(_) => return Column::StringValue(column_name, value),
+                };
+
+                let json = self.scrub(json, &mut rand::thread_rng());
+                Column::StringValue(column_name, json.to_string())
+            }
+            column => column,
+        }
+    }
+}
+
+/// replace each letter and digit by a random one - the other characters (e.g. `@`, `-`, ` `) are kept to keep the format
+fn scrub_string(value: &str, random: &mut ThreadRng) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            'a'..='z' => random.gen_range('a'..='z'),
+            'A'..='Z' => random.gen_range('A'..='Z'),
+            '0'..='9' => random.gen_range('0'..='9'),
+            c if c.is_alphabetic() => random.gen_range('a'..='z'),
+            c => c,
+        })
+        .collect()
+}
+
+/// random number with the same sign and number of digits - floats are multiplied by a random factor
+fn perturb_number(number: Number, random: &mut ThreadRng) -> Number {
+    if let Some(value) = number.as_i64() {
+        let digits = value.unsigned_abs().to_string().len() as u32;
+        let min = if digits > 1 { 10u64.pow(digits - 1) } else { 0 };
+        let max = 10u64.checked_pow(digits).map_or(u64::MAX, |max| max - 1);
+        let new_value = random.gen_range(min..=max) as i64;
+
+        return Number::from(if value < 0 { -new_value } else { new_value });
+    }
+
+    if let Some(value) = number.as_u64() {
+        return Number::from(random.gen_range(value / 2..=value));
+    }
+
+    match number
+        .as_f64()
+        .and_then(|value| Number::from_f64(value * random.gen_range(0.5..1.5)))
+    {
+        Some(new_number) => new_number,
+        None => number,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{transformer::Transformer, types::Column};
+
+    use super::{JsonScrubTransformer, JsonScrubTransformerOptions};
+
+    fn document() -> Value {
+        json!({
+            "name": "Lucas Martin",
+            "email": "lucas.martin@company.com",
+            "age": 42,
+            "score": 12.5,
+            "active": true,
+            "manager": null,
+            "addresses": [
+                {"street": "Rue de l'Eglise", "zip": "75001", "tags": ["home", "billing"]},
+                {"street": "Main Street", "zip": "10001", "tags": []}
+            ]
+        })
+    }
+
+    fn transform(options: JsonScrubTransformerOptions, value: &Value) -> Value {
+        let transformer = JsonScrubTransformer::new("public", "users", "profile", options);
+        let column = Column::StringValue("profile".to_string(), value.to_string());
+        let transformed_column = transformer.transform(column);
+
+        serde_json::from_str(transformed_column.string_value().unwrap()).unwrap()
+    }
+
+    /// check that both documents have the same keys and the same type of value at each path
+    fn assert_same_structure(left: &Value, right: &Value) {
+        match (left, right) {
+            (Value::Object(left), Value::Object(right)) => {
+                assert_eq!(
+                    left.keys().collect::<Vec<_>>(),
+                    right.keys().collect::<Vec<_>>()
+                );
+                for (key, value) in left {
+                    assert_same_structure(value, &right[key]);
+                }
+            }
+            (Value::Array(left), Value::Array(right)) => {
+                assert_eq!(left.len(), right.len());
+                for (left, right) in left.iter().zip(right) {
+                    assert_same_structure(left, right);
+                }
+            }
+            (Value::String(left), Value::String(right)) => {
+                assert_eq!(left.chars().count(), right.chars().count())
+            }
+            (Value::Number(_), Value::Number(_))
+            | (Value::Bool(_), Value::Bool(_))
+            | (Value::Null, Value::Null) => {}
+            (left, right) => panic!("{} and {} have not the same type", left, right),
+        }
+    }
+
+    #[test]
+    fn scrub_nested_objects_and_arrays() {
+        let document = document();
+        let scrubbed = transform(JsonScrubTransformerOptions::default(), &document);
+
+        assert_same_structure(&document, &scrubbed);
+
+        // the string leaves are changed
+        assert_ne!(scrubbed["name"], document["name"]);
+        assert_ne!(scrubbed["email"], document["email"]);
+        assert_ne!(
+            scrubbed["addresses"][0]["street"],
+            document["addresses"][0]["street"]
+        );
+        assert_ne!(
+            scrubbed["addresses"][0]["tags"][1],
+            document["addresses"][0]["tags"][1]
+        );
+
+        // the format of the strings is kept
+        assert!(scrubbed["email"].as_str().unwrap().contains('@'));
+        assert!(scrubbed["addresses"][1]["zip"]
+            .as_str()
+            .unwrap()
+            .chars()
+            .all(|c| c.is_ascii_digit()));
+
+        // numbers and booleans are kept by default
+        assert_eq!(scrubbed["age"], document["age"]);
+        assert_eq!(scrubbed["score"], document["score"]);
+        assert_eq!(scrubbed["active"], document["active"]);
+        assert_eq!(scrubbed["manager"], Value::Null);
+    }
+
+    #[test]
+    fn perturb_numbers_and_booleans() {
+        let document = json!({"values": [1234, -56, 0, 2.5], "flags": [true, false]});
+        let options = JsonScrubTransformerOptions {
+            perturb_numbers: true,
+            perturb_booleans: true,
+        };
+
+        let scrubbed = transform(options, &document);
+        assert_same_structure(&document, &scrubbed);
+
+        let values = scrubbed["values"].as_array().unwrap();
+        let big = values[0].as_i64().unwrap();
+        assert!((1000..=9999).contains(&big));
+        let negative = values[1].as_i64().unwrap();
+        assert!((-99..=-10).contains(&negative));
+        assert!((0..=9).contains(&values[2].as_i64().unwrap()));
+        let float = values[3].as_f64().unwrap();
+        assert!((1.25..3.75).contains(&float));
+    }
+
+    #[test]
+    fn keep_values_that_are_not_json() {
+        let transformer = JsonScrubTransformer::default();
+
+        let column = Column::StringValue("profile".to_string(), "not json".to_string());
+        let transformed_column = transformer.transform(column);
+        assert_eq!(transformed_column.string_value().unwrap(), "not json");
+
+        let column = Column::NumberValue("profile".to_string(), 42);
+        let transformed_column = transformer.transform(column);
+        assert_eq!(transformed_column.number_value().unwrap().to_owned(), 42);
+    }
+}
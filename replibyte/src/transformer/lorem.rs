@@ -0,0 +1,158 @@
+use crate::transformer::Transformer;
+use crate::types::Column;
+use fake::faker::lorem::raw::Words;
+use fake::locales::EN;
+use fake::Fake;
+
+/// This struct is dedicated to replacing a string by lorem ipsum text of the same length.
+pub struct LoremTransformer {
+    database_name: String,
+    table_name: String,
+    column_name: String,
+}
+
+impl LoremTransformer {
+    pub fn new<S>(database_name: S, table_name: S, column_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        LoremTransformer {
+            database_name: database_name.into(),
+            table_name: table_name.into(),
+            column_name: column_name.into(),
+        }
+    }
+}
+
+impl Default for LoremTransformer {
+    fn default() -> Self {
+        LoremTransformer {
+            database_name: String::default(),
+            table_name: String::default(),
+            column_name: String::default(),
+        }
+    }
+}
+
+impl Transformer for LoremTransformer {
+    fn id(&self) -> &str {
+        "lorem"
+    }
+
+    fn description(&self) -> &str {
+        "Generate lorem ipsum text of the same length (string only). [Hello World]->[sit amet ut]"
+    }
+
+    fn database_name(&self) -> &str {
+        self.database_name.as_str()
+    }
+
+    fn table_name(&self) -> &str {
+        self.table_name.as_str()
+    }
+
+    fn column_name(&self) -> &str {
+        self.column_name.as_str()
+    }
+
+    fn transform(&self, column: Column) -> Column {
+        match column {
+            Column::StringValue(column_name, value) => {
+                let new_value = lorem(value.chars().count());
+                Column::StringValue(column_name, new_value)
+            }
+            column => column,
+        }
+    }
+}
+
+/// generate lorem ipsum words until the expected number of chars is reached
+fn lorem(len: usize) -> String {
+    let mut text = String::with_capacity(len);
+
+    while text.chars().count() < len {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+
+        let words: Vec<String> = Words(EN, 5..10).fake();
+        text.push_str(words.join(" ").as_str());
+    }
+
+    // words are ASCII - the text can be truncated safely
+    text.truncate(len);
+
+    // do not end with a space
+    if text.ends_with(' ') {
+        text.pop();
+        text.push('a');
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{transformer::Transformer, types::Column};
+
+    use super::LoremTransformer;
+
+    #[test]
+    fn transform_lorem_with_number_value() {
+        let expected_value = 34;
+        let transformer = get_transformer();
+        let column = Column::NumberValue("description".to_string(), expected_value);
+        let transformed_column = transformer.transform(column);
+        let transformed_value = transformed_column.number_value().unwrap();
+
+        assert_eq!(transformed_value.to_owned(), expected_value)
+    }
+
+    #[test]
+    fn transform_lorem_with_empty_string_value() {
+        let transformer = get_transformer();
+        let column = Column::StringValue("description".to_string(), "".to_string());
+        let transformed_column = transformer.transform(column);
+        let transformed_value = transformed_column.string_value().unwrap();
+
+        assert_eq!(transformed_value, "")
+    }
+
+    #[test]
+    fn transform_lorem_with_short_string_value() {
+        let transformer = get_transformer();
+        let column = Column::StringValue("description".to_string(), "A".to_string());
+        let transformed_column = transformer.transform(column);
+        let transformed_value = transformed_column.string_value().unwrap();
+
+        assert_eq!(transformed_value.chars().count(), 1);
+        assert!(!transformed_value.trim().is_empty());
+    }
+
+    #[test]
+    fn transform_lorem_with_string_value() {
+        let transformer = get_transformer();
+
+        for value in [
+            "Hello World",
+            "Soft drinks, coffees, teas, beers, and ales",
+            "Sweet and savory sauces, relishes, spreads, and seasonings. \
+             Desserts, candies, and sweet breads. Cheeses. Breads, crackers, pasta, and cereal.",
+            "🦀ë池 multi-byte characters",
+        ] {
+            let column = Column::StringValue("description".to_string(), value.to_string());
+            let transformed_column = transformer.transform(column);
+            let transformed_value = transformed_column.string_value().unwrap();
+
+            let len = value.chars().count() as i64;
+            let transformed_len = transformed_value.chars().count() as i64;
+            assert!((len - transformed_len).abs() <= 1);
+            assert_ne!(transformed_value, value);
+            assert!(!transformed_value.ends_with(' '));
+        }
+    }
+
+    fn get_transformer() -> LoremTransformer {
+        LoremTransformer::new("github", "products", "description")
+    }
+}
@@ -1,22 +1,46 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::transformer::consistent_fake::ConsistentFakeTransformer;
 use crate::transformer::credit_card::CreditCardTransformer;
+use crate::transformer::credit_card_scrub::CreditCardScrubTransformer;
 use crate::transformer::custom_wasm::CustomWasmTransformer;
+use crate::transformer::derive_from_column::DeriveFromColumnTransformer;
 use crate::transformer::email::EmailTransformer;
 use crate::transformer::first_name::FirstNameTransformer;
+use crate::transformer::hash::HashTransformer;
+use crate::transformer::iban::IbanTransformer;
+use crate::transformer::json_scrub::JsonScrubTransformer;
 use crate::transformer::keep_first_char::KeepFirstCharTransformer;
+use crate::transformer::lorem::LoremTransformer;
 use crate::transformer::phone_number::PhoneNumberTransformer;
 use crate::transformer::random::RandomTransformer;
 use crate::transformer::redacted::RedactedTransformer;
+use crate::transformer::shuffle::ShuffleTransformer;
 use crate::transformer::transient::TransientTransformer;
+use crate::transformer::unique_email::UniqueEmailTransformer;
 use crate::types::Column;
 
+pub mod consistent_fake;
 pub mod credit_card;
+pub mod credit_card_scrub;
+pub mod derive_from_column;
 pub mod email;
 pub mod first_name;
+pub mod hash;
+pub mod iban;
+pub mod json_scrub;
 pub mod keep_first_char;
+pub mod lorem;
 pub mod phone_number;
 pub mod random;
 pub mod redacted;
+pub mod shuffle;
 pub mod transient;
+pub mod unique_email;
 
 // FIXME: CI release build is broken because of feature flag
 //#[cfg(feature = "wasm")]
@@ -25,17 +49,33 @@ pub mod custom_wasm;
 pub fn transformers() -> Vec<Box<dyn Transformer>> {
     vec![
         Box::new(EmailTransformer::default()),
+        Box::new(UniqueEmailTransformer::default()),
         Box::new(FirstNameTransformer::default()),
         Box::new(PhoneNumberTransformer::default()),
         Box::new(RandomTransformer::default()),
         Box::new(KeepFirstCharTransformer::default()),
         Box::new(TransientTransformer::default()),
         Box::new(CreditCardTransformer::default()),
+        Box::new(CreditCardScrubTransformer::default()),
+        Box::new(IbanTransformer::default()),
         Box::new(RedactedTransformer::default()),
+        Box::new(LoremTransformer::default()),
+        Box::new(JsonScrubTransformer::default()),
+        Box::new(ConsistentFakeTransformer::default()),
+        Box::new(HashTransformer::default()),
+        Box::new(ShuffleTransformer::default()),
+        Box::new(DeriveFromColumnTransformer::default()),
         Box::new(CustomWasmTransformer::default()),
     ]
 }
 
+/// Find a transformer by its id (e.g. `first-name`)
+pub fn transformer_by_id(id: &str) -> Option<Box<dyn Transformer>> {
+    transformers()
+        .into_iter()
+        .find(|transformer| transformer.id() == id)
+}
+
 /// Trait to implement to create a custom Transformer.
 pub trait Transformer {
     fn id(&self) -> &str;
@@ -84,4 +124,420 @@ pub trait Transformer {
     }
 
     fn transform(&self, column: Column) -> Column;
+
+    /// false to keep the original value of the current row - checked before each transformation
+    fn should_transform(&self) -> bool {
+        true
+    }
+
+    /// true if the transformer needs all the values of its column at once (e.g. to shuffle them) -
+    /// the rows of its table are kept in memory and `transform_all` is called instead of `transform`
+    fn transforms_all_values(&self) -> bool {
+        false
+    }
+
+    /// transform the values of every row of the column, in the dump order
+    fn transform_all(&self, columns: Vec<Column>) -> Vec<Column> {
+        columns
+    }
+
+    /// column of the same row the value is derived from - `transform` is called with this column (once transformed)
+    /// instead of the column of the transformer
+    fn source_column(&self) -> Option<&str> {
+        None
+    }
+
+    /// column of the same row keying the transformation (e.g. the primary key) - `transform_with_key` is called
+    /// with the original value of this column instead of `transform`
+    fn key_column(&self) -> Option<&str> {
+        None
+    }
+
+    /// transform the value of the column from the value of the `key_column` of its row
+    fn transform_with_key(&self, column: Column, _key: &str) -> Column {
+        self.transform(column)
+    }
+}
+
+/// Apply a transformer on a sampled fraction of the rows only - the other rows keep their original value
+pub struct SampledTransformer {
+    transformer: Box<dyn Transformer>,
+    probability: f64,
+    rng: RefCell<StdRng>,
+}
+
+impl SampledTransformer {
+    /// the same rows are sampled with the same seed
+    pub fn new(transformer: Box<dyn Transformer>, probability: f64, seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        SampledTransformer {
+            transformer,
+            probability,
+            rng: RefCell::new(rng),
+        }
+    }
+}
+
+impl Transformer for SampledTransformer {
+    fn id(&self) -> &str {
+        self.transformer.id()
+    }
+
+    fn description(&self) -> &str {
+        self.transformer.description()
+    }
+
+    fn database_name(&self) -> &str {
+        self.transformer.database_name()
+    }
+
+    fn table_name(&self) -> &str {
+        self.transformer.table_name()
+    }
+
+    fn column_name(&self) -> &str {
+        self.transformer.column_name()
+    }
+
+    fn transform(&self, column: Column) -> Column {
+        self.transformer.transform(column)
+    }
+
+    fn should_transform(&self) -> bool {
+        self.rng.borrow_mut().gen_bool(self.probability)
+    }
+
+    fn transforms_all_values(&self) -> bool {
+        self.transformer.transforms_all_values()
+    }
+
+    fn transform_all(&self, columns: Vec<Column>) -> Vec<Column> {
+        self.transformer.transform_all(columns)
+    }
+
+    fn source_column(&self) -> Option<&str> {
+        self.transformer.source_column()
+    }
+
+    fn key_column(&self) -> Option<&str> {
+        self.transformer.key_column()
+    }
+
+    fn transform_with_key(&self, column: Column, key: &str) -> Column {
+        self.transformer.transform_with_key(column, key)
+    }
+}
+
+/// Apply a transformer and alter the values it already wrote within the dump - to not break a unique index
+/// when the transformer maps different values to the same one
+pub struct UniqueWrapper {
+    transformer: Box<dyn Transformer>,
+    written_values: RefCell<WrittenValues>,
+}
+
+#[derive(Default)]
+struct WrittenValues {
+    values: HashSet<String>,
+    // last counter added to each duplicated value - to not try the same counters again
+    counters: HashMap<String, usize>,
+}
+
+impl UniqueWrapper {
+    pub fn new(transformer: Box<dyn Transformer>) -> Self {
+        UniqueWrapper {
+            transformer,
+            written_values: RefCell::new(WrittenValues::default()),
+        }
+    }
+
+    /// the value of the column - a counter is added to it if it is already written
+    fn unique(&self, column: Column) -> Column {
+        let mut written_values = self.written_values.borrow_mut();
+        let written_values = &mut *written_values;
+
+        match column {
+            Column::StringValue(column_name, value) if !value.is_empty() => {
+                let mut unique_value = value.clone();
+                let counter = written_values.counters.entry(value.clone()).or_insert(0);
+                while written_values.values.contains(unique_value.as_str()) {
+                    *counter += 1;
+                    unique_value = match value.split_once('@') {
+                        // the counter is added to the local part of an email address
+                        Some((local_part, domain)) => {
+                            format!("{}{}@{}", local_part, counter, domain)
+                        }
+                        None => format!("{}{}", value, counter),
+                    };
+                }
+
+                written_values.values.insert(unique_value.clone());
+                Column::StringValue(column_name, unique_value)
+            }
+            Column::NumberValue(column_name, value) => {
+                let mut unique_value = value;
+                let counter = written_values
+                    .counters
+                    .entry(value.to_string())
+                    .or_insert(0);
+                while written_values.values.contains(&unique_value.to_string()) {
+                    *counter += 1;
+                    unique_value = match value.checked_add(*counter as i128) {
+                        Some(unique_value) => unique_value,
+                        // past the greatest number, the free values are below the value
+                        None => value - (*counter as i128 - (i128::MAX - value)),
+                    };
+                }
+
+                written_values.values.insert(unique_value.to_string());
+                Column::NumberValue(column_name, unique_value)
+            }
+            column => column,
+        }
+    }
+}
+
+impl Transformer for UniqueWrapper {
+    fn id(&self) -> &str {
+        self.transformer.id()
+    }
+
+    fn description(&self) -> &str {
+        self.transformer.description()
+    }
+
+    fn database_name(&self) -> &str {
+        self.transformer.database_name()
+    }
+
+    fn table_name(&self) -> &str {
+        self.transformer.table_name()
+    }
+
+    fn column_name(&self) -> &str {
+        self.transformer.column_name()
+    }
+
+    fn transform(&self, column: Column) -> Column {
+        self.unique(self.transformer.transform(column))
+    }
+
+    fn should_transform(&self) -> bool {
+        self.transformer.should_transform()
+    }
+
+    fn transforms_all_values(&self) -> bool {
+        self.transformer.transforms_all_values()
+    }
+
+    fn transform_all(&self, columns: Vec<Column>) -> Vec<Column> {
+        self.transformer
+            .transform_all(columns)
+            .into_iter()
+            .map(|column| self.unique(column))
+            .collect()
+    }
+
+    fn source_column(&self) -> Option<&str> {
+        self.transformer.source_column()
+    }
+
+    fn key_column(&self) -> Option<&str> {
+        self.transformer.key_column()
+    }
+
+    fn transform_with_key(&self, column: Column, key: &str) -> Column {
+        self.unique(self.transformer.transform_with_key(column, key))
+    }
+}
+
+/// Number of values touched by each transformer during a dump, by transformer id and column.
+#[derive(Default)]
+pub struct TransformerMetrics {
+    counters: RefCell<BTreeMap<(String, String), usize>>,
+    // values kept as they are because the transformer panicked
+    failures: RefCell<BTreeMap<(String, String), usize>>,
+    // transformation errors, in the dump order - the values are kept as they are
+    errors: RefCell<Vec<String>>,
+    // values which look wrong but are not transformation errors (e.g. a text in a numeric column), in the dump order
+    warnings: RefCell<Vec<String>>,
+}
+
+fn metrics_key(transformer: &dyn Transformer) -> (String, String) {
+    (
+        transformer.id().to_string(),
+        transformer.database_and_table_and_column_name(),
+    )
+}
+
+impl TransformerMetrics {
+    pub fn increment(&self, transformer: &dyn Transformer) {
+        *self
+            .counters
+            .borrow_mut()
+            .entry(metrics_key(transformer))
+            .or_insert(0) += 1;
+    }
+
+    pub fn increment_failure(&self, transformer: &dyn Transformer) {
+        *self
+            .failures
+            .borrow_mut()
+            .entry(metrics_key(transformer))
+            .or_insert(0) += 1;
+    }
+
+    pub fn failure_count(&self, transformer_id: &str, column: &str) -> usize {
+        self.failures
+            .borrow()
+            .get(&(transformer_id.to_string(), column.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn add_error(&self, error: String) {
+        self.errors.borrow_mut().push(error);
+    }
+
+    /// return the transformation errors in the dump order
+    pub fn errors(&self) -> Vec<String> {
+        self.errors.borrow().clone()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.borrow().is_empty()
+    }
+
+    pub fn add_warning(&self, warning: String) {
+        self.warnings.borrow_mut().push(warning);
+    }
+
+    /// return the warnings in the dump order
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.borrow().clone()
+    }
+
+    /// return the counters as (transformer id, column, number of transformed values)
+    pub fn counters(&self) -> Vec<(String, String, usize)> {
+        self.counters
+            .borrow()
+            .iter()
+            .map(|((id, column), count)| (id.clone(), column.clone(), *count))
+            .collect()
+    }
+
+    pub fn count(&self, transformer_id: &str, column: &str) -> usize {
+        self.counters
+            .borrow()
+            .get(&(transformer_id.to_string(), column.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::transformer::first_name::FirstNameTransformer;
+    use crate::transformer::keep_first_char::KeepFirstCharTransformer;
+    use crate::transformer::transient::TransientTransformer;
+    use crate::transformer::{
+        transformer_by_id, transformers, SampledTransformer, Transformer, UniqueWrapper,
+    };
+    use crate::types::Column;
+
+    #[test]
+    fn find_transformer_by_id() {
+        let transformer = transformer_by_id("first-name").unwrap();
+        assert_eq!(transformer.id(), "first-name");
+
+        for transformer in transformers() {
+            assert!(transformer_by_id(transformer.id()).is_some());
+        }
+
+        assert!(transformer_by_id("frist-name").is_none());
+    }
+
+    fn sampled_rows(seed: Option<u64>) -> Vec<bool> {
+        let transformer = SampledTransformer::new(
+            Box::new(FirstNameTransformer::new("public", "users", "first_name")),
+            0.1,
+            seed,
+        );
+
+        (0..10_000)
+            .map(|_| transformer.should_transform())
+            .collect()
+    }
+
+    #[test]
+    fn sampled_transformer() {
+        let rows = sampled_rows(Some(42));
+        let transformed_rows = rows.iter().filter(|&&transformed| transformed).count();
+
+        // around 10% of the rows
+        assert!(transformed_rows > 800 && transformed_rows < 1200);
+
+        // the same rows are sampled with the same seed
+        assert_eq!(rows, sampled_rows(Some(42)));
+        assert_ne!(rows, sampled_rows(Some(43)));
+    }
+
+    #[test]
+    fn unique_wrapper() {
+        // 2 possible values only
+        let transformer = UniqueWrapper::new(Box::new(KeepFirstCharTransformer::new(
+            "public", "users", "name",
+        )));
+
+        let values = (0..1_000)
+            .map(|idx| {
+                let name = if idx % 2 == 0 { "Alice" } else { "Bob" };
+                let column = Column::StringValue("name".to_string(), name.to_string());
+                transformer
+                    .transform(column)
+                    .string_value()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(values[0], "A");
+        assert_eq!(values[1], "B");
+        assert_eq!(values[2], "A1");
+        assert_eq!(values.iter().collect::<HashSet<_>>().len(), values.len());
+
+        let transformer = UniqueWrapper::new(Box::new(KeepFirstCharTransformer::new(
+            "public", "users", "age",
+        )));
+
+        let values = (0..1_000)
+            .map(|idx| {
+                let column = Column::NumberValue("age".to_string(), idx % 3);
+                *transformer.transform(column).number_value().unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(values.iter().collect::<HashSet<_>>().len(), values.len());
+
+        let transformer =
+            UniqueWrapper::new(Box::new(TransientTransformer::new("public", "users", "id")));
+
+        let values = [i128::MAX - 1, i128::MAX, i128::MAX, i128::MAX]
+            .into_iter()
+            .map(|value| {
+                let column = Column::NumberValue("id".to_string(), value);
+                *transformer.transform(column).number_value().unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            values,
+            vec![i128::MAX - 1, i128::MAX, i128::MAX - 2, i128::MAX - 3]
+        );
+    }
 }
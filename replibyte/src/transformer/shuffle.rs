@@ -0,0 +1,163 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::transformer::Transformer;
+use crate::types::Column;
+
+/// This struct is dedicated to shuffling the values of a column among its rows - every value is kept,
+/// but attached to another row. All the rows of the table are kept in memory until the last one is read.
+pub struct ShuffleTransformer {
+    database_name: String,
+    table_name: String,
+    column_name: String,
+    options: ShuffleTransformerOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+pub struct ShuffleTransformerOptions {
+    /// the same rows are shuffled the same way with the same seed - random otherwise
+    pub seed: Option<u64>,
+}
+
+impl ShuffleTransformer {
+    pub fn new<S>(
+        database_name: S,
+        table_name: S,
+        column_name: S,
+        options: ShuffleTransformerOptions,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        ShuffleTransformer {
+            database_name: database_name.into(),
+            table_name: table_name.into(),
+            column_name: column_name.into(),
+            options,
+        }
+    }
+}
+
+impl Default for ShuffleTransformer {
+    fn default() -> Self {
+        ShuffleTransformer {
+            database_name: String::default(),
+            table_name: String::default(),
+            column_name: String::default(),
+            options: ShuffleTransformerOptions::default(),
+        }
+    }
+}
+
+impl Transformer for ShuffleTransformer {
+    fn id(&self) -> &str {
+        "shuffle"
+    }
+
+    fn description(&self) -> &str {
+        "Shuffle the values of a column among its rows - the distribution of the values is kept (PostgreSQL only). [1,2,3]->[3,1,2]"
+    }
+
+    fn database_name(&self) -> &str {
+        self.database_name.as_str()
+    }
+
+    fn table_name(&self) -> &str {
+        self.table_name.as_str()
+    }
+
+    fn column_name(&self) -> &str {
+        self.column_name.as_str()
+    }
+
+    /// a single value can't be shuffled - see `transform_all`
+    fn transform(&self, column: Column) -> Column {
+        column
+    }
+
+    fn transforms_all_values(&self) -> bool {
+        true
+    }
+
+    fn transform_all(&self, columns: Vec<Column>) -> Vec<Column> {
+        let mut rng = match self.options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        // Sattolo's algorithm - a single cycle, no value stays on its row
+        let mut columns = columns;
+        for i in (1..columns.len()).rev() {
+            let j = rng.gen_range(0..i);
+            columns.swap(i, j);
+        }
+
+        columns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{transformer::Transformer, types::Column};
+
+    use super::{ShuffleTransformer, ShuffleTransformerOptions};
+
+    fn columns(values: &[i128]) -> Vec<Column> {
+        values
+            .iter()
+            .map(|value| Column::NumberValue("age".to_string(), *value))
+            .collect()
+    }
+
+    fn values(columns: &[Column]) -> Vec<i128> {
+        columns
+            .iter()
+            .map(|column| *column.number_value().unwrap())
+            .collect()
+    }
+
+    fn get_transformer(seed: Option<u64>) -> ShuffleTransformer {
+        ShuffleTransformer::new("public", "users", "age", ShuffleTransformerOptions { seed })
+    }
+
+    #[test]
+    fn shuffle_values() {
+        let original_values = (0..100).collect::<Vec<i128>>();
+        let transformer = get_transformer(Some(42));
+
+        let shuffled_values = values(&transformer.transform_all(columns(&original_values)));
+
+        // every value is moved to another row
+        assert!(original_values
+            .iter()
+            .zip(shuffled_values.iter())
+            .all(|(original, shuffled)| original != shuffled));
+
+        let mut sorted_values = shuffled_values.clone();
+        sorted_values.sort();
+        assert_eq!(sorted_values, original_values);
+
+        // the same seed shuffles the same way
+        assert_eq!(
+            values(&transformer.transform_all(columns(&original_values))),
+            shuffled_values
+        );
+        assert_ne!(
+            values(&get_transformer(Some(43)).transform_all(columns(&original_values))),
+            shuffled_values
+        );
+    }
+
+    #[test]
+    fn shuffle_few_values() {
+        let transformer = get_transformer(None);
+
+        assert!(transformer.transform_all(vec![]).is_empty());
+        assert_eq!(values(&transformer.transform_all(columns(&[7]))), vec![7]);
+        assert_eq!(
+            values(&transformer.transform_all(columns(&[1, 2]))),
+            vec![2, 1]
+        );
+    }
+}
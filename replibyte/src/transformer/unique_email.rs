@@ -0,0 +1,154 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use fake::faker::internet::raw::SafeEmail;
+use fake::locales::EN;
+use fake::Fake;
+
+use crate::transformer::Transformer;
+use crate::types::Column;
+
+/// This struct is dedicated to replacing a string by an email address which is not used yet within the dump -
+/// to not break the unique constraints of the email columns on restore.
+///
+/// The generated addresses are kept in a `RefCell`: the transformer is not `Sync` and can't be shared between
+/// threads as it is - transforming the rows in parallel requires a lock around the generated addresses.
+pub struct UniqueEmailTransformer {
+    database_name: String,
+    table_name: String,
+    column_name: String,
+    generated_emails: RefCell<GeneratedEmails>,
+}
+
+#[derive(Default)]
+struct GeneratedEmails {
+    emails: HashSet<String>,
+    // last counter added to each fake address - to not try the same counters again
+    counters: HashMap<String, usize>,
+}
+
+impl UniqueEmailTransformer {
+    pub fn new<S>(database_name: S, table_name: S, column_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        UniqueEmailTransformer {
+            database_name: database_name.into(),
+            table_name: table_name.into(),
+            column_name: column_name.into(),
+            generated_emails: RefCell::new(GeneratedEmails::default()),
+        }
+    }
+
+    /// a fake email address - a counter is added to its local part if it is already generated
+    fn unique_email(&self) -> String {
+        let mut generated_emails = self.generated_emails.borrow_mut();
+        let generated_emails = &mut *generated_emails;
+
+        let email: String = SafeEmail(EN).fake();
+        let (local_part, domain) = email.split_once('@').unwrap_or((email.as_str(), ""));
+
+        let mut unique_email = email.clone();
+        let counter = generated_emails.counters.entry(email.clone()).or_insert(1);
+        while generated_emails.emails.contains(unique_email.as_str()) {
+            *counter += 1;
+            unique_email = format!("{}{}@{}", local_part, counter, domain);
+        }
+
+        generated_emails.emails.insert(unique_email.clone());
+        unique_email
+    }
+}
+
+impl Default for UniqueEmailTransformer {
+    fn default() -> Self {
+        UniqueEmailTransformer {
+            database_name: String::default(),
+            table_name: String::default(),
+            column_name: String::default(),
+            generated_emails: RefCell::new(GeneratedEmails::default()),
+        }
+    }
+}
+
+impl Transformer for UniqueEmailTransformer {
+    fn id(&self) -> &str {
+        "unique-email"
+    }
+
+    fn description(&self) -> &str {
+        "Generate an email address which is unique within the dump (string only). [john.doe@company.com]->[tony.stark2@avengers.com]"
+    }
+
+    fn database_name(&self) -> &str {
+        self.database_name.as_str()
+    }
+
+    fn table_name(&self) -> &str {
+        self.table_name.as_str()
+    }
+
+    fn column_name(&self) -> &str {
+        self.column_name.as_str()
+    }
+
+    fn transform(&self, column: Column) -> Column {
+        match column {
+            Column::StringValue(column_name, value) => {
+                let new_value = match value.len() {
+                    0 => value,
+                    _ => self.unique_email(),
+                };
+
+                Column::StringValue(column_name, new_value)
+            }
+            column => column,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{transformer::Transformer, types::Column};
+
+    use super::UniqueEmailTransformer;
+
+    fn get_transformer() -> UniqueEmailTransformer {
+        UniqueEmailTransformer::new("github", "users", "email")
+    }
+
+    #[test]
+    fn generate_unique_emails() {
+        let transformer = get_transformer();
+
+        // far more emails than the fake names - collisions are certain
+        let emails = (0..20_000)
+            .map(|_| {
+                let column = Column::StringValue("email".to_string(), "john@doe.com".to_string());
+                transformer
+                    .transform(column)
+                    .string_value()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+
+        assert!(emails.iter().all(|email| email.contains('@')));
+        assert_eq!(emails.iter().collect::<HashSet<_>>().len(), emails.len());
+    }
+
+    #[test]
+    fn transform_empty_and_non_string_values() {
+        let transformer = get_transformer();
+
+        let column = Column::StringValue("email".to_string(), "".to_string());
+        let transformed_column = transformer.transform(column);
+        assert_eq!(transformed_column.string_value().unwrap(), "");
+
+        let column = Column::NumberValue("email".to_string(), 42);
+        let transformed_column = transformer.transform(column);
+        assert_eq!(transformed_column.number_value().unwrap(), &42);
+    }
+}
@@ -80,3 +80,63 @@ pub fn wait_for_command(process: &mut Child) -> Result<(), Error> {
 pub fn get_replibyte_version() -> &'static str {
     env!("CARGO_PKG_VERSION")
 }
+
+/// match a value against a glob pattern - `*` matches any sequence of chars and `?` a single char
+pub fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+
+    let (mut p, mut v) = (0, 0);
+    // position of the last `*` in the pattern and of the value char it is matched from
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if let Some((star_p, star_v)) = backtrack {
+            // let the last `*` match one more char
+            backtrack = Some((star_p, star_v + 1));
+            p = star_p + 1;
+            v = star_v + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// 64-bit FNV-1a hash of bytes - stable from one run (and one Rust version) to another, unlike the hasher of the
+/// standard library
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::glob_match;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("users", "users"));
+        assert!(!glob_match("users", "users_log"));
+        assert!(glob_match("*_log", "users_log"));
+        assert!(glob_match("*_log", "_log"));
+        assert!(!glob_match("*_log", "users_log_archive"));
+        assert!(glob_match("audit_*", "audit_"));
+        assert!(glob_match("audit_*", "audit_events"));
+        assert!(!glob_match("audit_*", "users_audit"));
+        assert!(glob_match("*audit*", "users_audit_2022"));
+        assert!(glob_match("log_20??", "log_2022"));
+        assert!(!glob_match("log_20??", "log_202"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+    }
+}
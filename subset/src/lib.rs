@@ -1,7 +1,9 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::Error;
 
 mod dedup;
+pub mod mongodb;
+pub mod mysql;
 pub mod postgres;
 mod utils;
 
@@ -10,6 +12,9 @@ pub type Bytes = Vec<u8>;
 pub trait Subset {
     fn read<F: FnMut(String), P: FnMut(Progress)>(&self, data: F, progress: P)
         -> Result<(), Error>;
+
+    /// Return the number of rows the subset would produce by `<database>.<table>`, without producing them
+    fn estimate(&self) -> Result<HashMap<String, usize>, Error>;
 }
 
 pub struct Progress {
@@ -25,7 +30,30 @@ pub struct Progress {
 
 impl Progress {
     pub fn percent(&self) -> u8 {
-        ((self.processed_rows as f64 / self.total_rows_to_process as f64) * 100.0) as u8
+        if self.total_rows_to_process == 0 {
+            return 0;
+        }
+
+        let percent = (self.processed_rows as f64 / self.total_rows_to_process as f64) * 100.0;
+        percent.min(100.0) as u8
+    }
+}
+
+pub enum SubsetStrategy<'a> {
+    RandomPercent {
+        database: &'a str,
+        table: &'a str,
+        percent: u8,
+    },
+}
+
+impl<'a> SubsetStrategy<'a> {
+    pub fn random(database: &'a str, table: &'a str, percent: u8) -> Self {
+        SubsetStrategy::RandomPercent {
+            database,
+            table,
+            percent,
+        }
     }
 }
 
@@ -46,11 +74,21 @@ impl<'a> PassthroughTable<'a> {
 
 pub struct SubsetOptions<'a> {
     pub passthrough_tables: &'a HashSet<PassthroughTable<'a>>,
+    // number of threads visiting the tables linked to the reference rows - 1 to visit them serially
+    pub parallel_tables: usize,
 }
 
 impl<'a> SubsetOptions<'a> {
     pub fn new(passthrough_tables: &'a HashSet<PassthroughTable<'a>>) -> Self {
-        SubsetOptions { passthrough_tables }
+        SubsetOptions {
+            passthrough_tables,
+            parallel_tables: 1,
+        }
+    }
+
+    /// visit the table trees of independent reference rows on `workers` threads
+    pub fn set_parallel_tables(&mut self, workers: usize) {
+        self.parallel_tables = workers;
     }
 }
 
@@ -121,3 +159,36 @@ impl SubsetTableRelation {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::Progress;
+
+    fn progress(processed_rows: usize, total_rows_to_process: usize) -> Progress {
+        Progress {
+            total_rows: total_rows_to_process,
+            total_rows_to_process,
+            processed_rows,
+            last_process_time: 0,
+        }
+    }
+
+    #[test]
+    fn percent() {
+        assert_eq!(progress(0, 10).percent(), 0);
+        assert_eq!(progress(5, 10).percent(), 50);
+        assert_eq!(progress(10, 10).percent(), 100);
+    }
+
+    #[test]
+    fn percent_without_rows_to_process() {
+        assert_eq!(progress(0, 0).percent(), 0);
+        assert_eq!(progress(3, 0).percent(), 0);
+    }
+
+    #[test]
+    fn percent_is_capped_to_100() {
+        assert_eq!(progress(11, 10).percent(), 100);
+        assert_eq!(progress(usize::MAX, 1).percent(), 100);
+    }
+}
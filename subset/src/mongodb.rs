@@ -0,0 +1,575 @@
+use crate::{utils, Progress, Subset, SubsetOptions, SubsetStrategy, SubsetTable};
+use bson::{Bson, Document};
+use dump_parser::mongodb::{Prefix, PrefixedCollections};
+use std::collections::{HashMap, HashSet};
+use std::io::{Error, ErrorKind};
+
+/// `<database>.<collection>` and position of a document in its collection
+type DocumentRef = (Prefix, usize);
+
+/// positions of the documents of a collection by value of one of their fields
+type DocumentsByValue = HashMap<String, Vec<usize>>;
+
+/// Subset of the collections of a mongodump archive.
+///
+/// MongoDB has no foreign key - the references between the collections are the `relations` of the `subset_tables`,
+/// e.g. `orders.customer_id` referencing `customers._id`.
+pub struct MongoDBSubset<'a> {
+    collections: &'a PrefixedCollections,
+    subset_table_by_prefix: HashMap<Prefix, SubsetTable>,
+    documents_by_prefix_and_field: HashMap<(Prefix, String), DocumentsByValue>,
+    subset_strategy: SubsetStrategy<'a>,
+    subset_options: SubsetOptions<'a>,
+}
+
+impl<'a> MongoDBSubset<'a> {
+    pub fn new(
+        collections: &'a PrefixedCollections,
+        subset_tables: Vec<SubsetTable>,
+        subset_strategy: SubsetStrategy<'a>,
+        subset_options: SubsetOptions<'a>,
+    ) -> Result<Self, Error> {
+        // the reference collection must be part of the archive
+        match subset_strategy {
+            SubsetStrategy::RandomPercent {
+                database, table, ..
+            } => {
+                let _ = get_collection(collections, &to_prefix(database, table))?;
+            }
+        }
+
+        // index the referenced documents by the value of the referenced field
+        let mut documents_by_prefix_and_field = HashMap::new();
+        for subset_table in &subset_tables {
+            for relation in &subset_table.relations {
+                let referenced_prefix =
+                    to_prefix(relation.database.as_str(), relation.table.as_str());
+                let referenced_collection = get_collection(collections, &referenced_prefix)?;
+
+                let _ = documents_by_prefix_and_field
+                    .entry((referenced_prefix, relation.to_property.clone()))
+                    .or_insert_with(|| {
+                        let mut documents_by_value = DocumentsByValue::new();
+
+                        for (idx, doc) in referenced_collection.iter().enumerate() {
+                            for value in field_values(doc, relation.to_property.as_str()) {
+                                documents_by_value
+                                    .entry(value.to_string())
+                                    .or_default()
+                                    .push(idx);
+                            }
+                        }
+
+                        documents_by_value
+                    });
+            }
+        }
+
+        Ok(MongoDBSubset {
+            collections,
+            subset_table_by_prefix: subset_tables
+                .into_iter()
+                .map(|subset_table| {
+                    (
+                        to_prefix(subset_table.database.as_str(), subset_table.table.as_str()),
+                        subset_table,
+                    )
+                })
+                .collect(),
+            documents_by_prefix_and_field,
+            subset_strategy,
+            subset_options,
+        })
+    }
+
+    /// Return the documents of the subset by `<database>.<collection>`, in the order of the archive.
+    /// Every collection is part of the result - the ones without any document in the subset are empty.
+    pub fn subset_collections<P: FnMut(Progress)>(
+        &self,
+        progress: P,
+    ) -> Result<PrefixedCollections, Error> {
+        let visited_documents = self.visited_documents(progress)?;
+
+        Ok(self
+            .collections
+            .iter()
+            .map(|(prefix, collection)| {
+                let docs = collection
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| {
+                        self.is_passthrough(prefix)
+                            || visited_documents.contains(&(prefix.clone(), *idx))
+                    })
+                    .map(|(_, doc)| doc.clone())
+                    .collect::<Vec<_>>();
+
+                (prefix.clone(), docs)
+            })
+            .collect())
+    }
+
+    fn is_passthrough(&self, prefix: &str) -> bool {
+        self.subset_options
+            .passthrough_tables
+            .iter()
+            .any(|passthrough_table| {
+                to_prefix(passthrough_table.database, passthrough_table.table) == prefix
+            })
+    }
+
+    fn reference_documents(&self) -> Result<Vec<DocumentRef>, Error> {
+        match self.subset_strategy {
+            SubsetStrategy::RandomPercent {
+                database,
+                table,
+                percent,
+            } => {
+                let prefix = to_prefix(database, table);
+                let total_documents = get_collection(self.collections, &prefix)?.len();
+
+                Ok(list_percent_of_documents(percent, total_documents)
+                    .into_iter()
+                    .map(|idx| (prefix.clone(), idx))
+                    .collect())
+            }
+        }
+    }
+
+    /// every document of the subset - the reference documents and the ones they reference, recursively
+    fn visited_documents<P: FnMut(Progress)>(
+        &self,
+        mut progress: P,
+    ) -> Result<HashSet<DocumentRef>, Error> {
+        let reference_documents = self.reference_documents()?;
+
+        let total_rows = self
+            .collections
+            .values()
+            .fold(0usize, |acc, collection| acc + collection.len());
+
+        let total_rows_to_process = reference_documents.len();
+        let mut processed_rows = 0usize;
+
+        progress(Progress {
+            total_rows,
+            total_rows_to_process,
+            processed_rows,
+            last_process_time: 0,
+        });
+
+        let mut visited_documents = HashSet::new();
+        for document in reference_documents {
+            let start_time = utils::epoch_millis();
+            self.visits(document, &mut visited_documents);
+
+            processed_rows += 1;
+
+            progress(Progress {
+                total_rows,
+                total_rows_to_process,
+                processed_rows,
+                last_process_time: utils::epoch_millis() - start_time,
+            });
+        }
+
+        Ok(visited_documents)
+    }
+
+    /// visit the documents referenced by `document`, recursively. A document is visited once - the cyclic references
+    /// stop there.
+    fn visits(&self, document: DocumentRef, visited_documents: &mut HashSet<DocumentRef>) {
+        let mut documents = vec![document];
+
+        while let Some(document) = documents.pop() {
+            if !visited_documents.insert(document.clone()) {
+                continue;
+            }
+
+            let (prefix, idx) = document;
+            let subset_table = match self.subset_table_by_prefix.get(&prefix) {
+                Some(subset_table) => subset_table,
+                None => continue, // the collection does not reference any other collection
+            };
+
+            let doc = &self.collections[&prefix][idx];
+
+            for relation in &subset_table.relations {
+                let referenced_prefix =
+                    to_prefix(relation.database.as_str(), relation.table.as_str());
+
+                let documents_by_value = match self
+                    .documents_by_prefix_and_field
+                    .get(&(referenced_prefix.clone(), relation.to_property.clone()))
+                {
+                    Some(documents_by_value) => documents_by_value,
+                    None => continue,
+                };
+
+                for value in field_values(doc, relation.from_property.as_str()) {
+                    if let Some(referenced_idxs) = documents_by_value.get(&value.to_string()) {
+                        for referenced_idx in referenced_idxs {
+                            documents.push((referenced_prefix.clone(), *referenced_idx));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Subset for MongoDBSubset<'a> {
+    /// Return every document of the subset as a MongoDB Extended JSON (canonical mode) line -
+    /// `{"ns": "<database>.<collection>", "document": {...}}`
+    fn read<F: FnMut(String), P: FnMut(Progress)>(
+        &self,
+        mut data: F,
+        progress: P,
+    ) -> Result<(), Error> {
+        let mut collections = self
+            .subset_collections(progress)?
+            .into_iter()
+            .collect::<Vec<_>>();
+        collections.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for (prefix, docs) in collections {
+            for doc in docs {
+                // the bson crate does not support Decimal128 in extended JSON (and panics on it)
+                if doc.values().any(has_decimal128) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "a document of '{}' contains a Decimal128 value which can't be serialized as extended JSON",
+                            prefix
+                        ),
+                    ));
+                }
+
+                let mut line = Document::new();
+                let _ = line.insert("ns", prefix.as_str());
+                let _ = line.insert("document", doc);
+
+                data(Bson::Document(line).into_canonical_extjson().to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn estimate(&self) -> Result<HashMap<String, usize>, Error> {
+        let visited_documents = self.visited_documents(|_| {})?;
+
+        let mut visited_documents_by_prefix = HashMap::<&str, usize>::new();
+        for (prefix, _) in &visited_documents {
+            *visited_documents_by_prefix
+                .entry(prefix.as_str())
+                .or_default() += 1;
+        }
+
+        Ok(self
+            .collections
+            .iter()
+            .map(|(prefix, collection)| {
+                let total_documents = if self.is_passthrough(prefix) {
+                    collection.len()
+                } else {
+                    visited_documents_by_prefix
+                        .get(prefix.as_str())
+                        .copied()
+                        .unwrap_or(0)
+                };
+
+                (prefix.clone(), total_documents)
+            })
+            .collect())
+    }
+}
+
+fn to_prefix(database: &str, collection: &str) -> Prefix {
+    format!("{}.{}", database, collection)
+}
+
+fn get_collection<'b>(
+    collections: &'b PrefixedCollections,
+    prefix: &Prefix,
+) -> Result<&'b Vec<Document>, Error> {
+    collections
+        .get(prefix)
+        .ok_or_else(|| Error::new(ErrorKind::Other, format!("collection {} not found", prefix)))
+}
+
+/// positions of the documents picked by the random percent strategy - the same ones as the PostgreSQL subset
+fn list_percent_of_documents(percent: u8, total_documents: usize) -> Vec<usize> {
+    if percent == 0 || total_documents == 0 {
+        return vec![];
+    }
+
+    let percent = if percent > 100 { 100 } else { percent };
+
+    let total_documents_to_pick = total_documents as f32 * percent as f32 / 100.0;
+    let modulo = (total_documents as f32 / total_documents_to_pick) as usize;
+
+    (1..=total_documents)
+        .filter(|counter| counter % modulo == 0)
+        .map(|counter| counter - 1)
+        .collect()
+}
+
+/// values of the field at `path` (e.g. `address.country_id`) - the values of an array are returned one by one
+fn field_values<'b>(doc: &'b Document, path: &str) -> Vec<&'b Bson> {
+    let (key, sub_path) = match path.split_once('.') {
+        Some((key, sub_path)) => (key, Some(sub_path)),
+        None => (path, None),
+    };
+
+    match doc.get(key) {
+        Some(value) => values_at(value, sub_path),
+        None => vec![],
+    }
+}
+
+fn values_at<'b>(value: &'b Bson, path: Option<&str>) -> Vec<&'b Bson> {
+    match (value, path) {
+        (Bson::Array(values), _) => values
+            .iter()
+            .flat_map(|value| values_at(value, path))
+            .collect(),
+        (Bson::Document(doc), Some(path)) => field_values(doc, path),
+        // a null value does not reference anything
+        (Bson::Null, None) => vec![],
+        (_, None) => vec![value],
+        (_, Some(_)) => vec![],
+    }
+}
+
+fn has_decimal128(bson: &Bson) -> bool {
+    match bson {
+        Bson::Decimal128(_) => true,
+        Bson::Array(arr) => arr.iter().any(has_decimal128),
+        Bson::Document(doc) => doc.values().any(has_decimal128),
+        Bson::JavaScriptCodeWithScope(jsc) => jsc.scope.values().any(has_decimal128),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mongodb::MongoDBSubset;
+    use crate::{
+        PassthroughTable, Subset, SubsetOptions, SubsetStrategy, SubsetTable, SubsetTableRelation,
+    };
+    use bson::doc;
+    use dump_parser::mongodb::PrefixedCollections;
+    use std::collections::HashSet;
+
+    fn collections() -> PrefixedCollections {
+        let mut collections = PrefixedCollections::new();
+
+        collections.insert(
+            "shop.countries".to_string(),
+            vec![
+                doc! { "_id": "FR", "name": "France" },
+                doc! { "_id": "DE", "name": "Germany" },
+            ],
+        );
+
+        collections.insert(
+            "shop.customers".to_string(),
+            vec![
+                doc! { "_id": 1, "name": "Lucas", "address": { "country_id": "FR" } },
+                doc! { "_id": 2, "name": "Jane", "address": { "country_id": "DE" } },
+                doc! { "_id": 3, "name": "Georges", "address": { "country_id": "FR" } },
+            ],
+        );
+
+        collections.insert(
+            "shop.products".to_string(),
+            vec![
+                doc! { "_id": 10, "name": "book" },
+                doc! { "_id": 11, "name": "pen" },
+                doc! { "_id": 12, "name": "bag" },
+            ],
+        );
+
+        collections.insert(
+            "shop.orders".to_string(),
+            vec![
+                doc! { "_id": 100, "customer_id": 1, "product_ids": [10, 11] },
+                doc! { "_id": 101, "customer_id": 3, "product_ids": [11] },
+                doc! { "_id": 102, "customer_id": 1, "product_ids": [] },
+                doc! { "_id": 103, "customer_id": 2, "product_ids": [12] },
+            ],
+        );
+
+        collections.insert(
+            "shop.logs".to_string(),
+            vec![doc! { "message": "a" }, doc! { "message": "b" }],
+        );
+
+        collections
+    }
+
+    fn subset_tables() -> Vec<SubsetTable> {
+        vec![
+            SubsetTable::new(
+                "shop",
+                "orders",
+                vec![
+                    SubsetTableRelation::new("shop", "customers", "customer_id", "_id"),
+                    SubsetTableRelation::new("shop", "products", "product_ids", "_id"),
+                ],
+            ),
+            SubsetTable::new(
+                "shop",
+                "customers",
+                vec![SubsetTableRelation::new(
+                    "shop",
+                    "countries",
+                    "address.country_id",
+                    "_id",
+                )],
+            ),
+        ]
+    }
+
+    fn ids(collections: &PrefixedCollections, prefix: &str) -> Vec<String> {
+        collections
+            .get(prefix)
+            .unwrap()
+            .iter()
+            .map(|doc| doc.get("_id").unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn check_mongodb_subset() {
+        let collections = collections();
+        let mut s = HashSet::new();
+        s.insert(PassthroughTable::new("shop", "logs"));
+
+        let subset = MongoDBSubset::new(
+            &collections,
+            subset_tables(),
+            SubsetStrategy::random("shop", "orders", 50),
+            SubsetOptions::new(&s),
+        )
+        .unwrap();
+
+        let mut total_rows_to_process = 0usize;
+        let mut processed_rows = 0usize;
+        let subset_collections = subset
+            .subset_collections(|progress| {
+                total_rows_to_process = progress.total_rows_to_process;
+                processed_rows = progress.processed_rows;
+            })
+            .unwrap();
+
+        assert_eq!(total_rows_to_process, 2);
+        assert_eq!(processed_rows, 2);
+
+        // every other order is picked - the customers, products and countries they reference are kept once
+        assert_eq!(ids(&subset_collections, "shop.orders"), vec!["101", "103"]);
+        assert_eq!(ids(&subset_collections, "shop.customers"), vec!["2", "3"]);
+        assert_eq!(ids(&subset_collections, "shop.products"), vec!["11", "12"]);
+        assert_eq!(
+            ids(&subset_collections, "shop.countries"),
+            vec!["\"FR\"", "\"DE\""]
+        );
+        assert_eq!(subset_collections.get("shop.logs").unwrap().len(), 2);
+
+        let estimate = subset.estimate().unwrap();
+        for (prefix, docs) in &subset_collections {
+            assert_eq!(estimate.get(prefix), Some(&docs.len()), "{}", prefix);
+        }
+    }
+
+    #[test]
+    fn check_mongodb_subset_with_cyclic_references() {
+        let mut collections = PrefixedCollections::new();
+        collections.insert(
+            "shop.employees".to_string(),
+            vec![
+                doc! { "_id": 1, "manager_id": 2 },
+                doc! { "_id": 2, "manager_id": 1 },
+                doc! { "_id": 3, "manager_id": null },
+            ],
+        );
+
+        let s = HashSet::new();
+        let subset = MongoDBSubset::new(
+            &collections,
+            vec![SubsetTable::new(
+                "shop",
+                "employees",
+                vec![SubsetTableRelation::new(
+                    "shop",
+                    "employees",
+                    "manager_id",
+                    "_id",
+                )],
+            )],
+            SubsetStrategy::random("shop", "employees", 50),
+            SubsetOptions::new(&s),
+        )
+        .unwrap();
+
+        let subset_collections = subset.subset_collections(|_| {}).unwrap();
+        assert_eq!(ids(&subset_collections, "shop.employees"), vec!["1", "2"]);
+    }
+
+    #[test]
+    fn check_mongodb_subset_rows() {
+        let collections = collections();
+        let s = HashSet::new();
+
+        let subset = MongoDBSubset::new(
+            &collections,
+            subset_tables(),
+            SubsetStrategy::random("shop", "orders", 100),
+            SubsetOptions::new(&s),
+        )
+        .unwrap();
+
+        let mut rows = vec![];
+        subset
+            .read(
+                |row| {
+                    rows.push(row);
+                },
+                |_| {},
+            )
+            .unwrap();
+
+        assert_eq!(rows.len(), 4 + 3 + 3 + 2);
+        assert!(rows
+            .iter()
+            .all(|row| row.contains(r#""ns":"shop."#) && row.contains(r#""document":{"#)));
+        assert!(!rows.iter().any(|row| row.contains("shop.logs")));
+    }
+
+    #[test]
+    fn check_mongodb_subset_unknown_collection() {
+        let collections = collections();
+        let s = HashSet::new();
+
+        let err = MongoDBSubset::new(
+            &collections,
+            vec![SubsetTable::new(
+                "shop",
+                "orders",
+                vec![SubsetTableRelation::new(
+                    "shop",
+                    "clients",
+                    "customer_id",
+                    "_id",
+                )],
+            )],
+            SubsetStrategy::random("shop", "orders", 50),
+            SubsetOptions::new(&s),
+        )
+        .err()
+        .unwrap();
+
+        assert_eq!(err.to_string(), "collection shop.clients not found");
+    }
+}
@@ -0,0 +1,807 @@
+use crate::{
+    utils, Progress, Subset, SubsetOptions, SubsetStrategy, SubsetTable, SubsetTableRelation,
+};
+use dump_parser::mysql::{
+    get_column_names_from_insert_into_query, get_column_values_from_insert_into_query,
+    get_single_quoted_string_value_at_position, get_tokens_from_query_str,
+    match_keyword_at_position, Keyword, Token,
+};
+use dump_parser::utils::{list_sql_queries_from_dump_reader, ListQueryResult};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind, Read};
+use std::path::Path;
+
+type Table = String;
+
+/// `CONSTRAINT ... FOREIGN KEY (<from_property>) REFERENCES <to_table> (<to_property>)` - mysqldump declares them in the
+/// `CREATE TABLE` queries
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+    pub from_table: String,
+    pub from_property: String,
+    pub to_table: String,
+    pub to_property: String,
+}
+
+struct TableStats {
+    columns: Vec<String>,
+    total_rows: usize,
+    first_insert_into_row_index: usize,
+    last_insert_into_row_index: usize,
+}
+
+/// Subset of a mysqldump dump - with one row by `INSERT INTO` query (`--skip-extended-insert`) and their column names
+/// (`--complete-insert`).
+///
+/// The rows are identified by their index in the dump. The dump is sent in its order - the schema and data queries
+/// of mysqldump are interleaved table by table - without the `INSERT INTO` rows out of the subset.
+pub struct MysqlSubset<'a> {
+    subset_table_by_table_name: HashMap<Table, SubsetTable>,
+    dump: &'a Path,
+    subset_strategy: SubsetStrategy<'a>,
+    subset_options: SubsetOptions<'a>,
+}
+
+impl<'a> MysqlSubset<'a> {
+    pub fn new(
+        dump: &'a Path,
+        subset_strategy: SubsetStrategy<'a>,
+        subset_options: SubsetOptions<'a>,
+    ) -> Result<Self, Error> {
+        let database = match subset_strategy {
+            SubsetStrategy::RandomPercent { database, .. } => database,
+        };
+
+        Ok(MysqlSubset {
+            subset_table_by_table_name: get_subset_table_by_table_name(
+                database,
+                BufReader::new(File::open(dump)?),
+            )?,
+            dump,
+            subset_strategy,
+            subset_options,
+        })
+    }
+
+    fn dump_reader(&self) -> BufReader<File> {
+        BufReader::new(File::open(self.dump).unwrap())
+    }
+
+    fn database(&self) -> &str {
+        match self.subset_strategy {
+            SubsetStrategy::RandomPercent { database, .. } => database,
+        }
+    }
+
+    fn is_passthrough(&self, table: &str) -> bool {
+        self.subset_options
+            .passthrough_tables
+            .iter()
+            .any(|passthrough_table| {
+                passthrough_table.database == self.database() && passthrough_table.table == table
+            })
+    }
+
+    fn reference_rows(
+        &self,
+        table_stats: &HashMap<Table, TableStats>,
+    ) -> Result<Vec<(usize, String)>, Error> {
+        match self.subset_strategy {
+            SubsetStrategy::RandomPercent { table, percent, .. } => {
+                let table_stats = match table_stats.get(table) {
+                    Some(table_stats) => table_stats,
+                    None => {
+                        return Err(Error::new(
+                            ErrorKind::Other,
+                            format!("table {}.{} not found", self.database(), table),
+                        ));
+                    }
+                };
+
+                list_percent_of_insert_into_rows(percent, table, table_stats, self.dump_reader())
+            }
+        }
+    }
+
+    /// index of every row of the subset - the reference rows and the rows they reference, recursively
+    fn subset_rows<P: FnMut(Progress)>(
+        &self,
+        table_stats: &HashMap<Table, TableStats>,
+        mut progress: P,
+    ) -> Result<HashSet<usize>, Error> {
+        let rows = self.reference_rows(table_stats)?;
+
+        let total_rows = table_stats
+            .values()
+            .fold(0usize, |acc, y| acc + y.total_rows);
+
+        let total_rows_to_process = rows.len();
+        let mut processed_rows = 0usize;
+
+        progress(Progress {
+            total_rows,
+            total_rows_to_process,
+            processed_rows,
+            last_process_time: 0,
+        });
+
+        let mut subset_rows = HashSet::new();
+        for (row_idx, row) in rows {
+            let start_time = utils::epoch_millis();
+            let _ = self.visits(row_idx, row.as_str(), table_stats, &mut subset_rows)?;
+
+            processed_rows += 1;
+
+            progress(Progress {
+                total_rows,
+                total_rows_to_process,
+                processed_rows,
+                last_process_time: utils::epoch_millis() - start_time,
+            });
+        }
+
+        Ok(subset_rows)
+    }
+
+    /// add the row and the rows it references to `subset_rows`. A row is visited once - the cyclic references stop there.
+    fn visits(
+        &self,
+        row_idx: usize,
+        row: &str,
+        table_stats: &HashMap<Table, TableStats>,
+        subset_rows: &mut HashSet<usize>,
+    ) -> Result<(), Error> {
+        if !subset_rows.insert(row_idx) {
+            return Ok(());
+        }
+
+        let row_tokens = get_tokens_from_query_str(row);
+
+        let row_table = match get_insert_into_table_name(&row_tokens) {
+            Some(table) => table,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    format!("unable to find the table name of row {}", row),
+                ));
+            }
+        };
+
+        let row_subset_table = match self.subset_table_by_table_name.get(row_table) {
+            Some(subset_table) => subset_table,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    format!("table {}.{} not found", self.database(), row_table),
+                ));
+            }
+        };
+
+        let row_column_names = get_column_names_from_insert_into_query(&row_tokens);
+        let row_column_values = get_column_values_from_insert_into_query(&row_tokens);
+
+        for row_relation in &row_subset_table.relations {
+            let column = row_relation.from_property.as_str();
+            // find the value from the current row for the relation column
+            let value = match row_column_names
+                .iter()
+                .position(|x| *x == column)
+                .and_then(|column_idx| row_column_values.get(column_idx))
+            {
+                Some(value) => value,
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::Other,
+                        format!(
+                            "table {}.{} does not contain a value for column {}",
+                            self.database(),
+                            row_table,
+                            column
+                        ),
+                    ));
+                }
+            };
+
+            let value = match get_value(value) {
+                Some(value) => value,
+                None => continue, // NULL does not reference any row
+            };
+
+            let row_relation_table_stats = match table_stats.get(&row_relation.table) {
+                Some(table_stats) => table_stats,
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::Other,
+                        format!("table {}.{} not found", self.database(), row_relation.table),
+                    ));
+                }
+            };
+
+            let referenced_rows = filter_insert_into_rows(
+                row_relation.table.as_str(),
+                row_relation.to_property.as_str(),
+                value.as_str(),
+                self.dump_reader(),
+                row_relation_table_stats,
+            )?;
+
+            for (referenced_row_idx, referenced_row) in referenced_rows {
+                let _ = self.visits(
+                    referenced_row_idx,
+                    referenced_row.as_str(),
+                    table_stats,
+                    subset_rows,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// list the queries of the dump - the `INSERT INTO` rows which are not part of the subset (or of a passthrough
+    /// table) are dropped. `queries` receives the table of the `INSERT INTO` rows.
+    fn list_subset_queries<F: FnMut(&str, Option<&str>)>(
+        &self,
+        subset_rows: &HashSet<usize>,
+        mut queries: F,
+    ) -> Result<(), Error> {
+        let mut query_idx = 0usize;
+
+        list_sql_queries_from_dump_reader(self.dump_reader(), |query| {
+            let tokens = get_tokens_from_query_str(query);
+
+            match get_insert_into_table_name(&tokens) {
+                Some(table) => {
+                    if subset_rows.contains(&query_idx) || self.is_passthrough(table) {
+                        queries(query, Some(table));
+                    }
+                }
+                None => queries(query, None),
+            }
+
+            query_idx += 1;
+            ListQueryResult::Continue
+        })?;
+
+        Ok(())
+    }
+}
+
+impl<'a> Subset for MysqlSubset<'a> {
+    /// Return every query of the dump without the rows out of the subset
+    /// Algorithm used:
+    /// 1. take the X rows from the reference table with the appropriate SubsetStrategy
+    /// 2. for each row, find the rows referenced by its foreign keys (equivalent `SELECT * FROM table_1 WHERE table_1.id = 'xxx';`)
+    /// 3. do it recursively for the referenced rows - a row already visited is not visited again
+    /// 4. send the dump without the rows which have not been visited
+    fn read<F: FnMut(String), P: FnMut(Progress)>(
+        &self,
+        mut data: F,
+        progress: P,
+    ) -> Result<(), Error> {
+        let table_stats = table_stats_by_table_name(self.dump_reader())?;
+        let subset_rows = self.subset_rows(&table_stats, progress)?;
+
+        self.list_subset_queries(&subset_rows, |query, _| data(query.to_string()))
+    }
+
+    fn estimate(&self) -> Result<HashMap<String, usize>, Error> {
+        let table_stats = table_stats_by_table_name(self.dump_reader())?;
+        let subset_rows = self.subset_rows(&table_stats, |_| {})?;
+
+        // every table is part of the estimate - even the ones without any row in the subset
+        let mut rows_by_table = table_stats
+            .keys()
+            .map(|table| (format!("{}.{}", self.database(), table), 0usize))
+            .collect::<HashMap<_, _>>();
+
+        self.list_subset_queries(&subset_rows, |_, table| {
+            if let Some(table) = table {
+                *rows_by_table
+                    .entry(format!("{}.{}", self.database(), table))
+                    .or_default() += 1;
+            }
+        })?;
+
+        Ok(rows_by_table)
+    }
+}
+
+/// every foreign key of the dump
+pub fn list_foreign_keys<R: Read>(dump_reader: BufReader<R>) -> Result<Vec<ForeignKey>, Error> {
+    let mut foreign_keys = vec![];
+
+    list_sql_queries_from_dump_reader(dump_reader, |query| {
+        foreign_keys.append(&mut get_foreign_keys(&get_tokens_from_query_str(query)));
+
+        ListQueryResult::Continue
+    })?;
+
+    Ok(foreign_keys)
+}
+
+fn get_subset_table_by_table_name<R: Read>(
+    database: &str,
+    dump_reader: BufReader<R>,
+) -> Result<HashMap<Table, SubsetTable>, Error> {
+    let mut subset_table_by_table_name = HashMap::<Table, SubsetTable>::new();
+
+    list_sql_queries_from_dump_reader(dump_reader, |query| {
+        let tokens = get_tokens_from_query_str(query);
+
+        if let Some(table) = get_create_table_name(&tokens) {
+            // add table into index
+            let _ = subset_table_by_table_name
+                .insert(table.to_string(), SubsetTable::new(database, table, vec![]));
+        }
+
+        for fk in get_foreign_keys(&tokens) {
+            if let Some(subset_table) = subset_table_by_table_name.get_mut(&fk.from_table) {
+                subset_table.relations.push(SubsetTableRelation::new(
+                    database.to_string(),
+                    fk.to_table,
+                    fk.from_property,
+                    fk.to_property,
+                ));
+            }
+        }
+
+        ListQueryResult::Continue
+    })?;
+
+    Ok(subset_table_by_table_name)
+}
+
+fn table_stats_by_table_name<R: Read>(
+    dump_reader: BufReader<R>,
+) -> Result<HashMap<Table, TableStats>, Error> {
+    let mut table_stats_by_table_name = HashMap::<Table, TableStats>::new();
+
+    let mut query_idx = 0usize;
+    list_sql_queries_from_dump_reader(dump_reader, |query| {
+        let tokens = get_tokens_from_query_str(query);
+
+        if let Some(table) = get_create_table_name(&tokens) {
+            let _ = table_stats_by_table_name.insert(
+                table.to_string(),
+                TableStats {
+                    columns: vec![],
+                    total_rows: 0,
+                    first_insert_into_row_index: 0,
+                    last_insert_into_row_index: 0,
+                },
+            );
+        }
+
+        if let Some(table) = get_insert_into_table_name(&tokens) {
+            if let Some(table_stats) = table_stats_by_table_name.get_mut(table) {
+                if table_stats.total_rows == 0 {
+                    // I assume that the INSERT INTO row has all the column set
+                    table_stats.columns = get_column_names_from_insert_into_query(&tokens)
+                        .iter()
+                        .map(|name| name.to_string())
+                        .collect::<Vec<_>>();
+
+                    table_stats.first_insert_into_row_index = query_idx;
+                }
+
+                table_stats.last_insert_into_row_index = query_idx;
+                table_stats.total_rows += 1;
+            }
+        }
+
+        query_idx += 1;
+        ListQueryResult::Continue
+    })?;
+
+    Ok(table_stats_by_table_name)
+}
+
+/// index and query of the rows picked by the random percent strategy - the same ones as the PostgreSQL subset
+fn list_percent_of_insert_into_rows<R: Read>(
+    percent: u8,
+    table: &str,
+    table_stats: &TableStats,
+    dump_reader: BufReader<R>,
+) -> Result<Vec<(usize, String)>, Error> {
+    let mut insert_into_rows = vec![];
+
+    if percent == 0 || table_stats.total_rows == 0 {
+        return Ok(insert_into_rows);
+    }
+
+    let percent = if percent > 100 { 100 } else { percent };
+
+    let total_rows_to_pick = table_stats.total_rows as f32 * percent as f32 / 100.0;
+    let modulo = (table_stats.total_rows as f32 / total_rows_to_pick) as usize;
+
+    let mut counter = 1usize;
+    let _ = list_insert_into_rows(dump_reader, table, table_stats, |row_idx, row, _| {
+        if counter % modulo == 0 {
+            insert_into_rows.push((row_idx, row.to_string()));
+        }
+
+        counter += 1;
+    })?;
+
+    Ok(insert_into_rows)
+}
+
+/// index and query of the rows of `table` whose `column` is `value`
+fn filter_insert_into_rows<R: Read>(
+    table: &str,
+    column: &str,
+    value: &str,
+    dump_reader: BufReader<R>,
+    table_stats: &TableStats,
+) -> Result<Vec<(usize, String)>, Error> {
+    let column_idx = match table_stats
+        .columns
+        .iter()
+        .position(|r| r.as_str() == column)
+    {
+        Some(idx) => idx,
+        None => {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!("table {} does not contain column {}", table, column),
+            ));
+        }
+    };
+
+    let mut rows = vec![];
+    let _ = list_insert_into_rows(dump_reader, table, table_stats, |row_idx, row, tokens| {
+        let column_values = get_column_values_from_insert_into_query(tokens);
+
+        if column_values
+            .get(column_idx)
+            .and_then(|column_value| get_value(column_value))
+            .as_deref()
+            == Some(value)
+        {
+            rows.push((row_idx, row.to_string()));
+        }
+    })?;
+
+    Ok(rows)
+}
+
+fn list_insert_into_rows<R: Read, F: FnMut(usize, &str, &Vec<Token>)>(
+    dump_reader: BufReader<R>,
+    table: &str,
+    table_stats: &TableStats,
+    mut rows: F,
+) -> Result<(), Error> {
+    if table_stats.total_rows == 0 {
+        return Ok(());
+    }
+
+    let mut query_idx = 0usize;
+    let _ = list_sql_queries_from_dump_reader(dump_reader, |query| {
+        let mut query_res = ListQueryResult::Continue;
+
+        // optimization to avoid tokenizing unnecessary queries
+        if query_idx >= table_stats.first_insert_into_row_index
+            && query_idx <= table_stats.last_insert_into_row_index
+        {
+            let tokens = get_tokens_from_query_str(query);
+
+            if get_insert_into_table_name(&tokens) == Some(table) {
+                rows(query_idx, query, &tokens);
+            }
+        }
+
+        if query_idx >= table_stats.last_insert_into_row_index {
+            // early break to avoid parsing the dump while we have already parsed all the table rows
+            query_res = ListQueryResult::Break;
+        }
+
+        query_idx += 1;
+        query_res
+    })?;
+
+    Ok(())
+}
+
+fn get_insert_into_table_name(tokens: &Vec<Token>) -> Option<&str> {
+    if match_keyword_at_position(Keyword::Insert, tokens, 0)
+        && match_keyword_at_position(Keyword::Into, tokens, 2)
+    {
+        return get_single_quoted_string_value_at_position(tokens, 4);
+    }
+
+    None
+}
+
+fn get_create_table_name(tokens: &Vec<Token>) -> Option<&str> {
+    if match_keyword_at_position(Keyword::Create, tokens, 0)
+        && match_keyword_at_position(Keyword::Table, tokens, 2)
+    {
+        return get_single_quoted_string_value_at_position(tokens, 4);
+    }
+
+    None
+}
+
+/// foreign keys of a `CREATE TABLE` (or `ALTER TABLE ... ADD CONSTRAINT`) query - only the first column of a
+/// multi-column foreign key is followed
+fn get_foreign_keys(tokens: &Vec<Token>) -> Vec<ForeignKey> {
+    let from_table = match get_create_table_name(tokens) {
+        Some(table) => table,
+        None if match_keyword_at_position(Keyword::Alter, tokens, 0)
+            && match_keyword_at_position(Keyword::Table, tokens, 2) =>
+        {
+            match get_single_quoted_string_value_at_position(tokens, 4) {
+                Some(table) => table,
+                None => return vec![],
+            }
+        }
+        None => return vec![],
+    };
+
+    let tokens = tokens
+        .iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_)))
+        .collect::<Vec<_>>();
+
+    let mut foreign_keys = vec![];
+    for (idx, token) in tokens.iter().enumerate() {
+        if !is_keyword(token, Keyword::Foreign) {
+            continue;
+        }
+
+        // FOREIGN KEY (<from_property>, ...) REFERENCES <to_table> (<to_property>, ...)
+        let references_idx = match tokens
+            .iter()
+            .skip(idx)
+            .position(|token| is_keyword(token, Keyword::References))
+        {
+            Some(position) => idx + position,
+            None => continue,
+        };
+
+        let from_property = tokens.get(idx + 3).and_then(|token| get_name(token));
+        let to_table = tokens
+            .get(references_idx + 1)
+            .and_then(|token| get_name(token));
+        let to_property = tokens
+            .get(references_idx + 3)
+            .and_then(|token| get_name(token));
+
+        if let (Some(from_property), Some(to_table), Some(to_property)) =
+            (from_property, to_table, to_property)
+        {
+            foreign_keys.push(ForeignKey {
+                from_table: from_table.to_string(),
+                from_property,
+                to_table,
+                to_property,
+            });
+        }
+    }
+
+    foreign_keys
+}
+
+fn is_keyword(token: &Token, keyword: Keyword) -> bool {
+    match token {
+        Token::Word(word) => word.keyword == keyword,
+        _ => false,
+    }
+}
+
+/// name of a table or a column - quoted by backticks or not
+fn get_name(token: &Token) -> Option<String> {
+    match token {
+        Token::SingleQuotedString(name) => Some(name.clone()),
+        Token::Word(word) => Some(word.value.clone()),
+        _ => None,
+    }
+}
+
+/// value of a column of an `INSERT INTO` row - `None` for NULL
+fn get_value(token: &Token) -> Option<String> {
+    match token {
+        Token::Number(value, _)
+        | Token::SingleQuotedString(value)
+        | Token::NationalStringLiteral(value)
+        | Token::HexStringLiteral(value) => Some(value.clone()),
+        Token::Word(word) if word.keyword == Keyword::Null => None,
+        Token::Word(word) => Some(word.value.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mysql::{list_foreign_keys, ForeignKey, MysqlSubset};
+    use crate::{PassthroughTable, Subset, SubsetOptions, SubsetStrategy};
+    use std::collections::HashSet;
+    use std::io::{BufReader, Write};
+    use tempfile::NamedTempFile;
+
+    const DUMP: &str = r#"
+DROP TABLE IF EXISTS `countries`;
+CREATE TABLE `countries` (
+  `code` char(2) NOT NULL,
+  `name` varchar(40) NOT NULL,
+  PRIMARY KEY (`code`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+LOCK TABLES `countries` WRITE;
+INSERT INTO `countries` (`code`, `name`) VALUES ('FR','France');
+INSERT INTO `countries` (`code`, `name`) VALUES ('DE','Germany');
+UNLOCK TABLES;
+
+DROP TABLE IF EXISTS `customers`;
+CREATE TABLE `customers` (
+  `id` int NOT NULL,
+  `country_code` char(2) DEFAULT NULL,
+  `referrer_id` int DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `fk_customers_countries` (`country_code`),
+  CONSTRAINT `fk_customers_countries` FOREIGN KEY (`country_code`) REFERENCES `countries` (`code`),
+  CONSTRAINT `fk_customers_customers` FOREIGN KEY (`referrer_id`) REFERENCES `customers` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+LOCK TABLES `customers` WRITE;
+INSERT INTO `customers` (`id`, `country_code`, `referrer_id`) VALUES (1,'FR',2);
+INSERT INTO `customers` (`id`, `country_code`, `referrer_id`) VALUES (2,'FR',1);
+INSERT INTO `customers` (`id`, `country_code`, `referrer_id`) VALUES (3,NULL,NULL);
+UNLOCK TABLES;
+
+DROP TABLE IF EXISTS `orders`;
+CREATE TABLE `orders` (
+  `id` int NOT NULL,
+  `customer_id` int NOT NULL,
+  PRIMARY KEY (`id`),
+  CONSTRAINT `fk_orders_customers` FOREIGN KEY (`customer_id`) REFERENCES `customers` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+LOCK TABLES `orders` WRITE;
+INSERT INTO `orders` (`id`, `customer_id`) VALUES (1,1);
+INSERT INTO `orders` (`id`, `customer_id`) VALUES (2,1);
+INSERT INTO `orders` (`id`, `customer_id`) VALUES (3,3);
+INSERT INTO `orders` (`id`, `customer_id`) VALUES (4,2);
+UNLOCK TABLES;
+"#;
+
+    fn dump_file(dump: &str) -> NamedTempFile {
+        let mut dump_file = NamedTempFile::new().unwrap();
+        dump_file.write_all(dump.as_bytes()).unwrap();
+        dump_file
+    }
+
+    fn insert_into_rows(rows: &Vec<String>, table: &str) -> Vec<String> {
+        let prefix = format!("INSERT INTO `{}`", table);
+        rows.iter()
+            .map(|row| row.trim().to_string())
+            .filter(|row| row.starts_with(prefix.as_str()))
+            .collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn check_foreign_keys() {
+        let dump_file = dump_file(DUMP);
+        let foreign_keys = list_foreign_keys(BufReader::new(
+            std::fs::File::open(dump_file.path()).unwrap(),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            foreign_keys,
+            vec![
+                ForeignKey {
+                    from_table: "customers".to_string(),
+                    from_property: "country_code".to_string(),
+                    to_table: "countries".to_string(),
+                    to_property: "code".to_string(),
+                },
+                ForeignKey {
+                    from_table: "customers".to_string(),
+                    from_property: "referrer_id".to_string(),
+                    to_table: "customers".to_string(),
+                    to_property: "id".to_string(),
+                },
+                ForeignKey {
+                    from_table: "orders".to_string(),
+                    from_property: "customer_id".to_string(),
+                    to_table: "customers".to_string(),
+                    to_property: "id".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn check_mysql_subset() {
+        let dump_file = dump_file(DUMP);
+
+        let s = HashSet::new();
+        let mysql_subset = MysqlSubset::new(
+            dump_file.path(),
+            SubsetStrategy::random("world", "orders", 50),
+            SubsetOptions::new(&s),
+        )
+        .unwrap();
+
+        let mut rows = vec![];
+        mysql_subset
+            .read(
+                |row| {
+                    rows.push(row);
+                },
+                |_| {},
+            )
+            .unwrap();
+
+        // orders 2 and 4 are picked - they reference the customers 1 and 2 which reference each other
+        assert_eq!(
+            insert_into_rows(&rows, "orders"),
+            vec![
+                "INSERT INTO `orders` (`id`, `customer_id`) VALUES (2,1);",
+                "INSERT INTO `orders` (`id`, `customer_id`) VALUES (4,2);",
+            ]
+        );
+        assert_eq!(
+            insert_into_rows(&rows, "customers"),
+            vec![
+                "INSERT INTO `customers` (`id`, `country_code`, `referrer_id`) VALUES (1,'FR',2);",
+                "INSERT INTO `customers` (`id`, `country_code`, `referrer_id`) VALUES (2,'FR',1);",
+            ]
+        );
+        assert_eq!(
+            insert_into_rows(&rows, "countries"),
+            vec!["INSERT INTO `countries` (`code`, `name`) VALUES ('FR','France');"]
+        );
+
+        // the schema queries are kept
+        assert!(rows
+            .iter()
+            .any(|row| row.trim().starts_with("CREATE TABLE `orders`")));
+        assert!(rows
+            .iter()
+            .any(|row| row.trim().starts_with("LOCK TABLES `orders` WRITE")));
+
+        let estimate = mysql_subset.estimate().unwrap();
+        assert_eq!(estimate.get("world.orders"), Some(&2));
+        assert_eq!(estimate.get("world.customers"), Some(&2));
+        assert_eq!(estimate.get("world.countries"), Some(&1));
+    }
+
+    #[test]
+    fn check_mysql_subset_with_passthrough_tables() {
+        let dump_file = dump_file(DUMP);
+
+        let mut s = HashSet::new();
+        s.insert(PassthroughTable::new("world", "countries"));
+
+        let mysql_subset = MysqlSubset::new(
+            dump_file.path(),
+            SubsetStrategy::random("world", "orders", 50),
+            SubsetOptions::new(&s),
+        )
+        .unwrap();
+
+        let estimate = mysql_subset.estimate().unwrap();
+        assert_eq!(estimate.get("world.orders"), Some(&2));
+        assert_eq!(estimate.get("world.customers"), Some(&2));
+        assert_eq!(estimate.get("world.countries"), Some(&2));
+    }
+
+    #[test]
+    fn check_mysql_subset_with_unknown_table() {
+        let dump_file = dump_file(DUMP);
+
+        let s = HashSet::new();
+        let mysql_subset = MysqlSubset::new(
+            dump_file.path(),
+            SubsetStrategy::random("world", "invoices", 50),
+            SubsetOptions::new(&s),
+        )
+        .unwrap();
+
+        assert!(mysql_subset.read(|_| {}, |_| {}).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use crate::dedup::does_line_exist_and_set;
-use crate::postgres::SubsetStrategy::RandomPercent;
 use crate::{
-    utils, PassthroughTable, Progress, Subset, SubsetOptions, SubsetTable, SubsetTableRelation,
+    utils, PassthroughTable, Progress, Subset, SubsetOptions, SubsetStrategy, SubsetTable,
+    SubsetTableRelation,
 };
 use dump_parser::postgres::{
     get_column_names_from_insert_into_query, get_column_values_str_from_insert_into_query,
@@ -9,23 +9,28 @@ use dump_parser::postgres::{
     trim_pre_whitespaces, Keyword, Token,
 };
 use dump_parser::utils::{list_sql_queries_from_dump_reader, ListQueryResult};
-use std::collections::HashMap;
+use log::warn;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, Error, ErrorKind, Read};
 use std::ops::Index;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
 type Database = String;
 type Table = String;
 
-#[derive(Debug)]
-struct ForeignKey {
-    from_database: String,
-    from_table: String,
-    from_property: String,
-    to_database: String,
-    to_table: String,
-    to_property: String,
+/// `ALTER TABLE ... FOREIGN KEY (<from_property>) REFERENCES <to_database>.<to_table>(<to_property>)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+    pub from_database: String,
+    pub from_table: String,
+    pub from_property: String,
+    pub to_database: String,
+    pub to_table: String,
+    pub to_property: String,
 }
 
 struct TableStats {
@@ -37,26 +42,15 @@ struct TableStats {
     last_insert_into_row_index: usize,
 }
 
-pub enum SubsetStrategy<'a> {
-    RandomPercent {
-        database: &'a str,
-        table: &'a str,
-        percent: u8,
-    },
-}
-
-impl<'a> SubsetStrategy<'a> {
-    pub fn random(database: &'a str, table: &'a str, percent: u8) -> Self {
-        RandomPercent {
-            database,
-            table,
-            percent,
-        }
-    }
+/// sent by the threads visiting the reference rows in parallel
+enum VisitMessage {
+    Row(String),
+    Processed { last_process_time: u128 },
 }
 
 pub struct PostgresSubset<'a> {
     subset_table_by_database_and_table_name: HashMap<(Database, Table), SubsetTable>,
+    primary_key_by_database_and_table_name: HashMap<(Database, Table), Vec<String>>,
     dump: &'a Path,
     subset_strategy: SubsetStrategy<'a>,
     subset_options: SubsetOptions<'a>,
@@ -72,6 +66,9 @@ impl<'a> PostgresSubset<'a> {
             subset_table_by_database_and_table_name: get_subset_table_by_database_and_table_name(
                 BufReader::new(File::open(dump).unwrap()),
             )?,
+            primary_key_by_database_and_table_name: list_primary_keys(BufReader::new(
+                File::open(dump).unwrap(),
+            ))?,
             dump,
             subset_strategy,
             subset_options,
@@ -101,6 +98,111 @@ impl<'a> PostgresSubset<'a> {
         }
     }
 
+    /// split the reference rows into at most `workers` groups. The rows referencing the same parent rows
+    /// share their table tree - they are kept in the same group to not visit the tree from several threads.
+    fn partition_reference_rows(&self, rows: Vec<String>, workers: usize) -> Vec<Vec<String>> {
+        let mut groups = (0..workers.max(1)).map(|_| vec![]).collect::<Vec<_>>();
+        let mut group_idx_by_parent_values = HashMap::<Vec<String>, usize>::new();
+
+        for row in rows {
+            let row_tokens = get_tokens_from_query_str(row.as_str());
+            let row_column_names = get_column_names_from_insert_into_query(&row_tokens);
+            let row_column_values = get_column_values_str_from_insert_into_query(&row_tokens);
+
+            // values of the columns referencing the parent rows
+            let parent_values = get_insert_into_database_and_table_name(&row_tokens)
+                .and_then(|database_and_table| {
+                    self.subset_table_by_database_and_table_name
+                        .get(&database_and_table)
+                })
+                .map(|subset_table| {
+                    subset_table
+                        .relations
+                        .iter()
+                        .filter_map(|relation| {
+                            row_column_names
+                                .iter()
+                                .position(|x| *x == relation.from_property.as_str())
+                                .and_then(|column_idx| row_column_values.get(column_idx))
+                                .map(|value| value.to_string())
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+
+            let smallest_group_idx = groups
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, group)| group.len())
+                .map(|(idx, _)| idx)
+                .unwrap();
+
+            // a row without parent is a tree on its own
+            let group_idx = if parent_values.is_empty() {
+                smallest_group_idx
+            } else {
+                *group_idx_by_parent_values
+                    .entry(parent_values)
+                    .or_insert(smallest_group_idx)
+            };
+
+            groups[group_idx].push(row);
+        }
+
+        groups.retain(|group| !group.is_empty());
+        groups
+    }
+
+    /// the rows are deduplicated on the values of the primary key of their table. The rows of the tables
+    /// without primary key fall back to the hash of the whole row - a warning is logged once per table.
+    fn dedup_key(
+        &self,
+        row: &str,
+        tables_without_primary_key: &mut HashSet<(Database, Table)>,
+    ) -> String {
+        let row_tokens = get_tokens_from_query_str(row);
+
+        if let Some(database_and_table) = get_insert_into_database_and_table_name(&row_tokens) {
+            match self
+                .primary_key_by_database_and_table_name
+                .get(&database_and_table)
+            {
+                Some(primary_key) => {
+                    let row_tokens = trim_tokens(&row_tokens, Keyword::Insert);
+                    let row_column_names = get_column_names_from_insert_into_query(&row_tokens);
+                    let row_column_values =
+                        get_column_values_str_from_insert_into_query(&row_tokens);
+
+                    let primary_key_values = primary_key
+                        .iter()
+                        .map(|column| {
+                            row_column_names
+                                .iter()
+                                .position(|x| x == column)
+                                .and_then(|column_idx| row_column_values.get(column_idx))
+                                .map(|value| format!("{}={}", column, value))
+                        })
+                        .collect::<Option<Vec<_>>>();
+
+                    // a row without every primary key column is deduplicated on the whole row
+                    if let Some(primary_key_values) = primary_key_values {
+                        return primary_key_values.join(", ");
+                    }
+                }
+                None => {
+                    if tables_without_primary_key.insert(database_and_table.clone()) {
+                        warn!(
+                            "table {}.{} has no primary key - its rows are deduplicated by hashing the whole row",
+                            database_and_table.0, database_and_table.1
+                        );
+                    }
+                }
+            }
+        }
+
+        format!("{:x}", md5::compute(row.trim().as_bytes()))
+    }
+
     fn visits<F: FnMut(String)>(
         &self,
         row: String,
@@ -113,8 +215,16 @@ impl<'a> PostgresSubset<'a> {
         let row_tokens = get_tokens_from_query_str(row.as_str());
 
         // find the database and table names from this row
-        let (row_database, row_table) =
-            get_insert_into_database_and_table_name(&row_tokens).unwrap();
+        let (row_database, row_table) = match get_insert_into_database_and_table_name(&row_tokens)
+        {
+            Some(database_and_table) => database_and_table,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    format!("unable to find the database and table names of row {}", row),
+                ));
+            }
+        };
 
         if self.subset_options.passthrough_tables.is_empty()
             || !self
@@ -131,10 +241,18 @@ impl<'a> PostgresSubset<'a> {
         }
 
         // find the subset table from this row
-        let row_subset_table = self
+        let row_subset_table = match self
             .subset_table_by_database_and_table_name
             .get(&(row_database.to_string(), row_table.to_string()))
-            .unwrap();
+        {
+            Some(subset_table) => subset_table,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    format!("table {}.{} not found", row_database, row_table),
+                ));
+            }
+        };
 
         let row_column_names = get_column_names_from_insert_into_query(&row_tokens);
         let row_column_values = get_column_values_str_from_insert_into_query(&row_tokens);
@@ -142,20 +260,51 @@ impl<'a> PostgresSubset<'a> {
         for row_relation in &row_subset_table.relations {
             let column = row_relation.from_property.as_str();
             // find the value from the current row for the relation column
-            let column_idx = row_column_names.iter().position(|x| *x == column).unwrap(); // FIXME unwrap
-            let value = row_column_values.get(column_idx).unwrap();
+            let value = match row_column_names
+                .iter()
+                .position(|x| *x == column)
+                .and_then(|column_idx| row_column_values.get(column_idx))
+            {
+                Some(value) => value,
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::Other,
+                        format!(
+                            "table {}.{} does not contain a value for column {}",
+                            row_database, row_table, column
+                        ),
+                    ));
+                }
+            };
 
             let database_and_table_tuple =
                 (row_relation.database.clone(), row_relation.table.clone());
 
             // find the table stats for this row
-            let row_relation_table_stats = table_stats.get(&database_and_table_tuple).unwrap();
+            let row_relation_table_stats = match table_stats.get(&database_and_table_tuple) {
+                Some(table_stats) => table_stats,
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::Other,
+                        format!(
+                            "table {}.{} not found",
+                            row_relation.database, row_relation.table
+                        ),
+                    ));
+                }
+            };
+
+            // keep the first error to return it once the dump reader is released
+            let mut visit_err = None;
 
             // TODO break acyclic graph
-            let row_clb = |row: &str| match self.visits(row.to_string(), table_stats, data) {
-                Ok(_) => {}
-                Err(err) => {
-                    panic!("{}", err);
+            let row_clb = |row: &str| {
+                if visit_err.is_some() {
+                    return;
+                }
+
+                if let Err(err) = self.visits(row.to_string(), table_stats, data) {
+                    visit_err = Some(err);
                 }
             };
 
@@ -166,6 +315,10 @@ impl<'a> PostgresSubset<'a> {
                 row_relation_table_stats,
                 row_clb,
             )?;
+
+            if let Some(err) = visit_err {
+                return Err(err);
+            }
         }
 
         Ok(())
@@ -184,21 +337,69 @@ impl<'a> Subset for PostgresSubset<'a> {
     /// a. the algo must visits all the tables, even the one that has no relations.
     fn read<F: FnMut(String), P: FnMut(Progress)>(
         &self,
+        data: F,
+        progress: P,
+    ) -> Result<(), Error> {
+        self.read_in(std::env::temp_dir().as_path(), data, progress)
+    }
+
+    fn estimate(&self) -> Result<HashMap<String, usize>, Error> {
+        // every table is part of the estimate - even the ones without any row in the subset
+        let mut rows_by_table = self
+            .subset_table_by_database_and_table_name
+            .keys()
+            .map(|(database, table)| (format!("{}.{}", database, table), 0usize))
+            .collect::<HashMap<_, _>>();
+
+        let _ = self.read(
+            |row| {
+                let row_tokens = get_tokens_from_query_str(row.as_str());
+
+                if let Some((database, table)) =
+                    get_insert_into_database_and_table_name(&row_tokens)
+                {
+                    *rows_by_table
+                        .entry(format!("{}.{}", database, table))
+                        .or_insert(0) += 1;
+                }
+            },
+            |_| {},
+        )?;
+
+        Ok(rows_by_table)
+    }
+}
+
+impl<'a> PostgresSubset<'a> {
+    /// Same as `read` - the temp files used to dedup rows are created in `temp_directory`,
+    /// and removed once the read is done, even on error.
+    fn read_in<F: FnMut(String), P: FnMut(Progress)>(
+        &self,
+        temp_directory: &Path,
         mut data: F,
-        mut progress: P,
+        progress: P,
     ) -> Result<(), Error> {
-        let temp_dir = tempfile::tempdir()?;
+        let temp_dir = tempfile::tempdir_in(temp_directory)?;
+
+        // keep the first error - the following lines are ignored
+        let mut dedup_err = None;
+        let mut tables_without_primary_key = HashSet::new();
 
         let _ = read(
             self,
             |line| {
+                if dedup_err.is_some() {
+                    return;
+                }
+
                 if line.contains("INSERT INTO") {
                     // Dedup INSERT INTO queries
                     // check if the line has not already been sent
                     match does_line_exist_and_set(
                         temp_dir.path(),
                         &get_insert_into_md5_hash(line.as_str()),
-                        line.as_str(),
+                        self.dedup_key(line.as_str(), &mut tables_without_primary_key)
+                            .as_str(),
                     ) {
                         Ok(does_line_exist) => {
                             if !does_line_exist {
@@ -206,7 +407,7 @@ impl<'a> Subset for PostgresSubset<'a> {
                             }
                         }
                         Err(err) => {
-                            panic!("{}", err);
+                            dedup_err = Some(err);
                         }
                     }
                 } else {
@@ -216,7 +417,10 @@ impl<'a> Subset for PostgresSubset<'a> {
             progress,
         )?;
 
-        Ok(())
+        match dedup_err {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
     }
 }
 
@@ -227,12 +431,14 @@ fn read<F: FnMut(String), P: FnMut(Progress)>(
 ) -> Result<(), Error> {
     let table_stats = table_stats_by_database_and_table_name(postgres_subset.dump_reader())?;
     let rows = postgres_subset.reference_rows(&table_stats)?;
+    let table_dependencies = list_late_table_dependencies(postgres_subset.dump_reader())?;
 
     // send schema header
     let table_stats_values = table_stats.values().collect::<Vec<_>>();
     let _ = dump_header(
         postgres_subset.dump_reader(),
         last_header_row_idx(&table_stats_values),
+        &table_dependencies,
         |row| {
             data(row.to_string());
         },
@@ -253,18 +459,42 @@ fn read<F: FnMut(String), P: FnMut(Progress)>(
     });
 
     // send INSERT INTO rows
-    for row in rows {
-        let start_time = utils::epoch_millis();
-        let _ = postgres_subset.visits(row, &table_stats, &mut data)?;
-
-        processed_rows += 1;
-
-        progress(Progress {
-            total_rows,
-            total_rows_to_process,
-            processed_rows,
-            last_process_time: utils::epoch_millis() - start_time,
-        });
+    if postgres_subset.subset_options.parallel_tables > 1 {
+        let groups = postgres_subset
+            .partition_reference_rows(rows, postgres_subset.subset_options.parallel_tables);
+
+        let _ = parallel_visits(
+            postgres_subset,
+            groups,
+            &table_stats,
+            |message| match message {
+                VisitMessage::Row(row) => data(row),
+                VisitMessage::Processed { last_process_time } => {
+                    processed_rows += 1;
+
+                    progress(Progress {
+                        total_rows,
+                        total_rows_to_process,
+                        processed_rows,
+                        last_process_time,
+                    });
+                }
+            },
+        )?;
+    } else {
+        for row in rows {
+            let start_time = utils::epoch_millis();
+            let _ = postgres_subset.visits(row, &table_stats, &mut data)?;
+
+            processed_rows += 1;
+
+            progress(Progress {
+                total_rows,
+                total_rows_to_process,
+                processed_rows,
+                last_process_time: utils::epoch_millis() - start_time,
+            });
+        }
     }
 
     for passthrough_table in postgres_subset.subset_options.passthrough_tables {
@@ -284,6 +514,7 @@ fn read<F: FnMut(String), P: FnMut(Progress)>(
     let _ = dump_footer(
         postgres_subset.dump_reader(),
         first_footer_row_idx(&table_stats_values),
+        &table_dependencies,
         |row| {
             data(row.to_string());
         },
@@ -292,6 +523,77 @@ fn read<F: FnMut(String), P: FnMut(Progress)>(
     Ok(())
 }
 
+/// visit each group of reference rows on its own thread. The visited rows are sent back to the calling thread,
+/// so `messages` - and the dedup of the rows behind it - is never called concurrently.
+/// The first error stops the visit of the remaining reference rows.
+fn parallel_visits<F: FnMut(VisitMessage)>(
+    postgres_subset: &PostgresSubset,
+    groups: Vec<Vec<String>>,
+    table_stats: &HashMap<(Database, Table), TableStats>,
+    mut messages: F,
+) -> Result<(), Error> {
+    let failed = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel::<VisitMessage>();
+
+        let handles = groups
+            .into_iter()
+            .map(|rows| {
+                let tx = tx.clone();
+                let failed = &failed;
+
+                scope.spawn(move || -> Result<(), Error> {
+                    let mut send_row = |row: String| {
+                        let _ = tx.send(VisitMessage::Row(row));
+                    };
+
+                    for row in rows {
+                        if failed.load(Ordering::SeqCst) {
+                            break;
+                        }
+
+                        let start_time = utils::epoch_millis();
+
+                        if let Err(err) = postgres_subset.visits(row, table_stats, &mut send_row) {
+                            failed.store(true, Ordering::SeqCst);
+                            return Err(err);
+                        }
+
+                        let _ = tx.send(VisitMessage::Processed {
+                            last_process_time: utils::epoch_millis() - start_time,
+                        });
+                    }
+
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // the loop ends once every thread is done and has dropped its sender
+        drop(tx);
+        for message in rx {
+            messages(message);
+        }
+
+        let mut result = Ok(());
+        for handle in handles {
+            let thread_result = handle.join().unwrap_or_else(|_| {
+                Err(Error::new(
+                    ErrorKind::Other,
+                    "a thread visiting the subset tables panicked",
+                ))
+            });
+
+            if result.is_ok() {
+                result = thread_result;
+            }
+        }
+
+        result
+    })
+}
+
 fn get_insert_into_md5_hash(query: &str) -> String {
     let tokens = get_tokens_from_query_str(query);
     let tokens = trim_pre_whitespaces(tokens);
@@ -451,17 +753,33 @@ fn first_footer_row_idx(table_stats_values: &Vec<&TableStats>) -> usize {
 /// Get Postgres dump header - everything before the first `INSERT INTO ...` row
 /// pg_dump export dump data in 3 phases: `CREATE TABLE ...`, `INSERT INTO ...`, and `ALTER TABLE ...`.
 /// this function return all the `CREATE TABLE ...` rows.
+/// The `table_dependencies` found after the first `CREATE TABLE ...` are moved right before it.
 fn dump_header<R: Read, F: FnMut(&str)>(
     dump_reader: BufReader<R>,
     last_header_row_idx: usize,
+    table_dependencies: &Vec<(usize, String)>,
     mut rows: F,
 ) -> Result<(), Error> {
     let mut query_idx = 0usize;
+    let mut table_dependencies_sent = false;
     let _ = list_sql_queries_from_dump_reader(dump_reader, |query| {
         let mut query_res = ListQueryResult::Continue;
 
         if query_idx <= last_header_row_idx {
-            rows(query)
+            if !table_dependencies_sent
+                && get_create_table_database_and_table_name(&get_tokens_from_query_str(query))
+                    .is_some()
+            {
+                for (_, table_dependency) in table_dependencies {
+                    rows(table_dependency.as_str());
+                }
+
+                table_dependencies_sent = true;
+            }
+
+            if !table_dependencies.iter().any(|(idx, _)| *idx == query_idx) {
+                rows(query)
+            }
         }
 
         if query_idx > last_header_row_idx {
@@ -477,15 +795,18 @@ fn dump_header<R: Read, F: FnMut(&str)>(
 
 /// Get Postgres dump footer - everything after the last `INSERT INTO ...` row
 /// pg_dump export dump data in 3 phases: `CREATE TABLE ...`, `INSERT INTO ...`, and `ALTER TABLE ...`.
-/// this function return all the `ALTER TABLE ...` rows.
+/// this function return all the `ALTER TABLE ...` rows - but the `table_dependencies` moved to the header.
 fn dump_footer<R: Read, F: FnMut(&str)>(
     dump_reader: BufReader<R>,
     first_footer_row_idx: usize,
+    table_dependencies: &Vec<(usize, String)>,
     mut rows: F,
 ) -> Result<(), Error> {
     let mut query_idx = 0usize;
     let _ = list_sql_queries_from_dump_reader(dump_reader, |query| {
-        if query_idx >= first_footer_row_idx {
+        if query_idx >= first_footer_row_idx
+            && !table_dependencies.iter().any(|(idx, _)| *idx == query_idx)
+        {
             rows(query)
         }
 
@@ -496,6 +817,31 @@ fn dump_footer<R: Read, F: FnMut(&str)>(
     Ok(())
 }
 
+/// the `CREATE EXTENSION`, `CREATE SCHEMA` and `CREATE TYPE` (or `DOMAIN`) queries found after the first
+/// `CREATE TABLE ...` - with their index. A restore fails if a table is created before its dependencies.
+fn list_late_table_dependencies<R: Read>(
+    dump_reader: BufReader<R>,
+) -> Result<Vec<(usize, String)>, Error> {
+    let mut table_dependencies = vec![];
+    let mut has_create_table = false;
+    let mut query_idx = 0usize;
+
+    let _ = list_sql_queries_from_dump_reader(dump_reader, |query| {
+        if !has_create_table {
+            has_create_table =
+                get_create_table_database_and_table_name(&get_tokens_from_query_str(query))
+                    .is_some();
+        } else if is_table_dependency(query) {
+            table_dependencies.push((query_idx, query.to_string()));
+        }
+
+        query_idx += 1;
+        ListQueryResult::Continue
+    })?;
+
+    Ok(table_dependencies)
+}
+
 fn table_stats_by_database_and_table_name<R: Read>(
     dump_reader: BufReader<R>,
 ) -> Result<HashMap<(Database, Table), TableStats>, Error> {
@@ -503,11 +849,21 @@ fn table_stats_by_database_and_table_name<R: Read>(
         HashMap::<(Database, Table), TableStats>::new();
 
     let mut query_idx = 0usize;
+    // function being defined - its body may be split into several queries
+    let mut function_name: Option<String> = None;
+    let mut error: Option<Error> = None;
+
     let _ = list_sql_queries_from_dump_reader(dump_reader, |query| {
         let tokens = get_tokens_from_query_str(query);
 
+        if let Some(name) = get_create_function_name(query) {
+            function_name = Some(name);
+        }
+
         let _ = match get_create_table_database_and_table_name(&tokens) {
             Some((database, table)) => {
+                function_name = None;
+
                 table_stats_by_database_and_table_name.insert(
                     (database.clone(), table.clone()),
                     TableStats {
@@ -553,19 +909,47 @@ fn table_stats_by_database_and_table_name<R: Read>(
                             table_stats.total_rows += 1;
                         }
                         None => {
-                            // should not happen because INSERT INTO must come after CREATE TABLE
-                            println!("Query: {}", query);
-                            panic!("Unexpected: INSERT INTO happened before CREATE TABLE while creating table_stats structure")
+                            // INSERT INTO must come after CREATE TABLE - the query is likely part of a
+                            // function body which has not been parsed as a single query
+                            let reason = match &function_name {
+                                Some(function_name) => format!(
+                                    "it is probably part of the body of the function '{}'",
+                                    function_name
+                                ),
+                                None => "the dump can't be parsed".to_string(),
+                            };
+
+                            error = Some(Error::new(
+                                ErrorKind::Other,
+                                format!(
+                                    "INSERT INTO {}.{} found before the CREATE TABLE of its table - {}: {}",
+                                    database,
+                                    table,
+                                    reason,
+                                    query.trim()
+                                ),
+                            ));
+
+                            return ListQueryResult::Break;
                         }
                     }
                 }
             }
         }
 
+        if query.trim_end().ends_with("$;") {
+            // end of a function body
+            function_name = None;
+        }
+
         query_idx += 1;
         ListQueryResult::Continue
     })?;
 
+    if let Some(error) = error {
+        return Err(error);
+    }
+
     Ok(table_stats_by_database_and_table_name)
 }
 
@@ -580,6 +964,42 @@ fn trim_tokens(tokens: &Vec<Token>, keyword: Keyword) -> Vec<Token> {
         .collect::<Vec<_>>()
 }
 
+/// every foreign key of the dump - they are declared after the rows, at the end of the dump
+pub fn list_foreign_keys<R: Read>(dump_reader: BufReader<R>) -> Result<Vec<ForeignKey>, Error> {
+    let mut foreign_keys = vec![];
+
+    list_sql_queries_from_dump_reader(dump_reader, |query| {
+        if let Some(fk) = get_alter_table_foreign_key(&get_tokens_from_query_str(query)) {
+            foreign_keys.push(fk);
+        }
+
+        ListQueryResult::Continue
+    })?;
+
+    Ok(foreign_keys)
+}
+
+/// columns of the primary key of every table of the dump. A table without primary key is not part of the map.
+pub fn list_primary_keys<R: Read>(
+    dump_reader: BufReader<R>,
+) -> Result<HashMap<(Database, Table), Vec<String>>, Error> {
+    let mut primary_keys = HashMap::new();
+
+    list_sql_queries_from_dump_reader(dump_reader, |query| {
+        let tokens = get_tokens_from_query_str(query);
+
+        if let Some((database, table, columns)) =
+            get_create_table_primary_key(&tokens).or_else(|| get_alter_table_primary_key(&tokens))
+        {
+            let _ = primary_keys.insert((database, table), columns);
+        }
+
+        ListQueryResult::Continue
+    })?;
+
+    Ok(primary_keys)
+}
+
 fn get_subset_table_by_database_and_table_name<R: Read>(
     dump_reader: BufReader<R>,
 ) -> Result<HashMap<(Database, Table), SubsetTable>, Error> {
@@ -639,6 +1059,180 @@ fn get_create_table_database_and_table_name(tokens: &Vec<Token>) -> Option<(Data
     None
 }
 
+/// `CREATE TABLE <database>.<table> (<column> ... PRIMARY KEY, ...)` or `CREATE TABLE <database>.<table> (..., PRIMARY KEY (<columns>))`
+fn get_create_table_primary_key(tokens: &Vec<Token>) -> Option<(Database, Table, Vec<String>)> {
+    let (database, table) = get_create_table_database_and_table_name(tokens)?;
+
+    // split the table definition into its column and constraint definitions
+    let mut definitions = vec![];
+    let mut definition = vec![];
+    let mut depth = 0usize;
+
+    for token in trim_tokens(tokens, Keyword::Create) {
+        match token {
+            Token::LParen => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            Token::RParen => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    break;
+                }
+            }
+            Token::Comma if depth == 1 => {
+                definitions.push(definition);
+                definition = vec![];
+                continue;
+            }
+            Token::Whitespace(_) => continue,
+            _ => {}
+        }
+
+        if depth > 0 {
+            definition.push(token);
+        }
+    }
+    definitions.push(definition);
+
+    for definition in definitions {
+        if let Some(columns) = get_primary_key_columns(&definition) {
+            return Some((database, table, columns));
+        }
+
+        let is_column_definition = match definition.first() {
+            Some(Token::Word(word)) => {
+                word.keyword != Keyword::Constraint && word.keyword != Keyword::Primary
+            }
+            _ => false,
+        };
+
+        if is_column_definition && position_of_primary_key(&definition).is_some() {
+            // the primary key is declared along its column
+            return Some((database, table, vec![word_name(&definition[0])?]));
+        }
+    }
+
+    None
+}
+
+/// `ALTER TABLE [ONLY] <database>.<table> ADD CONSTRAINT <name> PRIMARY KEY (<columns>)`
+fn get_alter_table_primary_key(tokens: &Vec<Token>) -> Option<(Database, Table, Vec<String>)> {
+    let tokens = trim_tokens(&tokens, Keyword::Alter);
+
+    if !match_keyword_at_position(Keyword::Alter, &tokens, 0)
+        || !match_keyword_at_position(Keyword::Table, &tokens, 2)
+    {
+        return None;
+    }
+
+    let database_name_pos = if match_keyword_at_position(Keyword::Only, &tokens, 4) {
+        6
+    } else {
+        4
+    };
+
+    let database = get_word_value_at_position(&tokens, database_name_pos)?;
+    let table = get_word_value_at_position(&tokens, database_name_pos + 2)?;
+
+    let tokens = tokens
+        .iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_)))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let columns = get_primary_key_columns(&tokens)?;
+
+    Some((database.to_string(), table.to_string(), columns))
+}
+
+/// columns listed by `PRIMARY KEY (<columns>)` - `tokens` must not contain whitespaces
+fn get_primary_key_columns(tokens: &[Token]) -> Option<Vec<String>> {
+    let key_idx = position_of_primary_key(tokens)?;
+
+    if tokens.get(key_idx + 1) != Some(&Token::LParen) {
+        return None;
+    }
+
+    let columns = tokens
+        .iter()
+        .skip(key_idx + 2)
+        .take_while(|token| **token != Token::RParen)
+        .filter_map(word_name)
+        .collect::<Vec<_>>();
+
+    if columns.is_empty() {
+        return None;
+    }
+
+    Some(columns)
+}
+
+/// index of the `KEY` token of `PRIMARY KEY` - `tokens` must not contain whitespaces
+fn position_of_primary_key(tokens: &[Token]) -> Option<usize> {
+    tokens
+        .windows(2)
+        .position(|window| match window {
+            [Token::Word(primary), Token::Word(key)] => {
+                primary.keyword == Keyword::Primary && key.keyword == Keyword::Key
+            }
+            _ => false,
+        })
+        .map(|idx| idx + 1)
+}
+
+/// name of a column, quoted the same way as the column names of the `INSERT INTO` rows
+fn word_name(token: &Token) -> Option<String> {
+    match token {
+        Token::Word(word) => Some(match word.quote_style {
+            Some(quote) => format!("{}{}{}", quote, word.value, quote),
+            None => word.value.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// name of the function (or procedure) created by a `CREATE [OR REPLACE] FUNCTION ...` query
+fn get_create_function_name(query: &str) -> Option<String> {
+    let mut words = query.split_whitespace();
+
+    if !words.next()?.eq_ignore_ascii_case("create") {
+        return None;
+    }
+
+    let mut word = words.next()?;
+    if word.eq_ignore_ascii_case("or") {
+        // OR REPLACE
+        let _ = words.next()?;
+        word = words.next()?;
+    }
+
+    if !word.eq_ignore_ascii_case("function") && !word.eq_ignore_ascii_case("procedure") {
+        return None;
+    }
+
+    match words.next()?.split('(').next() {
+        Some(name) if !name.is_empty() => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+/// `CREATE EXTENSION ...`, `CREATE SCHEMA ...`, `CREATE TYPE ...` or `CREATE DOMAIN ...` query
+fn is_table_dependency(query: &str) -> bool {
+    let mut words = query.split_whitespace();
+
+    match (words.next(), words.next()) {
+        (Some(create), Some(object)) if create.eq_ignore_ascii_case("create") => {
+            ["extension", "schema", "type", "domain"]
+                .iter()
+                .any(|table_dependency| object.eq_ignore_ascii_case(table_dependency))
+        }
+        _ => false,
+    }
+}
+
 fn get_insert_into_database_and_table_name(tokens: &Vec<Token>) -> Option<(Database, Table)> {
     let tokens = trim_tokens(&tokens, Keyword::Insert);
 
@@ -659,7 +1253,7 @@ fn get_insert_into_database_and_table_name(tokens: &Vec<Token>) -> Option<(Datab
     None
 }
 
-fn get_alter_table_foreign_key(tokens: &Vec<Token>) -> Option<ForeignKey> {
+pub fn get_alter_table_foreign_key(tokens: &Vec<Token>) -> Option<ForeignKey> {
     let tokens = trim_tokens(&tokens, Keyword::Alter);
 
     if tokens.is_empty() {
@@ -739,14 +1333,14 @@ mod tests {
         dump_footer, dump_header, filter_insert_into_rows, first_footer_row_idx,
         get_alter_table_foreign_key, get_create_table_database_and_table_name,
         get_subset_table_by_database_and_table_name, last_header_row_idx,
-        list_percent_of_insert_into_rows, table_stats_by_database_and_table_name, PostgresSubset,
-        SubsetStrategy,
+        list_percent_of_insert_into_rows, list_primary_keys,
+        table_stats_by_database_and_table_name, PostgresSubset,
     };
-    use crate::{PassthroughTable, Subset, SubsetOptions};
+    use crate::{PassthroughTable, Subset, SubsetOptions, SubsetStrategy};
     use dump_parser::postgres::Tokenizer;
     use std::collections::HashSet;
     use std::fs::File;
-    use std::io::BufReader;
+    use std::io::{BufReader, Write};
     use std::path::{Path, PathBuf};
 
     fn dump_path() -> PathBuf {
@@ -838,6 +1432,44 @@ ALTER TABLE ONLY public.territories
         // TODO add more tests to check table.rows size
     }
 
+    #[test]
+    fn check_table_stats_with_function_body() {
+        let dump = r#"
+CREATE TABLE public.orders (
+    id integer NOT NULL
+);
+
+CREATE FUNCTION public.audit_order() RETURNS trigger
+    LANGUAGE plpgsql
+    AS $$
+BEGIN
+  insert into public.audit (order_id) values (NEW.id);
+  RETURN NEW;
+END;
+$$;
+
+INSERT INTO public.orders (id) VALUES (1);
+"#;
+
+        let err = table_stats_by_database_and_table_name(BufReader::new(dump.as_bytes()))
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert!(err.contains("INSERT INTO public.audit found before the CREATE TABLE"));
+        assert!(err.contains("the function 'public.audit_order'"));
+        assert!(err.contains("insert into public.audit (order_id) values (NEW.id);"));
+
+        // an INSERT INTO without CREATE TABLE and outside of a function body
+        let dump = "INSERT INTO public.orders (id) VALUES (1);\n";
+        let err = table_stats_by_database_and_table_name(BufReader::new(dump.as_bytes()))
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert!(err.contains("the dump can't be parsed"));
+    }
+
     #[test]
     fn check_percent_of_rows() {
         let table_stats = table_stats_by_database_and_table_name(dump_reader()).unwrap();
@@ -884,7 +1516,7 @@ ALTER TABLE ONLY public.territories
         assert!(idx > 0);
 
         let mut rows = vec![];
-        let _ = dump_header(dump_reader(), idx, |row| {
+        let _ = dump_header(dump_reader(), idx, &vec![], |row| {
             rows.push(row.to_string());
         })
         .unwrap();
@@ -905,7 +1537,7 @@ ALTER TABLE ONLY public.territories
         assert!(idx > 0);
 
         let mut rows = vec![];
-        let _ = dump_footer(dump_reader(), idx, |row| {
+        let _ = dump_footer(dump_reader(), idx, &vec![], |row| {
             rows.push(row.to_string());
         })
         .unwrap();
@@ -973,4 +1605,434 @@ ALTER TABLE ONLY public.territories
             51
         );
     }
+
+    #[test]
+    fn check_postgres_subset_error_removes_temp_files() {
+        // customers.country_id references a column which does not exist in the countries rows
+        let dump = r#"
+CREATE TABLE public.countries (
+    id smallint NOT NULL,
+    name character varying(40) NOT NULL
+);
+
+CREATE TABLE public.customers (
+    id smallint NOT NULL,
+    country_id smallint NOT NULL
+);
+
+CREATE TABLE public.orders (
+    id smallint NOT NULL,
+    customer_id smallint NOT NULL
+);
+
+INSERT INTO public.countries (id, name) VALUES (1, 'France');
+INSERT INTO public.customers (id, country_id) VALUES (1, 1);
+INSERT INTO public.orders (id, customer_id) VALUES (1, 1);
+INSERT INTO public.orders (id, customer_id) VALUES (2, 1);
+
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT fk_orders_customers FOREIGN KEY (customer_id) REFERENCES public.customers(id);
+
+ALTER TABLE ONLY public.customers
+    ADD CONSTRAINT fk_customers_countries FOREIGN KEY (country_id) REFERENCES public.countries(code);
+"#;
+
+        let mut dump_file = tempfile::NamedTempFile::new().unwrap();
+        dump_file.write_all(dump.as_bytes()).unwrap();
+
+        let s = HashSet::new();
+        let postgres_subset = PostgresSubset::new(
+            dump_file.path(),
+            SubsetStrategy::random("public", "orders", 100),
+            SubsetOptions::new(&s),
+        )
+        .unwrap();
+
+        let temp_directory = tempfile::tempdir().unwrap();
+        let mut rows = vec![];
+
+        let result = postgres_subset.read_in(
+            temp_directory.path(),
+            |row| {
+                rows.push(row);
+            },
+            |_| {},
+        );
+
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("does not contain column code"));
+        // rows have been deduplicated before the error
+        assert!(rows.iter().any(|row| row.contains("INSERT INTO")));
+        assert_eq!(temp_directory.path().read_dir().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn check_postgres_subset_sends_table_dependencies_first() {
+        // the extension, the schema and the type are created after the rows
+        let dump = r#"
+SET client_encoding = 'UTF8';
+
+CREATE TABLE public.orders (
+    id smallint NOT NULL,
+    status public.order_status NOT NULL
+);
+
+INSERT INTO public.orders (id, status) VALUES (1, 'new');
+INSERT INTO public.orders (id, status) VALUES (2, 'paid');
+
+CREATE EXTENSION IF NOT EXISTS pgcrypto WITH SCHEMA public;
+
+CREATE SCHEMA billing;
+
+CREATE TYPE public.order_status AS ENUM (
+    'new',
+    'paid'
+);
+
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT orders_pkey PRIMARY KEY (id);
+"#;
+
+        let mut dump_file = tempfile::NamedTempFile::new().unwrap();
+        dump_file.write_all(dump.as_bytes()).unwrap();
+
+        let s = HashSet::new();
+        let postgres_subset = PostgresSubset::new(
+            dump_file.path(),
+            SubsetStrategy::random("public", "orders", 100),
+            SubsetOptions::new(&s),
+        )
+        .unwrap();
+
+        let mut rows = vec![];
+        postgres_subset
+            .read(
+                |row| {
+                    rows.push(row);
+                },
+                |_| {},
+            )
+            .unwrap();
+
+        let position = |prefix: &str| {
+            let positions = rows
+                .iter()
+                .enumerate()
+                .filter(|(_, row)| row.trim_start().starts_with(prefix))
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>();
+
+            // sent once
+            assert_eq!(positions.len(), 1, "{}", prefix);
+            positions[0]
+        };
+
+        let create_table = position("CREATE TABLE");
+        assert!(position("SET client_encoding") < create_table);
+        assert!(position("CREATE EXTENSION") < create_table);
+        assert!(position("CREATE SCHEMA") < create_table);
+        assert!(position("CREATE TYPE") < create_table);
+        assert!(position("ALTER TABLE") > create_table);
+        assert_eq!(
+            rows.iter()
+                .filter(|row| row.contains("INSERT INTO"))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn check_parallel_postgres_subset() {
+        // orders -> customers -> countries - customers 1 and 2 share their country
+        let dump = r#"
+CREATE TABLE public.countries (
+    id smallint NOT NULL,
+    name character varying(40) NOT NULL
+);
+
+CREATE TABLE public.customers (
+    id smallint NOT NULL,
+    country_id smallint NOT NULL
+);
+
+CREATE TABLE public.orders (
+    id smallint NOT NULL,
+    customer_id smallint NOT NULL
+);
+
+INSERT INTO public.countries (id, name) VALUES (1, 'France');
+INSERT INTO public.countries (id, name) VALUES (2, 'Spain');
+INSERT INTO public.countries (id, name) VALUES (3, 'Italy');
+INSERT INTO public.customers (id, country_id) VALUES (1, 1);
+INSERT INTO public.customers (id, country_id) VALUES (2, 1);
+INSERT INTO public.customers (id, country_id) VALUES (3, 2);
+INSERT INTO public.customers (id, country_id) VALUES (4, 3);
+INSERT INTO public.orders (id, customer_id) VALUES (1, 1);
+INSERT INTO public.orders (id, customer_id) VALUES (2, 1);
+INSERT INTO public.orders (id, customer_id) VALUES (3, 2);
+INSERT INTO public.orders (id, customer_id) VALUES (4, 3);
+INSERT INTO public.orders (id, customer_id) VALUES (5, 4);
+INSERT INTO public.orders (id, customer_id) VALUES (6, 3);
+
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT fk_orders_customers FOREIGN KEY (customer_id) REFERENCES public.customers(id);
+
+ALTER TABLE ONLY public.customers
+    ADD CONSTRAINT fk_customers_countries FOREIGN KEY (country_id) REFERENCES public.countries(id);
+"#;
+
+        let mut dump_file = tempfile::NamedTempFile::new().unwrap();
+        dump_file.write_all(dump.as_bytes()).unwrap();
+
+        let s = HashSet::new();
+        let read_rows = |parallel_tables: usize| {
+            let mut subset_options = SubsetOptions::new(&s);
+            subset_options.set_parallel_tables(parallel_tables);
+
+            let postgres_subset = PostgresSubset::new(
+                dump_file.path(),
+                SubsetStrategy::random("public", "orders", 100),
+                subset_options,
+            )
+            .unwrap();
+
+            let mut rows = vec![];
+            let mut processed_rows = 0usize;
+            postgres_subset
+                .read(
+                    |row| {
+                        rows.push(row.trim().to_string());
+                    },
+                    |progress| {
+                        processed_rows = progress.processed_rows;
+                    },
+                )
+                .unwrap();
+
+            assert_eq!(processed_rows, 6);
+            rows
+        };
+
+        // the orders of the same customer are visited by the same thread
+        let postgres_subset = PostgresSubset::new(
+            dump_file.path(),
+            SubsetStrategy::random("public", "orders", 100),
+            SubsetOptions::new(&s),
+        )
+        .unwrap();
+        let orders = (1..=6)
+            .map(|id| {
+                format!(
+                    "INSERT INTO public.orders (id, customer_id) VALUES ({}, {});",
+                    id,
+                    [1, 1, 2, 3, 4, 3][id - 1]
+                )
+            })
+            .collect::<Vec<_>>();
+        let groups = postgres_subset.partition_reference_rows(orders, 4);
+        assert_eq!(groups.len(), 4);
+        assert!(groups.iter().any(|group| group.len() == 2
+            && group[0].contains("(1, 1)")
+            && group[1].contains("(2, 1)")));
+        assert!(groups.iter().any(|group| group.len() == 2
+            && group[0].contains("(4, 3)")
+            && group[1].contains("(6, 3)")));
+
+        let serial_rows = read_rows(1);
+        let parallel_rows = read_rows(4);
+
+        // rows are not duplicated
+        let insert_into_rows = parallel_rows
+            .iter()
+            .filter(|row| row.contains("INSERT INTO"))
+            .collect::<Vec<_>>();
+        assert_eq!(insert_into_rows.len(), 6 + 4 + 3);
+        assert_eq!(
+            insert_into_rows.iter().collect::<HashSet<_>>().len(),
+            insert_into_rows.len()
+        );
+
+        assert_eq!(
+            serial_rows.into_iter().collect::<HashSet<_>>(),
+            parallel_rows.into_iter().collect::<HashSet<_>>()
+        );
+    }
+
+    #[test]
+    fn check_postgres_subset_estimate() {
+        let path = dump_path();
+        let mut s = HashSet::new();
+        s.insert(PassthroughTable::new("public", "us_states"));
+
+        let postgres_subset = PostgresSubset::new(
+            path.as_path(),
+            SubsetStrategy::random("public", "orders", 50),
+            SubsetOptions::new(&s),
+        )
+        .unwrap();
+
+        let estimate = postgres_subset.estimate().unwrap();
+
+        let mut rows = vec![];
+        postgres_subset
+            .read(
+                |row| {
+                    rows.push(row);
+                },
+                |_| {},
+            )
+            .unwrap();
+
+        assert_eq!(estimate.get("public.us_states"), Some(&51));
+        assert_eq!(estimate.get("public.customer_demographics"), Some(&0));
+
+        for (table, estimated_rows) in &estimate {
+            let prefix = format!("INSERT INTO {} ", table);
+            let produced_rows = rows
+                .iter()
+                .filter(|row| row.trim_start().starts_with(prefix.as_str()))
+                .count();
+
+            assert_eq!(*estimated_rows, produced_rows, "table {}", table);
+        }
+
+        assert_eq!(
+            estimate.values().sum::<usize>(),
+            rows.iter().filter(|row| row.contains("INSERT INTO")).count()
+        );
+    }
+
+    #[test]
+    fn check_primary_keys() {
+        let dump = r#"
+CREATE TABLE public.countries (
+    code character(2) NOT NULL,
+    name character varying(40) NOT NULL
+);
+
+CREATE TABLE public.customers (
+    id smallint NOT NULL PRIMARY KEY,
+    name character varying(40) NOT NULL
+);
+
+CREATE TABLE public.order_details (
+    order_id smallint NOT NULL,
+    product_id smallint NOT NULL,
+    CONSTRAINT pk_order_details PRIMARY KEY (order_id, product_id)
+);
+
+CREATE TABLE public.orders (
+    id smallint NOT NULL,
+    customer_id smallint NOT NULL
+);
+
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT orders_pkey PRIMARY KEY (id);
+"#;
+
+        let primary_keys = list_primary_keys(BufReader::new(dump.as_bytes())).unwrap();
+        assert_eq!(primary_keys.len(), 3);
+        assert_eq!(
+            primary_keys.get(&("public".to_string(), "customers".to_string())),
+            Some(&vec!["id".to_string()])
+        );
+        assert_eq!(
+            primary_keys.get(&("public".to_string(), "order_details".to_string())),
+            Some(&vec!["order_id".to_string(), "product_id".to_string()])
+        );
+        assert_eq!(
+            primary_keys.get(&("public".to_string(), "orders".to_string())),
+            Some(&vec!["id".to_string()])
+        );
+        assert_eq!(
+            primary_keys.get(&("public".to_string(), "countries".to_string())),
+            None
+        );
+
+        let primary_keys = list_primary_keys(dump_reader()).unwrap();
+        assert_eq!(
+            primary_keys.get(&("public".to_string(), "customer_customer_demo".to_string())),
+            Some(&vec![
+                "customer_id".to_string(),
+                "customer_type_id".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn check_postgres_subset_dedups_rows_of_tables_without_primary_key() {
+        // countries has no primary key - its rows are deduplicated by hashing the whole row
+        let dump = r#"
+CREATE TABLE public.countries (
+    code character(2) NOT NULL,
+    name character varying(40) NOT NULL
+);
+
+CREATE TABLE public.customers (
+    id smallint NOT NULL,
+    country_code character(2) NOT NULL
+);
+
+CREATE TABLE public.orders (
+    id smallint NOT NULL,
+    customer_id smallint NOT NULL
+);
+
+INSERT INTO public.countries (code, name) VALUES ('FR', 'France');
+INSERT INTO public.countries (code, name) VALUES ('DE', 'Germany');
+INSERT INTO public.customers (id, country_code) VALUES (1, 'FR');
+INSERT INTO public.customers (id, country_code) VALUES (2, 'FR');
+INSERT INTO public.orders (id, customer_id) VALUES (1, 1);
+INSERT INTO public.orders (id, customer_id) VALUES (2, 1);
+INSERT INTO public.orders (id, customer_id) VALUES (3, 2);
+
+ALTER TABLE ONLY public.countries
+    ADD CONSTRAINT countries_code_key UNIQUE (code);
+
+ALTER TABLE ONLY public.customers
+    ADD CONSTRAINT customers_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT orders_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT fk_orders_customers FOREIGN KEY (customer_id) REFERENCES public.customers(id);
+
+ALTER TABLE ONLY public.customers
+    ADD CONSTRAINT fk_customers_countries FOREIGN KEY (country_code) REFERENCES public.countries(code);
+"#;
+
+        let mut dump_file = tempfile::NamedTempFile::new().unwrap();
+        dump_file.write_all(dump.as_bytes()).unwrap();
+
+        let s = HashSet::new();
+        let postgres_subset = PostgresSubset::new(
+            dump_file.path(),
+            SubsetStrategy::random("public", "orders", 100),
+            SubsetOptions::new(&s),
+        )
+        .unwrap();
+
+        let mut rows = vec![];
+        postgres_subset
+            .read(
+                |row| {
+                    rows.push(row);
+                },
+                |_| {},
+            )
+            .unwrap();
+
+        let count = |prefix: &str| {
+            rows.iter()
+                .filter(|row| row.trim_start().starts_with(prefix))
+                .count()
+        };
+
+        assert_eq!(count("INSERT INTO public.orders "), 3);
+        assert_eq!(count("INSERT INTO public.customers "), 2);
+        // the country is referenced by both customers but sent once, and Germany is not referenced
+        assert_eq!(count("INSERT INTO public.countries "), 1);
+        assert!(rows.iter().any(|row| row.contains("'France'")));
+    }
 }